@@ -0,0 +1,362 @@
+use std::fmt::Write;
+
+use serde_json::Value;
+use fluvio_jolt::ir::{KeyMatch, MatchNode, OperationIr, PathSegment};
+use fluvio_jolt::pointer::JsonPointer;
+use fluvio_jolt::OperationRef;
+
+use crate::CodegenError;
+
+// Calls the function with the keys of the input the `shift` matches against the keys of the
+// spec: the keys of objects, the indexes of arrays and the text of scalars
+const FOR_EACH_KEY: &str = r#"
+fn for_each_key(input: &Value, mut f: impl FnMut(&str, &Value)) {
+    match input {
+        Value::Object(map) => {
+            for (key, value) in map {
+                f(key, value);
+            }
+        }
+        Value::Array(arr) => {
+            for (idx, value) in arr.iter().enumerate() {
+                f(&idx.to_string(), value);
+            }
+        }
+        Value::Bool(b) => f(if *b { "true" } else { "false" }, input),
+        Value::Number(n) => f(&n.to_string(), input),
+        Value::String(s) => f(s, input),
+        Value::Null => f("null", input),
+    }
+}"#;
+
+// Writes a value of the `shift` with the default `merge` conflicts
+const WRITE: &str = r#"
+fn write(mut out: &mut Value, path: &[&str], value: Value) {
+    for key in path {
+        if !out.is_object() {
+            *out = Value::Object(serde_json::Map::new());
+        }
+        out = match out {
+            Value::Object(map) => map.entry(*key).or_insert(Value::Null),
+            _ => return,
+        };
+    }
+    match out {
+        Value::Null => *out = value,
+        Value::Array(arr) => arr.push(value),
+        other => *other = Value::Array(vec![std::mem::take(other), value]),
+    }
+}"#;
+
+// Writes a value of the `default` operation
+const INSERT: &str = r#"
+fn insert(mut dest: &mut Value, path: &[&str], value: Value) {
+    for key in path {
+        dest = match dest {
+            Value::Object(map) => map
+                .entry(*key)
+                .or_insert_with(|| Value::Object(serde_json::Map::new())),
+            _ => return,
+        };
+    }
+    match (dest, value) {
+        (Value::Object(dest), Value::Object(src)) => dest.extend(src),
+        (dest, value) => *dest = value,
+    }
+}"#;
+
+// Removes a key of the `remove` operation, keeping the order of the remaining keys
+const REMOVE: &str = r#"
+fn remove(value: &mut Value, parent: &str, key: &str) {
+    if let Some(Value::Object(map)) = value.pointer_mut(parent) {
+        map.retain(|k, _| k != key);
+    }
+}"#;
+
+/// Source of the function, indented with four spaces.
+#[derive(Default)]
+struct Source {
+    text: String,
+    indent: usize,
+}
+
+impl Source {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.text.push_str("    ");
+            }
+        }
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    fn open(&mut self, line: &str) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, line: &str) {
+        self.indent -= 1;
+        self.line(line);
+    }
+
+    fn lines(&mut self, text: &str) {
+        for line in text.lines() {
+            self.line(line);
+        }
+    }
+}
+
+/// Generator of the function applying the operations of a chain.
+#[derive(Default)]
+pub(crate) struct Generator {
+    body: Source,
+    items: Source,
+    for_each_key: bool,
+    write: bool,
+    insert: bool,
+    remove: bool,
+}
+
+impl Generator {
+    pub(crate) fn operation(
+        &mut self,
+        index: usize,
+        operation: OperationRef,
+    ) -> Result<(), CodegenError> {
+        let unsupported = |reason: String| CodegenError::Unsupported { index, reason };
+        if let Value::Object(raw) = operation.raw() {
+            if let Some(field) = raw
+                .keys()
+                .find(|field| !["operation", "spec", "name", "enabled"].contains(&field.as_str()))
+            {
+                return Err(unsupported(format!("the `{field}` field is not supported")));
+            }
+        }
+
+        let mut comment = format!("// operation {index}: {}", operation.kind());
+        if let Some(name) = operation.name() {
+            write!(comment, " {name:?}").ok();
+        }
+        if !operation.enabled() {
+            self.body.line(&format!("{comment}, disabled"));
+            return Ok(());
+        }
+        self.body.line(&comment);
+
+        match operation.to_ir() {
+            OperationIr::Shift { matches } => {
+                let function = format!("shift_{index}");
+                match self
+                    .shift_level(index, function, &matches, &mut 0)
+                    .map_err(unsupported)?
+                {
+                    Some(function) => {
+                        self.body.line("let mut out = Value::Null;");
+                        self.body.line(&format!("{function}(&value, &mut out);"));
+                        self.body.line("value = out;");
+                    }
+                    // the spec writes nothing
+                    None => self.body.line("value.take();"),
+                }
+            }
+            OperationIr::Default { values } => {
+                self.insert = true;
+                for default in values {
+                    let pointer = JsonPointer::new(
+                        std::iter::once(String::new())
+                            .chain(default.path.iter().cloned())
+                            .collect(),
+                    );
+                    self.body.open(&format!(
+                        "if value.pointer({:?}).is_none() {{",
+                        pointer.join_rfc6901()
+                    ));
+                    self.body.line(&format!(
+                        "insert(&mut value, {}, {});",
+                        path(&default.path),
+                        value(&default.value)
+                    ));
+                    self.body.close("}");
+                }
+            }
+            OperationIr::Remove { paths } => {
+                self.remove = true;
+                for mut path in paths {
+                    let key = path.pop().unwrap_or_default();
+                    let parent =
+                        JsonPointer::new(std::iter::once(String::new()).chain(path).collect());
+                    self.body.line(&format!(
+                        "remove(&mut value, {:?}, {key:?});",
+                        parent.join_rfc6901()
+                    ));
+                }
+            }
+            _ => {
+                return Err(unsupported(format!(
+                    "the `{}` operation is not supported",
+                    operation.kind()
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    // Generates the function matching a level of the shift, none if the level writes nothing
+    fn shift_level(
+        &mut self,
+        index: usize,
+        function: String,
+        nodes: &[MatchNode],
+        count: &mut usize,
+    ) -> Result<Option<String>, String> {
+        let mut arms = Vec::new();
+        for node in nodes {
+            let KeyMatch::Literal { value: key, marker } = &node.key else {
+                return Err(format!(
+                    "only the literal keys of the `shift` are supported, found a key of kind `{}`",
+                    kind(&node.key)
+                ));
+            };
+            // the generated function can't fail on a missing key
+            if marker.as_deref() == Some("required") {
+                return Err(format!(
+                    "the required key `{key}!` of the `shift` is not supported"
+                ));
+            }
+            let mut writes = Vec::new();
+            for destination in &node.destinations {
+                let keys = destination
+                    .iter()
+                    .map(|segment| match segment {
+                        PathSegment::Key { value } => Ok(value.clone()),
+                        segment => Err(format!(
+                            "only the output paths of literal keys are supported, found a segment of \
+                             kind `{}`",
+                            kind(segment)
+                        )),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                writes.push(format!("write(out, {}, value.clone())", path(&keys)));
+            }
+            if !node.children.is_empty() {
+                *count += 1;
+                let nested = format!("shift_{index}_{count}");
+                if let Some(nested) = self.shift_level(index, nested, &node.children, count)? {
+                    writes.push(format!("{nested}(value, out)"));
+                }
+            }
+            if !writes.is_empty() {
+                arms.push((format!("{key:?}"), writes));
+            }
+        }
+        if arms.is_empty() {
+            return Ok(None);
+        }
+
+        self.for_each_key = true;
+        self.write = true;
+        let items = &mut self.items;
+        items.line("");
+        items.open(&format!("fn {function}(input: &Value, out: &mut Value) {{"));
+        items.open("for_each_key(input, |key, value| match key {");
+        for (key, writes) in arms {
+            match writes.as_slice() {
+                [write] => items.line(&format!("{key} => {write},")),
+                writes => {
+                    items.open(&format!("{key} => {{"));
+                    for write in writes {
+                        items.line(&format!("{write};"));
+                    }
+                    items.close("}");
+                }
+            }
+        }
+        items.line("_ => {}");
+        items.close("});");
+        items.close("}");
+        Ok(Some(function))
+    }
+
+    /// Returns the source of the function.
+    pub(crate) fn finish(self, fn_name: &str, fingerprint: u64) -> String {
+        let mut source = Source::default();
+        source.line(&format!(
+            "/// Applies the specification with the fingerprint `{fingerprint:#018x}`."
+        ));
+        source.line("///");
+        source.line("/// Generated by `fluvio-jolt-codegen`, regenerate it instead of editing it.");
+        source.line("#[allow(clippy::all)]");
+        source.open(&format!(
+            "pub fn {fn_name}(input: serde_json::Value) -> serde_json::Value {{"
+        ));
+        if self.body.text.is_empty() {
+            source.line("input");
+            source.close("}");
+            return source.text;
+        }
+
+        source.line("use serde_json::Value;");
+        for (used, helper) in [
+            (self.for_each_key, FOR_EACH_KEY),
+            (self.write, WRITE),
+            (self.insert, INSERT),
+            (self.remove, REMOVE),
+        ] {
+            if used {
+                source.lines(helper);
+            }
+        }
+        source.lines(&self.items.text);
+        source.line("");
+        source.line("let mut value = input;");
+        source.lines(&self.body.text);
+        source.line("value");
+        source.close("}");
+        source.text
+    }
+}
+
+// The `kind` tag of a node of the intermediate representation
+fn kind(node: &impl serde::Serialize) -> String {
+    serde_json::to_value(node)
+        .ok()
+        .and_then(|node| node["kind"].as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+// A path as a slice of string literals
+fn path(keys: &[String]) -> String {
+    let keys: Vec<String> = keys.iter().map(|key| format!("{key:?}")).collect();
+    format!("&[{}]", keys.join(", "))
+}
+
+// An expression building the value
+fn value(value: &Value) -> String {
+    match value {
+        Value::Null => "Value::Null".to_string(),
+        Value::Bool(b) => format!("Value::Bool({b})"),
+        Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+            (Some(n), _, _) => format!("Value::from({n}u64)"),
+            (_, Some(n), _) => format!("Value::from({n}i64)"),
+            (_, _, Some(n)) => format!("Value::from({n:?}f64)"),
+            _ => "Value::Null".to_string(),
+        },
+        Value::String(s) => format!("Value::from({s:?})"),
+        Value::Array(arr) => {
+            let elements: Vec<String> = arr.iter().map(self::value).collect();
+            format!("Value::Array(vec![{}])", elements.join(", "))
+        }
+        Value::Object(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(key, value)| format!("(String::from({key:?}), {})", self::value(value)))
+                .collect();
+            format!(
+                "Value::Object(serde_json::Map::from_iter([{}]))",
+                entries.join(", ")
+            )
+        }
+    }
+}
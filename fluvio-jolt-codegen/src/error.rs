@@ -0,0 +1,13 @@
+use thiserror::Error as ThisError;
+
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+pub enum CodegenError {
+    #[error("`{0}` is not a valid name of a Rust function")]
+    InvalidName(String),
+    #[error("The `expects` block of the specification is not supported.")]
+    Expectations,
+    #[error("The `envelope` block of the specification is not supported.")]
+    Envelope,
+    #[error("Operation {index} is not supported: {reason}")]
+    Unsupported { index: usize, reason: String },
+}
@@ -0,0 +1,186 @@
+//! Generates the Rust source of a function applying a [`TransformSpec`], for the hot paths where
+//! the interpretation of the specification costs too much. The generated function reads the keys
+//! of the specification with plain `match` arms instead of walking the specification for every
+//! record, and depends on `serde_json` only.
+//!
+//! The function is usually generated by a build script from the specification of the crate:
+//!
+//! ```no_run
+//! // build.rs
+//! use std::{env, fs, path::Path};
+//! use fluvio_jolt::TransformSpec;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     println!("cargo:rerun-if-changed=spec.json");
+//!     let spec: TransformSpec = serde_json::from_str(&fs::read_to_string("spec.json")?)?;
+//!     let source = fluvio_jolt_codegen::generate(&spec, "to_event")?;
+//!     fs::write(Path::new(&env::var("OUT_DIR")?).join("to_event.rs"), source)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! and included by the crate with
+//! `include!(concat!(env!("OUT_DIR"), "/to_event.rs"));`, which defines
+//! `pub fn to_event(input: serde_json::Value) -> serde_json::Value`.
+//!
+//! The generated function returns the output [`transform`](fluvio_jolt::transform) returns with
+//! the default options. Only the chains of the following operations can be generated, the other
+//! specifications fail with [`CodegenError::Unsupported`]:
+//!  - `shift` with literal keys writing to paths of literal keys, e.g. `{"id": "data.id"}`
+//!  - `default`
+//!  - `remove`
+//!
+//! The operations can have the `name` and `enabled` fields but no options, `when` conditions or
+//! constants, and the chain can't have an `expects` or an `envelope` block.
+
+mod emit;
+mod error;
+
+use fluvio_jolt::TransformSpec;
+
+use crate::emit::Generator;
+pub use crate::error::CodegenError;
+
+/// Generates the source of the public function `fn_name` applying the specification.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::TransformSpec;
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id"}}
+/// ]))
+/// .unwrap();
+///
+/// let source = fluvio_jolt_codegen::generate(&spec, "to_event").unwrap();
+///
+/// assert!(source.contains("pub fn to_event(input: serde_json::Value) -> serde_json::Value"));
+/// assert!(source.contains(r#""id" => write(out, &["data", "id"], value.clone()),"#));
+/// ```
+pub fn generate(spec: &TransformSpec, fn_name: &str) -> Result<String, CodegenError> {
+    if !is_identifier(fn_name) {
+        return Err(CodegenError::InvalidName(fn_name.to_string()));
+    }
+    if spec.has_expectations() {
+        return Err(CodegenError::Expectations);
+    }
+    if spec.has_envelope() {
+        return Err(CodegenError::Envelope);
+    }
+
+    let mut generator = Generator::default();
+    for (index, operation) in spec.iter().enumerate() {
+        generator.operation(index, operation)?;
+    }
+    Ok(generator.finish(fn_name, spec.fingerprint()))
+}
+
+// Whether the name is a Rust identifier that is not a keyword
+fn is_identifier(name: &str) -> bool {
+    const KEYWORDS: [&str; 38] = [
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ];
+
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && name != "_"
+        && !KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    fn spec(spec: serde_json::Value) -> TransformSpec {
+        serde_json::from_value(spec).expect("parsed spec")
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        let spec = spec(json!([]));
+
+        assert!(generate(&spec, "to_event").is_ok());
+        assert!(generate(&spec, "_to_event2").is_ok());
+        for name in ["", "_", "2to_event", "to-event", "match"] {
+            assert_eq!(
+                generate(&spec, name),
+                Err(CodegenError::InvalidName(name.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let unsupported = |spec: serde_json::Value| match generate(&self::spec(spec), "f") {
+            Err(CodegenError::Unsupported { index, reason }) => (index, reason),
+            other => panic!("unexpected result: {other:?}"),
+        };
+
+        assert_eq!(
+            unsupported(json!([
+                {"operation": "remove", "spec": {"a": ""}},
+                {"operation": "shift", "spec": {"items": {"*": "&(0)"}}}
+            ])),
+            (
+                1,
+                "only the literal keys of the `shift` are supported, found a key of kind \
+                 `wildcard`"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            unsupported(json!([{"operation": "shift", "spec": {"id": "ids[]"}}])),
+            (
+                0,
+                "only the output paths of literal keys are supported, found a segment of kind \
+                 `append`"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            unsupported(json!([
+                {"operation": "shift", "typed_captures": true, "spec": {"id": "id"}}
+            ])),
+            (0, "the `typed_captures` field is not supported".to_string())
+        );
+        assert_eq!(
+            unsupported(json!([{"operation": "shift", "spec": {"id!": "id"}}])),
+            (
+                0,
+                "the required key `id!` of the `shift` is not supported".to_string()
+            )
+        );
+        assert_eq!(
+            unsupported(json!([{"operation": "retain", "spec": {"id": ""}}])),
+            (0, "the `retain` operation is not supported".to_string())
+        );
+        assert_eq!(
+            generate(
+                &spec(json!({
+                    "expects": {"/id": "number"},
+                    "operations": [{"operation": "shift", "spec": {"id": "id"}}]
+                })),
+                "f"
+            ),
+            Err(CodegenError::Expectations)
+        );
+        assert_eq!(
+            generate(
+                &spec(json!({
+                    "envelope": {"schema_version": "1"},
+                    "operations": [{"operation": "shift", "spec": {"id": "id"}}]
+                })),
+                "f"
+            ),
+            Err(CodegenError::Envelope)
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::Path;
+
+use proptest::prelude::*;
+use serde_json::{json, Value};
+use fluvio_jolt::{transform, TransformSpec};
+
+mod generated {
+    include!("generated/mapping.rs");
+}
+
+fn mapping_spec() -> TransformSpec {
+    let spec =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/specs/mapping.json"))
+            .expect("spec file");
+    serde_json::from_str(&spec).expect("parsed spec")
+}
+
+// The checked-in function is generated from the spec, `JOLT_CODEGEN_BLESS=1` regenerates it
+#[test]
+fn generated_source_is_up_to_date() {
+    let source =
+        fluvio_jolt_codegen::generate(&mapping_spec(), "mapping").expect("generated source");
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/generated/mapping.rs");
+    if std::env::var_os("JOLT_CODEGEN_BLESS").is_some() {
+        fs::write(&path, &source).expect("written source");
+    }
+    assert_eq!(fs::read_to_string(&path).expect("generated file"), source);
+}
+
+#[test]
+fn generated_function_matches_transform() {
+    let spec = mapping_spec();
+    for input in [
+        json!({
+            "id": 1,
+            "user": {"name": "Ann", "email": "ann@example.com", "ignored": true},
+            "tags": ["a", "b"],
+            "status": "active",
+            "escaped.key": "e"
+        }),
+        json!({"a": 1, "b": {"x": 2}, "id": "x", "c": 3}),
+        json!({"c": 3, "id": 1}),
+        json!({"user": null, "status": "inactive", "tags": {"0": "first"}}),
+        json!({"id": null, "data": {"kind": "admin"}}),
+        json!([1, 2]),
+        json!("id"),
+        json!(null),
+    ] {
+        assert_eq!(
+            generated::mapping(input.clone()),
+            transform(input.clone(), &spec).unwrap(),
+            "{input}"
+        );
+    }
+}
+
+fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just("id".to_string()),
+        Just("user".to_string()),
+        Just("name".to_string()),
+        Just("email".to_string()),
+        Just("tags".to_string()),
+        Just("0".to_string()),
+        Just("status".to_string()),
+        Just("active".to_string()),
+        Just("a".to_string()),
+        Just("b".to_string()),
+        Just("c".to_string()),
+        Just("data".to_string()),
+        "[a-z]{1,3}",
+    ]
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        key().prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::vec((key(), inner), 0..5)
+                .prop_map(|entries| Value::Object(entries.into_iter().collect())),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn generated_function_is_transform(input in value()) {
+        let spec = mapping_spec();
+        prop_assert_eq!(
+            generated::mapping(input.clone()),
+            transform(input, &spec).unwrap()
+        );
+    }
+}
@@ -0,0 +1,129 @@
+/// Applies the specification with the fingerprint `0xeefc239990384485`.
+///
+/// Generated by `fluvio-jolt-codegen`, regenerate it instead of editing it.
+#[allow(clippy::all)]
+pub fn mapping(input: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    fn for_each_key(input: &Value, mut f: impl FnMut(&str, &Value)) {
+        match input {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    f(key, value);
+                }
+            }
+            Value::Array(arr) => {
+                for (idx, value) in arr.iter().enumerate() {
+                    f(&idx.to_string(), value);
+                }
+            }
+            Value::Bool(b) => f(if *b { "true" } else { "false" }, input),
+            Value::Number(n) => f(&n.to_string(), input),
+            Value::String(s) => f(s, input),
+            Value::Null => f("null", input),
+        }
+    }
+
+    fn write(mut out: &mut Value, path: &[&str], value: Value) {
+        for key in path {
+            if !out.is_object() {
+                *out = Value::Object(serde_json::Map::new());
+            }
+            out = match out {
+                Value::Object(map) => map.entry(*key).or_insert(Value::Null),
+                _ => return,
+            };
+        }
+        match out {
+            Value::Null => *out = value,
+            Value::Array(arr) => arr.push(value),
+            other => *other = Value::Array(vec![std::mem::take(other), value]),
+        }
+    }
+
+    fn insert(mut dest: &mut Value, path: &[&str], value: Value) {
+        for key in path {
+            dest = match dest {
+                Value::Object(map) => map
+                    .entry(*key)
+                    .or_insert_with(|| Value::Object(serde_json::Map::new())),
+                _ => return,
+            };
+        }
+        match (dest, value) {
+            (Value::Object(dest), Value::Object(src)) => dest.extend(src),
+            (dest, value) => *dest = value,
+        }
+    }
+
+    fn remove(value: &mut Value, parent: &str, key: &str) {
+        if let Some(Value::Object(map)) = value.pointer_mut(parent) {
+            map.retain(|k, _| k != key);
+        }
+    }
+
+    fn shift_0_1(input: &Value, out: &mut Value) {
+        for_each_key(input, |key, value| match key {
+            "name" => {
+                write(out, &["data", "name"], value.clone());
+                write(out, &["names"], value.clone());
+            }
+            "email" => write(out, &["data", "contact", "email"], value.clone()),
+            _ => {}
+        });
+    }
+
+    fn shift_0_2(input: &Value, out: &mut Value) {
+        for_each_key(input, |key, value| match key {
+            "0" => write(out, &["data", "first_tag"], value.clone()),
+            _ => {}
+        });
+    }
+
+    fn shift_0_3(input: &Value, out: &mut Value) {
+        for_each_key(input, |key, value| match key {
+            "active" => write(out, &["data", "active"], value.clone()),
+            _ => {}
+        });
+    }
+
+    fn shift_0(input: &Value, out: &mut Value) {
+        for_each_key(input, |key, value| match key {
+            "id" => write(out, &["data", "id"], value.clone()),
+            "user" => shift_0_1(value, out),
+            "tags" => shift_0_2(value, out),
+            "status" => shift_0_3(value, out),
+            "a" => write(out, &["data", "merged"], value.clone()),
+            "b" => write(out, &["data", "merged"], value.clone()),
+            "c" => write(out, &["data", "id", "nested"], value.clone()),
+            "escaped.key" => write(out, &["data", "escaped"], value.clone()),
+            _ => {}
+        });
+    }
+
+    let mut value = input;
+    // operation 0: shift "move"
+    let mut out = Value::Null;
+    shift_0(&value, &mut out);
+    value = out;
+    // operation 1: default
+    if value.pointer("/data/kind").is_none() {
+        insert(&mut value, &["data", "kind"], Value::from("user"));
+    }
+    if value.pointer("/data/version").is_none() {
+        insert(&mut value, &["data", "version"], Value::from(2u64));
+    }
+    if value.pointer("/data/ratio").is_none() {
+        insert(&mut value, &["data", "ratio"], Value::from(0.5f64));
+    }
+    if value.pointer("/data/flags/0").is_none() {
+        insert(&mut value, &["data", "flags", "0"], Value::Bool(true));
+    }
+    if value.pointer("/data/flags/1").is_none() {
+        insert(&mut value, &["data", "flags", "1"], Value::Null);
+    }
+    // operation 2: remove, disabled
+    // operation 3: remove
+    remove(&mut value, "/data", "contact");
+    value
+}
@@ -1,11 +1,102 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use serde_json::Value;
+//! Benchmarks of the transformations.
+//!
+//! The suites run generated inputs of growing sizes through heavy wildcard, deeply nested and
+//! large array `shift` specs, and the `shift` test corpus ported from the Java library.
+//!
+//! Besides the timings, the allocations of a single run of every benchmark are
+//! compared with the baseline file `benches/baseline.json`, a JSON object mapping the benchmark
+//! names to their allocations. An increase is reported as a regression at the end of the run.
+//! The allocations don't depend on the machine, so the baseline is versioned with the code, and
+//! it is rewritten with:
+//! ```text
+//! JOLT_BENCH_BASELINE=save cargo bench
+//! ```
+//! The timings are compared with the baselines of criterion, e.g. `cargo bench -- --save-baseline
+//! main` on the main branch and `cargo bench -- --baseline main` on a change.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use criterion::{black_box, BenchmarkId, Criterion, Throughput};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
 use fluvio_jolt::{transform, TransformSpec};
 
+// Counts the allocations to report the allocations of a single transformation
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const BASELINE_PATH: &str = "benches/baseline.json";
+const JAVA_SHIFT_CORPUS: &str = "tests/java/resources/shift";
+
+// Allocations of a single run of every benchmark
+static MEASURED: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+// Test data of the corpus, the spec of the `shift` test data is a bare `shift` spec
+#[derive(Deserialize)]
+struct TestData {
+    input: Value,
+    spec: Value,
+}
+
+impl TestData {
+    fn transform_spec(&self) -> TransformSpec {
+        match &self.spec {
+            Value::Object(_) => {
+                serde_json::from_value(json!([{"operation": "shift", "spec": self.spec}]))
+            }
+            spec => serde_json::from_value(spec.clone()),
+        }
+        .expect("parsed transform spec")
+    }
+}
+
+// Records the allocations of transforming every input with its spec
+fn measure_allocations(name: &str, cases: &[(Value, TransformSpec)]) {
+    // the first transformation initializes the tables shared by the transformations
+    for (input, spec) in cases {
+        transform(input.clone(), spec).expect("transformed input");
+    }
+    let inputs: Vec<Value> = cases.iter().map(|(input, _)| input.clone()).collect();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let outputs: Vec<_> = inputs
+        .into_iter()
+        .zip(cases)
+        .map(|(input, (_, spec))| transform(input, spec))
+        .collect();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(outputs);
+
+    MEASURED
+        .lock()
+        .expect("measured allocations")
+        .insert(name.to_owned(), allocations);
+}
+
 pub fn transform_benchmark(c: &mut Criterion) {
     let spec: TransformSpec =
         serde_json::from_str(include_str!("spec.json")).expect("parsed transform spec");
     let input: Value = serde_json::from_str(include_str!("input.json")).expect("parsed spec");
+    measure_allocations("default op", &[(input.clone(), spec.clone())]);
     c.bench_function("default op", |b| {
         b.iter_with_large_setup(
             || input.clone(),
@@ -14,5 +105,266 @@ pub fn transform_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, transform_benchmark);
-criterion_main!(benches);
+pub fn corpus_benchmark(c: &mut Criterion) {
+    let corpus = [
+        (
+            "shift_wildcards",
+            include_str!("../tests/data/shift_wildcards.json"),
+        ),
+        (
+            "shift array",
+            include_str!("../tests/data/shift/array.json"),
+        ),
+    ];
+    for (name, data) in corpus {
+        let data: TestData = serde_json::from_str(data).expect("parsed test data");
+        let spec = data.transform_spec();
+        let input = data.input;
+
+        measure_allocations(name, &[(input.clone(), spec.clone())]);
+        c.bench_function(name, |b| {
+            b.iter_with_large_setup(
+                || input.clone(),
+                |input| transform(black_box(input), black_box(&spec)),
+            )
+        });
+    }
+}
+
+// Runs the generated inputs of every size through the spec as a group of benchmarks
+fn bench_sizes(
+    c: &mut Criterion,
+    group_name: &str,
+    sizes: &[usize],
+    case: impl Fn(usize) -> (Value, Value),
+) {
+    let mut group = c.benchmark_group(group_name);
+    for &size in sizes {
+        let (input, spec) = case(size);
+        let spec: TransformSpec =
+            serde_json::from_value(json!([{"operation": "shift", "spec": spec}]))
+                .expect("parsed transform spec");
+
+        measure_allocations(
+            &format!("{group_name}/{size}"),
+            &[(input.clone(), spec.clone())],
+        );
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter_with_large_setup(
+                || input.clone(),
+                |input| transform(black_box(input), black_box(&spec)),
+            )
+        });
+    }
+    group.finish();
+}
+
+// Keys matched by several wildcards and alternatives, the captures are written to composite keys
+fn wildcards_case(keys: usize) -> (Value, Value) {
+    let records: Map<String, Value> = (0..keys)
+        .map(|i| {
+            let fields = json!({
+                "field_a": i,
+                "field_b": format!("value {i}"),
+                "other_c": i % 2 == 0,
+                "ignored": null,
+            });
+            (format!("user-{i}-{}", i % 7), fields)
+        })
+        .collect();
+    let input = json!({ "records": records });
+    let spec = json!({
+        "records": {
+            "*-*-*": {
+                "field_*|other_*": "out.&(1,3).&(1,2)_&(0,1)",
+                "$": "ids[]"
+            }
+        }
+    });
+    (input, spec)
+}
+
+// Objects nested to the depth, every level is matched by a wildcard
+fn nesting_case(depth: usize) -> (Value, Value) {
+    let mut input = json!({ "value": depth, "name": "leaf" });
+    let mut spec = json!({ "value": "out.&(1).&(0)", "name": "names[]" });
+    for level in (0..depth).rev() {
+        input = json!({ format!("level{level}"): input, "sibling": level });
+        spec = json!({ "level*": spec, "sibling": "siblings[]" });
+    }
+    (input, spec)
+}
+
+// Hundreds of prefix and suffix wildcards, every key of the input is matched by one of the last
+fn affixes_case(lines: usize) -> (Value, Value) {
+    let input: Map<String, Value> = (0..lines)
+        .map(|i| (format!("field_{i}_value"), json!(i)))
+        .collect();
+    let spec: Map<String, Value> = (0..lines)
+        .flat_map(|i| {
+            [
+                (format!("prefix_{i}_*"), json!("prefixes.&(0,1)")),
+                (format!("*_suffix_{i}"), json!("suffixes.&(0,1)")),
+            ]
+        })
+        .chain([("field_*".to_owned(), json!("fields.&(0,1)"))])
+        .collect();
+    (Value::Object(input), Value::Object(spec))
+}
+
+// Elements of an array moved to several arrays of the output
+fn array_case(len: usize) -> (Value, Value) {
+    let items: Vec<Value> = (0..len)
+        .map(|i| json!({ "id": i, "name": format!("item {i}"), "tags": ["a", "b"] }))
+        .collect();
+    let input = json!({ "items": items });
+    let spec = json!({
+        "items": {
+            "*": {
+                "id": "ids[]",
+                "name": "items[&(1)].name",
+                "tags": { "*": "items[&(2)].tags[]" }
+            }
+        }
+    });
+    (input, spec)
+}
+
+pub fn wildcards_benchmark(c: &mut Criterion) {
+    bench_sizes(c, "shift wildcards", &[10, 100, 1000], wildcards_case);
+}
+
+pub fn affixes_benchmark(c: &mut Criterion) {
+    bench_sizes(c, "shift affixes", &[100, 500], affixes_case);
+}
+
+pub fn nesting_benchmark(c: &mut Criterion) {
+    bench_sizes(c, "shift nesting", &[8, 32, 64], nesting_case);
+}
+
+pub fn large_array_benchmark(c: &mut Criterion) {
+    bench_sizes(c, "shift large array", &[100, 1000, 10000], array_case);
+}
+
+// Removes the `//` comments of the Java test files
+fn strip_comments(contents: &str) -> String {
+    contents
+        .lines()
+        .map(|line| {
+            let mut in_str = false;
+            let mut prev = None;
+            for (idx, c) in line.char_indices() {
+                match c {
+                    '"' if prev != Some('\\') => in_str = !in_str,
+                    '/' if !in_str && prev == Some('/') => return &line[..idx - 1],
+                    _ => (),
+                }
+                prev = Some(c);
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Transforms every test case of the Java `shift` corpus in a single benchmark
+pub fn java_corpus_benchmark(c: &mut Criterion) {
+    let mut paths: Vec<_> = fs::read_dir(JAVA_SHIFT_CORPUS)
+        .expect("java shift corpus")
+        .map(|entry| entry.expect("corpus entry").path())
+        .collect();
+    paths.sort();
+    let cases: Vec<(Value, TransformSpec)> = paths
+        .iter()
+        .map(|path| {
+            let contents = fs::read_to_string(path).expect("corpus file");
+            let data: TestData = serde_json::from_str(&strip_comments(&contents))
+                .unwrap_or_else(|e| panic!("failed to parse {}: {e}", path.display()));
+            let spec = data.transform_spec();
+            (data.input, spec)
+        })
+        .collect();
+
+    let name = "java shift corpus";
+    measure_allocations(name, &cases);
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(cases.len() as u64));
+    group.bench_function("all", |b| {
+        b.iter_with_large_setup(
+            || {
+                cases
+                    .iter()
+                    .map(|(input, _)| input.clone())
+                    .collect::<Vec<_>>()
+            },
+            |inputs| {
+                inputs
+                    .into_iter()
+                    .zip(&cases)
+                    .map(|(input, (_, spec))| transform(black_box(input), black_box(spec)))
+                    .collect::<Vec<_>>()
+            },
+        )
+    });
+    group.finish();
+}
+
+// Compares the measured allocations with the baseline, or saves them as the new baseline
+fn check_baseline() {
+    let measured = MEASURED.lock().expect("measured allocations");
+    if measured.is_empty() {
+        return;
+    }
+
+    if std::env::var("JOLT_BENCH_BASELINE").as_deref() == Ok("save") {
+        let baseline = serde_json::to_string_pretty(&*measured).expect("serialized baseline");
+        fs::write(BASELINE_PATH, baseline + "\n").expect("saved baseline");
+        println!("saved the allocations baseline to {BASELINE_PATH}");
+        return;
+    }
+
+    let baseline: BTreeMap<String, usize> = match fs::read_to_string(BASELINE_PATH) {
+        Ok(baseline) => serde_json::from_str(&baseline).expect("parsed baseline"),
+        Err(_) => {
+            println!("no allocations baseline at {BASELINE_PATH}");
+            BTreeMap::new()
+        }
+    };
+
+    println!("allocations of a run of the benchmarks:");
+    let mut regressions = 0;
+    for (name, &allocations) in measured.iter() {
+        match baseline.get(name) {
+            Some(&base) if allocations > base => {
+                regressions += 1;
+                println!("  {name}: {allocations} (regressed from {base})");
+            }
+            Some(&base) if allocations < base => {
+                println!("  {name}: {allocations} (improved from {base})")
+            }
+            Some(_) => println!("  {name}: {allocations}"),
+            None => println!("  {name}: {allocations} (not in the baseline)"),
+        }
+    }
+    if regressions > 0 {
+        println!("{regressions} benchmarks allocate more than the baseline");
+    }
+}
+
+criterion::criterion_group!(
+    benches,
+    transform_benchmark,
+    corpus_benchmark,
+    wildcards_benchmark,
+    affixes_benchmark,
+    nesting_benchmark,
+    large_array_benchmark,
+    java_corpus_benchmark
+);
+
+fn main() {
+    benches();
+    check_baseline();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,107 @@
+#![no_main]
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use serde_json::{Map, Value};
+use fluvio_jolt::{transform, TransformSpec};
+
+const MAX_DEPTH: usize = 4;
+const MAX_LEN: usize = 4;
+
+// keys are picked from the DSL expressions to reach the evaluation more often than random strings
+const KEYS: [&str; 24] = [
+    "a", "b", "0", "1", "*", "a*", "*b", "a|b", "&", "&(1)", "&(1,1)", "$", "$(1)", "@(1,a)",
+    "#x", "a.b", "a[]", "a[0]", "a[&(1)]", "a[#2]", "&(0).a", "@(2,b)", "x|y.z", "",
+];
+
+#[derive(Debug)]
+struct Input {
+    spec: Value,
+    input: Value,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(1..=3)?;
+        let mut operations = Vec::with_capacity(len);
+        for _ in 0..len {
+            operations.push(operation(u)?);
+        }
+        Ok(Self {
+            spec: Value::Array(operations),
+            input: value(u, 0)?,
+        })
+    }
+}
+
+fn operation(u: &mut Unstructured<'_>) -> Result<Value> {
+    let mut op = Map::new();
+    let name = *u.choose(&["shift", "default", "remove", "retain", "identity"])?;
+    op.insert("operation".into(), name.into());
+    let spec = match name {
+        "identity" => Value::Object(Map::new()),
+        _ => object(u, 0)?,
+    };
+    op.insert("spec".into(), spec);
+    Ok(Value::Object(op))
+}
+
+fn key(u: &mut Unstructured<'_>) -> Result<String> {
+    if u.ratio(1, 4)? {
+        String::arbitrary(u)
+    } else {
+        Ok(u.choose(&KEYS)?.to_string())
+    }
+}
+
+fn object(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let len = u.int_in_range(0..=MAX_LEN)?;
+    let mut obj = Map::new();
+    for _ in 0..len {
+        obj.insert(key(u)?, value(u, depth + 1)?);
+    }
+    Ok(Value::Object(obj))
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let kind = if depth >= MAX_DEPTH {
+        u.int_in_range(0..=3)?
+    } else {
+        u.int_in_range(0..=5)?
+    };
+    let value = match kind {
+        0 => Value::Null,
+        1 => Value::Bool(bool::arbitrary(u)?),
+        2 => Value::from(i64::arbitrary(u)? % 8),
+        3 => Value::String(key(u)?),
+        4 => object(u, depth)?,
+        _ => {
+            let len = u.int_in_range(0..=MAX_LEN)?;
+            let mut arr = Vec::with_capacity(len);
+            for _ in 0..len {
+                arr.push(value(u, depth + 1)?);
+            }
+            Value::Array(arr)
+        }
+    };
+    Ok(value)
+}
+
+// Runs the whole pipeline on generated specs and inputs. Run with `-rss_limit_mb` to catch
+// unbounded memory growth, e.g. `cargo fuzz run transform -- -rss_limit_mb=512`.
+fuzz_target!(|data: Input| {
+    let spec: TransformSpec = match serde_json::from_value(data.spec) {
+        Ok(spec) => spec,
+        Err(_) => return,
+    };
+
+    if let Ok(output) = transform(data.input, &spec) {
+        let serialized = serde_json::to_string(&output).expect("serialized output");
+        let parsed: Value = serde_json::from_str(&serialized).expect("parsed output");
+        assert_eq!(parsed, output);
+        assert_eq!(
+            serde_json::to_string(&parsed).expect("serialized output"),
+            serialized
+        );
+    }
+});
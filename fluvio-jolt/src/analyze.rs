@@ -0,0 +1,551 @@
+//! Diagnostics of a specification document, located by byte ranges of the document, for the
+//! language servers and the editor plugins of the specifications.
+//!
+//! ```
+//! use fluvio_jolt::{analyze_spec, Severity};
+//!
+//! let text = r#"[{"operation": "shift", "spec": {"a": "b[&1]"}}]"#;
+//! let diagnostics = analyze_spec(text);
+//!
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].severity, Severity::Error);
+//! assert_eq!(&text[diagnostics[0].range.clone()], "1");
+//! ```
+
+use std::ops::Range;
+
+use serde_json::Value;
+
+use crate::constants::Constants;
+use crate::dsl::{IndexOp, Lhs, ParseError, Rhs, RhsEntry, RhsPart};
+use crate::spec_path::SpecPath;
+use crate::{strict, Operation, TransformSpec};
+
+/// A finding of [`analyze_spec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Byte range of the document the finding is about.
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The specification fails to parse.
+    Error,
+    /// The specification parses, but it is likely not what was meant, e.g. an unknown field that
+    /// is ignored or a reference that fails at runtime.
+    Warning,
+}
+
+/// Analyzes the text of a specification, reporting the invalid JSON, the unknown operations and
+/// fields, the expressions failing to parse and the references out of the levels of a `shift`
+/// spec. The diagnostics are ordered by their position in the document.
+pub fn analyze_spec(text: &str) -> Vec<Diagnostic> {
+    let value: Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => {
+            let pos = line_col_offset(text, e.line(), e.column());
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                range: pos..pos,
+                message: e.to_string(),
+            }];
+        }
+    };
+    let root = Scanner { text, pos: 0 }.node();
+
+    let mut diagnostics = Vec::new();
+    let (operations, constants, strict) = match (&root.kind, &value) {
+        (NodeKind::Array(nodes), Value::Array(raws)) => {
+            (nodes.iter().zip(raws).collect(), Constants::new(), false)
+        }
+        (NodeKind::Object(members), Value::Object(obj)) => {
+            let operations = match (member(members, "operations"), obj.get("operations")) {
+                (
+                    Some(Node {
+                        kind: NodeKind::Array(nodes),
+                        ..
+                    }),
+                    Some(Value::Array(raws)),
+                ) => nodes.iter().zip(raws).collect(),
+                _ => Vec::new(),
+            };
+            let constants = obj
+                .get("constants")
+                .and_then(|constants| serde_json::from_value(constants.clone()).ok())
+                .unwrap_or_default();
+            let strict = obj.get("strict").and_then(Value::as_bool).unwrap_or(false);
+            (operations, constants, strict)
+        }
+        _ => (Vec::new(), Constants::new(), false),
+    };
+    for (node, raw) in operations {
+        analyze_operation(text, node, raw, &constants, strict, &mut diagnostics);
+    }
+
+    // the errors not attributed to a position, e.g. the unknown fields of the chain
+    let has_errors = diagnostics.iter().any(|d| d.severity == Severity::Error);
+    if let (false, Err(e)) = (has_errors, serde_json::from_value::<TransformSpec>(value)) {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            range: root.span.clone(),
+            message: e.to_string(),
+        });
+    }
+
+    diagnostics.sort_by_key(|d| (d.range.start, d.range.end));
+    diagnostics
+}
+
+fn analyze_operation(
+    text: &str,
+    node: &Node,
+    raw: &Value,
+    constants: &Constants,
+    strict: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (NodeKind::Object(members), Value::Object(obj)) = (&node.kind, raw) else {
+        return;
+    };
+    let errors = diagnostics.len();
+
+    let severity = if strict {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    for (key, message) in strict::unknown_fields(obj) {
+        if let Some(m) = members.iter().find(|m| m.key == key) {
+            diagnostics.push(Diagnostic {
+                severity,
+                range: m.key_span.clone(),
+                message,
+            });
+        }
+    }
+
+    let kind = obj.get("operation").and_then(Value::as_str);
+    if let (Some(kind), Some(node)) = (kind, member(members, "operation")) {
+        if let Err(message) = strict::check_kind(kind) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                range: node.span.clone(),
+                message,
+            });
+        }
+    }
+
+    if let (Some("shift"), Some(spec)) = (kind, member(members, "spec")) {
+        analyze_shift(text, spec, &mut Vec::new(), diagnostics);
+    }
+
+    let located = diagnostics[errors..]
+        .iter()
+        .any(|d| d.severity == Severity::Error);
+    if let (false, Err(e)) = (located, Operation::from_raw(raw.clone(), constants, strict)) {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            range: node.span.clone(),
+            message: e.to_string(),
+        });
+    }
+}
+
+// Parses the keys and the output paths of the spec, `path` being the keys leading to it
+fn analyze_shift<'a>(
+    text: &str,
+    spec: &'a Node,
+    path: &mut Vec<&'a str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let NodeKind::Object(members) = &spec.kind else {
+        return;
+    };
+    for m in members {
+        // the constants are substituted in the `#` literals before they are parsed
+        if m.key.starts_with('#') && m.key.contains("${") {
+            continue;
+        }
+        let lhs = match Lhs::parse_marked(&m.key) {
+            Ok((lhs, _)) => lhs,
+            Err(e) => {
+                diagnostics.push(parse_error(text, &m.key_span, &m.key, e));
+                continue;
+            }
+        };
+
+        path.push(&m.key);
+        // the keys leading to an invalid key are already reported
+        if let Ok(spec_path) = SpecPath::parse(path.iter()) {
+            if let Lhs::Amp(level, idx) | Lhs::DollarSign(level, idx) = lhs {
+                if let Err(e) = spec_path.check_lhs(level, idx) {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        range: m.key_span.clone(),
+                        message: format!("`{lhs}` fails at runtime: {e}"),
+                    });
+                }
+            }
+            match &m.value.kind {
+                NodeKind::Object(_) => analyze_shift(text, &m.value, path, diagnostics),
+                NodeKind::Array(nodes) => {
+                    for node in nodes {
+                        analyze_rhs(text, node, &spec_path, diagnostics);
+                    }
+                }
+                NodeKind::Scalar => analyze_rhs(text, &m.value, &spec_path, diagnostics),
+            }
+        }
+        path.pop();
+    }
+}
+
+fn analyze_rhs(text: &str, node: &Node, path: &SpecPath, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(expr) = node.string(text) else {
+        return;
+    };
+    // the constants are substituted before the output paths are parsed
+    if expr.contains("${") {
+        return;
+    }
+    let rhss = match Rhs::parse_many(&expr) {
+        Ok(rhss) => rhss,
+        Err(e) => {
+            diagnostics.push(parse_error(text, &node.span, &expr, e));
+            return;
+        }
+    };
+
+    let mut refs = Vec::new();
+    for part in rhss.iter().flat_map(|rhs| rhs.0.iter()) {
+        match part {
+            RhsPart::Key(entry) => refs.extend(entry_ref(entry)),
+            RhsPart::CompositeKey(entries) => refs.extend(entries.iter().filter_map(entry_ref)),
+            RhsPart::Index(
+                idx_op @ (IndexOp::Amp(level, idx) | IndexOp::DollarSign(level, idx)),
+            ) => refs.push((idx_op.to_string(), *level, *idx)),
+            RhsPart::Index(IndexOp::At(level, rhs)) => {
+                refs.push((format!("@({level},{rhs})"), *level, 0))
+            }
+            RhsPart::Index(_) => (),
+        }
+    }
+    for (reference, level, idx) in refs {
+        if let Err(e) = path.check_rhs(level, idx) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                range: node.span.clone(),
+                message: format!("`{reference}` fails at runtime: {e}"),
+            });
+        }
+    }
+}
+
+// The reference of the entry with its level and its match index, `@(N,path)` reading the whole
+// key of the level
+fn entry_ref(entry: &RhsEntry) -> Option<(String, usize, usize)> {
+    match entry {
+        RhsEntry::Amp(level, idx) | RhsEntry::AmpFormat(level, idx, _) => {
+            Some((entry.to_string(), *level, *idx))
+        }
+        RhsEntry::At(level, _) => Some((entry.to_string(), *level, 0)),
+        RhsEntry::Key(_) => None,
+    }
+}
+
+// The parse error of the expression of the JSON string at `span`, located at the character it
+// is found at
+fn parse_error(text: &str, span: &Range<usize>, expr: &str, e: ParseError) -> Diagnostic {
+    let pos = e.pos().min(expr.len());
+    let end = expr[pos..]
+        .chars()
+        .next()
+        .map_or(pos, |c| pos + c.len_utf8());
+    let mut message = e.cause.to_string();
+    if let Some(help) = &e.help {
+        message.push_str(&format!("; help: {help}"));
+    }
+    Diagnostic {
+        severity: Severity::Error,
+        range: string_offset(text, span, pos)..string_offset(text, span, end),
+        message,
+    }
+}
+
+// Offset in the document of the byte `pos` of the decoded JSON string at `span`
+fn string_offset(text: &str, span: &Range<usize>, pos: usize) -> usize {
+    let raw = &text[span.start + 1..span.end - 1];
+    let mut decoded = 0;
+    let mut chars = raw.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        if decoded >= pos {
+            return span.start + 1 + idx;
+        }
+        decoded += match c {
+            '\\' => match chars.next() {
+                Some((_, 'u')) => {
+                    let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    match u32::from_str_radix(&hex, 16) {
+                        // the high surrogate is followed by the `\uXXXX` of the low one
+                        Ok(0xD800..=0xDBFF) => {
+                            chars.by_ref().take(6).for_each(drop);
+                            4
+                        }
+                        Ok(code) => char::from_u32(code).map_or(3, char::len_utf8),
+                        Err(_) => 1,
+                    }
+                }
+                _ => 1,
+            },
+            c => c.len_utf8(),
+        };
+    }
+    span.end - 1
+}
+
+// Byte offset of the 1-based line and column reported by `serde_json`
+fn line_col_offset(text: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + column.saturating_sub(1)).min(text.len())
+}
+
+fn member<'a>(members: &'a [Member], key: &str) -> Option<&'a Node> {
+    members.iter().find(|m| m.key == key).map(|m| &m.value)
+}
+
+// A JSON value of the document with its byte range
+#[derive(Debug)]
+struct Node {
+    span: Range<usize>,
+    kind: NodeKind,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Object(Vec<Member>),
+    Array(Vec<Node>),
+    Scalar,
+}
+
+#[derive(Debug)]
+struct Member {
+    key: String,
+    key_span: Range<usize>,
+    value: Node,
+}
+
+impl Node {
+    // The decoded string if the value is a string
+    fn string(&self, text: &str) -> Option<String> {
+        text[self.span.clone()]
+            .starts_with('"')
+            .then(|| serde_json::from_str(&text[self.span.clone()]).ok())
+            .flatten()
+    }
+}
+
+// Records the byte ranges of the values of a document already validated by `serde_json`
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    // Skips the whitespace and the given byte
+    fn skip(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn node(&mut self) -> Node {
+        self.skip_whitespace();
+        let start = self.pos;
+        let kind = match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                while !self.skip(b'}') && self.peek().is_some() {
+                    self.skip(b',');
+                    self.skip_whitespace();
+                    let key_span = self.string();
+                    let key =
+                        serde_json::from_str(&self.text[key_span.clone()]).unwrap_or_default();
+                    self.skip(b':');
+                    let value = self.node();
+                    members.push(Member {
+                        key,
+                        key_span,
+                        value,
+                    });
+                }
+                NodeKind::Object(members)
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut nodes = Vec::new();
+                while !self.skip(b']') && self.peek().is_some() {
+                    self.skip(b',');
+                    nodes.push(self.node());
+                }
+                NodeKind::Array(nodes)
+            }
+            Some(b'"') => {
+                self.string();
+                NodeKind::Scalar
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|b| !matches!(b, b',' | b']' | b'}') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                NodeKind::Scalar
+            }
+        };
+        Node {
+            span: start..self.pos,
+            kind,
+        }
+    }
+
+    // The range of the string at the position, including the quotes
+    fn string(&mut self) -> Range<usize> {
+        let start = self.pos;
+        self.pos += 1;
+        while let Some(b) = self.peek() {
+            self.pos += if b == b'\\' { 2 } else { 1 };
+            if b == b'"' {
+                break;
+            }
+        }
+        start..self.pos.min(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn located(text: &str) -> Vec<(Severity, &str, String)> {
+        analyze_spec(text)
+            .into_iter()
+            .map(|d| (d.severity, &text[d.range], d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_analyze_valid() {
+        assert!(analyze_spec(r#"[{"operation": "shift", "spec": {"*": "&(0)"}}]"#).is_empty());
+        assert!(analyze_spec(
+            r#"{"constants": {"p": "x"}, "operations": [
+                {"operation": "shift", "spec": {"a": "${p}.b"}}
+            ]}"#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_analyze_invalid_json() {
+        let diagnostics = analyze_spec("[\n  {\"operation\": }\n]");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range, 18..18);
+    }
+
+    #[test]
+    fn test_analyze_operations() {
+        let diagnostics = located(
+            r#"[
+                {"operation": "shfit", "spec": {}},
+                {"operation": "default", "replace_nul": true, "spec": {}}
+            ]"#,
+        );
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    Severity::Error,
+                    r#""shfit""#,
+                    "unknown operation `shfit`, did you mean `shift`?".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    r#""replace_nul""#,
+                    "unknown field `replace_nul` of operation `default`, did you mean \
+                     `replace_null`?"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_expressions() {
+        let diagnostics = located(
+            r#"[{"operation": "shift", "spec": {
+                "user.name": "name",
+                "é\"*": {"*": ["a.&(3)", "b[0"]}
+            }}]"#,
+        );
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].0, Severity::Error);
+        assert_eq!(diagnostics[0].1, ".");
+        assert!(diagnostics[0].2.contains("escape them: `user\\.name`"));
+        assert_eq!(diagnostics[1].0, Severity::Warning);
+        assert_eq!(diagnostics[1].1, r#""a.&(3)""#);
+        assert_eq!(
+            diagnostics[1].2,
+            "`&(3,0)` fails at runtime: Path index out of range when using wildcard. \
+             Index=3;Length=3;"
+        );
+        assert_eq!(diagnostics[2].0, Severity::Error);
+        assert_eq!(diagnostics[2].1, "");
+    }
+
+    #[test]
+    fn test_analyze_unlocated() {
+        let diagnostics = located(r#"{"operations": [], "expect": {}}"#);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].1, r#"{"operations": [], "expect": {}}"#);
+        assert!(diagnostics[0].2.contains("unknown field `expect`"));
+    }
+
+    #[test]
+    fn test_string_offset() {
+        let text = r#""a\"\u00e9\ud83d\ude00b""#;
+        let span = 0..text.len();
+
+        assert_eq!(string_offset(text, &span, 0), 1);
+        assert_eq!(string_offset(text, &span, 1), 2);
+        assert_eq!(string_offset(text, &span, 2), 4);
+        assert_eq!(string_offset(text, &span, 4), 10);
+        assert_eq!(string_offset(text, &span, 8), 22);
+        assert_eq!(string_offset(text, &span, 9), 23);
+    }
+}
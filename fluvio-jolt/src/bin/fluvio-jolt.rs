@@ -0,0 +1,272 @@
+//! Command line tools for the authors of specifications.
+//!
+//! `fluvio-jolt bench --spec spec.json --input data.ndjson [--repeat N]` replays the records of
+//! a JSON Lines file through the specification and reports the throughput, the latencies and the
+//! allocations of the transformations, to compare the cost of alternative formulations of a
+//! specification.
+//!
+//! `fluvio-jolt test <spec.json>...` runs the test cases of the `tests` blocks of the
+//! specifications, failing if any of them fails.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use fluvio_jolt::{transform, TransformSpec};
+
+const USAGE: &str = "\
+Usage: fluvio-jolt bench --spec <spec.json> --input <data.ndjson> [--repeat <N>]
+       fluvio-jolt test <spec.json>...
+
+Commands:
+  bench  Replays the records of a JSON Lines file through the specification and reports the
+         throughput, the latencies and the allocations of the transformations
+  test   Runs the test cases of the `tests` blocks of the specifications
+
+Options:
+  --spec <FILE>   Specification of the transformation
+  --input <FILE>  Input records, one JSON document per line
+  --repeat <N>    Number of times the records are replayed [default: 1]";
+
+// Counts the allocations of the process, to report the allocations of the transformations
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[derive(Debug, PartialEq)]
+struct BenchArgs {
+    spec: String,
+    input: String,
+    repeat: usize,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, args)) if command == "bench" => parse_bench_args(args).and_then(bench),
+        Some((command, specs)) if command == "test" => test(specs),
+        Some((help, _)) if help == "--help" || help == "-h" => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some((command, _)) => Err(format!("unknown command `{command}`\n\n{USAGE}")),
+        None => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_bench_args(args: &[String]) -> Result<BenchArgs, String> {
+    let mut spec = None;
+    let mut input = None;
+    let mut repeat = 1;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value of `{arg}`\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--spec" => spec = Some(value()?),
+            "--input" => input = Some(value()?),
+            "--repeat" => {
+                repeat = value()?
+                    .parse()
+                    .map_err(|e| format!("invalid value of `--repeat`: {e}"))?;
+                if repeat == 0 {
+                    return Err("`--repeat` must be at least 1".to_string());
+                }
+            }
+            _ => return Err(format!("unknown option `{arg}`\n\n{USAGE}")),
+        }
+    }
+    Ok(BenchArgs {
+        spec: spec.ok_or_else(|| format!("missing option `--spec`\n\n{USAGE}"))?,
+        input: input.ok_or_else(|| format!("missing option `--input`\n\n{USAGE}"))?,
+        repeat,
+    })
+}
+
+fn bench(args: BenchArgs) -> Result<(), String> {
+    let spec = fs::read_to_string(&args.spec)
+        .map_err(|e| format!("failed to read `{}`: {e}", args.spec))?;
+    let spec: TransformSpec =
+        serde_json::from_str(&spec).map_err(|e| format!("invalid spec `{}`: {e}", args.spec))?;
+    let input = fs::read_to_string(&args.input)
+        .map_err(|e| format!("failed to read `{}`: {e}", args.input))?;
+    let records = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str::<Value>(line)
+                .map_err(|e| format!("invalid record at line {}: {e}", idx + 1))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if records.is_empty() {
+        return Err(format!("no records in `{}`", args.input));
+    }
+
+    let mut latencies = Vec::with_capacity(records.len() * args.repeat);
+    let mut failed = 0;
+    let mut allocations = 0;
+    let mut allocated_bytes = 0;
+    let start = Instant::now();
+    for _ in 0..args.repeat {
+        for record in &records {
+            // the copy of the record consumed by the transformation is not measured
+            let record = record.clone();
+            let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+            let bytes_before = ALLOCATED_BYTES.load(Ordering::Relaxed);
+            let transform_start = Instant::now();
+            let output = transform(record, &spec);
+            latencies.push(transform_start.elapsed());
+            allocations += ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+            allocated_bytes += ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes_before;
+            if output.is_err() {
+                failed += 1;
+            }
+            drop(output);
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let transforms = latencies.len();
+    let busy: Duration = latencies.iter().sum();
+    latencies.sort_unstable();
+    println!(
+        "records:     {} x {} ({failed} failed)",
+        records.len(),
+        args.repeat
+    );
+    println!(
+        "throughput:  {:.0} records/s ({:.0} records/s with the copies of the records)",
+        transforms as f64 / busy.as_secs_f64(),
+        transforms as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "latency:     p50 {:?}, p99 {:?}, max {:?}",
+        percentile(&latencies, 50),
+        percentile(&latencies, 99),
+        latencies.last().copied().unwrap_or_default()
+    );
+    println!(
+        "allocations: {:.1} per record, {:.0} bytes per record",
+        allocations as f64 / transforms as f64,
+        allocated_bytes as f64 / transforms as f64
+    );
+    Ok(())
+}
+
+fn test(specs: &[String]) -> Result<(), String> {
+    if specs.is_empty() {
+        return Err(format!("missing specifications to test\n\n{USAGE}"));
+    }
+    let mut failed = 0;
+    for path in specs {
+        let spec = fs::read_to_string(path).map_err(|e| format!("failed to read `{path}`: {e}"))?;
+        let spec: TransformSpec =
+            serde_json::from_str(&spec).map_err(|e| format!("invalid spec `{path}`: {e}"))?;
+        match spec.run_embedded_tests() {
+            Ok(passed) => println!("{path}: {passed} passed"),
+            Err(failures) => {
+                println!("{path}: {} failed", failures.len());
+                for failure in &failures {
+                    println!("  {failure}");
+                }
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{failed} of {} specifications failed", specs.len()));
+    }
+    Ok(())
+}
+
+// The nearest-rank percentile of the sorted durations
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = (sorted.len() * percent).div_ceil(100);
+    sorted
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_bench_args() {
+        let args = |args: &[&str]| {
+            parse_bench_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(
+            args(&["--input", "data.ndjson", "--spec", "spec.json"]),
+            Ok(BenchArgs {
+                spec: "spec.json".to_string(),
+                input: "data.ndjson".to_string(),
+                repeat: 1,
+            })
+        );
+        assert_eq!(
+            args(&[
+                "--spec",
+                "spec.json",
+                "--input",
+                "data.ndjson",
+                "--repeat",
+                "5"
+            ])
+            .map(|args| args.repeat),
+            Ok(5)
+        );
+        assert!(args(&["--spec", "spec.json"]).is_err());
+        assert!(args(&["--spec"]).is_err());
+        assert!(args(&["--spec", "spec.json", "--input", "a", "--repeat", "0"]).is_err());
+        assert!(args(&["--spec", "spec.json", "--input", "a", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=200).map(Duration::from_micros).collect();
+
+        assert_eq!(percentile(&latencies, 50), Duration::from_micros(100));
+        assert_eq!(percentile(&latencies, 99), Duration::from_micros(198));
+        assert_eq!(percentile(&[], 99), Duration::ZERO);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{transform, Result, SpecChange, TransformSpec};
+
+/// Classification of a specification upgrade, ordered from the safest to the riskiest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    /// The outputs keep their fields and types.
+    Neutral,
+    /// The outputs gain fields, existing fields keep their types.
+    Additive,
+    /// The outputs may lose fields, have them renamed, change their types or be dropped.
+    Breaking,
+}
+
+/// The result of [`TransformSpec::compatibility`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatibilityReport {
+    /// The riskiest classification of all the reasons, `Neutral` without reasons.
+    pub compatibility: Compatibility,
+    /// Every change that is not neutral along with its classification.
+    pub reasons: Vec<(Compatibility, String)>,
+}
+
+pub(crate) fn compatibility(
+    old: &TransformSpec,
+    new: &TransformSpec,
+    samples: &[Value],
+) -> Result<CompatibilityReport> {
+    let mut reasons: Vec<(Compatibility, String)> = old
+        .diff(new)
+        .into_iter()
+        .map(|change| (classify(&change), change.to_string()))
+        .filter(|(compatibility, _)| *compatibility != Compatibility::Neutral)
+        .collect();
+
+    for (idx, sample) in samples.iter().enumerate() {
+        let old_fields = fields(&transform(sample.clone(), old)?);
+        let new_fields = fields(&transform(sample.clone(), new)?);
+        for (path, old_type) in old_fields.iter() {
+            match new_fields.get(path) {
+                None => reasons.push((
+                    Compatibility::Breaking,
+                    format!("sample {idx}: output field {path} removed"),
+                )),
+                Some(new_type) if new_type != old_type => reasons.push((
+                    Compatibility::Breaking,
+                    format!(
+                        "sample {idx}: output field {path} changed from {old_type} to {new_type}"
+                    ),
+                )),
+                Some(_) => {}
+            }
+        }
+        for path in new_fields
+            .keys()
+            .filter(|path| !old_fields.contains_key(*path))
+        {
+            reasons.push((
+                Compatibility::Additive,
+                format!("sample {idx}: output field {path} added"),
+            ));
+        }
+    }
+
+    Ok(CompatibilityReport {
+        compatibility: reasons
+            .iter()
+            .map(|(compatibility, _)| *compatibility)
+            .max()
+            .unwrap_or(Compatibility::Neutral),
+        reasons,
+    })
+}
+
+// Classifies a change of the specification by the effect of the operation on the output:
+// `shift` and `default` write fields, `remove` and `retain` drop them, `halt-if` and `sample`
+// drop documents, `identity` moves the whole document and `rename` moves all the fields
+fn classify(change: &SpecChange) -> Compatibility {
+    use Compatibility::*;
+
+    match change {
+        SpecChange::OperationAdded { operation, .. } => match operation.as_str() {
+            "default" => Additive,
+            _ => Breaking,
+        },
+        SpecChange::OperationRemoved { operation, .. } => match operation.as_str() {
+            "remove" | "halt-if" | "sample" => Additive,
+            _ => Breaking,
+        },
+        SpecChange::MappingAdded { operation, .. } => match operation.as_str() {
+            "shift" | "default" | "retain" => Additive,
+            _ => Breaking,
+        },
+        SpecChange::MappingRemoved { operation, .. } => match operation.as_str() {
+            "remove" => Additive,
+            _ => Breaking,
+        },
+        SpecChange::MappingModified {
+            operation,
+            from,
+            to,
+            ..
+        } => match operation.as_str() {
+            "default" if type_name(from) == type_name(to) => Neutral,
+            _ => Breaking,
+        },
+        SpecChange::OptionChanged { .. } => Breaking,
+    }
+}
+
+// The types of the scalars, empty objects and empty arrays of the output by their pointers,
+// array indexes are replaced by `*`
+fn fields(output: &Value) -> BTreeMap<String, &'static str> {
+    fn visit(path: String, value: &Value, out: &mut BTreeMap<String, &'static str>) {
+        match value {
+            Value::Object(obj) if !obj.is_empty() => {
+                for (key, value) in obj {
+                    let key = key.replace('~', "~0").replace('/', "~1");
+                    visit(format!("{path}/{key}"), value, out);
+                }
+            }
+            Value::Array(arr) if !arr.is_empty() => {
+                for value in arr {
+                    visit(format!("{path}/*"), value, out);
+                }
+            }
+            leaf => {
+                out.insert(path, type_name(leaf));
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    visit(String::new(), output, &mut out);
+    out
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    fn spec(spec: Value) -> TransformSpec {
+        serde_json::from_value(spec).expect("parsed spec")
+    }
+
+    #[test]
+    fn test_neutral() {
+        //given
+        let old = spec(json!([{"operation": "default", "spec": {"kind": "user"}}]));
+        let new = spec(json!([{"operation": "default", "spec": {"kind": "member"}}]));
+
+        //when
+        let report = old.compatibility(&new, &[json!({})]).unwrap();
+
+        //then
+        assert_eq!(report.compatibility, Compatibility::Neutral);
+        assert!(report.reasons.is_empty());
+    }
+
+    #[test]
+    fn test_additive() {
+        //given
+        let old = spec(json!([{"operation": "shift", "spec": {"id": "data.id"}}]));
+        let new =
+            spec(json!([{"operation": "shift", "spec": {"id": "data.id", "name": "data.name"}}]));
+
+        //when
+        let report = old
+            .compatibility(&new, &[json!({"id": 1, "name": "John"})])
+            .unwrap();
+
+        //then
+        assert_eq!(report.compatibility, Compatibility::Additive);
+        assert_eq!(
+            report.reasons,
+            vec![
+                (
+                    Compatibility::Additive,
+                    r#"operation 0 (shift): + name -> "data.name""#.to_string()
+                ),
+                (
+                    Compatibility::Additive,
+                    "sample 0: output field /data/name added".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_breaking() {
+        //given
+        let old = spec(json!([
+            {"operation": "shift", "spec": {"id": "data.id", "name": "data.name"}}
+        ]));
+        let new = spec(json!([
+            {"operation": "shift", "spec": {"id": "data.id[]", "name": "data.full_name"}}
+        ]));
+
+        //when
+        let report = old
+            .compatibility(&new, &[json!({"id": 1, "name": "John"})])
+            .unwrap();
+
+        //then
+        assert_eq!(report.compatibility, Compatibility::Breaking);
+        assert!(report.reasons.contains(&(
+            Compatibility::Breaking,
+            "sample 0: output field /data/name removed".to_string()
+        )));
+        assert!(report.reasons.contains(&(
+            Compatibility::Breaking,
+            "sample 0: output field /data/id removed".to_string()
+        )));
+    }
+}
@@ -0,0 +1,142 @@
+use serde_json::{Map, Value};
+
+use crate::dsl::escape_key;
+
+/// Constants of a chain or of an operation, referenced as `${name}` from the output paths and the
+/// `#` literals of the `shift` operations.
+pub(crate) type Constants = Map<String, Value>;
+
+/// Replaces the references to the constants in the output paths and the `#` literals of a
+/// `shift` spec.
+pub(crate) fn substitute(spec: &mut Value, constants: &Constants) -> Result<(), String> {
+    match spec {
+        Value::Object(obj) => {
+            for (key, mut value) in std::mem::take(obj) {
+                let key = if key.starts_with('#') {
+                    replace(&key, constants)?
+                } else {
+                    key
+                };
+                substitute(&mut value, constants)?;
+                obj.insert(key, value);
+            }
+        }
+        Value::Array(rhss) => {
+            for rhs in rhss {
+                substitute(rhs, constants)?;
+            }
+        }
+        Value::String(rhs) => *rhs = replace(rhs, constants)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+// Replaces the `${name}` references of the text with the values of the constants, escaped so
+// every value is a single key. The escaped characters are kept as they are, so `\${name}` is the
+// literal `${name}`.
+fn replace(text: &str, constants: &Constants) -> Result<String, String> {
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['\\', '$']) {
+        replaced.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if !rest.starts_with("${") {
+            // a `$` alone or a backslash with the character it escapes
+            let len = rest
+                .char_indices()
+                .nth(if rest.starts_with('\\') { 2 } else { 1 })
+                .map_or(rest.len(), |(idx, _)| idx);
+            replaced.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        let len = rest
+            .find('}')
+            .ok_or_else(|| format!("unterminated constant reference in `{text}`"))?;
+        let name = &rest[2..len];
+        let value = match constants.get(name) {
+            Some(Value::String(value)) => value.clone(),
+            Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+            Some(_) => {
+                return Err(format!(
+                    "constant `{name}` is not a string, a number or a boolean"
+                ))
+            }
+            None => return Err(format!("unknown constant `{name}`")),
+        };
+        replaced.push_str(&escape_key(&value));
+        rest = &rest[len + 1..];
+    }
+    replaced.push_str(rest);
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        //given
+        let constants: Constants = serde_json::from_value(json!({
+            "version": 2,
+            "target": "data.v1",
+            "kind": "user"
+        }))
+        .unwrap();
+        let mut spec = json!({
+            "id": "${target}.id",
+            "#${kind}": ["kind", "types.${kind}"],
+            "items": {"*": "items_v${version}[]"}
+        });
+
+        //when
+        let result = substitute(&mut spec, &constants);
+
+        //then
+        assert!(result.is_ok());
+        assert_eq!(
+            spec,
+            json!({
+                "id": "data\\.v1.id",
+                "#user": ["kind", "types.user"],
+                "items": {"*": "items_v2[]"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_escaped_references() {
+        //given
+        let constants: Constants = serde_json::from_value(json!({"x": "a"})).unwrap();
+        let mut spec = json!({
+            "#\\${x}": "literal",
+            "a": ["\\${x}", "\\\\${x}", "$", "price$"]
+        });
+
+        //when
+        let result = substitute(&mut spec, &constants);
+
+        //then
+        assert!(result.is_ok());
+        assert_eq!(
+            spec,
+            json!({
+                "#\\${x}": "literal",
+                "a": ["\\${x}", "\\\\a", "$", "price$"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_references() {
+        let constants: Constants = serde_json::from_value(json!({"tags": ["a"]})).unwrap();
+
+        assert!(substitute(&mut json!({"a": "${missing}"}), &constants).is_err());
+        assert!(substitute(&mut json!({"a": "${tags}"}), &constants).is_err());
+        assert!(substitute(&mut json!({"a": "${tags"}), &constants).is_err());
+    }
+}
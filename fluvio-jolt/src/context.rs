@@ -0,0 +1,284 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use serde_json::{Map, Value};
+
+/// Values shared by all operations of a transformation, like the context of the Java Jolt
+/// `Chainr`. The same context is passed to every operation of the chain.
+///
+/// The variables are read by the `@context(name)` expressions of the `default` operations with
+/// the `expressions` option:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_with_context, Context, TransformOptions, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "default", "expressions": true, "spec": {"region": "@context(region)"}}
+/// ]))
+/// .unwrap();
+/// let context = Context::default().variable("region", json!("eu-west"));
+///
+/// let output =
+///     transform_with_context(json!({"id": 1}), &spec, &TransformOptions::default(), &context);
+///
+/// assert_eq!(context.get("region"), Some(&json!("eu-west")));
+/// assert_eq!(output.unwrap(), json!({"id": 1, "region": "eu-west"}));
+/// ```
+///
+/// The operations drawing random numbers, like the `sample` operation with a `probability`, use
+/// the [`RandomSource`] of the context. It is seeded by the system unless it is set, a seeded
+/// context makes the transformations reproducible in tests and replays:
+/// ```
+/// use fluvio_jolt::Context;
+///
+/// let first = Context::default().seed(42);
+/// let second = Context::default().seed(42);
+///
+/// assert_eq!(first.next_random(), second.next_random());
+/// ```
+///
+/// Likewise, the operations reading the current time use the clock of the context, which is the
+/// system time unless it is set:
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use fluvio_jolt::Context;
+///
+/// let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// let context = Context::default().clock(move || time);
+///
+/// assert_eq!(context.now(), time);
+/// ```
+///
+/// The `sample` operations count the documents transformed with the context, so a stream of
+/// documents is transformed with the same context, e.g. by a [`JoltEngine`](crate::JoltEngine).
+/// The clones of the context share the counts, [`Context::new_stream`] counts from zero:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_filtered, Context, TransformOptions, TransformSpec};
+///
+/// let spec: TransformSpec =
+///     serde_json::from_value(json!([{"operation": "sample", "spec": {"every_n": 2}}])).unwrap();
+/// let options = TransformOptions::default();
+/// let context = Context::default();
+///
+/// let first = transform_filtered(json!(1), &spec, &options, &context).unwrap();
+/// let second = transform_filtered(json!(2), &spec, &options, &context).unwrap();
+/// let other = transform_filtered(json!(3), &spec, &options, &context.new_stream()).unwrap();
+///
+/// assert_eq!((first, second, other), (Some(json!(1)), None, Some(json!(3))));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Context {
+    variables: Map<String, Value>,
+    random: Option<Arc<dyn RandomSource>>,
+    clock: Option<Clock>,
+    samples: Arc<SampleCounts>,
+}
+
+// The number of documents counted by every `sample` operation, by the id of the operation
+#[derive(Debug, Default)]
+struct SampleCounts(Mutex<HashMap<u64, u64>>);
+
+impl Context {
+    /// Sets the value of the variable.
+    pub fn variable(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.variables.insert(name.into(), value);
+        self
+    }
+
+    /// Returns the value of the variable.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    /// Returns all variables.
+    pub fn variables(&self) -> &Map<String, Value> {
+        &self.variables
+    }
+
+    /// Sets the source of the random numbers drawn by the operations.
+    pub fn random_source(mut self, random: impl RandomSource + 'static) -> Self {
+        self.random = Some(Arc::new(random));
+        self
+    }
+
+    /// Draws the random numbers from a [`SeededRandom`] with the seed.
+    pub fn seed(self, seed: u64) -> Self {
+        self.random_source(SeededRandom::new(seed))
+    }
+
+    /// Returns the next random number of the source of the context, or of a source seeded by
+    /// the system if none is set.
+    pub fn next_random(&self) -> u64 {
+        match &self.random {
+            Some(random) => random.next_u64(),
+            None => SYSTEM_RANDOM.with(|random| random.next_u64()),
+        }
+    }
+
+    /// Sets the clock returning the current time to the operations.
+    pub fn clock(mut self, clock: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        self.clock = Some(Clock(Arc::new(clock)));
+        self
+    }
+
+    /// Returns the current time of the clock of the context, or the system time if none is set.
+    pub fn now(&self) -> SystemTime {
+        match &self.clock {
+            Some(Clock(clock)) => clock(),
+            None => SystemTime::now(),
+        }
+    }
+
+    /// Returns a copy of the context counting the documents of the `sample` operations from
+    /// zero, for another stream of documents. The variables, the random source and the clock are
+    /// kept.
+    pub fn new_stream(&self) -> Self {
+        Self {
+            samples: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Counts a document of the `sample` operation, returning the number of the documents it
+    /// counted before.
+    pub(crate) fn count_sample(&self, id: u64) -> u64 {
+        let mut counts = self
+            .samples
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let count = counts.entry(id).or_default();
+        *count += 1;
+        *count - 1
+    }
+
+    /// Returns a random number between 0 inclusive and 1 exclusive.
+    pub(crate) fn next_fraction(&self) -> f64 {
+        // the 53 high bits fill the mantissa
+        (self.next_random() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The contexts are equal when they have the same variables and share the same random source and
+/// clock, whatever the documents counted by the `sample` operations.
+impl PartialEq for Context {
+    fn eq(&self, other: &Self) -> bool {
+        let random = match (&self.random, &other.random) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        let clock = match (&self.clock, &other.clock) {
+            (Some(Clock(a)), Some(Clock(b))) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        self.variables == other.variables && random && clock
+    }
+}
+
+#[derive(Clone)]
+struct Clock(Arc<dyn Fn() -> SystemTime + Send + Sync>);
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+thread_local! {
+    static SYSTEM_RANDOM: SeededRandom = SeededRandom::new(RandomState::new().build_hasher().finish());
+}
+
+/// A source of random numbers of a [`Context`]. It is shared by the threads applying the
+/// transformations with the context, so it draws the numbers through a shared reference.
+pub trait RandomSource: fmt::Debug + Send + Sync {
+    /// Returns the next random number.
+    fn next_u64(&self) -> u64;
+}
+
+/// A [`RandomSource`] drawing the same sequence of numbers for the same seed, with the
+/// [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator. Not suitable for
+/// cryptography.
+#[derive(Debug)]
+pub struct SeededRandom {
+    state: AtomicU64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_u64(&self) -> u64 {
+        const GAMMA: u64 = 0x9e3779b97f4a7c15;
+        let mut z = self
+            .state
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_seeded_random() {
+        //given
+        let random = SeededRandom::new(1234567);
+
+        //when
+        let numbers: Vec<u64> = (0..3).map(|_| random.next_u64()).collect();
+
+        //then
+        // the reference outputs of SplitMix64
+        assert_eq!(
+            numbers,
+            vec![
+                6457827717110365317,
+                3203168211198807973,
+                9817491932198370423
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clock() {
+        //given
+        let time = SystemTime::UNIX_EPOCH;
+        let context = Context::default().clock(move || time);
+
+        //when
+        let cloned = context.clone();
+
+        //then
+        assert_eq!(cloned.now(), time);
+        assert_eq!(cloned, context);
+        assert_ne!(cloned, Context::default().clock(move || time));
+        assert!(Context::default().now() > time);
+    }
+
+    #[test]
+    fn test_next_fraction() {
+        let context = Context::default().seed(7);
+
+        assert!((0..1000)
+            .map(|_| context.next_fraction())
+            .all(|fraction| (0.0..1.0).contains(&fraction)));
+    }
+}
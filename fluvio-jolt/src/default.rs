@@ -1,16 +1,81 @@
+use serde::Deserialize;
 use serde_json::Value;
-use crate::insert;
+use crate::dsl::Lhs;
+use crate::{insert, Context};
+use crate::pointer::JsonPointer;
+use crate::shift::eval_default;
 use crate::spec::Spec;
 
-pub(crate) fn default(mut input: Value, spec: &Spec) -> Value {
+/// Controls which present values are considered missing by the `default` operation.
+/// Absent values are always considered missing.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DefaultOptions {
+    /// Apply default values to `null` values.
+    #[serde(default)]
+    pub(crate) replace_null: bool,
+    /// Apply default values to empty strings.
+    #[serde(default)]
+    pub(crate) replace_empty_string: bool,
+    /// Evaluate string values starting with `&` or `@` as expressions against the input, or
+    /// `@context(name)` as the variable of the context.
+    #[serde(default)]
+    pub(crate) expressions: bool,
+}
+
+impl DefaultOptions {
+    fn is_missing(&self, value: Option<&Value>) -> bool {
+        match value {
+            None => true,
+            Some(Value::Null) => self.replace_null,
+            Some(Value::String(s)) if s.is_empty() => self.replace_empty_string,
+            Some(_) => false,
+        }
+    }
+
+    // The value to write, none if the expression of the value can't be evaluated
+    fn value(
+        &self,
+        leaf: &Value,
+        input: &Value,
+        path: &JsonPointer,
+        context: &Context,
+    ) -> Option<Value> {
+        match leaf {
+            Value::String(expr) if self.expressions && expr.starts_with(['&', '@']) => {
+                if let Some(name) = context_variable(expr) {
+                    return context.get(name).cloned();
+                }
+                match Lhs::parse(expr) {
+                    Ok(lhs) => eval_default(&lhs, input, path),
+                    Err(_) => Some(leaf.clone()),
+                }
+            }
+            leaf => Some(leaf.clone()),
+        }
+    }
+}
+
+pub(crate) fn default(
+    mut input: Value,
+    spec: &Spec,
+    options: &DefaultOptions,
+    context: &Context,
+) -> Value {
     for (path, leaf) in spec.iter() {
-        if input.pointer(&path.join_rfc6901()).is_none() {
-            insert(&mut input, path, leaf.clone());
+        if options.is_missing(input.pointer(&path.join_rfc6901())) {
+            if let Some(value) = options.value(leaf, &input, &path, context) {
+                insert(&mut input, path, value);
+            }
         }
     }
     input
 }
 
+// The name of the variable of a `@context(name)` expression, which isn't a valid lookup
+fn context_variable(expr: &str) -> Option<&str> {
+    expr.strip_prefix("@context(")?.strip_suffix(')')
+}
+
 #[cfg(test)]
 mod test {
 
@@ -35,7 +100,12 @@ mod test {
         .expect("parsed spec");
 
         //when
-        let output = default(input, &spec);
+        let output = default(
+            input,
+            &spec,
+            &DefaultOptions::default(),
+            &Context::default(),
+        );
 
         //then
         assert_eq!(
@@ -66,7 +136,12 @@ mod test {
         .expect("parsed spec");
 
         //when
-        let output = default(input, &spec);
+        let output = default(
+            input,
+            &spec,
+            &DefaultOptions::default(),
+            &Context::default(),
+        );
 
         //then
         assert_eq!(
@@ -77,4 +152,168 @@ mod test {
             })
         )
     }
+
+    #[test]
+    fn test_skip_insert_if_null() {
+        //given
+        let spec: Spec = serde_json::from_value(json!({
+            "a" : "default_value",
+            "b" : "default_value"
+        }))
+        .expect("parsed spec");
+
+        let input: Value = serde_json::from_value(json!({
+            "a" : null,
+            "b" : ""
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = default(
+            input,
+            &spec,
+            &DefaultOptions::default(),
+            &Context::default(),
+        );
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "a" : null,
+                "b" : ""
+            })
+        )
+    }
+
+    #[test]
+    fn test_replace_null() {
+        //given
+        let spec: Spec = serde_json::from_value(json!({
+            "a" : "default_value",
+            "b" : "default_value"
+        }))
+        .expect("parsed spec");
+
+        let input: Value = serde_json::from_value(json!({
+            "a" : null,
+            "b" : ""
+        }))
+        .expect("parsed spec");
+        let options = DefaultOptions {
+            replace_null: true,
+            replace_empty_string: false,
+            expressions: false,
+        };
+
+        //when
+        let output = default(input, &spec, &options, &Context::default());
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "a" : "default_value",
+                "b" : ""
+            })
+        )
+    }
+
+    #[test]
+    fn test_replace_empty_string() {
+        //given
+        let spec: Spec = serde_json::from_value(json!({
+            "a" : "default_value",
+            "b" : "default_value"
+        }))
+        .expect("parsed spec");
+
+        let input: Value = serde_json::from_value(json!({
+            "a" : null,
+            "b" : ""
+        }))
+        .expect("parsed spec");
+        let options = DefaultOptions {
+            replace_null: false,
+            replace_empty_string: true,
+            expressions: false,
+        };
+
+        //when
+        let output = default(input, &spec, &options, &Context::default());
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "a" : null,
+                "b" : "default_value"
+            })
+        )
+    }
+
+    #[test]
+    fn test_expressions() {
+        //given
+        let spec: Spec = serde_json::from_value(json!({
+            "display_name": "@(1,first_name)",
+            "profile": {
+                "key": "&",
+                "email": "@(2,contact.email)",
+                "phone": "@(2,contact.phone)"
+            },
+            "literal": "@(1,first_name)"
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "first_name": "John",
+            "contact": {"email": "john@example.com"},
+            "literal": "present"
+        });
+        let options = DefaultOptions {
+            replace_null: false,
+            replace_empty_string: false,
+            expressions: true,
+        };
+
+        //when
+        let output = default(input, &spec, &options, &Context::default());
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "first_name": "John",
+                "contact": {"email": "john@example.com"},
+                "literal": "present",
+                "display_name": "John",
+                "profile": {
+                    "key": "key",
+                    "email": "john@example.com"
+                }
+            })
+        )
+    }
+
+    #[test]
+    fn test_expressions_disabled() {
+        //given
+        let spec: Spec = serde_json::from_value(json!({"display_name": "@(1,first_name)"}))
+            .expect("parsed spec");
+        let input = json!({"first_name": "John"});
+
+        //when
+        let output = default(
+            input,
+            &spec,
+            &DefaultOptions::default(),
+            &Context::default(),
+        );
+
+        //then
+        assert_eq!(
+            output,
+            json!({"first_name": "John", "display_name": "@(1,first_name)"})
+        )
+    }
 }
@@ -0,0 +1,369 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{OperationRef, TransformSpec};
+
+/// A change between two versions of a specification, reported by [`TransformSpec::diff`].
+///
+/// Mappings are the leaves of the `spec` field of an operation: the left hand side is the path
+/// of keys leading to the leaf and the right hand side is the leaf, e.g. the output path of a
+/// `shift` or the value of a `default`. The fields of the operation next to `spec` are options.
+///
+/// The `index` of a change is the index of the operation in the new specification, except for
+/// removed operations where it is the index in the old specification.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SpecChange {
+    OperationAdded {
+        index: usize,
+        operation: String,
+    },
+    OperationRemoved {
+        index: usize,
+        operation: String,
+    },
+    MappingAdded {
+        index: usize,
+        operation: String,
+        lhs: Vec<String>,
+        rhs: Value,
+    },
+    MappingRemoved {
+        index: usize,
+        operation: String,
+        lhs: Vec<String>,
+        rhs: Value,
+    },
+    MappingModified {
+        index: usize,
+        operation: String,
+        lhs: Vec<String>,
+        from: Value,
+        to: Value,
+    },
+    OptionChanged {
+        index: usize,
+        operation: String,
+        option: String,
+        from: Option<Value>,
+        to: Option<Value>,
+    },
+}
+
+impl fmt::Display for SpecChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecChange::OperationAdded { index, operation } => {
+                write!(f, "operation {index} ({operation}): added")
+            }
+            SpecChange::OperationRemoved { index, operation } => {
+                write!(f, "operation {index} ({operation}): removed")
+            }
+            SpecChange::MappingAdded {
+                index,
+                operation,
+                lhs,
+                rhs,
+            } => write!(
+                f,
+                "operation {index} ({operation}): + {} -> {rhs}",
+                lhs.join(".")
+            ),
+            SpecChange::MappingRemoved {
+                index,
+                operation,
+                lhs,
+                rhs,
+            } => write!(
+                f,
+                "operation {index} ({operation}): - {} -> {rhs}",
+                lhs.join(".")
+            ),
+            SpecChange::MappingModified {
+                index,
+                operation,
+                lhs,
+                from,
+                to,
+            } => write!(
+                f,
+                "operation {index} ({operation}): ~ {} -> {from} => {to}",
+                lhs.join(".")
+            ),
+            SpecChange::OptionChanged {
+                index,
+                operation,
+                option,
+                from,
+                to,
+            } => write!(
+                f,
+                "operation {index} ({operation}): option `{option}` {} => {}",
+                from.as_ref().unwrap_or(&Value::Null),
+                to.as_ref().unwrap_or(&Value::Null)
+            ),
+        }
+    }
+}
+
+pub(crate) fn diff(old: &TransformSpec, new: &TransformSpec) -> Vec<SpecChange> {
+    let old: Vec<OperationRef<'_>> = old.iter().collect();
+    let new: Vec<OperationRef<'_>> = new.iter().collect();
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (old_idx, new_idx) in align(&old, &new) {
+        changes.extend(old[i..old_idx].iter().enumerate().map(|(k, op)| {
+            SpecChange::OperationRemoved {
+                index: i + k,
+                operation: op.kind().to_string(),
+            }
+        }));
+        changes.extend(new[j..new_idx].iter().enumerate().map(|(k, op)| {
+            SpecChange::OperationAdded {
+                index: j + k,
+                operation: op.kind().to_string(),
+            }
+        }));
+        diff_operation(&old[old_idx], &new[new_idx], new_idx, &mut changes);
+        (i, j) = (old_idx + 1, new_idx + 1);
+    }
+    changes.extend(
+        old[i..]
+            .iter()
+            .enumerate()
+            .map(|(k, op)| SpecChange::OperationRemoved {
+                index: i + k,
+                operation: op.kind().to_string(),
+            }),
+    );
+    changes.extend(
+        new[j..]
+            .iter()
+            .enumerate()
+            .map(|(k, op)| SpecChange::OperationAdded {
+                index: j + k,
+                operation: op.kind().to_string(),
+            }),
+    );
+    changes
+}
+
+// Pairs of the indexes of the operations kept between the versions: the longest common
+// subsequence of the operation kinds
+fn align(old: &[OperationRef<'_>], new: &[OperationRef<'_>]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i].kind() == new[j].kind() {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].kind() == new[j].kind() {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn diff_operation(
+    old: &OperationRef<'_>,
+    new: &OperationRef<'_>,
+    index: usize,
+    changes: &mut Vec<SpecChange>,
+) {
+    let operation = new.kind().to_string();
+
+    let old_mappings = mappings(old.spec());
+    let new_mappings = mappings(new.spec());
+    for (lhs, from) in old_mappings.iter() {
+        match new_mappings.iter().find(|(new_lhs, _)| new_lhs == lhs) {
+            None => changes.push(SpecChange::MappingRemoved {
+                index,
+                operation: operation.clone(),
+                lhs: lhs.clone(),
+                rhs: from.clone(),
+            }),
+            Some((_, to)) if to != from => changes.push(SpecChange::MappingModified {
+                index,
+                operation: operation.clone(),
+                lhs: lhs.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (lhs, rhs) in new_mappings.iter() {
+        if !old_mappings.iter().any(|(old_lhs, _)| old_lhs == lhs) {
+            changes.push(SpecChange::MappingAdded {
+                index,
+                operation: operation.clone(),
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            });
+        }
+    }
+
+    let old_options = options(old.raw());
+    let new_options = options(new.raw());
+    let mut names: Vec<&String> = old_options.keys().chain(new_options.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let (from, to) = (old_options.get(name), new_options.get(name));
+        if from != to {
+            changes.push(SpecChange::OptionChanged {
+                index,
+                operation: operation.clone(),
+                option: name.clone(),
+                from: from.cloned(),
+                to: to.cloned(),
+            });
+        }
+    }
+}
+
+// The leaves of the spec with the keys leading to them. Arrays are leaves, e.g. the multiple
+// output paths of a `shift`.
+fn mappings(spec: &Value) -> Vec<(Vec<String>, Value)> {
+    fn visit(lhs: &mut Vec<String>, value: &Value, out: &mut Vec<(Vec<String>, Value)>) {
+        match value {
+            Value::Object(obj) if !obj.is_empty() => {
+                for (key, value) in obj {
+                    lhs.push(key.clone());
+                    visit(lhs, value, out);
+                    lhs.pop();
+                }
+            }
+            leaf => out.push((lhs.clone(), leaf.clone())),
+        }
+    }
+
+    let mut out = Vec::new();
+    visit(&mut Vec::new(), spec, &mut out);
+    out
+}
+
+fn options(raw: &Value) -> Map<String, Value> {
+    raw.as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "operation" | "spec"))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_diff_mappings() {
+        //given
+        let old: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"id": "data.id", "name": "data.name", "a": {"b": "c"}}},
+            {"operation": "default", "spec": {"data": {"kind": "user"}}}
+        ]))
+        .unwrap();
+        let new: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"id": "data.id", "name": "data.full_name", "email": "data.email"}},
+            {"operation": "default", "replace_null": true, "spec": {"data": {"kind": "user"}}}
+        ]))
+        .unwrap();
+
+        //when
+        let changes = old.diff(&new);
+
+        //then
+        assert_eq!(
+            changes,
+            vec![
+                SpecChange::MappingModified {
+                    index: 0,
+                    operation: "shift".to_string(),
+                    lhs: vec!["name".to_string()],
+                    from: json!("data.name"),
+                    to: json!("data.full_name"),
+                },
+                SpecChange::MappingRemoved {
+                    index: 0,
+                    operation: "shift".to_string(),
+                    lhs: vec!["a".to_string(), "b".to_string()],
+                    rhs: json!("c"),
+                },
+                SpecChange::MappingAdded {
+                    index: 0,
+                    operation: "shift".to_string(),
+                    lhs: vec!["email".to_string()],
+                    rhs: json!("data.email"),
+                },
+                SpecChange::OptionChanged {
+                    index: 1,
+                    operation: "default".to_string(),
+                    option: "replace_null".to_string(),
+                    from: None,
+                    to: Some(json!(true)),
+                },
+            ]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            r#"operation 0 (shift): ~ name -> "data.name" => "data.full_name""#
+        );
+    }
+
+    #[test]
+    fn test_diff_operations() {
+        //given
+        let old: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"id": "data.id"}},
+            {"operation": "remove", "spec": {"data": {"tmp": ""}}}
+        ]))
+        .unwrap();
+        let new: TransformSpec = serde_json::from_value(json!([
+            {"operation": "identity", "spec": {"prefix": "raw"}},
+            {"operation": "shift", "spec": {"id": "data.id"}}
+        ]))
+        .unwrap();
+
+        //when
+        let changes = old.diff(&new);
+
+        //then
+        assert_eq!(
+            changes,
+            vec![
+                SpecChange::OperationAdded {
+                    index: 0,
+                    operation: "identity".to_string(),
+                },
+                SpecChange::OperationRemoved {
+                    index: 1,
+                    operation: "remove".to_string(),
+                },
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+}
@@ -1,4 +1,8 @@
+use std::fmt;
+
 use super::parser::Parser;
+use super::suggest::Side;
+use super::tokenizer::{MARKER_CHARS, SPECIAL_CHARS};
 use super::ParseError;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -14,14 +18,101 @@ pub enum Lhs {
 
 impl Lhs {
     pub fn parse(input: &str) -> Result<Self, ParseError> {
-        Parser::new(input).parse_lhs()
+        Parser::new(input)
+            .parse_lhs()
+            .map_err(|e| e.with_help(input, Side::Lhs))
+    }
+
+    /// Parses a key of the spec, with the `?` or `!` marker at the end of a literal key or of a
+    /// `@` lookup. The other keys ending with `?` or `!` are parsed as they are, e.g. `#yes!`.
+    pub fn parse_marked(input: &str) -> Result<(Self, Option<Marker>), ParseError> {
+        if let Some((unmarked, marker)) = strip_marker(input) {
+            if let Ok(lhs @ (Lhs::Literal(_) | Lhs::At(..))) = Lhs::parse(unmarked) {
+                return Ok((lhs, Some(marker)));
+            }
+        }
+        Lhs::parse(input).map(|lhs| (lhs, None))
+    }
+}
+
+// The input without the marker at its end, unless the marker is escaped
+fn strip_marker(input: &str) -> Option<(&str, Marker)> {
+    let c = input.chars().last()?;
+    let marker = Marker::from_char(c)?;
+    let unmarked = &input[..input.len() - c.len_utf8()];
+    let backslashes = unmarked.chars().rev().take_while(|c| *c == '\\').count();
+    (!unmarked.is_empty() && backslashes % 2 == 0).then_some((unmarked, marker))
+}
+
+/// Marker at the end of a literal key or of a `@` lookup of the left hand side
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum Marker {
+    /// `?`, the key may be missing, even with strict lookups
+    Optional,
+    /// `!`, the key must be present
+    Required,
+}
+
+impl Marker {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '?' => Some(Marker::Optional),
+            '!' => Some(Marker::Required),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Marker::Optional => "optional",
+            Marker::Required => "required",
+        }
+    }
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Marker::Optional => write!(f, "?"),
+            Marker::Required => write!(f, "!"),
+        }
     }
 }
 
 /// Bunch of literals separated by stars
-/// "*" is represented as vec!["", ""]
+/// The patterns with a single star at the end or at the start are detected when they are parsed
+/// and matched without splitting the key into pieces, "*" is represented as `Prefix("")`
 #[derive(Debug, PartialEq, Clone, Eq)]
-pub struct Stars(pub Vec<String>);
+pub enum Stars {
+    /// `prefix*`
+    Prefix(String),
+    /// `*suffix`
+    Suffix(String),
+    /// The literals of the other patterns, e.g. vec!["a", "b", ""] for "a*b*"
+    Pieces(Vec<String>),
+}
+
+impl Stars {
+    pub fn new(mut pieces: Vec<String>) -> Self {
+        match pieces.as_slice() {
+            [_, suffix] if suffix.is_empty() => {
+                pieces.truncate(1);
+                Stars::Prefix(pieces.remove(0))
+            }
+            [prefix, _] if prefix.is_empty() => Stars::Suffix(pieces.remove(1)),
+            _ => Stars::Pieces(pieces),
+        }
+    }
+
+    /// The literals separated by the stars
+    pub fn pieces(&self) -> Vec<&str> {
+        match self {
+            Stars::Prefix(prefix) => vec![prefix, ""],
+            Stars::Suffix(suffix) => vec!["", suffix],
+            Stars::Pieces(pieces) => pieces.iter().map(String::as_str).collect(),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub struct Rhs(pub Vec<RhsPart>);
@@ -36,20 +127,294 @@ pub enum RhsPart {
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub enum RhsEntry {
     Amp(usize, usize),
+    /// `&(N,M):format` formats the match, e.g. `&:snake` or `&:%03d`
+    AmpFormat(usize, usize, KeyFormat),
     At(usize, Box<Rhs>),
     Key(String),
 }
 
+/// Formats of the matches on the right hand side
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum KeyFormat {
+    /// `:snake`, `:camel`... convert the case of the match
+    Case(KeyCase),
+    /// `:%0Nd` pads the numeric matches with zeros to N digits
+    ZeroPad(usize),
+}
+
+impl KeyFormat {
+    pub fn apply(&self, key: &str) -> String {
+        match self {
+            KeyFormat::Case(case) => case.convert(key),
+            KeyFormat::ZeroPad(width)
+                if !key.is_empty() && key.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                format!("{key:0>width$}")
+            }
+            KeyFormat::ZeroPad(_) => key.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyFormat::Case(case) => write!(f, "{case}"),
+            KeyFormat::ZeroPad(width) => write!(f, "%0{width}d"),
+        }
+    }
+}
+
+/// Case conventions the matches are converted to on the right hand side
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum KeyCase {
+    /// `snake_case`
+    Snake,
+    /// `kebab-case`
+    Kebab,
+    /// `camelCase`
+    Camel,
+    /// `PascalCase`
+    Pascal,
+    /// `lowercase`, the separators are kept
+    Lower,
+    /// `UPPERCASE`, the separators are kept
+    Upper,
+}
+
+impl KeyCase {
+    pub const ALL: [KeyCase; 6] = [
+        KeyCase::Snake,
+        KeyCase::Kebab,
+        KeyCase::Camel,
+        KeyCase::Pascal,
+        KeyCase::Lower,
+        KeyCase::Upper,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyCase::Snake => "snake",
+            KeyCase::Kebab => "kebab",
+            KeyCase::Camel => "camel",
+            KeyCase::Pascal => "pascal",
+            KeyCase::Lower => "lower",
+            KeyCase::Upper => "upper",
+        }
+    }
+
+    pub fn convert(&self, key: &str) -> String {
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                })
+                .into_iter()
+                .flatten()
+                .collect::<String>()
+        };
+        match self {
+            KeyCase::Lower => key.to_lowercase(),
+            KeyCase::Upper => key.to_uppercase(),
+            KeyCase::Snake => words(key).join("_").to_lowercase(),
+            KeyCase::Kebab => words(key).join("-").to_lowercase(),
+            KeyCase::Pascal => words(key).into_iter().map(capitalize).collect(),
+            KeyCase::Camel => words(key)
+                .into_iter()
+                .enumerate()
+                .map(|(idx, word)| match idx {
+                    0 => word.to_lowercase(),
+                    _ => capitalize(word),
+                })
+                .collect(),
+        }
+    }
+}
+
+// The words of the key, separated by `_`, `-`, spaces or the case changes, an acronym is a word
+// of its own, e.g. `HTTPServer_id` is `HTTP`, `Server`, `id`
+fn words(key: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let chars: Vec<(usize, char)> = key.char_indices().collect();
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            if let Some(start) = start.take() {
+                words.push(&key[start..pos]);
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|i| chars[i].1);
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|prev| {
+                prev.is_lowercase()
+                    || prev.is_numeric()
+                    || (prev.is_uppercase() && next.is_some_and(char::is_lowercase))
+            });
+        match start {
+            Some(from) if boundary => {
+                words.push(&key[from..pos]);
+                start = Some(pos);
+            }
+            None => start = Some(pos),
+            _ => (),
+        }
+    }
+    if let Some(start) = start {
+        words.push(&key[start..]);
+    }
+    words
+}
+
+impl fmt::Display for KeyCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub enum IndexOp {
     Amp(usize, usize),
+    /// `$(N,M)` keeps the type of the matched key, so array indexes are numbers
+    DollarSign(usize, usize),
     Literal(usize),
     At(usize, Box<Rhs>),
+    /// `#N` counts the keys matched before the key N levels up
+    Square(usize),
     Empty,
+    /// `?` appends the value unless the array already contains it
+    Unique,
+    /// `*` gathers the values of all the elements, only in `@` lookups
+    Star,
 }
 
 impl Rhs {
     pub fn parse(input: &str) -> Result<Self, ParseError> {
-        Parser::new(input).parse_rhs()
+        Parser::new(input)
+            .parse_rhs()
+            .map_err(|e| e.with_help(input, Side::Rhs))
+    }
+
+    /// Parses the destinations separated by `|`
+    pub fn parse_many(input: &str) -> Result<Vec<Self>, ParseError> {
+        Parser::new(input)
+            .parse_rhss()
+            .map_err(|e| e.with_help(input, Side::Rhs))
+    }
+}
+
+impl fmt::Display for Lhs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lhs::DollarSign(idx0, idx1) => write!(f, "$({idx0},{idx1})"),
+            Lhs::Amp(idx0, idx1) => write!(f, "&({idx0},{idx1})"),
+            Lhs::At(0, rhs) if rhs.0.is_empty() => write!(f, "@"),
+            Lhs::At(idx, rhs) => write!(f, "@({idx},{rhs})"),
+            // dots aren't escaped after `#`, see `Parser::parse_square_lhs`
+            Lhs::Square(lit) => {
+                write!(f, "#")?;
+                for (idx, piece) in lit.split('.').enumerate() {
+                    if idx > 0 {
+                        write!(f, ".")?;
+                    }
+                    write_key(f, piece)?;
+                }
+                Ok(())
+            }
+            Lhs::Pipes(pipes) => {
+                for (idx, stars) in pipes.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, "|")?;
+                    }
+                    write!(f, "{stars}")?;
+                }
+                Ok(())
+            }
+            // a marker at the end of the literal is escaped, see `Lhs::parse_marked`
+            Lhs::Literal(lit) => match lit.chars().last() {
+                Some(c) if MARKER_CHARS.contains(&c) => {
+                    write_key(f, &lit[..lit.len() - c.len_utf8()])?;
+                    write!(f, "\\{c}")
+                }
+                _ => write_key(f, lit),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Stars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, piece) in self.pieces().into_iter().enumerate() {
+            if idx > 0 {
+                write!(f, "*")?;
+            }
+            write_key(f, piece)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rhs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, part) in self.0.iter().enumerate() {
+            match part {
+                RhsPart::Key(_) | RhsPart::CompositeKey(_) if idx > 0 => write!(f, ".{part}")?,
+                _ => write!(f, "{part}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RhsPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RhsPart::Key(entry) => write!(f, "{entry}"),
+            RhsPart::CompositeKey(entries) => {
+                entries.iter().try_for_each(|entry| write!(f, "{entry}"))
+            }
+            RhsPart::Index(idx_op) => write!(f, "[{idx_op}]"),
+        }
+    }
+}
+
+impl fmt::Display for RhsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RhsEntry::Amp(idx0, idx1) => write!(f, "&({idx0},{idx1})"),
+            RhsEntry::AmpFormat(idx0, idx1, format) => write!(f, "&({idx0},{idx1}):{format}"),
+            RhsEntry::At(idx, rhs) => write!(f, "@({idx},{rhs})"),
+            RhsEntry::Key(key) => write_key(f, key),
+        }
+    }
+}
+
+fn write_key(f: &mut fmt::Formatter<'_>, key: &str) -> fmt::Result {
+    for c in key.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            write!(f, "\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for IndexOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexOp::Amp(idx0, idx1) => write!(f, "&({idx0},{idx1})"),
+            IndexOp::DollarSign(idx0, idx1) => write!(f, "$({idx0},{idx1})"),
+            IndexOp::Literal(idx) => write!(f, "{idx}"),
+            IndexOp::At(idx, rhs) => write!(f, "@({idx},{rhs})"),
+            IndexOp::Square(level) => write!(f, "#{level}"),
+            IndexOp::Empty => Ok(()),
+            IndexOp::Unique => write!(f, "?"),
+            IndexOp::Star => write!(f, "*"),
+        }
     }
 }
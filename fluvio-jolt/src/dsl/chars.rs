@@ -21,6 +21,7 @@ impl<'input> Chars<'input> {
             return Err(ParseError {
                 pos: self.pos,
                 cause: Box::new(ParseErrorCause::PutBackBufferFull),
+                help: None,
             });
         }
 
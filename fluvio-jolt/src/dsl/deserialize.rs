@@ -7,12 +7,13 @@ use serde::{
     Deserialize,
 };
 
-use super::ast::{Rhs, Lhs, Stars};
+use super::ast::{Rhs, Lhs, Stars, RhsPart, IndexOp, Marker};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum InfallibleLhs {
     DollarSign(usize, usize),
-    At(usize, Box<Rhs>),
+    /// `@(N,path)`, with the marker overriding the strict lookups of the shift
+    At(usize, Box<Rhs>, Option<Marker>),
     Square(String),
 }
 
@@ -20,6 +21,8 @@ pub enum InfallibleLhs {
 pub struct Object {
     pub infallible: Vec<(InfallibleLhs, Vec<Rhs>)>,
     pub literal: Vec<(String, REntry)>,
+    /// The markers of the literal keys, `!` keys must be present in the input
+    pub markers: Vec<(String, Marker)>,
     pub amp: Vec<((usize, usize), REntry)>,
     pub pipes: Vec<(Vec<Stars>, REntry)>,
 }
@@ -84,9 +87,19 @@ impl<'de> Visitor<'de> for RhssVisitor {
     where
         E: de::Error,
     {
-        let r = Rhs::parse(value)
+        let rhss = Rhs::parse_many(value)
             .map_err(|e| E::custom(format!("failed to parse: {value}.error={e}")))?;
-        Ok(vec![r])
+        // `[*]` gathers values, it doesn't address an output index
+        if rhss
+            .iter()
+            .flat_map(|rhs| rhs.0.iter())
+            .any(|part| matches!(part, RhsPart::Index(IndexOp::Star)))
+        {
+            return Err(E::custom(format!(
+                "failed to parse: {value}.error=`[*]` is only allowed in `@` lookups"
+            )));
+        }
+        Ok(rhss)
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -131,7 +144,8 @@ impl<'de> Visitor<'de> for ObjectVisitor {
         let mut key_set = HashSet::new();
 
         while let Some(lhs_s) = map.next_key::<String>()? {
-            let lhs = LhsVisitor.visit_str(&lhs_s)?;
+            let (lhs, marker) = Lhs::parse_marked(&lhs_s)
+                .map_err(|e| A::Error::custom(format!("failed to parse: {lhs_s}.error={e}")))?;
 
             if !key_set.insert(lhs_s) {
                 return Err(A::Error::custom("duplicate lhs"));
@@ -148,8 +162,10 @@ impl<'de> Visitor<'de> for ObjectVisitor {
                     obj.amp.push(((idx0, idx1), map.next_value()?));
                 }
                 Lhs::At(idx, rhs) => {
-                    obj.infallible
-                        .push((InfallibleLhs::At(idx, rhs), map.next_value::<Rhss>()?.0));
+                    obj.infallible.push((
+                        InfallibleLhs::At(idx, rhs, marker),
+                        map.next_value::<Rhss>()?.0,
+                    ));
                 }
                 Lhs::Square(lit) => {
                     obj.infallible
@@ -159,6 +175,9 @@ impl<'de> Visitor<'de> for ObjectVisitor {
                     obj.pipes.push((pipes, map.next_value()?));
                 }
                 Lhs::Literal(lit) => {
+                    if let Some(marker) = marker {
+                        obj.markers.push((lit.clone(), marker));
+                    }
                     obj.literal.push((lit, map.next_value()?));
                 }
             }
@@ -230,20 +249,14 @@ impl<'de> Visitor<'de> for REntryVisitor {
     where
         E: de::Error,
     {
-        RhsVisitor.visit_str(value).map(|r| REntry::Rhs(vec![r]))
+        RhssVisitor.visit_str(value).map(REntry::Rhs)
     }
 
-    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
     where
         A: de::SeqAccess<'de>,
     {
-        let mut arr = Vec::new();
-
-        while let Some(rhs) = seq.next_element()? {
-            arr.push(rhs);
-        }
-
-        Ok(REntry::Rhs(arr))
+        RhssVisitor.visit_seq(seq).map(REntry::Rhs)
     }
 
     fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
@@ -4,13 +4,16 @@ use std::num::ParseIntError;
 use thiserror::Error as ThisError;
 use super::token::Token;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
     pub(crate) pos: usize,
     pub(crate) cause: Box<ParseErrorCause>,
+    /// Fix of the common mistake the error is likely caused by, e.g. the unescaped dots of a key
+    /// or `&1` instead of `&(1)`.
+    pub(crate) help: Option<String>,
 }
 
-#[derive(Debug, ThisError, PartialEq)]
+#[derive(Debug, Clone, ThisError, PartialEq)]
 pub enum ParseErrorCause {
     #[error("Unexpected end of input")]
     UnexpectedEndOfInput,
@@ -28,13 +31,24 @@ pub enum ParseErrorCause {
     ExpectedIdx,
 }
 
+impl ParseError {
+    /// Byte offset of the expression the error is found at.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "Failed to parse input. Error near {}.\n{}",
             self.pos, self.cause
-        )
+        )?;
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {help}")?;
+        }
+        Ok(())
     }
 }
 
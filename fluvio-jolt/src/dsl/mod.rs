@@ -4,10 +4,15 @@ mod parser;
 mod token;
 mod tokenizer;
 mod deserialize;
+mod serialize;
 #[cfg(test)]
 mod test;
 mod chars;
+mod suggest;
 
 pub use error::ParseError;
-pub use ast::{Rhs, Lhs, RhsEntry, IndexOp, RhsPart};
+pub(crate) use tokenizer::Tokenizer;
+pub use tokenizer::escape_key;
+pub use token::TokenKind;
+pub use ast::{Rhs, Lhs, RhsEntry, IndexOp, RhsPart, Stars, KeyCase, KeyFormat, Marker};
 pub use deserialize::{InfallibleLhs, Object, REntry};
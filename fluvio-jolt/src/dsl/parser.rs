@@ -2,7 +2,7 @@ use super::error::{ParseErrorCause, ParseError};
 use super::token::{Token, TokenKind};
 use super::tokenizer::Tokenizer;
 use std::result::Result as StdResult;
-use super::ast::{Lhs, Rhs, IndexOp, RhsEntry, Stars, RhsPart};
+use super::ast::{Lhs, Rhs, IndexOp, RhsEntry, Stars, RhsPart, KeyCase, KeyFormat};
 
 const MAX_DEPTH: usize = 4;
 
@@ -22,12 +22,12 @@ impl<'input> Parser<'input> {
     pub fn parse_lhs(&mut self) -> Result<Lhs> {
         let token = match self.input.next()? {
             Some(token) => token,
-            None => return Ok(Lhs::Pipes(vec![Stars(vec![String::new()])])),
+            None => return Ok(Lhs::Pipes(vec![Stars::new(vec![String::new()])])),
         };
 
         let res = match token.kind {
             TokenKind::Square => self.parse_square_lhs().map(Lhs::Square),
-            TokenKind::At => self.parse_at_tuple(0).map(|t| Lhs::At(t.0, t.1)),
+            TokenKind::At => self.parse_at_lhs().map(|t| Lhs::At(t.0, t.1)),
             TokenKind::DollarSign => self.parse_num_tuple().map(|t| Lhs::DollarSign(t.0, t.1)),
             TokenKind::Amp => self.parse_num_tuple().map(|t| Lhs::Amp(t.0, t.1)),
             TokenKind::Key(_) | TokenKind::Star | TokenKind::Pipe => {
@@ -38,6 +38,7 @@ impl<'input> Parser<'input> {
                 return Err(ParseError {
                     pos: token.pos,
                     cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
+                    help: None,
                 });
             }
         }?;
@@ -46,6 +47,7 @@ impl<'input> Parser<'input> {
             return Err(ParseError {
                 pos: token.pos,
                 cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
+                help: None,
             });
         }
 
@@ -59,17 +61,51 @@ impl<'input> Parser<'input> {
             return Err(ParseError {
                 pos: token.pos,
                 cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
+                help: None,
             });
         }
 
         Ok(rhs)
     }
 
+    /// Parses right hand side expressions separated by `|`
+    pub fn parse_rhss(&mut self) -> Result<Vec<Rhs>> {
+        let mut rhss = Vec::new();
+
+        loop {
+            let rhs = self.parse_rhs_impl(0)?;
+            let next = self.input.next()?;
+
+            // `|` doesn't separate empty expressions
+            if rhs.0.is_empty() && (next.is_some() || !rhss.is_empty()) {
+                return Err(ParseError {
+                    pos: next.map(|token| token.pos).unwrap_or(self.input.pos()),
+                    cause: ParseErrorCause::UnexpectedEndOfInput.into(),
+                    help: None,
+                });
+            }
+            rhss.push(rhs);
+
+            match next {
+                None => return Ok(rhss),
+                Some(token) if token.kind == TokenKind::Pipe => (),
+                Some(token) => {
+                    return Err(ParseError {
+                        pos: token.pos,
+                        cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
+                        help: None,
+                    });
+                }
+            }
+        }
+    }
+
     fn parse_rhs_impl(&mut self, depth: usize) -> Result<Rhs> {
         if depth > MAX_DEPTH {
             return Err(ParseError {
                 pos: self.input.pos(),
                 cause: ParseErrorCause::MaximumRecursion(MAX_DEPTH).into(),
+                help: None,
             });
         }
 
@@ -123,7 +159,13 @@ impl<'input> Parser<'input> {
 
         while let Some(token) = self.input.next()? {
             let res = match token.kind {
-                TokenKind::Amp => self.parse_num_tuple().map(|t| RhsEntry::Amp(t.0, t.1))?,
+                TokenKind::Amp => {
+                    let t = self.parse_num_tuple()?;
+                    match self.parse_format()? {
+                        Some(format) => RhsEntry::AmpFormat(t.0, t.1, format),
+                        None => RhsEntry::Amp(t.0, t.1),
+                    }
+                }
                 TokenKind::At => self.parse_at_tuple(depth).map(|t| RhsEntry::At(t.0, t.1))?,
                 TokenKind::Key(key) => RhsEntry::Key(key),
                 _ => {
@@ -144,6 +186,45 @@ impl<'input> Parser<'input> {
         Ok(Some(part))
     }
 
+    // `:case` or `:%0Nd` right after a capture, the rest of the key is a literal following the
+    // capture and a `:` followed by no format is a literal
+    fn parse_format(&mut self) -> Result<Option<KeyFormat>> {
+        let Some(token) = self.input.next()? else {
+            return Ok(None);
+        };
+        if let TokenKind::Key(key) = &token.kind {
+            let name = key.strip_prefix(':').unwrap_or_default();
+            if let Some((format, len)) = Self::key_format(name) {
+                let rest = &name[len..];
+                if !rest.is_empty() {
+                    self.input.put_back(Token {
+                        pos: token.pos + 1 + len,
+                        kind: TokenKind::Key(rest.to_string()),
+                    })?;
+                }
+                return Ok(Some(format));
+            }
+        }
+        self.input.put_back(token)?;
+        Ok(None)
+    }
+
+    // The format the name starts with and its length
+    fn key_format(name: &str) -> Option<(KeyFormat, usize)> {
+        if let Some(case) = KeyCase::ALL
+            .into_iter()
+            .find(|case| name.starts_with(case.name()))
+        {
+            return Some((KeyFormat::Case(case), case.name().len()));
+        }
+        let digits = name.strip_prefix("%0")?;
+        let len = digits.bytes().take_while(u8::is_ascii_digit).count();
+        let width = digits[..len].parse().ok()?;
+        digits[len..]
+            .starts_with('d')
+            .then_some((KeyFormat::ZeroPad(width), 2 + len + 1))
+    }
+
     fn parse_index_op(&mut self, depth: usize) -> Result<IndexOp> {
         let token = self.get_next()?;
 
@@ -152,19 +233,39 @@ impl<'input> Parser<'input> {
                 let t = self.parse_num_tuple()?;
                 IndexOp::Amp(t.0, t.1)
             }
+            TokenKind::DollarSign => {
+                let t = self.parse_num_tuple()?;
+                IndexOp::DollarSign(t.0, t.1)
+            }
             TokenKind::CloseBrkt => {
                 self.input.put_back(token)?;
                 IndexOp::Empty
             }
+            TokenKind::Key(key) if key == "?" => IndexOp::Unique,
+            TokenKind::Star => IndexOp::Star,
             TokenKind::Key(key) => IndexOp::Literal(Self::parse_index(&key, token.pos)?),
             TokenKind::At => {
                 let t = self.parse_at_tuple(depth)?;
                 IndexOp::At(t.0, t.1)
             }
+            TokenKind::Square => {
+                let token = self.get_next()?;
+                match token.kind {
+                    TokenKind::Key(key) => IndexOp::Square(Self::parse_index(&key, token.pos)?),
+                    _ => {
+                        return Err(ParseError {
+                            pos: token.pos,
+                            cause: ParseErrorCause::ExpectedIdx.into(),
+                            help: None,
+                        });
+                    }
+                }
+            }
             _ => {
                 return Err(ParseError {
                     pos: token.pos,
                     cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
+                    help: None,
                 });
             }
         };
@@ -172,18 +273,38 @@ impl<'input> Parser<'input> {
         Ok(op)
     }
 
+    // dots don't need to be escaped in literals, so decimal numbers can be written as they are
     fn parse_square_lhs(&mut self) -> Result<String> {
-        let token = match self.input.next()? {
-            Some(token) => token,
-            None => return Ok(String::new()),
-        };
+        let mut lit = String::new();
+        while let Some(token) = self.input.next()? {
+            match token.kind {
+                TokenKind::Key(key) => lit.push_str(&key),
+                TokenKind::Dot => lit.push('.'),
+                _ => {
+                    return Err(ParseError {
+                        pos: token.pos,
+                        cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
+                        help: None,
+                    })
+                }
+            }
+        }
 
-        match token.kind {
-            TokenKind::Key(key) => Ok(key),
-            _ => Err(ParseError {
-                pos: token.pos,
-                cause: Box::new(ParseErrorCause::UnexpectedToken(token)),
-            }),
+        Ok(lit)
+    }
+
+    // `@key.path` on the left hand side is the shorthand of `@(0,key.path)`
+    fn parse_at_lhs(&mut self) -> Result<(usize, Box<Rhs>)> {
+        match self.input.next()? {
+            Some(token) if matches!(token.kind, TokenKind::Key(_)) => {
+                self.input.put_back(token)?;
+                Ok((0, self.parse_rhs_impl(1)?.into()))
+            }
+            Some(token) => {
+                self.input.put_back(token)?;
+                self.parse_at_tuple(0)
+            }
+            None => self.parse_at_tuple(0),
         }
     }
 
@@ -212,6 +333,7 @@ impl<'input> Parser<'input> {
                 return Err(ParseError {
                     pos: token.pos,
                     cause: ParseErrorCause::UnexpectedToken(token).into(),
+                    help: None,
                 });
             }
         };
@@ -241,6 +363,7 @@ impl<'input> Parser<'input> {
                 _ => Err(ParseError {
                     pos: token.pos,
                     cause: ParseErrorCause::ExpectedIdx.into(),
+                    help: None,
                 }),
             }
         };
@@ -257,6 +380,7 @@ impl<'input> Parser<'input> {
                 return Err(ParseError {
                     pos: token.pos,
                     cause: ParseErrorCause::UnexpectedToken(token).into(),
+                    help: None,
                 })
             }
         }
@@ -271,13 +395,9 @@ impl<'input> Parser<'input> {
     fn parse_pipes_or_lit(&mut self) -> Result<Lhs> {
         let pipes = self.parse_pipes()?;
 
-        if pipes.len() == 1 && pipes[0].0.len() == 1 {
-            // this will never panic because we check the lengths
-            // beforehand
-            let mut pipes = pipes;
-            Ok(Lhs::Literal(pipes.pop().unwrap().0.pop().unwrap()))
-        } else {
-            Ok(Lhs::Pipes(pipes))
+        match pipes.as_slice() {
+            [Stars::Pieces(pieces)] if pieces.len() == 1 => Ok(Lhs::Literal(pieces[0].clone())),
+            _ => Ok(Lhs::Pipes(pipes)),
         }
     }
 
@@ -305,6 +425,7 @@ impl<'input> Parser<'input> {
                             return Err(ParseError {
                                 pos: token.pos,
                                 cause: ParseErrorCause::UnexpectedToken(token).into(),
+                                help: None,
                             })
                         }
                     }
@@ -313,12 +434,13 @@ impl<'input> Parser<'input> {
                 }
                 TokenKind::Pipe => {
                     match last {
-                        Last::None => pipes.push(Stars(vec![String::new()])),
+                        Last::None => pipes.push(Stars::new(vec![String::new()])),
                         Last::Stars => (),
                         Last::Pipe => {
                             return Err(ParseError {
                                 pos: token.pos,
                                 cause: ParseErrorCause::UnexpectedToken(token).into(),
+                                help: None,
                             })
                         }
                     }
@@ -333,7 +455,7 @@ impl<'input> Parser<'input> {
         }
 
         if last == Last::Pipe {
-            pipes.push(Stars(vec![String::new()]));
+            pipes.push(Stars::new(vec![String::new()]));
         }
 
         Ok(pipes)
@@ -360,6 +482,7 @@ impl<'input> Parser<'input> {
                             return Err(ParseError {
                                 pos: token.pos,
                                 cause: ParseErrorCause::UnexpectedToken(token).into(),
+                                help: None,
                             })
                         }
                         Last::Key => (),
@@ -378,6 +501,7 @@ impl<'input> Parser<'input> {
                                     kind: TokenKind::Key(key),
                                 })
                                 .into(),
+                                help: None,
                             })
                         }
                     }
@@ -395,13 +519,14 @@ impl<'input> Parser<'input> {
             stars.push(String::new());
         }
 
-        Ok(Stars(stars))
+        Ok(Stars::new(stars))
     }
 
     fn parse_index(key: &str, pos: usize) -> Result<usize> {
         key.parse().map_err(|e| ParseError {
             pos,
             cause: Box::new(ParseErrorCause::InvalidIndex(e)),
+            help: None,
         })
     }
 
@@ -412,6 +537,7 @@ impl<'input> Parser<'input> {
                 return Err(ParseError {
                     pos,
                     cause: ParseErrorCause::ExpectedIdx.into(),
+                    help: None,
                 });
             }
         };
@@ -423,6 +549,7 @@ impl<'input> Parser<'input> {
         self.input.next()?.ok_or(ParseError {
             pos: self.input.pos(),
             cause: Box::new(ParseErrorCause::UnexpectedEndOfInput),
+            help: None,
         })
     }
 
@@ -434,6 +561,7 @@ impl<'input> Parser<'input> {
             Err(ParseError {
                 pos: got.pos,
                 cause: Box::new(ParseErrorCause::UnexpectedToken(got)),
+                help: None,
             })
         }
     }
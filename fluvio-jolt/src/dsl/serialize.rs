@@ -0,0 +1,108 @@
+use serde::ser::{SerializeMap, SerializeSeq, Serializer};
+use serde::Serialize;
+
+use super::ast::{Lhs, Marker, Rhs};
+use super::deserialize::{InfallibleLhs, Object, REntry};
+
+// The keys and the expressions are written with their `Display` impls, which escape the special
+// characters of the literals, so the serialized spec parses into the same `Object`
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = self.infallible.len() + self.literal.len() + self.amp.len() + self.pipes.len();
+        let mut map = serializer.serialize_map(Some(len))?;
+        for (lhs, rhss) in &self.infallible {
+            map.serialize_entry(&lhs.key(), &Rhss(rhss))?;
+        }
+        for (lit, entry) in &self.literal {
+            map.serialize_entry(&self.literal_key(lit), entry)?;
+        }
+        for ((idx0, idx1), entry) in &self.amp {
+            map.serialize_entry(&Lhs::Amp(*idx0, *idx1).to_string(), entry)?;
+        }
+        for (pipes, entry) in &self.pipes {
+            map.serialize_entry(&Lhs::Pipes(pipes.clone()).to_string(), entry)?;
+        }
+        map.end()
+    }
+}
+
+impl Object {
+    /// The key of the spec of the entry of the object, `None` if the entry isn't one of its
+    /// entries.
+    pub(crate) fn key_of(&self, entry: &REntry) -> Option<String> {
+        if let Some((lit, _)) = self
+            .literal
+            .iter()
+            .find(|(_, other)| std::ptr::eq(other, entry))
+        {
+            return Some(self.literal_key(lit));
+        }
+        if let Some(((idx0, idx1), _)) = self
+            .amp
+            .iter()
+            .find(|(_, other)| std::ptr::eq(other, entry))
+        {
+            return Some(Lhs::Amp(*idx0, *idx1).to_string());
+        }
+        self.pipes
+            .iter()
+            .find(|(_, other)| std::ptr::eq(other, entry))
+            .map(|(pipes, _)| Lhs::Pipes(pipes.clone()).to_string())
+    }
+
+    /// The key of the spec of a literal, with its marker.
+    pub(crate) fn literal_key(&self, lit: &str) -> String {
+        let marker = self
+            .markers
+            .iter()
+            .find_map(|(key, marker)| (key == lit).then_some(*marker));
+        marked(&Lhs::Literal(lit.to_string()), marker)
+    }
+}
+
+impl InfallibleLhs {
+    /// The key of the spec the lhs is parsed from.
+    pub(crate) fn key(&self) -> String {
+        let (lhs, marker) = match self {
+            InfallibleLhs::DollarSign(idx0, idx1) => (Lhs::DollarSign(*idx0, *idx1), None),
+            InfallibleLhs::At(idx, rhs, marker) => (Lhs::At(*idx, rhs.clone()), *marker),
+            InfallibleLhs::Square(lit) => (Lhs::Square(lit.clone()), None),
+        };
+        marked(&lhs, marker)
+    }
+}
+
+fn marked(lhs: &Lhs, marker: Option<Marker>) -> String {
+    match marker {
+        Some(marker) => format!("{lhs}{marker}"),
+        None => lhs.to_string(),
+    }
+}
+
+impl Serialize for REntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            REntry::Obj(obj) => obj.serialize(serializer),
+            REntry::Rhs(rhss) => Rhss(rhss).serialize(serializer),
+            REntry::Thrash => serializer.serialize_none(),
+        }
+    }
+}
+
+// A single destination is a string, several destinations are an array of strings
+struct Rhss<'a>(&'a [Rhs]);
+
+impl Serialize for Rhss<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            [rhs] => serializer.collect_str(rhs),
+            rhss => {
+                let mut seq = serializer.serialize_seq(Some(rhss.len()))?;
+                for rhs in rhss {
+                    seq.serialize_element(&rhs.to_string())?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use super::error::{ParseError, ParseErrorCause};
+use super::token::{Token, TokenKind};
+use super::tokenizer::{Tokenizer, SPECIAL_CHARS};
+
+/// Side of the spec the expression is written on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Side {
+    Lhs,
+    Rhs,
+}
+
+impl ParseError {
+    /// Explains the error with the fix of a common mistake found in the tokens of the input.
+    pub(super) fn with_help(mut self, input: &str, side: Side) -> Self {
+        let tokens = tokens(input);
+        self.help = index_without_parentheses(input, &tokens)
+            .or_else(|| unmatched_bracket(&tokens))
+            .or_else(|| match side {
+                Side::Lhs => unescaped_key(input, &tokens, &self.cause),
+                Side::Rhs => star_in_rhs(input, &self.cause),
+            });
+        self
+    }
+}
+
+// The tokens of the input up to the first invalid character
+fn tokens(input: &str) -> Vec<Token> {
+    let mut tokenizer = Tokenizer::new(input);
+    let mut tokens = Vec::new();
+    while let Ok(Some(token)) = tokenizer.next() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+// `&1` and `$1` instead of `&(1)` and `$(1)`
+fn index_without_parentheses(input: &str, tokens: &[Token]) -> Option<String> {
+    let mut fixed = String::with_capacity(input.len() + 2);
+    let mut copied = 0;
+    for pair in tokens.windows(2) {
+        let (TokenKind::Amp | TokenKind::DollarSign, TokenKind::Key(key)) =
+            (&pair[0].kind, &pair[1].kind)
+        else {
+            continue;
+        };
+        let digits = key.len() - key.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            continue;
+        }
+        let pos = pair[1].pos;
+        fixed.push_str(input.get(copied..pos)?);
+        fixed.push('(');
+        fixed.push_str(input.get(pos..pos + digits)?);
+        fixed.push(')');
+        copied = pos + digits;
+    }
+    if copied == 0 {
+        return None;
+    }
+    fixed.push_str(input.get(copied..)?);
+    Some(format!(
+        "the indexes of `&` and `$` are written in parentheses: `{fixed}`"
+    ))
+}
+
+// `[` or `(` never closed, or `]` or `)` closing nothing
+fn unmatched_bracket(tokens: &[Token]) -> Option<String> {
+    let mut open = Vec::new();
+    for token in tokens {
+        let (opening, closing) = match token.kind {
+            TokenKind::OpenBrkt | TokenKind::OpenPrnth => {
+                open.push(token);
+                continue;
+            }
+            TokenKind::CloseBrkt => ('[', ']'),
+            TokenKind::ClosePrnth => ('(', ')'),
+            _ => continue,
+        };
+        match open.pop() {
+            Some(last) if bracket(last) == opening => {}
+            Some(last) => {
+                let expected = if bracket(last) == '[' { ']' } else { ')' };
+                return Some(format!(
+                    "the `{}` at {} is closed by `{expected}`, not by the `{closing}` at {}",
+                    bracket(last),
+                    last.pos,
+                    token.pos
+                ));
+            }
+            None => {
+                return Some(format!(
+                    "the `{closing}` at {} closes no `{opening}`",
+                    token.pos
+                ))
+            }
+        }
+    }
+    open.pop().map(|last| {
+        let closing = if bracket(last) == '[' { ']' } else { ')' };
+        format!(
+            "the `{}` at {} is never closed, add a `{closing}`",
+            bracket(last),
+            last.pos
+        )
+    })
+}
+
+fn bracket(token: &Token) -> char {
+    match token.kind {
+        TokenKind::OpenBrkt => '[',
+        _ => '(',
+    }
+}
+
+// Special characters in a literal key of the left hand side, e.g. the dots of `a.b`
+fn unescaped_key(input: &str, tokens: &[Token], cause: &ParseErrorCause) -> Option<String> {
+    let ParseErrorCause::UnexpectedToken(token) = cause else {
+        return None;
+    };
+    if !matches!(
+        tokens.first().map(|token| &token.kind),
+        Some(TokenKind::Key(_) | TokenKind::Star | TokenKind::Pipe)
+    ) || matches!(
+        token.kind,
+        TokenKind::Key(_) | TokenKind::Star | TokenKind::Pipe
+    ) {
+        return None;
+    }
+
+    // `*` and `|` are the wildcards of the keys
+    let mut escaped = String::with_capacity(input.len() + 1);
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            escaped.push(c);
+            escaped.extend(chars.next());
+            continue;
+        }
+        if SPECIAL_CHARS.contains(&c) && c != '*' && c != '|' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Some(match token.kind {
+        TokenKind::Dot => format!(
+            "the dots of the keys on the left hand side don't separate levels, escape them: \
+             `{escaped}`"
+        ),
+        _ => format!("escape the special characters of the key: `{escaped}`"),
+    })
+}
+
+// `*` outside of the brackets of an output path, e.g. `data.*`
+fn star_in_rhs(input: &str, cause: &ParseErrorCause) -> Option<String> {
+    let ParseErrorCause::UnexpectedToken(Token {
+        kind: TokenKind::Star,
+        pos,
+    }) = cause
+    else {
+        return None;
+    };
+    let fixed = format!("{}&{}", input.get(..*pos)?, input.get(pos + 1..)?);
+    Some(format!(
+        "`*` only matches the keys on the left hand side, write the matched key with `&`: \
+         `{fixed}`"
+    ))
+}
@@ -1,4 +1,4 @@
-use super::ast::{Rhs, Lhs, Stars, IndexOp, RhsEntry, RhsPart};
+use super::ast::{Rhs, Lhs, Stars, IndexOp, RhsEntry, RhsPart, KeyCase, KeyFormat, Marker};
 
 struct LhsTestCase<'a> {
     expr: &'a str,
@@ -39,6 +39,15 @@ fn test_parse_lhs_square() {
     .run();
 }
 
+#[test]
+fn test_parse_lhs_square_dots() {
+    LhsTestCase {
+        expr: "#2.5",
+        expected: Lhs::Square("2.5".into()),
+    }
+    .run();
+}
+
 #[test]
 fn test_parse_lhs_key() {
     LhsTestCase {
@@ -52,7 +61,7 @@ fn test_parse_lhs_key() {
 fn test_parse_lhs_star() {
     LhsTestCase {
         expr: "*",
-        expected: Lhs::Pipes(vec![Stars(vec!["".into(), "".into()])]),
+        expected: Lhs::Pipes(vec![Stars::new(vec!["".into(), "".into()])]),
     }
     .run();
 }
@@ -61,7 +70,11 @@ fn test_parse_lhs_star() {
 fn test_parse_lhs_stars() {
     LhsTestCase {
         expr: "qwe*asd*zxc",
-        expected: Lhs::Pipes(vec![Stars(vec!["qwe".into(), "asd".into(), "zxc".into()])]),
+        expected: Lhs::Pipes(vec![Stars::new(vec![
+            "qwe".into(),
+            "asd".into(),
+            "zxc".into(),
+        ])]),
     }
     .run();
 }
@@ -70,7 +83,7 @@ fn test_parse_lhs_stars() {
 fn test_parse_lhs_stars_leading() {
     LhsTestCase {
         expr: "*qwe*asd*zxc",
-        expected: Lhs::Pipes(vec![Stars(vec![
+        expected: Lhs::Pipes(vec![Stars::new(vec![
             "".into(),
             "qwe".into(),
             "asd".into(),
@@ -84,7 +97,7 @@ fn test_parse_lhs_stars_leading() {
 fn test_parse_lhs_stars_trailing() {
     LhsTestCase {
         expr: "qwe*asd*zxc*",
-        expected: Lhs::Pipes(vec![Stars(vec![
+        expected: Lhs::Pipes(vec![Stars::new(vec![
             "qwe".into(),
             "asd".into(),
             "zxc".into(),
@@ -94,14 +107,27 @@ fn test_parse_lhs_stars_trailing() {
     .run();
 }
 
+#[test]
+fn test_parse_lhs_prefix_suffix() {
+    LhsTestCase {
+        expr: "qwe*|*asd|*",
+        expected: Lhs::Pipes(vec![
+            Stars::Prefix("qwe".into()),
+            Stars::Suffix("asd".into()),
+            Stars::Prefix("".into()),
+        ]),
+    }
+    .run();
+}
+
 #[test]
 fn test_parse_lhs_pipe() {
     LhsTestCase {
         expr: "qwe|asd|zxc",
         expected: Lhs::Pipes(vec![
-            Stars(vec!["qwe".into()]),
-            Stars(vec!["asd".into()]),
-            Stars(vec!["zxc".into()]),
+            Stars::new(vec!["qwe".into()]),
+            Stars::new(vec!["asd".into()]),
+            Stars::new(vec!["zxc".into()]),
         ]),
     }
     .run();
@@ -112,10 +138,10 @@ fn test_parse_lhs_pipe_trailing() {
     LhsTestCase {
         expr: "qwe|asd|zxc|",
         expected: Lhs::Pipes(vec![
-            Stars(vec!["qwe".into()]),
-            Stars(vec!["asd".into()]),
-            Stars(vec!["zxc".into()]),
-            Stars(vec!["".into()]),
+            Stars::new(vec!["qwe".into()]),
+            Stars::new(vec!["asd".into()]),
+            Stars::new(vec!["zxc".into()]),
+            Stars::new(vec!["".into()]),
         ]),
     }
     .run();
@@ -126,10 +152,10 @@ fn test_parse_lhs_pipe_leading() {
     LhsTestCase {
         expr: "|qwe|asd|zxc",
         expected: Lhs::Pipes(vec![
-            Stars(vec!["".into()]),
-            Stars(vec!["qwe".into()]),
-            Stars(vec!["asd".into()]),
-            Stars(vec!["zxc".into()]),
+            Stars::new(vec!["".into()]),
+            Stars::new(vec!["qwe".into()]),
+            Stars::new(vec!["asd".into()]),
+            Stars::new(vec!["zxc".into()]),
         ]),
     }
     .run();
@@ -144,6 +170,27 @@ fn test_parse_lhs_at_full() {
     .run();
 }
 
+#[test]
+fn test_parse_lhs_at_key() {
+    LhsTestCase {
+        expr: "@qwe",
+        expected: Lhs::At(0, "qwe".into()),
+    }
+    .run();
+    LhsTestCase {
+        expr: "@qwe.asd",
+        expected: Lhs::At(
+            0,
+            Rhs(vec![
+                RhsPart::Key(RhsEntry::Key("qwe".into())),
+                RhsPart::Key(RhsEntry::Key("asd".into())),
+            ])
+            .into(),
+        ),
+    }
+    .run();
+}
+
 #[test]
 fn test_parse_lhs_at_short() {
     LhsTestCase {
@@ -287,6 +334,127 @@ fn test_parse_rhs_amp_short_troll() {
     .run();
 }
 
+#[test]
+fn test_parse_rhs_amp_case() {
+    RhsTestCase {
+        expr: "data.&(1):snake.&:camelId",
+        expected: Rhs(vec![
+            RhsPart::Key(RhsEntry::Key("data".into())),
+            RhsPart::Key(RhsEntry::AmpFormat(1, 0, KeyFormat::Case(KeyCase::Snake))),
+            RhsPart::CompositeKey(vec![
+                RhsEntry::AmpFormat(0, 0, KeyFormat::Case(KeyCase::Camel)),
+                RhsEntry::Key("Id".into()),
+            ]),
+        ]),
+    }
+    .run();
+}
+
+#[test]
+fn test_parse_rhs_amp_zero_pad() {
+    RhsTestCase {
+        expr: "line-&(1):%03d.&:%0d",
+        expected: Rhs(vec![
+            RhsPart::CompositeKey(vec![
+                RhsEntry::Key("line-".into()),
+                RhsEntry::AmpFormat(1, 0, KeyFormat::ZeroPad(3)),
+            ]),
+            RhsPart::CompositeKey(vec![RhsEntry::Amp(0, 0), RhsEntry::Key(":%0d".into())]),
+        ]),
+    }
+    .run();
+    assert_eq!(KeyFormat::ZeroPad(3).apply("7"), "007");
+    assert_eq!(KeyFormat::ZeroPad(3).apply("1234"), "1234");
+    assert_eq!(KeyFormat::ZeroPad(3).apply("a1"), "a1");
+}
+
+#[test]
+fn test_parse_rhs_amp_literal_colon() {
+    RhsTestCase {
+        expr: "&:id",
+        expected: Rhs(vec![RhsPart::CompositeKey(vec![
+            RhsEntry::Amp(0, 0),
+            RhsEntry::Key(":id".into()),
+        ])]),
+    }
+    .run();
+}
+
+#[test]
+fn test_key_case_convert() {
+    let convert = |case: KeyCase| {
+        [
+            "userId",
+            "HTTPServer_id",
+            "first-name",
+            "Order ID2x",
+            "already_snake",
+        ]
+        .map(|key| case.convert(key))
+    };
+
+    assert_eq!(
+        convert(KeyCase::Snake),
+        [
+            "user_id",
+            "http_server_id",
+            "first_name",
+            "order_id2x",
+            "already_snake"
+        ]
+    );
+    assert_eq!(
+        convert(KeyCase::Kebab),
+        [
+            "user-id",
+            "http-server-id",
+            "first-name",
+            "order-id2x",
+            "already-snake"
+        ]
+    );
+    assert_eq!(
+        convert(KeyCase::Camel),
+        [
+            "userId",
+            "httpServerId",
+            "firstName",
+            "orderId2x",
+            "alreadySnake"
+        ]
+    );
+    assert_eq!(
+        convert(KeyCase::Pascal),
+        [
+            "UserId",
+            "HttpServerId",
+            "FirstName",
+            "OrderId2x",
+            "AlreadySnake"
+        ]
+    );
+    assert_eq!(
+        convert(KeyCase::Lower),
+        [
+            "userid",
+            "httpserver_id",
+            "first-name",
+            "order id2x",
+            "already_snake"
+        ]
+    );
+    assert_eq!(
+        convert(KeyCase::Upper),
+        [
+            "USERID",
+            "HTTPSERVER_ID",
+            "FIRST-NAME",
+            "ORDER ID2X",
+            "ALREADY_SNAKE"
+        ]
+    );
+}
+
 #[test]
 fn test_parse_rhs_at_full() {
     RhsTestCase {
@@ -366,6 +534,15 @@ fn test_parse_rhs_misc() {
         ]),
     }
     .run();
+    RhsTestCase {
+        expr: "photos[$(1,0)].id",
+        expected: Rhs(vec![
+            RhsPart::Key(RhsEntry::Key("photos".into())),
+            RhsPart::Index(IndexOp::DollarSign(1, 0)),
+            RhsPart::Key(RhsEntry::Key("id".into())),
+        ]),
+    }
+    .run();
     RhsTestCase {
         expr: "This is a review",
         expected: Rhs(vec![RhsPart::Key(RhsEntry::Key("This is a review".into()))]),
@@ -387,6 +564,14 @@ fn test_parse_rhs_misc() {
         ]),
     }
     .run();
+    RhsTestCase {
+        expr: "tags[?]",
+        expected: Rhs(vec![
+            RhsPart::Key(RhsEntry::Key("tags".into())),
+            RhsPart::Index(IndexOp::Unique),
+        ]),
+    }
+    .run();
     RhsTestCase {
         expr: "sillyListOfTunaIds[].id",
         expected: Rhs(vec![
@@ -410,6 +595,15 @@ fn test_parse_rhs_misc() {
         expected: Rhs(vec![RhsPart::Key(RhsEntry::Key("9876".into()))]),
     }
     .run();
+    RhsTestCase {
+        expr: "list[#2].id",
+        expected: Rhs(vec![
+            RhsPart::Key(RhsEntry::Key("list".into())),
+            RhsPart::Index(IndexOp::Square(2)),
+            RhsPart::Key(RhsEntry::Key("id".into())),
+        ]),
+    }
+    .run();
     RhsTestCase {
         expr: "&[]",
         expected: Rhs(vec![
@@ -556,6 +750,19 @@ fn test_parse_rhs_empty() {
     .run();
 }
 
+#[test]
+fn test_parse_rhs_idx_star() {
+    RhsTestCase {
+        expr: "items[*].id",
+        expected: Rhs(vec![
+            RhsPart::Key(RhsEntry::Key("items".into())),
+            RhsPart::Index(IndexOp::Star),
+            RhsPart::Key(RhsEntry::Key("id".into())),
+        ]),
+    }
+    .run();
+}
+
 #[test]
 fn test_parse_rhs_idx_at() {
     RhsTestCase {
@@ -567,3 +774,139 @@ fn test_parse_rhs_idx_at() {
     }
     .run();
 }
+
+#[test]
+fn test_parse_rhs_fan_out() {
+    let rhss = Rhs::parse_many("a.b|x[]|\\|y").expect("parsed rhs");
+
+    assert_eq!(
+        rhss,
+        vec![
+            Rhs(vec![
+                RhsPart::Key(RhsEntry::Key("a".into())),
+                RhsPart::Key(RhsEntry::Key("b".into())),
+            ]),
+            Rhs(vec![
+                RhsPart::Key(RhsEntry::Key("x".into())),
+                RhsPart::Index(IndexOp::Empty),
+            ]),
+            Rhs(vec![RhsPart::Key(RhsEntry::Key("|y".into()))]),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_rhs_fan_out_empty() {
+    assert_eq!(Rhs::parse_many("").unwrap(), vec![Rhs(vec![])]);
+    assert!(Rhs::parse_many("a|").is_err());
+    assert!(Rhs::parse_many("|a").is_err());
+    assert!(Rhs::parse_many("a||b").is_err());
+}
+
+#[test]
+fn test_display_rhs() {
+    for expr in [
+        "a.b",
+        "a[]",
+        "a[?]",
+        "a[1].b",
+        "@(1,items[*].id)",
+        "&(1,0).@(2,x.y)[&(0,1)]",
+        "list[#2].id",
+        "photo-&(0,0)-url",
+        "a\\.b",
+        "&(1,0):snake.&(0,0):pascal-id",
+        "lines.&(1,0):%04d",
+    ] {
+        let rhs = Rhs::parse(expr).expect("parsed rhs");
+        assert_eq!(rhs.to_string(), expr);
+    }
+}
+
+#[test]
+fn test_display_lhs() {
+    for expr in [
+        "@", "@(1,a.b)", "$(1,0)", "&(0,2)", "#2.5", "#a\\@b", "a\\.b", "*", "a*b*", "|qwe|a*",
+    ] {
+        let lhs = Lhs::parse(expr).expect("parsed lhs");
+        assert_eq!(lhs.to_string(), expr);
+    }
+}
+
+#[test]
+fn test_parse_marked_lhs() {
+    let parse = |expr: &str| Lhs::parse_marked(expr).expect("parsed lhs");
+
+    assert_eq!(
+        parse("id!"),
+        (Lhs::Literal("id".into()), Some(Marker::Required))
+    );
+    assert_eq!(parse("id\\?"), (Lhs::Literal("id?".into()), None));
+    assert_eq!(parse("?"), (Lhs::Literal("?".into()), None));
+    assert_eq!(parse("#yes!"), (Lhs::Square("yes!".into()), None));
+    assert_eq!(
+        parse("@(1,a)?"),
+        (
+            Lhs::At(
+                1,
+                Box::new(Rhs(vec![RhsPart::Key(RhsEntry::Key("a".into()))]))
+            ),
+            Some(Marker::Optional)
+        )
+    );
+    assert_eq!(
+        parse("a*?"),
+        (
+            Lhs::Pipes(vec![Stars::Pieces(vec!["a".into(), "?".into()])]),
+            None
+        )
+    );
+    assert_eq!(Lhs::Literal("id?".into()).to_string(), "id\\?");
+}
+
+#[test]
+fn test_display_rhs_part() {
+    let rhs = Rhs::parse("a&(1,0).b[?]").expect("parsed rhs");
+    let parts: Vec<String> = rhs.0.iter().map(ToString::to_string).collect();
+    assert_eq!(parts, ["a&(1,0)", "b", "[?]"]);
+}
+
+#[test]
+fn test_parse_error_help() {
+    let lhs_help = |expr: &str| Lhs::parse(expr).expect_err("invalid lhs").help;
+    let rhs_help = |expr: &str| Rhs::parse(expr).expect_err("invalid rhs").help;
+
+    assert_eq!(
+        lhs_help("user.name").as_deref(),
+        Some("the dots of the keys on the left hand side don't separate levels, escape them: `user\\.name`")
+    );
+    assert_eq!(
+        lhs_help("user@example").as_deref(),
+        Some("escape the special characters of the key: `user\\@example`")
+    );
+    assert_eq!(
+        lhs_help("&1").as_deref(),
+        Some("the indexes of `&` and `$` are written in parentheses: `&(1)`")
+    );
+    assert_eq!(
+        rhs_help("items[&1].id").as_deref(),
+        Some("the indexes of `&` and `$` are written in parentheses: `items[&(1)].id`")
+    );
+    assert_eq!(
+        rhs_help("items[0.id").as_deref(),
+        Some("the `[` at 5 is never closed, add a `]`")
+    );
+    assert_eq!(
+        rhs_help("items0].id").as_deref(),
+        Some("the `]` at 6 closes no `[`")
+    );
+    assert_eq!(
+        rhs_help("@(1,items]").as_deref(),
+        Some("the `(` at 1 is closed by `)`, not by the `]` at 9")
+    );
+    assert_eq!(
+        rhs_help("data.*").as_deref(),
+        Some("`*` only matches the keys on the left hand side, write the matched key with `&`: `data.&`")
+    );
+    assert_eq!(rhs_help("a[b]"), None);
+}
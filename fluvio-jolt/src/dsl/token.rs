@@ -4,6 +4,8 @@ pub struct Token {
     pub pos: usize,
 }
 
+/// Kind of a token of the jolt expressions, see the
+/// [grammar](https://github.com/infinyon/fluvio-jolt/blob/master/SPEC.md).
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind {
     DollarSign,
@@ -18,5 +20,6 @@ pub enum TokenKind {
     ClosePrnth,
     Dot,
     Comma,
+    /// Key or index literal, with the escaped characters unescaped
     Key(String),
 }
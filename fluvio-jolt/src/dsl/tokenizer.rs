@@ -26,11 +26,13 @@ impl<'input> Tokenizer<'input> {
         let c = self.chars.next().ok_or(ParseError {
             pos: self.pos(),
             cause: Box::new(ParseErrorCause::UnexpectedEndOfInput),
+            help: None,
         })?;
-        if !SPECIAL_CHARS.contains(&c) {
+        if !SPECIAL_CHARS.contains(&c) && !MARKER_CHARS.contains(&c) {
             return Err(ParseError {
                 pos: self.pos(),
                 cause: Box::new(ParseErrorCause::UnexpectedChar(c)),
+                help: None,
             });
         }
         Ok(c)
@@ -61,6 +63,7 @@ impl<'input> Tokenizer<'input> {
             return Err(ParseError {
                 pos: self.pos(),
                 cause: Box::new(ParseErrorCause::PutBackBufferFull),
+                help: None,
             });
         }
 
@@ -139,6 +142,34 @@ impl<'input> Tokenizer<'input> {
     }
 }
 
-const SPECIAL_CHARS: [char; 13] = [
+pub(crate) const SPECIAL_CHARS: [char; 13] = [
     '$', '&', '@', '#', '*', '|', '[', ']', '(', ')', '.', ',', '\\',
 ];
+
+/// The markers of the left hand side keys, they are only special at the end of a key, see
+/// `Lhs::parse_marked`.
+pub(crate) const MARKER_CHARS: [char; 2] = ['?', '!'];
+
+/// Escapes the special characters of the key with `\`, so the key is matched or written as it
+/// is, e.g. by the specifications generated by tools.
+///
+/// ```
+/// use fluvio_jolt::escape_key;
+///
+/// assert_eq!(escape_key("foo.bar"), r"foo\.bar");
+/// assert_eq!(escape_key("@literal"), r"\@literal");
+/// assert_eq!(escape_key("plain key"), "plain key");
+/// assert_eq!(escape_key("done?"), r"done\?");
+/// ```
+pub fn escape_key(key: &str) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        let marker = chars.peek().is_none() && MARKER_CHARS.contains(&c);
+        if marker || SPECIAL_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
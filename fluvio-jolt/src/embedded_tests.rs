@@ -0,0 +1,128 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{transform_with_context, Context, Result, TransformOptions, TransformSpec};
+
+/// A test case of the `tests` block of a specification: the input and the expected output of the
+/// chain, `null` for the documents dropped by the chain.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct EmbeddedTest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    input: Value,
+    expected: Value,
+}
+
+impl EmbeddedTest {
+    // The clock of the tests is set at the Unix epoch, so the outputs don't depend on the time
+    // the tests are run at
+    pub(crate) fn run(&self, index: usize, spec: &TransformSpec) -> Option<EmbeddedTestFailure> {
+        let context = Context::default().clock(|| SystemTime::UNIX_EPOCH);
+        let actual = transform_with_context(
+            self.input.clone(),
+            spec,
+            &TransformOptions::default(),
+            &context,
+        );
+        match &actual {
+            Ok(output) if *output == self.expected => None,
+            _ => Some(EmbeddedTestFailure {
+                index,
+                name: self.name.clone(),
+                expected: self.expected.clone(),
+                actual,
+            }),
+        }
+    }
+}
+
+/// A test case of the `tests` block that failed, returned by
+/// [`TransformSpec::run_embedded_tests`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedTestFailure {
+    /// Index of the test case in the `tests` block.
+    pub index: usize,
+    /// Name of the test case, if any.
+    pub name: Option<String>,
+    /// The expected output.
+    pub expected: Value,
+    /// The output of the chain, or the error the chain failed with.
+    pub actual: Result<Value>,
+}
+
+impl fmt::Display for EmbeddedTestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "test {}", self.index)?;
+        if let Some(name) = &self.name {
+            write!(f, " '{name}'")?;
+        }
+        match &self.actual {
+            Ok(output) => write!(f, " failed: expected {}, got {output}", self.expected),
+            Err(e) => write!(f, " failed: expected {}, got error: {e}", self.expected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use crate::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_run_embedded_tests() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "tests": [
+                {"name": "renames", "input": {"id": 1}, "expected": {"data": {"id": 1}}},
+                {"input": {"id": 2}, "expected": {"data": {"id": 3}}},
+                {"input": {"id": 3, "idx": "x"}, "expected": {"data": {"id": 3}}},
+                {"input": {"id": 0}, "expected": null}
+            ],
+            "operations": [
+                {"operation": "halt-if", "drop": true, "spec": {"path": "id", "equals": 0}},
+                {"operation": "shift", "spec": {"id": "data.id", "idx": "list[@(1,idx)]"}}
+            ]
+        }))
+        .expect("parsed spec");
+
+        //when
+        let result = spec.run_embedded_tests();
+        let sliced = spec.slice(1..).run_embedded_tests();
+
+        //then
+        let failures = result.unwrap_err();
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| failure.index)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            failures[0].to_string(),
+            r#"test 1 failed: expected {"data":{"id":3}}, got {"data":{"id":2}}"#
+        );
+        assert!(matches!(
+            &failures[1].actual,
+            Err(Error::SpecKey { source, .. }) if matches!(**source, Error::InvalidIndexVal { .. })
+        ));
+        assert_eq!(sliced, Ok(0));
+    }
+
+    #[test]
+    fn test_unknown_test_field() {
+        let spec = serde_json::from_value::<TransformSpec>(json!({
+            "tests": [{"input": {}, "output": {}}],
+            "operations": []
+        }));
+
+        assert!(spec.is_err());
+    }
+}
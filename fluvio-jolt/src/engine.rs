@@ -0,0 +1,176 @@
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::{
+    apply_chain, transform_filtered, transform_with_context, Context, Error, Result,
+    TransformOptions, TransformSpec,
+};
+
+/// A transformation ready to be applied to many inputs: the specification along with the
+/// [`TransformOptions`] and the [`Context`] it is applied with. The engine is `Send + Sync` and
+/// can be shared between threads behind an `Arc`.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{JoltEngine, TransformOptions, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id"}}
+/// ]))
+/// .unwrap();
+///
+/// let engine = JoltEngine::new(spec).with_options(TransformOptions::default());
+///
+/// assert_eq!(engine.run(json!({"id": 1})).unwrap(), json!({"data": {"id": 1}}));
+/// assert_eq!(engine.run_bytes(br#"{"id": 2}"#).unwrap(), br#"{"data":{"id":2}}"#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JoltEngine {
+    spec: TransformSpec,
+    options: TransformOptions,
+    context: Context,
+}
+
+impl JoltEngine {
+    /// Creates an engine applying the specification with the default options and an empty
+    /// context.
+    pub fn new(spec: TransformSpec) -> Self {
+        Self {
+            spec,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the options the specification is applied with.
+    pub fn with_options(mut self, options: TransformOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the context passed to the operations.
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn spec(&self) -> &TransformSpec {
+        &self.spec
+    }
+
+    pub fn options(&self) -> &TransformOptions {
+        &self.options
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Transforms the input.
+    pub fn run(&self, input: Value) -> Result<Value> {
+        transform_with_context(input, &self.spec, &self.options, &self.context)
+    }
+
+    /// Transforms the input. Returns `None` if the input is dropped by a `halt-if` or `sample`
+    /// operation.
+    pub fn run_filtered(&self, input: Value) -> Result<Option<Value>> {
+        transform_filtered(input, &self.spec, &self.options, &self.context)
+    }
+
+    /// Transforms the input like [`JoltEngine::run_filtered`] without consuming it, see
+    /// [`transform_ref`](crate::transform_ref). The input is only copied if an operation of the
+    /// chain modifies it, e.g. to apply the chain to a document holding a large state.
+    pub fn run_filtered_ref(&self, input: &Value) -> Result<Option<Value>> {
+        apply_chain(
+            Cow::Borrowed(input),
+            &self.spec,
+            &self.options,
+            &self.context,
+            |_| {},
+        )
+    }
+
+    /// Transforms the serialized JSON input into the serialized JSON output.
+    pub fn run_bytes(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let input = serde_json::from_slice(input).map_err(|e| Error::InvalidJson(e.to_string()))?;
+        let output = self.run(input)?;
+        serde_json::to_vec(&output).map_err(|e| Error::InvalidJson(e.to_string()))
+    }
+
+    /// Transforms every input of the batch. A failed input doesn't stop the batch, its error is
+    /// returned in place of the output.
+    ///
+    /// With the `arena` feature the scratch space of the `shift` operations is reset between the
+    /// inputs and reused, instead of being allocated for every input.
+    pub fn run_batch(&self, inputs: impl IntoIterator<Item = Value>) -> Vec<Result<Value>> {
+        inputs.into_iter().map(|input| self.run(input)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_run_batch() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"id": "ids[@(1,idx)]"}}
+        ]))
+        .expect("parsed spec");
+        let engine = JoltEngine::new(spec).with_options(TransformOptions::default());
+
+        //when
+        let result = engine.run_batch([
+            json!({"id": 1, "idx": 0}),
+            json!({"id": 2, "idx": "x"}),
+            json!({"id": 3, "idx": 1}),
+        ]);
+
+        //then
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].as_ref().unwrap(), &json!({"ids": [1]}));
+        assert!(matches!(
+            &result[1],
+            Err(Error::SpecKey { source, .. })
+                if matches!(&**source, Error::InvalidIndexVal { value, .. } if value == "x")
+        ));
+        assert_eq!(result[2].as_ref().unwrap(), &json!({"ids": [null, 3]}));
+    }
+
+    #[test]
+    fn test_run_filtered_ref() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "halt-if", "drop": true, "spec": {"path": "cur", "equals": 0}},
+            {"operation": "shift", "spec": {"cur": "id", "@(0,state.@(0,cur))": "name"}}
+        ]))
+        .expect("parsed spec");
+        let engine = JoltEngine::new(spec);
+        let mut input = json!({"cur": 1, "state": {"1": "John", "2": "Jane"}});
+
+        //when
+        let kept = engine.run_filtered_ref(&input);
+        input["cur"] = json!(0);
+        let dropped = engine.run_filtered_ref(&input);
+
+        //then
+        assert_eq!(kept.unwrap(), Some(json!({"id": 1, "name": "John"})));
+        assert_eq!(dropped.unwrap(), None);
+        assert_eq!(input["state"]["2"], "Jane");
+    }
+
+    #[test]
+    fn test_run_bytes_invalid_json() {
+        //given
+        let engine = JoltEngine::default();
+
+        //when
+        let result = engine.run_bytes(b"{");
+
+        //then
+        assert!(matches!(result, Err(Error::InvalidJson(_))));
+    }
+}
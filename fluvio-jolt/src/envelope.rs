@@ -0,0 +1,172 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::Context;
+
+/// The envelope the output of a chain is wrapped in: the version of the schema of the output, the
+/// time the output is produced at and the output itself.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Envelope {
+    /// Version of the schema of the output, written to the `schema_version` field.
+    schema_version: String,
+    /// Whether the `produced_at` field is written, the milliseconds since the Unix epoch of the
+    /// clock of the context.
+    #[serde(default = "produced_at")]
+    produced_at: bool,
+    /// Key of the output in the envelope.
+    #[serde(default = "data")]
+    data: String,
+}
+
+fn produced_at() -> bool {
+    true
+}
+
+fn data() -> String {
+    "data".to_string()
+}
+
+impl Envelope {
+    pub(crate) fn data(&self) -> &str {
+        &self.data
+    }
+
+    pub(crate) fn wrap(&self, output: Value, context: &Context) -> Value {
+        let mut envelope = Map::with_capacity(3);
+        envelope.insert(
+            "schema_version".to_string(),
+            Value::String(self.schema_version.clone()),
+        );
+        if self.produced_at {
+            // a clock set before the epoch produces at the epoch
+            let millis = context
+                .now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64);
+            envelope.insert("produced_at".to_string(), Value::from(millis));
+        }
+        envelope.insert(self.data.clone(), output);
+        Value::Object(envelope)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use serde_json::json;
+    use crate::{transform, transform_filtered, transform_stepwise, TransformOptions, TransformSpec};
+
+    use super::*;
+
+    #[test]
+    fn test_envelope() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "envelope": {"schema_version": "2.1"},
+            "operations": [
+                {"operation": "halt-if", "drop": true, "spec": {"path": "id", "equals": 0}},
+                {"operation": "shift", "spec": {"id": "id"}}
+            ]
+        }))
+        .expect("parsed spec");
+        let time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let context = Context::default().clock(move || time);
+        let options = TransformOptions::default();
+
+        //when
+        let output = transform_filtered(json!({"id": 1}), &spec, &options, &context);
+        let dropped = transform_filtered(json!({"id": 0}), &spec, &options, &context);
+
+        //then
+        assert_eq!(
+            output.unwrap(),
+            Some(json!({
+                "schema_version": "2.1",
+                "produced_at": 1_700_000_000_123u64,
+                "data": {"id": 1}
+            }))
+        );
+        assert_eq!(dropped.unwrap(), None);
+    }
+
+    #[test]
+    fn test_envelope_options() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "envelope": {"schema_version": "1", "produced_at": false, "data": "payload"},
+            "operations": [{"operation": "shift", "spec": {"id": "id"}}]
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = transform(json!({"id": 1}), &spec);
+
+        //then
+        assert_eq!(
+            output.unwrap(),
+            json!({"schema_version": "1", "payload": {"id": 1}})
+        );
+        let serialized = serde_json::to_value(&spec).expect("serialized spec");
+        assert_eq!(serialized["envelope"]["data"], "payload");
+        assert_eq!(
+            serde_json::from_value::<TransformSpec>(serialized).expect("parsed spec"),
+            spec
+        );
+        assert!(spec.slice(1..).has_envelope());
+        assert!(!spec.slice(..0).has_envelope());
+    }
+
+    #[test]
+    fn test_envelope_stepwise() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "envelope": {"schema_version": "1", "produced_at": false},
+            "operations": [
+                {"operation": "shift", "spec": {"id": "id"}},
+                {"operation": "halt-if", "spec": {"path": "id", "equals": 0}},
+                {"operation": "default", "spec": {"kind": "user"}}
+            ]
+        }))
+        .expect("parsed spec");
+
+        //when
+        let steps = transform_stepwise(json!({"id": 1}), &spec).unwrap();
+        let halted = transform_stepwise(json!({"id": 0}), &spec).unwrap();
+
+        //then
+        assert_eq!(
+            steps,
+            vec![
+                json!({"id": 1}),
+                json!({"id": 1}),
+                json!({"schema_version": "1", "data": {"id": 1, "kind": "user"}})
+            ]
+        );
+        assert_eq!(
+            steps.last(),
+            Some(&transform(json!({"id": 1}), &spec).unwrap())
+        );
+        assert_eq!(
+            halted,
+            vec![
+                json!({"id": 0}),
+                json!({"schema_version": "1", "data": {"id": 0}})
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_envelope_field() {
+        let spec = serde_json::from_value::<TransformSpec>(json!({
+            "envelope": {"schema_version": "1", "version": "1"},
+            "operations": []
+        }));
+
+        assert!(spec.is_err());
+    }
+}
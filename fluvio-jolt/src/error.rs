@@ -1,7 +1,13 @@
 use thiserror::Error as ThisError;
-use std::{result::Result as StdResult, num::ParseIntError};
+use std::result::Result as StdResult;
+use serde_json::{json, Value};
 
-#[derive(Debug, ThisError)]
+use crate::SandboxLimit;
+
+/// Maximum length in bytes of the serialized input embedded into [`Error::to_json`] documents.
+pub const MAX_ERROR_INPUT_LEN: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, ThisError)]
 pub enum Error {
     #[error("Path index out of range when using wildcard. Index={idx};Length={len};")]
     PathIndexOutOfRange { idx: usize, len: usize },
@@ -15,20 +21,269 @@ pub enum Error {
     UnexpectedObjectInRhs,
     #[error("Not implemented yet.")]
     Todo,
-    #[error("Invalid index in expression.\n{0}")]
-    InvalidIndex(ParseIntError),
+    #[error("Match `{reference}` can't be used as an array index, it evaluated to {value}.")]
+    InvalidIndex { reference: String, value: Value },
     #[error("Array index out of range. Index={idx};Length={len};")]
     ArrIndexOutOfRange { idx: usize, len: usize },
-    #[error("Json value can't be used as an index: {0:?}")]
-    InvalidIndexVal(serde_json::Value),
+    #[error("Lookup `{lookup}` can't be used as an array index, it evaluated to {value}.")]
+    InvalidIndexVal { lookup: String, value: Value },
     #[error("Key not found in object:{0}")]
     KeyNotFound(String),
+    #[error("Required key `{key}` is missing from the input at `{pointer}`.")]
+    MissingRequiredKey {
+        key: String,
+        /// RFC 6901 pointer of the input value missing the key.
+        pointer: String,
+    },
     #[error("Expression didn't evaluate to a string.")]
     EvalString,
     #[error("Empty path while executing shift. THIS SHOULD NEVER HAPPEN.")]
     ShiftEmptyPath,
     #[error("Path is not empty after executing shift. THIS SHOULD NEVER HAPPEN.")]
     ShiftPathNotEmpty,
+    #[error("Unexpected output value while executing shift. THIS SHOULD NEVER HAPPEN.")]
+    ShiftUnexpectedOutput,
+    #[error("Write to array index skips indexes. Index={idx};Length={len};")]
+    SparseArrayWrite { idx: usize, len: usize },
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
+    #[error("Conflicting writes to output path {path}: `{first}` and `{second}`.")]
+    OutputConflict {
+        path: String,
+        first: String,
+        second: String,
+    },
+    #[error("Input doesn't meet the expectations of the specification: {}", .0.join("; "))]
+    UnmetExpectations(Vec<String>),
+    #[error("Sandbox limit of the {limit} exceeded. Maximum={max};")]
+    SandboxLimitExceeded { limit: SandboxLimit, max: u64 },
+    #[error("Evaluation budget of {fuel} steps exhausted.")]
+    BudgetExhausted { fuel: u64 },
+    #[error(
+        "Output written up to {} when the operation failed: {source}",
+        pointer.as_deref().unwrap_or("no output path")
+    )]
+    PartialOutput {
+        /// RFC 6901 pointer of the output value being written, `-` for an appended element,
+        /// `None` if the operation failed while matching the input.
+        pointer: Option<String>,
+        /// The output of the operation built before it failed.
+        output: Box<Value>,
+        source: Box<Error>,
+    },
+    #[error("Spec key {path} failed: {source}")]
+    SpecKey {
+        /// RFC 6901 pointer of the key in the spec of the operation, e.g. `/items/*` for the
+        /// `*` key nested in the `items` key of a shift.
+        path: String,
+        source: Box<Error>,
+    },
+    #[error("Operation '{name}' (index {index}) failed: {source}")]
+    Operation {
+        name: String,
+        index: usize,
+        source: Box<Error>,
+    },
 }
 
 pub type Result<T> = StdResult<T, Error>;
+
+impl Error {
+    /// Stable machine-readable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::PathIndexOutOfRange { .. } => "path_index_out_of_range",
+            Error::MatchIndexOutOfRange { .. } => "match_index_out_of_range",
+            Error::UnexpectedEndOfRhs => "unexpected_end_of_rhs",
+            Error::UnexpectedRhsEntry => "unexpected_rhs_entry",
+            Error::UnexpectedObjectInRhs => "unexpected_object_in_rhs",
+            Error::Todo => "not_implemented",
+            Error::InvalidIndex { .. } => "invalid_index",
+            Error::ArrIndexOutOfRange { .. } => "array_index_out_of_range",
+            Error::InvalidIndexVal { .. } => "invalid_index_value",
+            Error::KeyNotFound(_) => "key_not_found",
+            Error::MissingRequiredKey { .. } => "missing_required_key",
+            Error::EvalString => "eval_string",
+            Error::ShiftEmptyPath => "shift_empty_path",
+            Error::ShiftPathNotEmpty => "shift_path_not_empty",
+            Error::ShiftUnexpectedOutput => "shift_unexpected_output",
+            Error::SparseArrayWrite { .. } => "sparse_array_write",
+            Error::OutputConflict { .. } => "output_conflict",
+            Error::InvalidJson(_) => "invalid_json",
+            Error::UnmetExpectations(_) => "unmet_expectations",
+            Error::SandboxLimitExceeded { .. } => "sandbox_limit_exceeded",
+            Error::BudgetExhausted { .. } => "budget_exhausted",
+            Error::PartialOutput { source, .. }
+            | Error::SpecKey { source, .. }
+            | Error::Operation { source, .. } => source.code(),
+        }
+    }
+
+    /// The pointer of the output value being written when the operation failed, see
+    /// [`TransformOptions::partial_output`](crate::TransformOptions::partial_output).
+    pub fn output_pointer(&self) -> Option<&str> {
+        match self {
+            Error::PartialOutput { pointer, .. } => pointer.as_deref(),
+            Error::SpecKey { source, .. } | Error::Operation { source, .. } => {
+                source.output_pointer()
+            }
+            _ => None,
+        }
+    }
+
+    /// The output built by the operation before it failed, see
+    /// [`TransformOptions::partial_output`](crate::TransformOptions::partial_output).
+    pub fn partial_output(&self) -> Option<&Value> {
+        match self {
+            Error::PartialOutput { output, .. } => Some(output),
+            Error::SpecKey { source, .. } | Error::Operation { source, .. } => {
+                source.partial_output()
+            }
+            _ => None,
+        }
+    }
+
+    /// RFC 6901 pointer of the input value the error is about, e.g. the value missing a required
+    /// key.
+    pub fn input_pointer(&self) -> Option<&str> {
+        match self {
+            Error::MissingRequiredKey { pointer, .. } => Some(pointer),
+            Error::PartialOutput { source, .. }
+            | Error::SpecKey { source, .. }
+            | Error::Operation { source, .. } => source.input_pointer(),
+            _ => None,
+        }
+    }
+
+    /// RFC 6901 pointer of the spec key that failed. It starts at the index of the operation in
+    /// the chain, e.g. `/1/spec/items/*`, for a named operation, see
+    /// [`Error::Operation`], and at the operation, e.g. `/spec/items/*`, otherwise.
+    pub fn spec_path(&self) -> Option<String> {
+        match self {
+            Error::Operation { index, source, .. } => Some(format!(
+                "/{index}{}",
+                source.spec_path().unwrap_or_default()
+            )),
+            Error::SpecKey { path, .. } => Some(format!("/spec{path}")),
+            Error::PartialOutput { source, .. } => source.spec_path(),
+            _ => None,
+        }
+    }
+
+    /// Produces a structured error document for the given transformation input, suitable
+    /// for routing failed records to a dead-letter topic.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::Error;
+    ///
+    /// let error = Error::KeyNotFound("id".to_string());
+    /// let doc = error.to_json(&json!({"name": "John"}));
+    ///
+    /// assert_eq!(doc["code"], "key_not_found");
+    /// assert_eq!(doc["input"], r#"{"name":"John"}"#);
+    /// ```
+    ///
+    /// The `spec_path`, `input_pointer` and `output_pointer` fields are `null` when the error
+    /// doesn't carry the location, see [`Error::spec_path`], [`Error::input_pointer`] and
+    /// [`Error::output_pointer`], and the `operation` field is `null` unless the failed
+    /// operation is named.
+    /// The input is serialized and truncated to [`MAX_ERROR_INPUT_LEN`] bytes.
+    pub fn to_json(&self, input: &Value) -> Value {
+        let mut input = input.to_string();
+        let input_truncated = input.len() > MAX_ERROR_INPUT_LEN;
+        if input_truncated {
+            let mut len = MAX_ERROR_INPUT_LEN;
+            while !input.is_char_boundary(len) {
+                len -= 1;
+            }
+            input.truncate(len);
+        }
+
+        let operation = match self {
+            Error::Operation { name, index, .. } => json!({"name": name, "index": index}),
+            _ => Value::Null,
+        };
+
+        json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "operation": operation,
+            "spec_path": self.spec_path(),
+            "input_pointer": self.input_pointer(),
+            "output_pointer": self.output_pointer(),
+            "input": input,
+            "input_truncated": input_truncated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        //given
+        let error = Error::ArrIndexOutOfRange { idx: 3, len: 1 };
+
+        //when
+        let doc = error.to_json(&json!({"a": [1]}));
+
+        //then
+        assert_eq!(
+            doc,
+            json!({
+                "code": "array_index_out_of_range",
+                "message": "Array index out of range. Index=3;Length=1;",
+                "operation": null,
+                "spec_path": null,
+                "input_pointer": null,
+                "output_pointer": null,
+                "input": r#"{"a":[1]}"#,
+                "input_truncated": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_json_locations() {
+        //given
+        let spec: crate::TransformSpec = serde_json::from_value(json!([
+            {"operation": "default", "spec": {"kind": "order"}},
+            {
+                "operation": "shift",
+                "name": "map-orders",
+                "spec": {"orders": {"*": {"id!": "ids[]"}}}
+            }
+        ]))
+        .expect("parsed spec");
+        let input = json!({"orders": [{"id": 1}, {"sku": "a"}]});
+
+        //when
+        let error = crate::transform(input.clone(), &spec).unwrap_err();
+        let doc = error.to_json(&input);
+
+        //then
+        assert_eq!(doc["code"], "missing_required_key");
+        assert_eq!(doc["operation"], json!({"name": "map-orders", "index": 1}));
+        assert_eq!(doc["spec_path"], "/1/spec/orders/*/id!");
+        assert_eq!(doc["input_pointer"], "/orders/1");
+        assert_eq!(doc["output_pointer"], Value::Null);
+    }
+
+    #[test]
+    fn test_to_json_truncates_input() {
+        //given
+        let error = Error::KeyNotFound("a".to_string());
+        let input = Value::String("ä".repeat(MAX_ERROR_INPUT_LEN));
+
+        //when
+        let doc = error.to_json(&input);
+
+        //then
+        assert_eq!(doc["input_truncated"], true);
+        assert!(doc["input"].as_str().unwrap().len() <= MAX_ERROR_INPUT_LEN);
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{Error, Result};
+
+/// Expectations of the input of a chain: the RFC 6901 pointers of the required values, in the
+/// declared order, with their allowed types. They are checked before any operation is applied.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Expects(Vec<(String, Vec<JsonType>)>);
+
+/// Type of an expected value, `any` accepts every type but requires the value to be present.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum JsonType {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Array,
+    Object,
+    Any,
+}
+
+impl Expects {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Checks the input, failing with all the unmet expectations.
+    pub(crate) fn check(&self, input: &Value) -> Result<()> {
+        let unmet: Vec<String> = self
+            .0
+            .iter()
+            .filter_map(|(pointer, types)| match input.pointer(pointer) {
+                None => Some(format!("{pointer}: missing, expected {}", Types(types))),
+                Some(value) if !types.iter().any(|ty| ty.matches(value)) => Some(format!(
+                    "{pointer}: expected {}, found {}",
+                    Types(types),
+                    JsonType::of(value)
+                )),
+                Some(_) => None,
+            })
+            .collect();
+
+        if unmet.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::UnmetExpectations(unmet))
+        }
+    }
+}
+
+impl JsonType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonType::Null,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Number(_) => JsonType::Number,
+            Value::String(_) => JsonType::String,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        *self == JsonType::Any || *self == JsonType::of(value)
+    }
+}
+
+impl fmt::Display for JsonType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            JsonType::Null => "null",
+            JsonType::Boolean => "boolean",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+            JsonType::Any => "any",
+        };
+        f.write_str(name)
+    }
+}
+
+// Displays the allowed types as `number or string`
+struct Types<'a>(&'a [JsonType]);
+
+impl fmt::Display for Types<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, ty) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" or ")?;
+            }
+            write!(f, "{ty}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Expects {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let map = Map::<String, Value>::deserialize(deserializer)?;
+        map.into_iter()
+            .map(|(pointer, types)| {
+                if !pointer.is_empty() && !pointer.starts_with('/') {
+                    return Err(de::Error::custom(format!(
+                        "expected a RFC 6901 pointer, found `{pointer}`"
+                    )));
+                }
+                // a single type or a list of the allowed types
+                let types = match types {
+                    Value::Array(types) => Vec::<JsonType>::deserialize(Value::Array(types)),
+                    ty => JsonType::deserialize(ty).map(|ty| vec![ty]),
+                }
+                .map_err(|e| de::Error::custom(format!("{pointer}: {e}")))?;
+                if types.is_empty() {
+                    return Err(de::Error::custom(format!("{pointer}: no expected type")));
+                }
+                Ok((pointer, types))
+            })
+            .collect::<std::result::Result<_, _>>()
+            .map(Expects)
+    }
+}
+
+impl Serialize for Expects {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (pointer, types) in &self.0 {
+            match types.as_slice() {
+                [ty] => map.serialize_entry(pointer, ty)?,
+                types => map.serialize_entry(pointer, types)?,
+            }
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_check() {
+        //given
+        let expects: Expects = serde_json::from_value(json!({
+            "/id": "number",
+            "/tags": ["array", "null"],
+            "/account/name": "any"
+        }))
+        .expect("parsed expects");
+
+        //when
+        let met = expects.check(&json!({"id": 1, "tags": null, "account": {"name": false}}));
+        let unmet = expects.check(&json!({"id": "1", "account": {}}));
+
+        //then
+        assert!(met.is_ok());
+        assert_eq!(
+            unmet,
+            Err(Error::UnmetExpectations(vec![
+                "/id: expected number, found string".to_string(),
+                "/tags: missing, expected array or null".to_string(),
+                "/account/name: missing, expected any".to_string(),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_invalid_expects() {
+        assert!(serde_json::from_value::<Expects>(json!({"id": "number"})).is_err());
+        assert!(serde_json::from_value::<Expects>(json!({"/id": "integer"})).is_err());
+        assert!(serde_json::from_value::<Expects>(json!({"/id": []})).is_err());
+    }
+}
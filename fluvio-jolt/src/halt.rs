@@ -0,0 +1,131 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ir::OperationIr;
+use crate::pointer::JsonPointer;
+
+/// Specification of the `halt-if` operation.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub(crate) struct HaltIf {
+    /// Dot notation path of the tested value.
+    path: String,
+    #[serde(flatten)]
+    condition: Condition,
+}
+
+/// Test of the value found at the path of the `halt-if` operation.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Condition {
+    /// The value is present and equal to the given one.
+    Equals(Value),
+    /// The value is absent or not equal to the given one.
+    NotEquals(Value),
+    /// The value is present if `true`, absent if `false`.
+    Exists(bool),
+}
+
+/// Options of the `halt-if` operation, specified next to the `spec` field of the operation.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HaltOptions {
+    /// Drop the document instead of returning it when the chain is halted.
+    #[serde(default)]
+    pub(crate) drop: bool,
+}
+
+/// Condition of the `when` field of an operation, the operation is skipped when it doesn't hold.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum When {
+    /// Test of the value at a dot notation path, like the spec of the `halt-if` operation.
+    Path(HaltIf),
+    /// The value at the RFC 6901 pointer is present.
+    Exists { exists: String },
+}
+
+impl When {
+    /// Whether the operation is applied to the given document.
+    pub(crate) fn holds(&self, input: &Value) -> bool {
+        match self {
+            When::Path(condition) => condition.holds(input),
+            When::Exists { exists } => input.pointer(exists).is_some(),
+        }
+    }
+}
+
+impl HaltIf {
+    /// Whether the chain halts on the given document.
+    pub(crate) fn holds(&self, input: &Value) -> bool {
+        let pointer = JsonPointer::from_dot_notation(&self.path);
+        let value = input.pointer(&pointer.join_rfc6901());
+        match &self.condition {
+            Condition::Equals(expected) => value == Some(expected),
+            Condition::NotEquals(expected) => value != Some(expected),
+            Condition::Exists(exists) => value.is_some() == *exists,
+        }
+    }
+
+    pub(crate) fn to_ir(&self, options: &HaltOptions) -> OperationIr {
+        let (condition, value) = match &self.condition {
+            Condition::Equals(value) => ("equals", value.clone()),
+            Condition::NotEquals(value) => ("not_equals", value.clone()),
+            Condition::Exists(exists) => ("exists", Value::Bool(*exists)),
+        };
+        OperationIr::HaltIf {
+            path: JsonPointer::from_dot_notation(&self.path).entries()[1..].to_vec(),
+            condition: condition.to_string(),
+            value,
+            drop: options.drop,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_holds() {
+        //given
+        let equals: HaltIf =
+            serde_json::from_value(json!({"path": "a.type", "equals": "order"})).unwrap();
+        let not_equals: HaltIf =
+            serde_json::from_value(json!({"path": "a.type", "not_equals": "order"})).unwrap();
+        let exists: HaltIf =
+            serde_json::from_value(json!({"path": "a.type", "exists": true})).unwrap();
+        let order = json!({"a": {"type": "order"}});
+        let refund = json!({"a": {"type": "refund"}});
+        let missing = json!({"a": {}});
+
+        //then
+        assert!(equals.holds(&order));
+        assert!(!equals.holds(&refund));
+        assert!(!equals.holds(&missing));
+
+        assert!(!not_equals.holds(&order));
+        assert!(not_equals.holds(&refund));
+        assert!(not_equals.holds(&missing));
+
+        assert!(exists.holds(&order));
+        assert!(!exists.holds(&missing));
+    }
+
+    #[test]
+    fn test_when_holds() {
+        //given
+        let path: When =
+            serde_json::from_value(json!({"path": "a.type", "equals": "order"})).unwrap();
+        let pointer: When = serde_json::from_value(json!({"exists": "/a/type"})).unwrap();
+        let order = json!({"a": {"type": "order"}});
+        let missing = json!({"a": {}});
+
+        //then
+        assert!(path.holds(&order));
+        assert!(!path.holds(&missing));
+        assert!(pointer.holds(&order));
+        assert!(!pointer.holds(&missing));
+        assert!(serde_json::from_value::<When>(json!({"exists": true})).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::insert;
+use crate::ir::OperationIr;
+use crate::pointer::JsonPointer;
+
+/// Specification of the `identity` operation.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Identity {
+    /// Dot notation path the input is placed under. Empty path means the root.
+    #[serde(default)]
+    prefix: Option<String>,
+    /// Maximum number of nested objects and arrays that are copied. Deeper values are dropped.
+    #[serde(default)]
+    depth: Option<usize>,
+    /// Maximum number of entries copied from every object and array.
+    #[serde(default)]
+    breadth: Option<usize>,
+}
+
+impl Identity {
+    pub(crate) fn to_ir(&self) -> OperationIr {
+        OperationIr::Identity {
+            prefix: self.prefix.clone(),
+            depth: self.depth,
+            breadth: self.breadth,
+        }
+    }
+
+    pub(crate) fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref().filter(|prefix| !prefix.is_empty())
+    }
+}
+
+pub(crate) fn identity(input: Value, spec: &Identity) -> Value {
+    let copied = if spec.depth.is_none() && spec.breadth.is_none() {
+        input
+    } else {
+        limit(input, spec.depth.unwrap_or(usize::MAX), spec.breadth).unwrap_or(Value::Null)
+    };
+
+    match spec.prefix() {
+        None => copied,
+        Some(prefix) => {
+            let mut out = Value::Object(Map::new());
+            insert(&mut out, JsonPointer::from_dot_notation(prefix), copied);
+            out
+        }
+    }
+}
+
+// Returns `None` if the value is a container deeper than allowed.
+fn limit(input: Value, depth: usize, breadth: Option<usize>) -> Option<Value> {
+    match input {
+        Value::Object(map) => {
+            let depth = depth.checked_sub(1)?;
+            let map = map
+                .into_iter()
+                .filter_map(|(k, v)| Some((k, limit(v, depth, breadth)?)))
+                .take(breadth.unwrap_or(usize::MAX))
+                .collect();
+            Some(Value::Object(map))
+        }
+        Value::Array(arr) => {
+            let depth = depth.checked_sub(1)?;
+            let arr = arr
+                .into_iter()
+                .filter_map(|v| limit(v, depth, breadth))
+                .take(breadth.unwrap_or(usize::MAX))
+                .collect();
+            Some(Value::Array(arr))
+        }
+        other => Some(other),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+    use crate::{transform, TransformSpec};
+
+    #[test]
+    fn test_identity() {
+        //given
+        let spec: Identity = serde_json::from_value(json!({})).expect("parsed spec");
+        let input = json!({
+            "a" : {
+                "b" : "b"
+            }
+        });
+
+        //when
+        let output = identity(input.clone(), &spec);
+
+        //then
+        assert_eq!(output, input)
+    }
+
+    #[test]
+    fn test_identity_with_prefix() {
+        //given
+        let spec: Identity = serde_json::from_value(json!({
+            "prefix": "data.raw"
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = identity(json!({"a" : "a"}), &spec);
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "data" : {
+                    "raw" : {
+                        "a" : "a"
+                    }
+                }
+            })
+        )
+    }
+
+    #[test]
+    fn test_identity_with_depth_and_breadth() {
+        //given
+        let spec: Identity = serde_json::from_value(json!({
+            "depth": 2,
+            "breadth": 2
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "a" : {
+                "b" : "b",
+                "c" : {
+                    "d" : "d"
+                },
+                "e" : "e",
+                "f" : "f"
+            },
+            "g" : [1, 2, 3]
+        });
+
+        //when
+        let output = identity(input, &spec);
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "a" : {
+                    "b" : "b",
+                    "e" : "e"
+                },
+                "g" : [1, 2]
+            })
+        )
+    }
+
+    #[test]
+    fn test_noop_without_spec() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "identity"},
+            {"operation": "noop", "name": "placeholder"},
+            {"operation": "shift", "spec": {"a": "b"}}
+        ]))
+        .expect("parsed spec");
+
+        //when
+        let output = transform(json!({"a": 1}), &spec);
+
+        //then
+        assert_eq!(output, Ok(json!({"b": 1})));
+        assert_eq!(spec.get(1).map(|op| op.kind()), Some("identity"));
+    }
+}
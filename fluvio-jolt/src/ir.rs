@@ -0,0 +1,470 @@
+//! Machine-readable intermediate representation of a [`TransformSpec`](crate::TransformSpec).
+//!
+//! The representation is returned by [`TransformSpec::to_ir`](crate::TransformSpec::to_ir) and
+//! serializes to JSON with the following schema:
+//!
+//! <pre>
+//! {
+//!     "version": 1,
+//!     "operations": [Operation]
+//! }
+//! </pre>
+//!
+//! Every `Operation` is an object tagged by the `operation` field:
+//!  - `{"operation": "shift", "matches": [MatchNode]}`
+//!  - `{"operation": "default", "values": [{"path": [string], "value": any}]}`
+//!  - `{"operation": "remove", "paths": [[string]]}`
+//!  - `{"operation": "retain", "matches": [RetainNode]}`
+//!  - `{"operation": "identity", "prefix": string | null, "depth": number | null, "breadth": number | null}`
+//!  - `{"operation": "halt-if", "path": [string], "condition": "equals" | "not_equals" | "exists", "value": any, "drop": bool}`
+//!  - `{"operation": "sample", "rate": number | null, "every_n": number | null,
+//!    "probability": number | null}`
+//!  - `{"operation": "rename", "convention": string, "recursive": bool, "except": [string]}`
+//!  - `{"operation": "try", "operations": [Operation], "on_error": [Operation]}`
+//!
+//! A `MatchNode` is `{"key": KeyMatch, "children": [MatchNode], "destinations": [[PathSegment]]}`.
+//! A node without children and destinations matches the input and discards it.
+//!
+//! A `RetainNode` is `{"key": KeyMatch, "children": [RetainNode] | null}`, `null` children
+//! retain the whole matched value.
+//!
+//! A `KeyMatch` is an object tagged by the `kind` field:
+//!  - `{"kind": "literal", "value": string}` for literal keys, with the
+//!    `"marker": "optional" | "required"` field for the keys ending with `?` or `!`
+//!  - `{"kind": "wildcard", "patterns": [string]}` for `*` patterns separated by `|`
+//!  - `{"kind": "capture", "level": number, "index": number}` for `&(level,index)`
+//!  - `{"kind": "key", "level": number, "index": number}` for `$(level,index)`
+//!  - `{"kind": "lookup", "level": number, "path": [PathSegment]}` for `@(level,path)`, with the
+//!    `"marker"` field of the literal keys
+//!  - `{"kind": "constant", "value": string}` for `#value`
+//!
+//! A `PathSegment` is an object tagged by the `kind` field:
+//!  - `{"kind": "key", "value": string}` for literal keys
+//!  - `{"kind": "capture", "level": number, "index": number}` for `&(level,index)`, with the
+//!    `"case": "snake" | "kebab" | "camel" | "pascal" | "lower" | "upper"` field for
+//!    `&(level,index):case` and the `"pad": number` field for `&(level,index):%0Nd`
+//!  - `{"kind": "lookup", "level": number, "path": [PathSegment]}` for `@(level,path)`
+//!  - `{"kind": "composite", "parts": [PathSegment]}` for keys concatenated from several parts
+//!  - `{"kind": "index", "value": number}` for `[N]`
+//!  - `{"kind": "capture_index", "level": number, "index": number}` for `[&(level,index)]`
+//!  - `{"kind": "key_index", "level": number, "index": number}` for `[$(level,index)]`
+//!  - `{"kind": "lookup_index", "level": number, "path": [PathSegment]}` for `[@(level,path)]`
+//!  - `{"kind": "counter_index", "level": number}` for `[#level]`
+//!  - `{"kind": "append"}` for `[]`
+//!  - `{"kind": "append_unique"}` for `[?]`
+//!
+//! New fields and kinds may be added within the same `version`, existing ones are only changed
+//! along with the `version`.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::dsl::{IndexOp, InfallibleLhs, KeyFormat, Object, REntry, Rhs, RhsEntry, RhsPart, Stars};
+
+/// Version of the intermediate representation schema.
+pub const IR_VERSION: u32 = 1;
+
+/// The intermediate representation of a specification.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpecIr {
+    pub version: u32,
+    pub operations: Vec<OperationIr>,
+}
+
+/// An operation of the specification.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum OperationIr {
+    Shift {
+        matches: Vec<MatchNode>,
+    },
+    Default {
+        values: Vec<DefaultValue>,
+    },
+    Remove {
+        paths: Vec<Vec<String>>,
+    },
+    Retain {
+        matches: Vec<RetainNode>,
+    },
+    Identity {
+        prefix: Option<String>,
+        depth: Option<usize>,
+        breadth: Option<usize>,
+    },
+    #[serde(rename = "halt-if")]
+    HaltIf {
+        path: Vec<String>,
+        condition: String,
+        value: Value,
+        drop: bool,
+    },
+    Sample {
+        rate: Option<f64>,
+        every_n: Option<u64>,
+        probability: Option<f64>,
+    },
+    Rename {
+        convention: String,
+        recursive: bool,
+        except: Vec<String>,
+    },
+    Try {
+        operations: Vec<OperationIr>,
+        on_error: Vec<OperationIr>,
+    },
+}
+
+/// A key of the `shift` specification with the nested matches and the destinations the matched
+/// value is written to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchNode {
+    pub key: KeyMatch,
+    pub children: Vec<MatchNode>,
+    pub destinations: Vec<Vec<PathSegment>>,
+}
+
+/// A key of the `retain` specification.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetainNode {
+    pub key: KeyMatch,
+    pub children: Option<Vec<RetainNode>>,
+}
+
+/// A value written by the `default` operation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DefaultValue {
+    pub path: Vec<String>,
+    pub value: Value,
+}
+
+/// The left hand side of a specification entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KeyMatch {
+    Literal {
+        value: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        marker: Option<String>,
+    },
+    Wildcard {
+        patterns: Vec<String>,
+    },
+    Capture {
+        level: usize,
+        index: usize,
+    },
+    Key {
+        level: usize,
+        index: usize,
+    },
+    Lookup {
+        level: usize,
+        path: Vec<PathSegment>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        marker: Option<String>,
+    },
+    Constant {
+        value: String,
+    },
+}
+
+/// A segment of the right hand side path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PathSegment {
+    Key {
+        value: String,
+    },
+    Capture {
+        level: usize,
+        index: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        case: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pad: Option<usize>,
+    },
+    Lookup {
+        level: usize,
+        path: Vec<PathSegment>,
+    },
+    Composite {
+        parts: Vec<PathSegment>,
+    },
+    Index {
+        value: usize,
+    },
+    CaptureIndex {
+        level: usize,
+        index: usize,
+    },
+    KeyIndex {
+        level: usize,
+        index: usize,
+    },
+    LookupIndex {
+        level: usize,
+        path: Vec<PathSegment>,
+    },
+    CounterIndex {
+        level: usize,
+    },
+    Append,
+    AppendUnique,
+    AllIndexes,
+}
+
+pub(crate) fn match_nodes(obj: &Object) -> Vec<MatchNode> {
+    let infallible = obj.infallible.iter().map(|(lhs, rhss)| {
+        let key = match lhs {
+            InfallibleLhs::DollarSign(level, index) => KeyMatch::Key {
+                level: *level,
+                index: *index,
+            },
+            InfallibleLhs::At(level, rhs, marker) => KeyMatch::Lookup {
+                level: *level,
+                path: path(rhs),
+                marker: marker.map(|marker| marker.name().to_string()),
+            },
+            InfallibleLhs::Square(value) => KeyMatch::Constant {
+                value: value.clone(),
+            },
+        };
+        MatchNode {
+            key,
+            children: Vec::new(),
+            destinations: rhss.iter().map(path).collect(),
+        }
+    });
+    let literal = obj.literal.iter().map(|(value, entry)| {
+        let marker = obj
+            .markers
+            .iter()
+            .find_map(|(key, marker)| (key == value).then(|| marker.name().to_string()));
+        match_node(
+            KeyMatch::Literal {
+                value: value.clone(),
+                marker,
+            },
+            entry,
+        )
+    });
+    let amp = obj.amp.iter().map(|((level, index), entry)| {
+        match_node(
+            KeyMatch::Capture {
+                level: *level,
+                index: *index,
+            },
+            entry,
+        )
+    });
+    let pipes = obj
+        .pipes
+        .iter()
+        .map(|(pipes, entry)| match_node(wildcard(pipes), entry));
+
+    infallible.chain(literal).chain(amp).chain(pipes).collect()
+}
+
+fn match_node(key: KeyMatch, entry: &REntry) -> MatchNode {
+    let (children, destinations) = match entry {
+        REntry::Obj(obj) => (match_nodes(obj), Vec::new()),
+        REntry::Rhs(rhss) => (Vec::new(), rhss.iter().map(path).collect()),
+        REntry::Thrash => (Vec::new(), Vec::new()),
+    };
+    MatchNode {
+        key,
+        children,
+        destinations,
+    }
+}
+
+pub(crate) fn wildcard(pipes: &[Stars]) -> KeyMatch {
+    KeyMatch::Wildcard {
+        patterns: pipes.iter().map(|stars| stars.pieces().join("*")).collect(),
+    }
+}
+
+fn path(rhs: &Rhs) -> Vec<PathSegment> {
+    rhs.0
+        .iter()
+        .map(|part| match part {
+            RhsPart::Key(entry) => segment(entry),
+            RhsPart::CompositeKey(entries) => PathSegment::Composite {
+                parts: entries.iter().map(segment).collect(),
+            },
+            RhsPart::Index(IndexOp::Literal(value)) => PathSegment::Index { value: *value },
+            RhsPart::Index(IndexOp::Amp(level, index)) => PathSegment::CaptureIndex {
+                level: *level,
+                index: *index,
+            },
+            RhsPart::Index(IndexOp::DollarSign(level, index)) => PathSegment::KeyIndex {
+                level: *level,
+                index: *index,
+            },
+            RhsPart::Index(IndexOp::At(level, rhs)) => PathSegment::LookupIndex {
+                level: *level,
+                path: path(rhs),
+            },
+            RhsPart::Index(IndexOp::Square(level)) => PathSegment::CounterIndex { level: *level },
+            RhsPart::Index(IndexOp::Empty) => PathSegment::Append,
+            RhsPart::Index(IndexOp::Unique) => PathSegment::AppendUnique,
+            RhsPart::Index(IndexOp::Star) => PathSegment::AllIndexes,
+        })
+        .collect()
+}
+
+fn segment(entry: &RhsEntry) -> PathSegment {
+    match entry {
+        RhsEntry::Key(value) => PathSegment::Key {
+            value: value.clone(),
+        },
+        RhsEntry::Amp(level, index) => PathSegment::Capture {
+            level: *level,
+            index: *index,
+            case: None,
+            pad: None,
+        },
+        RhsEntry::AmpFormat(level, index, format) => PathSegment::Capture {
+            level: *level,
+            index: *index,
+            case: match format {
+                KeyFormat::Case(case) => Some(case.to_string()),
+                KeyFormat::ZeroPad(_) => None,
+            },
+            pad: match format {
+                KeyFormat::ZeroPad(width) => Some(*width),
+                KeyFormat::Case(_) => None,
+            },
+        },
+        RhsEntry::At(level, rhs) => PathSegment::Lookup {
+            level: *level,
+            path: path(rhs),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use crate::TransformSpec;
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_shift_to_ir() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "id": "data.id",
+                    "items": {
+                        "*": {
+                            "name": "names[&(1)]",
+                            "$": "keys[]"
+                        }
+                    },
+                    "ignored": null
+                }
+            }
+        ]))
+        .expect("parsed spec");
+
+        //when
+        let ir = serde_json::to_value(spec.to_ir()).expect("serialized ir");
+
+        //then
+        assert_eq!(
+            ir,
+            json!({
+                "version": 1,
+                "operations": [
+                    {
+                        "operation": "shift",
+                        "matches": [
+                            {
+                                "key": {"kind": "literal", "value": "id"},
+                                "children": [],
+                                "destinations": [[
+                                    {"kind": "key", "value": "data"},
+                                    {"kind": "key", "value": "id"}
+                                ]]
+                            },
+                            {
+                                "key": {"kind": "literal", "value": "items"},
+                                "children": [
+                                    {
+                                        "key": {"kind": "wildcard", "patterns": ["*"]},
+                                        "children": [
+                                            {
+                                                "key": {"kind": "key", "level": 0, "index": 0},
+                                                "children": [],
+                                                "destinations": [[
+                                                    {"kind": "key", "value": "keys"},
+                                                    {"kind": "append"}
+                                                ]]
+                                            },
+                                            {
+                                                "key": {"kind": "literal", "value": "name"},
+                                                "children": [],
+                                                "destinations": [[
+                                                    {"kind": "key", "value": "names"},
+                                                    {"kind": "capture_index", "level": 1, "index": 0}
+                                                ]]
+                                            }
+                                        ],
+                                        "destinations": []
+                                    }
+                                ],
+                                "destinations": []
+                            },
+                            {
+                                "key": {"kind": "literal", "value": "ignored"},
+                                "children": [],
+                                "destinations": []
+                            }
+                        ]
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_operations_to_ir() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "default", "spec": {"a": {"b": 1}}},
+            {"operation": "remove", "spec": {"c": ""}},
+            {"operation": "retain", "spec": {"a": {"b*": null}, "d": null}},
+            {"operation": "identity", "spec": {"prefix": "x", "depth": 2}}
+        ]))
+        .expect("parsed spec");
+
+        //when
+        let ir = serde_json::to_value(spec.to_ir()).expect("serialized ir");
+
+        //then
+        assert_eq!(
+            ir["operations"],
+            json!([
+                {"operation": "default", "values": [{"path": ["a", "b"], "value": 1}]},
+                {"operation": "remove", "paths": [["c"]]},
+                {
+                    "operation": "retain",
+                    "matches": [
+                        {
+                            "key": {"kind": "literal", "value": "a"},
+                            "children": [
+                                {"key": {"kind": "wildcard", "patterns": ["b*"]}, "children": null}
+                            ]
+                        },
+                        {"key": {"kind": "literal", "value": "d"}, "children": null}
+                    ]
+                },
+                {"operation": "identity", "prefix": "x", "depth": 2, "breadth": null}
+            ])
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::io;
+
+use serde::Serialize;
+use serde_json::ser::{Formatter, Serializer};
+use serde_json::Value;
+
+/// Formatter of `serde_json` writing the numbers like Jackson does in Java Jolt, so the outputs can
+/// be compared byte by byte with the outputs of Java Jolt. The integers are written like
+/// `serde_json` does and the floating point numbers like Java's `Double.toString`:
+///  - numbers from `0.001` up to `10000000` are written in decimal with at least one fractional
+///    digit, e.g. `3.0` or `0.0015`
+///  - other numbers are written in scientific notation with an uppercase `E`, e.g. `1.0E20` or
+///    `1.5E-4`, where `serde_json` writes `1e20` and `0.00015`
+///
+/// The digits are the shortest ones that parse back to the same number, like in Java 19 and
+/// later. The other values are written compactly, like with [`serde_json::to_string`].
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_json::json;
+/// use fluvio_jolt::JavaFormatter;
+///
+/// let mut writer = Vec::new();
+/// let mut serializer = serde_json::Serializer::with_formatter(&mut writer, JavaFormatter);
+/// json!({"amount": 1e20}).serialize(&mut serializer).unwrap();
+///
+/// assert_eq!(String::from_utf8(writer).unwrap(), r#"{"amount":1.0E20}"#);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JavaFormatter;
+
+impl Formatter for JavaFormatter {
+    fn write_f32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f32) -> io::Result<()> {
+        writer.write_all(java_number(&format!("{value:e}")).as_bytes())
+    }
+
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        writer.write_all(java_number(&format!("{value:e}")).as_bytes())
+    }
+}
+
+/// Serializes the value to a compact JSON string with the number formatting of Java Jolt, see
+/// [`JavaFormatter`].
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::to_java_json_string;
+///
+/// let output = json!({"count": 3, "price": 3.0, "ratio": 0.0001});
+///
+/// assert_eq!(
+///     to_java_json_string(&output).unwrap(),
+///     r#"{"count":3,"price":3.0,"ratio":1.0E-4}"#
+/// );
+/// ```
+pub fn to_java_json_string(value: &Value) -> serde_json::Result<String> {
+    let mut writer = Vec::new();
+    let mut serializer = Serializer::with_formatter(&mut writer, JavaFormatter);
+    value.serialize(&mut serializer)?;
+    String::from_utf8(writer).map_err(serde::ser::Error::custom)
+}
+
+// Formats a number given in the scientific notation of Rust, like `-1.5e-4`, like Java's
+// `Double.toString`. Other texts, like `NaN`, are returned as they are.
+fn java_number(number: &str) -> String {
+    let (sign, scientific) = match number.strip_prefix('-') {
+        Some(scientific) => ("-", scientific),
+        None => ("", number),
+    };
+    let Some((mantissa, exponent)) = scientific.split_once('e') else {
+        return number.to_owned();
+    };
+    let Ok(exponent) = exponent.parse::<i32>() else {
+        return number.to_owned();
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+
+    if digits == "0" {
+        return format!("{sign}0.0");
+    }
+    match exponent {
+        -3..=-1 => {
+            let zeros = "0".repeat((-exponent - 1) as usize);
+            format!("{sign}0.{zeros}{digits}")
+        }
+        0..=6 => {
+            let int_len = exponent as usize + 1;
+            if digits.len() > int_len {
+                format!("{sign}{}.{}", &digits[..int_len], &digits[int_len..])
+            } else {
+                let zeros = "0".repeat(int_len - digits.len());
+                format!("{sign}{digits}{zeros}.0")
+            }
+        }
+        _ => {
+            let (first, rest) = digits.split_at(1);
+            let rest = if rest.is_empty() { "0" } else { rest };
+            format!("{sign}{first}.{rest}E{exponent}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_java_numbers() {
+        let numbers = [
+            (0.0, "0.0"),
+            (-0.0, "-0.0"),
+            (3.0, "3.0"),
+            (-3.5, "-3.5"),
+            (0.001, "0.001"),
+            (0.00015, "1.5E-4"),
+            (123.456, "123.456"),
+            (1234567.0, "1234567.0"),
+            (9999999.5, "9999999.5"),
+            (10000000.0, "1.0E7"),
+            (1e20, "1.0E20"),
+            (-1.25e-10, "-1.25E-10"),
+            (f64::MAX, "1.7976931348623157E308"),
+        ];
+        for (number, expected) in numbers {
+            assert_eq!(java_number(&format!("{number:e}")), expected, "{number}");
+        }
+        assert_eq!(java_number("NaN"), "NaN");
+    }
+
+    #[test]
+    fn test_to_java_json_string() {
+        //given
+        let value = json!({
+            "amounts": [1.0, 2.5e8, 0.0005],
+            "id": 42,
+            "name": "a \"quoted\"\nname",
+            "negative": -7
+        });
+
+        //when
+        let output = to_java_json_string(&value).unwrap();
+
+        //then
+        assert_eq!(
+            output,
+            r#"{"amounts":[1.0,2.5E8,5.0E-4],"id":42,"name":"a \"quoted\"\nname","negative":-7}"#
+        );
+    }
+}
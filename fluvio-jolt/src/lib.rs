@@ -1,27 +1,106 @@
 mod spec;
+mod analyze;
+mod spec_set;
+mod stats;
+mod spec_stats;
 mod shift;
 mod default;
 mod remove;
-mod pointer;
+mod retain;
+mod identity;
+mod halt;
+mod sample;
+mod rename;
+mod try_catch;
+mod migrate;
+mod lineage;
+mod options;
+mod observer;
+mod context;
+mod engine;
+mod merge;
+mod diff;
+mod compat;
+mod constants;
+mod strict;
+mod expects;
+mod envelope;
+mod java_json;
+mod scratch;
+mod sandbox;
+mod verify;
+mod embedded_tests;
+#[cfg(feature = "slow_transforms")]
+mod slow;
+pub mod ir;
+pub mod pointer;
+pub mod spec_path;
+pub mod tokens;
 mod transform;
 mod error;
+#[cfg(feature = "testing")]
+pub mod testing;
 #[cfg(not(feature = "fuzz"))]
 mod dsl;
 #[cfg(feature = "fuzz")]
 pub mod dsl;
 
+use std::borrow::Cow;
+use std::ops::RangeBounds;
+use std::sync::Arc;
+
 use serde_json::{Map, Value};
 use serde_json::map::Entry;
 use transform::Transform;
 
 use crate::default::default;
 use crate::remove::remove;
+use crate::retain::retain;
+use crate::identity::identity;
+use crate::rename::rename;
+use crate::try_catch::try_catch;
+use crate::merge::merge_shallow;
 use crate::spec::SpecEntry;
+use crate::sandbox::Budget;
+use crate::shift::Recorded;
 
-pub use spec::TransformSpec;
+pub use spec::{Operation, OperationRef, TransformSpec};
+pub use analyze::{analyze_spec, Diagnostic, Severity};
+pub use spec_set::TransformSpecSet;
+pub use stats::TransformStats;
+pub use spec_stats::SpecStats;
+pub use migrate::MigrationNote;
+pub use lineage::{lineage, lineage_with_context, Lineage};
+pub use options::TransformOptions;
+pub use observer::TransformObserver;
+pub use context::{Context, RandomSource, SeededRandom};
+pub use engine::JoltEngine;
+pub use sandbox::{Sandbox, SandboxLimit};
+pub use shift::{Conflicts, MatchOrder, Nulls, MAX_ARRAY_PADDING};
+pub use merge::{merge_values, MergeStrategy};
+pub use diff::SpecChange;
+pub use compat::{Compatibility, CompatibilityReport};
+pub use verify::{verify, OutputDifference, SpecEquivalenceReport};
+pub use embedded_tests::EmbeddedTestFailure;
+pub use java_json::{to_java_json_string, JavaFormatter};
+#[cfg(feature = "slow_transforms")]
+pub use slow::{OperationTiming, SlowTransform};
 use crate::pointer::JsonPointer;
 
-pub use error::{Error, Result};
+pub use dsl::escape_key;
+pub use error::{Error, Result, MAX_ERROR_INPUT_LEN};
+
+// The specification, the options, the context and the engine can be shared between threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<TransformSpec>();
+    assert_send_sync::<TransformSpecSet>();
+    assert_send_sync::<Operation>();
+    assert_send_sync::<TransformOptions>();
+    assert_send_sync::<Context>();
+    assert_send_sync::<JoltEngine>();
+};
 
 /// Perform JSON to JSON transformation where the "specification" is a JSON.
 ///
@@ -33,6 +112,10 @@ pub use error::{Error, Result};
 /// 1. [`shift`](TransformSpec#shift-operation): copy data from the input tree and put it the output tree
 /// 2. [`default`](TransformSpec#default-operation): apply default values to the tree
 /// 3. [`remove`](TransformSpec#remove-operation): remove data from the tree
+/// 4. [`retain`](TransformSpec#retain-operation): keep only the listed data in the tree
+/// 5. [`identity`](TransformSpec#identity-operation): copy the whole tree, optionally under a prefix
+/// 6. [`halt-if`](TransformSpec#halt-if-operation): stop the chain when a condition holds
+/// 7. [`sample`](TransformSpec#sample-operation): pass only a fraction of the documents
 ///
 /// For example, if you want to repack your JSON record, you can do the following:
 /// ```
@@ -75,15 +158,388 @@ pub use error::{Error, Result};
 /// ```
 ///
 /// Checkout supported operations in [TransformSpec] docs.
+///
+/// ### Ordering of keys
+/// With the `preserve_order` feature (enabled by default), output objects keep the insertion order
+/// of keys end-to-end, so outputs can be compared byte by byte with the outputs of Java Jolt.
+/// Without the feature, the keys of output objects are sorted, and so are the keys of the parsed
+/// specifications and inputs. The order of the spec matters to a `shift`: when several wildcard
+/// keys match the same input key, the first of them in the sorted order wins with the default
+/// [`MatchOrder::Spec`], not the first one written, and the values appended to an array follow
+/// the sorted order of the keys. Specifications relying on the written order of their keys should
+/// set the `match_order` option, or keep the feature.
+///
+/// ### Thread safety
+/// [TransformSpec], [TransformOptions], [Context] and [JoltEngine] are `Send + Sync`, so a
+/// specification parsed once can be shared behind an `Arc` by many threads.
+///
+/// ### Ownership of the input
+/// The transformation consumes the input and moves its values to the output, e.g. the objects and
+/// arrays matched by a `shift` aren't copied unless they are written several times. Use
+/// [`transform_ref`] to keep the input, it is copied only when an operation modifies it.
+///
+/// ### Panics
+/// The transformation doesn't panic, whatever the input and the specification: every failure is
+/// returned as an [`Error`], so a bad record can't abort the process applying the chain, e.g. a
+/// WASM SmartModule. Only the functions documenting a `# Panics` section, like
+/// [`transform_range`], panic on misuse of their arguments.
 pub fn transform(input: Value, spec: &TransformSpec) -> Result<Value> {
+    transform_with_options(input, spec, &TransformOptions::default())
+}
+
+/// Perform JSON to JSON transformation like [`transform`] without consuming the input.
+///
+/// The input is never modified: it is only copied when an operation of the chain modifies the
+/// document, a chain starting with a `shift` reads the input without copying it. The consuming
+/// functions like [`transform`] move the values of the input to the output instead, so they are
+/// cheaper when the input isn't needed afterwards.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_ref, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"account": "data.account"}}
+/// ]))
+/// .unwrap();
+/// let input = json!({"id": 1, "account": {"id": 1000, "type": "Checking"}});
+///
+/// let output = transform_ref(&input, &spec).unwrap();
+///
+/// assert_eq!(output, json!({"data": {"account": {"id": 1000, "type": "Checking"}}}));
+/// assert_eq!(input["account"]["id"], 1000);
+/// ```
+pub fn transform_ref(input: &Value, spec: &TransformSpec) -> Result<Value> {
+    let options = TransformOptions::default();
+    let context = Context::default();
+    let output = apply_chain(Cow::Borrowed(input), spec, &options, &context, |_| {})?;
+    Ok(output.unwrap_or(Value::Null))
+}
+
+/// Perform JSON to JSON transformation like [`transform`] with the given [`TransformOptions`].
+pub fn transform_with_options(
+    input: Value,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+) -> Result<Value> {
+    transform_with_context(input, spec, options, &Context::default())
+}
+
+/// Perform JSON to JSON transformation like [`transform_with_options`], returning the
+/// [`TransformStats`] of the transformation along with the output. The events counted by the
+/// statistics are still reported to the observer of the options.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_with_stats, TransformOptions, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id", "tags": {"*": "data.tags[]"}}}
+/// ]))
+/// .unwrap();
+/// let input = json!({"id": 1, "tags": ["a", "b"], "legacy": true});
+///
+/// let (output, stats) = transform_with_stats(input, &spec, &TransformOptions::default()).unwrap();
+///
+/// assert_eq!(output, json!({"data": {"id": 1, "tags": ["a", "b"]}}));
+/// assert_eq!(stats.input_nodes, 6);
+/// assert_eq!(stats.output_nodes, 6);
+/// assert_eq!(stats.matched_keys, 4);
+/// assert_eq!(stats.dropped_keys, 1);
+/// assert_eq!(stats.arrays_created, 1);
+/// ```
+pub fn transform_with_stats(
+    input: Value,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+) -> Result<(Value, TransformStats)> {
+    let input_nodes = stats::count_nodes(&input);
+    let observer = Arc::new(stats::StatsObserver::new(options.observer_arc()));
+    let options = options.clone().observer(observer.clone());
+    let output = transform_with_options(input, spec, &options)?;
+    let stats = observer.stats(input_nodes, &output);
+    Ok((output, stats))
+}
+
+/// Perform JSON to JSON transformation like [`transform_with_options`], passing the given
+/// [`Context`] to every operation of the chain.
+///
+/// A document dropped by a `halt-if` or `sample` operation is returned as `null`.
+pub fn transform_with_context(
+    input: Value,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+    context: &Context,
+) -> Result<Value> {
+    Ok(transform_filtered(input, spec, options, context)?.unwrap_or(Value::Null))
+}
+
+/// Perform JSON to JSON transformation applying only the operations in the given range of
+/// indexes of the chain, e.g. to show the intermediate documents of the chain step by step.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_range, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id"}},
+///     {"operation": "default", "spec": {"data": {"kind": "user"}}}
+/// ]))
+/// .unwrap();
+///
+/// let after_shift = transform_range(json!({"id": 1}), &spec, 0..1).unwrap();
+/// assert_eq!(after_shift, json!({"data": {"id": 1}}));
+///
+/// let output = transform_range(after_shift, &spec, 1..).unwrap();
+/// assert_eq!(output, json!({"data": {"id": 1, "kind": "user"}}));
+/// ```
+///
+/// # Panics
+/// Panics if the range is out of the bounds of the chain, see [`TransformSpec::slice`].
+pub fn transform_range(
+    input: Value,
+    spec: &TransformSpec,
+    range: impl RangeBounds<usize>,
+) -> Result<Value> {
+    transform(input, &spec.slice(range))
+}
+
+/// Perform JSON to JSON transformation like [`transform`] and return the document after every
+/// operation of the chain, the last one being the output.
+///
+/// The documents end with the operation that halts the chain, a dropped document is `null`. The
+/// last document is wrapped in the `envelope` of the specification, if any.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_stepwise, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id"}},
+///     {"operation": "default", "spec": {"data": {"kind": "user"}}}
+/// ]))
+/// .unwrap();
+///
+/// let steps = transform_stepwise(json!({"id": 1}), &spec).unwrap();
+///
+/// assert_eq!(
+///     steps,
+///     vec![
+///         json!({"data": {"id": 1}}),
+///         json!({"data": {"id": 1, "kind": "user"}})
+///     ]
+/// );
+/// ```
+pub fn transform_stepwise(input: Value, spec: &TransformSpec) -> Result<Vec<Value>> {
+    let options = TransformOptions::default();
+    let context = Context::default();
+    spec.check_input(&input)?;
+    let mut steps: Vec<Value> = Vec::with_capacity(spec.len());
+    let mut result = input;
+    for (index, operation) in spec.operations().enumerate() {
+        if !operation.applies(&result) {
+            steps.push(result.clone());
+            continue;
+        }
+        let entry = operation.entry();
+        if let Some(drop) = operation.halts(&result, &context) {
+            steps.push(if drop {
+                Value::Null
+            } else {
+                spec.wrap_output(result, &context)
+            });
+            return Ok(steps);
+        }
+        result = operation
+            .apply_scoped(Cow::Owned(result), |input| {
+                apply_entry(input, entry, &options, &context, &Budget::default())
+            })
+            .map_err(|e| operation.attribute(index, e))?;
+        steps.push(result.clone());
+    }
+    if let Some(last) = steps.last_mut() {
+        *last = spec.wrap_output(last.take(), &context);
+    }
+    Ok(steps)
+}
+
+/// Perform JSON to JSON transformation like [`transform_with_context`]. Returns `None` if the
+/// document is dropped by a `halt-if` or `sample` operation.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_filtered, Context, TransformOptions, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "halt-if", "drop": true, "spec": {"path": "type", "not_equals": "order"}},
+///     {"operation": "shift", "spec": {"id": "order.id"}}
+/// ]))
+/// .unwrap();
+/// let options = TransformOptions::default();
+/// let context = Context::default();
+///
+/// let order = transform_filtered(json!({"type": "order", "id": 1}), &spec, &options, &context);
+/// let refund = transform_filtered(json!({"type": "refund", "id": 2}), &spec, &options, &context);
+///
+/// assert_eq!(order.unwrap(), Some(json!({"order": {"id": 1}})));
+/// assert_eq!(refund.unwrap(), None);
+/// ```
+pub fn transform_filtered(
+    input: Value,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+    context: &Context,
+) -> Result<Option<Value>> {
+    #[cfg(feature = "slow_transforms")]
+    if let Some(threshold) = options.get_slow_transform_threshold() {
+        return slow::apply_timed(input, spec, options, context, threshold);
+    }
+    apply_chain(Cow::Owned(input), spec, options, context, |_| {})
+}
+
+// An operation applied by `apply_chain`
+pub(crate) struct Applied<'a> {
+    pub(crate) index: usize,
+    pub(crate) operation: &'a Operation,
+    // the document once the operation is applied, `None` if the operation failed
+    pub(crate) output: Option<&'a Value>,
+    // the writes of the `shift` operations applied, if the options record them
+    pub(crate) writes: Vec<Recorded>,
+}
+
+// Applies the operations of the chain, calling `on_applied` after every operation applied,
+// including the operation failing or halting the chain. A borrowed input is copied by the first
+// operation modifying the document.
+pub(crate) fn apply_chain(
+    input: Cow<'_, Value>,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+    context: &Context,
+    mut on_applied: impl FnMut(Applied<'_>),
+) -> Result<Option<Value>> {
+    let observer = options.get_observer();
+    let observe_error = |error: Error| {
+        if let Some(observer) = observer {
+            observer.on_error(&error);
+        }
+        error
+    };
+
+    let budget = Budget::new(options);
+    budget.check_spec(spec).map_err(observe_error)?;
+    spec.check_input(&input).map_err(observe_error)?;
     let mut result = input;
-    for entry in spec.entries() {
-        match entry {
-            SpecEntry::Shift(shift) => result = shift.apply(&result)?,
-            SpecEntry::Default(spec) => result = default(result, spec),
-            SpecEntry::Remove(spec) => result = remove(result, spec),
+    for (index, operation) in spec.operations().enumerate() {
+        if !operation.applies(&result) {
+            continue;
         }
+        if let Some(observer) = observer {
+            observer.on_operation_start(operation.as_ref(), index);
+        }
+        let entry = operation.entry();
+        let halted = operation.halts(&result, context);
+        let applied = match halted {
+            None => operation
+                .apply_scoped(result, |input| {
+                    apply_entry(input, entry, options, context, &budget)
+                })
+                .map(Cow::Owned),
+            Some(_) => Ok(result),
+        };
+        on_applied(Applied {
+            index,
+            operation,
+            output: applied.as_deref().ok(),
+            writes: budget.take_writes(),
+        });
+        // the operation ends before the error of the transformation is observed
+        if let Some(observer) = observer {
+            observer.on_operation_end(operation.as_ref(), index);
+        }
+        result = applied.map_err(|e| observe_error(operation.attribute(index, e)))?;
+        if let Some(drop) = halted {
+            return Ok((!drop).then(|| spec.wrap_output(result.into_owned(), context)));
+        }
+    }
+    Ok(Some(spec.wrap_output(result.into_owned(), context)))
+}
+
+// Applies the operations of a chain nested in an operation, e.g. the chains of a `try`
+// operation, with the budget of the enclosing chain. A document dropped by the nested chain is
+// `null`.
+pub(crate) fn apply_nested(
+    input: Cow<'_, Value>,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+    context: &Context,
+    budget: &Budget,
+) -> Result<Value> {
+    spec.check_input(&input)?;
+    let mut result = input;
+    for (index, operation) in spec.operations().enumerate() {
+        if !operation.applies(&result) {
+            continue;
+        }
+        if let Some(drop) = operation.halts(&result, context) {
+            return Ok(if drop {
+                Value::Null
+            } else {
+                spec.wrap_output(result.into_owned(), context)
+            });
+        }
+        result = Cow::Owned(
+            operation
+                .apply_scoped(result, |input| {
+                    apply_entry(input, operation.entry(), options, context, budget)
+                })
+                .map_err(|e| operation.attribute(index, e))?,
+        );
     }
+    Ok(spec.wrap_output(result.into_owned(), context))
+}
+
+// Whether the chain halts before the entry, and whether the document is then dropped
+pub(crate) fn halt(entry: &SpecEntry, input: &Value, context: &Context) -> Option<bool> {
+    match entry {
+        SpecEntry::HaltIf { spec, options } if spec.holds(input) => Some(options.drop),
+        SpecEntry::Sample { spec } if !spec.keep(context) => Some(true),
+        _ => None,
+    }
+}
+
+// Applies the entry to the input, the values of an owned input are moved to the output
+pub(crate) fn apply_entry(
+    input: Cow<'_, Value>,
+    entry: &SpecEntry,
+    transform_options: &TransformOptions,
+    context: &Context,
+    budget: &Budget,
+) -> Result<Value> {
+    let result = match entry {
+        SpecEntry::Shift { spec, options } => {
+            let options = transform_options.shift_options(options);
+            let observer = transform_options.get_observer();
+            let partial = transform_options.get_partial_output();
+            if observer.is_none() && budget.is_unlimited() && !partial && !budget.records_writes() {
+                match input {
+                    Cow::Owned(input) => spec.apply_owned(input, &options)?,
+                    Cow::Borrowed(input) => spec.apply(input, &options, context)?,
+                }
+            } else {
+                spec.apply_limited(&input, &options, observer, budget, partial)?
+            }
+        }
+        SpecEntry::Default { spec, options } => default(input.into_owned(), spec, options, context),
+        SpecEntry::Remove { spec } => remove(input.into_owned(), spec),
+        SpecEntry::Retain { spec } => retain(input.into_owned(), spec),
+        SpecEntry::Identity { spec } => identity(input.into_owned(), spec),
+        SpecEntry::Rename { spec } => rename(input.into_owned(), spec),
+        SpecEntry::Try { spec, options } => {
+            try_catch(input, spec, options, transform_options, context, budget)?
+        }
+        SpecEntry::HaltIf { .. } | SpecEntry::Sample { .. } => input.into_owned(),
+    };
+    budget.check_output(&result)?;
     Ok(result)
 }
 
@@ -101,24 +557,16 @@ pub(crate) fn insert(dest: &mut Value, position: JsonPointer, val: Value) {
             _ => None,
         });
     if let Some(pointer_mut) = folded {
-        merge(pointer_mut, val);
+        merge_shallow(pointer_mut, val);
     }
 }
 
-/// Merge one `Value` node into another if they are both `Value::Object`, otherwise overwrite.
-fn merge(dest: &mut Value, new_value: Value) {
-    match (dest, new_value) {
-        (Value::Object(dest), Value::Object(new_value)) => {
-            for (key, value) in new_value.into_iter() {
-                dest.insert(key, value);
-            }
-        }
-        (dest, new_value) => *dest = new_value,
-    };
-}
-
 pub(crate) fn delete(dest: &mut Value, position: &JsonPointer) -> Option<()> {
     if let Some(Value::Object(map)) = dest.pointer_mut(position.parent().join_rfc6901().as_str()) {
+        // keep the order of the remaining keys
+        #[cfg(feature = "preserve_order")]
+        map.shift_remove(position.leaf_name());
+        #[cfg(not(feature = "preserve_order"))]
         map.remove(position.leaf_name());
     }
     Some(())
@@ -325,4 +773,303 @@ mod test {
             })
         );
     }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_delete_preserves_order() {
+        //given
+        let mut input = json!({
+            "c": "c",
+            "a": "a",
+            "b": "b",
+        });
+
+        //when
+        let _ = delete(&mut input, &JsonPointer::from_dot_notation("c"));
+
+        //then
+        assert_eq!(input.to_string(), r#"{"a":"a","b":"b"}"#);
+    }
+
+    #[test]
+    fn test_transform_with_options() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "name": "names.@(1,id)"
+                }
+            }
+        ]))
+        .expect("parsed spec");
+        let input = json!({"name": "John"});
+
+        //when
+        let lenient = transform(input.clone(), &spec);
+        let strict = transform_with_options(
+            input,
+            &spec,
+            &TransformOptions::default().strict_lookups(true),
+        );
+
+        //then
+        assert_eq!(lenient.unwrap(), Value::Null);
+        assert_eq!(strict.map_err(|e| e.code()), Err("key_not_found"));
+    }
+
+    #[test]
+    fn test_transform_with_context() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a": "b"}},
+            {"operation": "default", "expressions": true, "spec": {
+                "tenant": "@context(tenant)",
+                "region": "@context(region)"
+            }}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"a": 0});
+        let context = Context::default().variable("tenant", json!("acme"));
+
+        //when
+        let result =
+            transform_with_context(input.clone(), &spec, &TransformOptions::default(), &context);
+        let without_context = transform(input, &spec);
+
+        //then
+        assert_eq!(result.unwrap(), json!({"b": 0, "tenant": "acme"}));
+        assert_eq!(without_context.unwrap(), json!({"b": 0}));
+    }
+
+    #[test]
+    fn test_public_types_traits() {
+        fn assert_traits<T: Clone + std::fmt::Debug + PartialEq>() {}
+
+        assert_traits::<TransformSpec>();
+        assert_traits::<TransformSpecSet>();
+        assert_traits::<TransformStats>();
+        assert_traits::<Operation>();
+        assert_traits::<TransformOptions>();
+        assert_traits::<Conflicts>();
+        assert_traits::<MatchOrder>();
+        assert_traits::<Nulls>();
+        assert_traits::<Context>();
+        assert_traits::<JoltEngine>();
+        assert_traits::<Error>();
+        assert_traits::<MigrationNote>();
+        assert_traits::<Lineage>();
+        assert_traits::<ir::SpecIr>();
+    }
+
+    #[test]
+    fn test_transform_stepwise_halted() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"type": "type", "id": "data.id"}},
+            {"operation": "halt-if", "drop": true, "spec": {"path": "type", "not_equals": "order"}},
+            {"operation": "default", "spec": {"data": {"kind": "order"}}}
+        ]))
+        .unwrap();
+
+        //when
+        let order = transform_stepwise(json!({"type": "order", "id": 1}), &spec).unwrap();
+        let refund = transform_stepwise(json!({"type": "refund", "id": 2}), &spec).unwrap();
+
+        //then
+        assert_eq!(
+            order,
+            vec![
+                json!({"type": "order", "data": {"id": 1}}),
+                json!({"type": "order", "data": {"id": 1}}),
+                json!({"type": "order", "data": {"id": 1, "kind": "order"}}),
+            ]
+        );
+        assert_eq!(
+            refund,
+            vec![json!({"type": "refund", "data": {"id": 2}}), Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_transform_stepwise_toggled() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "enabled": false, "spec": {"id": "data.id"}},
+            {"operation": "halt-if", "when": {"exists": "/debug"}, "spec": {"path": "id", "exists": true}},
+            {"operation": "default", "spec": {"kind": "user"}}
+        ]))
+        .unwrap();
+
+        //when
+        let steps = transform_stepwise(json!({"id": 1}), &spec).unwrap();
+        let halted = transform_stepwise(json!({"id": 1, "debug": true}), &spec).unwrap();
+
+        //then
+        assert!(!spec.get(0).unwrap().enabled());
+        assert_eq!(
+            steps,
+            vec![
+                json!({"id": 1}),
+                json!({"id": 1}),
+                json!({"id": 1, "kind": "user"}),
+            ]
+        );
+        assert_eq!(
+            halted,
+            vec![
+                json!({"id": 1, "debug": true}),
+                json!({"id": 1, "debug": true})
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transform_ref() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "halt-if", "drop": true, "spec": {"path": "type", "equals": "test"}},
+            {"operation": "shift", "spec": {"account": "data.account", "id": "data.id"}},
+            {"operation": "default", "spec": {"data": {"kind": "user"}}},
+            {"operation": "shift", "root": "/data", "spec": {"account": "account", "id": "key"}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"id": 1, "account": {"id": 1000, "type": "Checking"}});
+        let dropped = json!({"type": "test"});
+
+        //when
+        let output = transform_ref(&input, &spec);
+        let dropped_output = transform_ref(&dropped, &spec);
+
+        //then
+        assert_eq!(output, transform(input.clone(), &spec));
+        assert_eq!(
+            output.unwrap(),
+            json!({"data": {"account": {"id": 1000, "type": "Checking"}, "key": 1}})
+        );
+        assert_eq!(dropped_output.unwrap(), Value::Null);
+        assert_eq!(input["account"]["type"], "Checking");
+    }
+
+    #[test]
+    fn test_scoped_operations() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "root": "/payload", "spec": {"id": "user_id", "*": "&"}},
+            {"operation": "halt-if", "root": "/payload", "drop": true, "spec": {"path": "skip", "exists": true}},
+            {"operation": "default", "root": "/missing", "spec": {"kind": "user"}}
+        ]))
+        .unwrap();
+
+        //when
+        let output = transform(
+            json!({"meta": {"key": "a"}, "payload": {"id": 1, "name": "John"}}),
+            &spec,
+        );
+        let dropped = transform_filtered(
+            json!({"payload": {"skip": true}}),
+            &spec,
+            &TransformOptions::default(),
+            &Context::default(),
+        );
+        let invalid = serde_json::from_value::<TransformSpec>(json!([
+            {"operation": "shift", "root": "payload", "spec": {}}
+        ]));
+
+        //then
+        assert_eq!(
+            output.unwrap(),
+            json!({"meta": {"key": "a"}, "payload": {"user_id": 1, "name": "John"}})
+        );
+        assert_eq!(dropped.unwrap(), None);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_named_operation_error() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a": "a", "b": "b"}},
+            {"operation": "shift", "name": "index-items", "spec": {"*": "items[&(0)]"}}
+        ]))
+        .unwrap();
+
+        //when
+        let result = transform(json!({"a": 1}), &spec);
+
+        //then
+        let error = result.unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::Operation { name, index: 1, source } if name == "index-items" && matches!(
+                &**source,
+                Error::SpecKey { path, source }
+                    if path == "/*" && matches!(**source, Error::InvalidIndex { .. })
+            )
+        ));
+        assert_eq!(error.code(), "invalid_index");
+        assert!(error
+            .to_string()
+            .starts_with("Operation 'index-items' (index 1) failed: "));
+        assert_eq!(
+            error.to_json(&Value::Null)["operation"],
+            json!({"name": "index-items", "index": 1})
+        );
+    }
+
+    #[test]
+    fn test_partial_output() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "name": "orders", "strict_lookups": true, "spec": {
+                "id": "order.id",
+                "lines": {"*": {"sku": "order.lines[&(1)].@(3,missing)"}}
+            }}
+        ]))
+        .unwrap();
+        let input = json!({"id": 1, "lines": [{"sku": "a"}]});
+        let options = TransformOptions::default().partial_output(true);
+
+        //when
+        let partial = transform_with_options(input.clone(), &spec, &options).unwrap_err();
+        let failed = transform(input.clone(), &spec).unwrap_err();
+
+        //then
+        assert_eq!(partial.code(), "key_not_found");
+        assert_eq!(partial.output_pointer(), Some("/order/lines/0"));
+        assert_eq!(partial.partial_output(), Some(&json!({"order": {"id": 1}})));
+        assert_eq!(
+            partial.to_json(&input)["output_pointer"],
+            json!("/order/lines/0")
+        );
+        assert!(matches!(
+            &partial,
+            Error::Operation { source, .. } if matches!(**source, Error::PartialOutput { .. })
+        ));
+        assert_eq!(failed.output_pointer(), None);
+        assert_eq!(failed.partial_output(), None);
+    }
+
+    #[test]
+    fn test_transform_unmet_expectations() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "expects": {"/headers": "object"},
+            "operations": [{"operation": "shift", "spec": {"headers": {"*": "&"}}}]
+        }))
+        .unwrap();
+
+        //when
+        let result = transform(json!({"headers": "none"}), &spec);
+        let steps = transform_stepwise(json!({}), &spec);
+
+        //then
+        assert_eq!(result.unwrap_err().code(), "unmet_expectations");
+        assert_eq!(
+            steps,
+            Err(Error::UnmetExpectations(vec![
+                "/headers: missing, expected object".to_string()
+            ]))
+        );
+    }
 }
@@ -0,0 +1,496 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::pointer::{to_rfc6901, JsonPointer};
+use crate::spec::SpecEntry;
+use crate::{apply_chain, Applied, Context, Result, TransformOptions, TransformSpec};
+
+/// The source of a field of the transformation output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Lineage {
+    /// [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointer to the output field.
+    pub output_path: String,
+    /// Pointer to the input field the output field is read from. `None` if the value is
+    /// produced by the specification, e.g. `#` literals of `shift` or values of `default`.
+    pub input_path: Option<String>,
+    /// Indexes of the operations of the specification that moved or produced the value, in
+    /// the order they were applied. The indexes match the operations of
+    /// [`TransformSpec::to_ir`].
+    pub operations: Vec<usize>,
+}
+
+impl Lineage {
+    /// The output path in the jolt dot-notation.
+    pub fn output_dot_path(&self) -> String {
+        JsonPointer::from_rfc6901(&self.output_path)
+            .unwrap_or_default()
+            .to_dot_notation()
+    }
+
+    /// The input path in the jolt dot-notation.
+    pub fn input_dot_path(&self) -> Option<String> {
+        self.input_path
+            .as_deref()
+            .and_then(JsonPointer::from_rfc6901)
+            .map(|pointer| pointer.to_dot_notation())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Origin {
+    input: Option<Vec<String>>,
+    operations: Vec<usize>,
+}
+
+impl Origin {
+    fn produced(idx: usize) -> Self {
+        Self {
+            input: None,
+            operations: vec![idx],
+        }
+    }
+
+    fn moved(&self, idx: usize) -> Self {
+        let mut operations = self.operations.clone();
+        operations.push(idx);
+        Self {
+            input: self.input.clone(),
+            operations,
+        }
+    }
+}
+
+/// Reports the input field every output field is read from when the specification is applied
+/// to the given sample record.
+///
+/// Output fields are the scalars, empty objects and empty arrays of the output, reported in
+/// the order of the output.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{lineage, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"account": {"id": "data.account_id"}}},
+///     {"operation": "default", "spec": {"data": {"source": "crm"}}}
+/// ]))
+/// .unwrap();
+///
+/// let lineage = lineage(&json!({"account": {"id": 1000}}), &spec).unwrap();
+///
+/// assert_eq!(lineage[0].output_path, "/data/account_id");
+/// assert_eq!(lineage[0].input_path.as_deref(), Some("/account/id"));
+/// assert_eq!(lineage[0].operations, vec![0]);
+/// assert_eq!(lineage[0].input_dot_path().as_deref(), Some("account.id"));
+///
+/// assert_eq!(lineage[1].output_path, "/data/source");
+/// assert_eq!(lineage[1].input_path, None);
+/// assert_eq!(lineage[1].operations, vec![1]);
+/// ```
+///
+/// Values moved between array indexes by `retain` are reported as produced by the operation, as
+/// are the values moved by a `shift` scoped with a `root` and the values under the keys renamed
+/// by `rename`.
+/// Nothing is reported for a record dropped by a `halt-if` or `sample` operation.
+/// The fields of the `envelope` other than the output have no input path and no operations.
+pub fn lineage(input: &Value, spec: &TransformSpec) -> Result<Vec<Lineage>> {
+    lineage_with_context(
+        input,
+        spec,
+        &TransformOptions::default(),
+        &Context::default(),
+    )
+}
+
+/// Reports the lineage of the output fields like [`lineage`] when the specification is applied
+/// with the given options and [`Context`], see
+/// [`transform_with_context`](crate::transform_with_context).
+pub fn lineage_with_context(
+    input: &Value,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+    context: &Context,
+) -> Result<Vec<Lineage>> {
+    let mut origins: HashMap<Vec<String>, Origin> = nodes(input)
+        .into_iter()
+        .map(|(ptr, _)| {
+            let origin = Origin {
+                input: Some(ptr.clone()),
+                operations: Vec::new(),
+            };
+            (ptr, origin)
+        })
+        .collect();
+    let mut current = input.clone();
+
+    let options = options.clone().record_writes();
+    let output = apply_chain(Cow::Borrowed(input), spec, &options, context, |applied| {
+        if let Some(output) = applied.output {
+            origins = trace(&applied, output, &current, &origins);
+            current = output.clone();
+        }
+    })?;
+    // nothing is reported for a dropped record
+    let Some(output) = output else {
+        return Ok(Vec::new());
+    };
+    let data = spec.envelope_data();
+
+    Ok(nodes(&output)
+        .into_iter()
+        .filter(|(_, value)| is_leaf(value))
+        .map(|(ptr, _)| {
+            // the fields of the envelope other than the output have no origin
+            let origin = match data {
+                Some(data) => ptr
+                    .split_first()
+                    .filter(|(key, _)| key.as_str() == data)
+                    .and_then(|(_, rest)| origins.remove(rest)),
+                None => origins.remove(&ptr),
+            };
+            Lineage {
+                output_path: to_rfc6901(&ptr),
+                input_path: origin
+                    .as_ref()
+                    .and_then(|origin| origin.input.as_deref())
+                    .map(to_rfc6901),
+                operations: origin.map(|origin| origin.operations).unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+// The origins of the nodes of the output of the applied operation, from the origins of the
+// nodes of the document it is applied to
+fn trace(
+    applied: &Applied<'_>,
+    output: &Value,
+    current: &Value,
+    origins: &HashMap<Vec<String>, Origin>,
+) -> HashMap<Vec<String>, Origin> {
+    let idx = applied.index;
+    let entry = applied.operation.entry();
+    let root = applied.operation.root();
+    match entry {
+        SpecEntry::Shift { .. } if root.is_empty() => nodes(output)
+            .into_iter()
+            .map(|(ptr, _)| {
+                // the latest of the deepest writes the node is part of
+                let write = applied
+                    .writes
+                    .iter()
+                    .filter(|write| ptr.starts_with(&write.output))
+                    .max_by_key(|write| write.output.len());
+                let origin = write
+                    .and_then(|write| {
+                        let mut input = write.input.clone()?;
+                        input.extend_from_slice(&ptr[write.output.len()..]);
+                        origins.get(&input)
+                    })
+                    .map(|origin| origin.moved(idx))
+                    .unwrap_or_else(|| Origin::produced(idx));
+                (ptr, origin)
+            })
+            .collect(),
+        entry => {
+            let prefix = match entry {
+                SpecEntry::Identity { spec } => spec
+                    .prefix()
+                    .map(|prefix| JsonPointer::from_dot_notation(prefix).entries()[1..].to_vec()),
+                _ => None,
+            };
+            nodes(output)
+                .into_iter()
+                .map(|(ptr, value)| {
+                    // the prefix is relative to the root, the nodes outside of the root
+                    // are untouched
+                    let previous = match (&prefix, ptr.strip_prefix(root.as_slice())) {
+                        (Some(prefix), Some(scoped)) => scoped
+                            .strip_prefix(prefix.as_slice())
+                            .map(|rest| [root.as_slice(), rest].concat()),
+                        _ => Some(ptr.clone()),
+                    };
+                    let origin = previous
+                        .filter(|previous| {
+                            get(current, previous).is_some_and(|prev| same(prev, value))
+                        })
+                        .and_then(|previous| origins.get(&previous))
+                        .map(|origin| match entry {
+                            SpecEntry::Identity { .. } if ptr.starts_with(&root) => {
+                                origin.moved(idx)
+                            }
+                            _ => origin.clone(),
+                        })
+                        .unwrap_or_else(|| Origin::produced(idx));
+                    (ptr, origin)
+                })
+                .collect()
+        }
+    }
+}
+
+// All nodes of the value with their pointers, in the order of the value
+fn nodes(value: &Value) -> Vec<(Vec<String>, &Value)> {
+    fn visit<'a>(ptr: &mut Vec<String>, value: &'a Value, out: &mut Vec<(Vec<String>, &'a Value)>) {
+        out.push((ptr.clone(), value));
+        match value {
+            Value::Object(obj) => {
+                for (key, value) in obj {
+                    ptr.push(key.clone());
+                    visit(ptr, value, out);
+                    ptr.pop();
+                }
+            }
+            Value::Array(arr) => {
+                for (idx, value) in arr.iter().enumerate() {
+                    ptr.push(idx.to_string());
+                    visit(ptr, value, out);
+                    ptr.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut out = Vec::new();
+    visit(&mut Vec::new(), value, &mut out);
+    out
+}
+
+fn get<'a>(value: &'a Value, ptr: &[String]) -> Option<&'a Value> {
+    ptr.iter().try_fold(value, |value, segment| match value {
+        Value::Object(obj) => obj.get(segment),
+        Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+// Containers are the same if they have the same type, scalars if they are equal
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Object(_), Value::Object(_)) | (Value::Array(_), Value::Array(_)) => true,
+        (a, b) => a == b,
+    }
+}
+
+fn is_leaf(value: &Value) -> bool {
+    match value {
+        Value::Object(obj) => obj.is_empty(),
+        Value::Array(arr) => arr.is_empty(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    fn paths(lineage: &[Lineage]) -> Vec<(&str, Option<&str>, Vec<usize>)> {
+        lineage
+            .iter()
+            .map(|l| {
+                (
+                    l.output_path.as_str(),
+                    l.input_path.as_deref(),
+                    l.operations.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lineage_shift_wildcards() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "items": {
+                        "*": {
+                            "name": "names[]",
+                            "$": "keys[]",
+                            "#item": "kinds[]"
+                        }
+                    },
+                    "@(items[0].name)": "first"
+                }
+            }
+        ]))
+        .expect("parsed spec");
+        let input = json!({
+            "items": [
+                {"name": "a"},
+                {"name": "b"}
+            ]
+        });
+
+        //when
+        let lineage = lineage(&input, &spec).expect("lineage");
+
+        //then
+        assert_eq!(
+            paths(&lineage),
+            vec![
+                ("/first", Some("/items/0/name"), vec![0]),
+                ("/keys/0", Some("/items/0"), vec![0]),
+                ("/keys/1", Some("/items/1"), vec![0]),
+                ("/kinds/0", None, vec![0]),
+                ("/kinds/1", None, vec![0]),
+                ("/names/0", Some("/items/0/name"), vec![0]),
+                ("/names/1", Some("/items/1/name"), vec![0]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_lineage_collisions() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "a": "out",
+                    "b": "out",
+                    "c": "obj",
+                    "d": "obj.x"
+                }
+            }
+        ]))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2, "c": 3, "d": 4});
+
+        //when
+        let lineage = lineage(&input, &spec).expect("lineage");
+
+        //then
+        assert_eq!(
+            paths(&lineage),
+            vec![
+                ("/out/0", Some("/a"), vec![0]),
+                ("/out/1", Some("/b"), vec![0]),
+                ("/obj/x", Some("/d"), vec![0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lineage_chain() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"id": "data.id", "name": "data.name"}},
+            {"operation": "remove", "spec": {"data": {"name": ""}}},
+            {"operation": "default", "spec": {"data": {"kind": "user"}}},
+            {"operation": "identity", "spec": {"prefix": "record"}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"id": 1, "name": "John"});
+
+        //when
+        let lineage = lineage(&input, &spec).expect("lineage");
+
+        //then
+        assert_eq!(
+            paths(&lineage),
+            vec![
+                ("/record/data/id", Some("/id"), vec![0, 3]),
+                ("/record/data/kind", None, vec![2, 3]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_lineage_scoped() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "root": "/payload", "spec": {"id": "user_id"}},
+            {"operation": "identity", "root": "/meta", "spec": {"prefix": "headers"}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"payload": {"id": 1}, "meta": {"key": "a"}});
+
+        //when
+        let lineage = lineage(&input, &spec).expect("lineage");
+
+        //then
+        assert_eq!(
+            paths(&lineage),
+            vec![
+                ("/payload/user_id", None, vec![0]),
+                ("/meta/headers/key", Some("/meta/key"), vec![1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lineage_envelope_and_try() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "envelope": {"schema_version": "1", "produced_at": false},
+            "operations": [
+                {"operation": "try", "spec": [{"operation": "shift", "spec": {"id": "id"}}]},
+                {"operation": "default", "spec": {"kind": "user"}}
+            ]
+        }))
+        .expect("parsed spec");
+        let input = json!({"id": 1});
+
+        //when
+        let lineage = lineage(&input, &spec).expect("lineage");
+
+        //then
+        let mut paths = paths(&lineage);
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                ("/data/id", Some("/id"), vec![]),
+                ("/data/kind", None, vec![1]),
+                ("/schema_version", None, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lineage_options() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a": "out", "b": "out"}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2});
+        let options = TransformOptions::default().conflicts(crate::Conflicts::Overwrite);
+
+        //when
+        let lineage =
+            lineage_with_context(&input, &spec, &options, &Context::default()).expect("lineage");
+
+        //then
+        assert_eq!(paths(&lineage), vec![("/out", Some("/b"), vec![0])]);
+    }
+
+    #[test]
+    fn test_lineage_dot_paths() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a\\.b": {"c/d": "out.x\\.y"}}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"a.b": {"c/d": 1}});
+
+        //when
+        let lineage = lineage(&input, &spec).expect("lineage");
+
+        //then
+        assert_eq!(lineage[0].output_path, "/out/x.y");
+        assert_eq!(lineage[0].output_dot_path(), "out.x\\.y");
+        assert_eq!(lineage[0].input_path.as_deref(), Some("/a.b/c~1d"));
+        assert_eq!(lineage[0].input_dot_path().as_deref(), Some("a\\.b.c/d"));
+    }
+}
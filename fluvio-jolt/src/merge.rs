@@ -0,0 +1,167 @@
+use serde_json::Value;
+
+use crate::pointer::to_rfc6901;
+use crate::{Error, Result};
+
+/// Handling of the keys present in both values merged by [`merge_values`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The keys of the source object replace the keys of the destination object, any other
+    /// source value replaces the destination value.
+    #[default]
+    Shallow,
+    /// Objects are merged recursively, any other source value replaces the destination value.
+    Deep,
+    /// Objects are merged recursively and the source arrays are appended to the destination
+    /// arrays, any other source value replaces the destination value.
+    ArrayConcat,
+    /// Objects are merged recursively, different values under the same key fail with
+    /// [`Error::OutputConflict`].
+    Error,
+}
+
+/// Merges the source value into the destination value with the given [`MergeStrategy`].
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{merge_values, MergeStrategy};
+///
+/// let mut dest = json!({"a": {"b": 1}, "tags": ["x"]});
+///
+/// merge_values(&mut dest, json!({"a": {"c": 2}, "tags": ["y"]}), MergeStrategy::ArrayConcat)
+///     .unwrap();
+///
+/// assert_eq!(dest, json!({"a": {"b": 1, "c": 2}, "tags": ["x", "y"]}));
+/// ```
+///
+/// On error the destination may be partially merged.
+pub fn merge_values(dest: &mut Value, src: Value, strategy: MergeStrategy) -> Result<()> {
+    merge_at(dest, src, strategy, &mut Vec::new())
+}
+
+fn merge_at(
+    dest: &mut Value,
+    src: Value,
+    strategy: MergeStrategy,
+    path: &mut Vec<String>,
+) -> Result<()> {
+    match (dest, src) {
+        (dest, src) if strategy == MergeStrategy::Shallow => merge_shallow(dest, src),
+        (Value::Object(dest), Value::Object(src)) => {
+            for (key, value) in src {
+                match dest.get_mut(&key) {
+                    Some(existing) => {
+                        path.push(key);
+                        merge_at(existing, value, strategy, path)?;
+                        path.pop();
+                    }
+                    None => {
+                        dest.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(dest), Value::Array(src)) if strategy == MergeStrategy::ArrayConcat => {
+            dest.extend(src);
+        }
+        (dest, src) if strategy == MergeStrategy::Error && *dest != src => {
+            return Err(Error::OutputConflict {
+                path: to_rfc6901(path),
+                first: dest.to_string(),
+                second: src.to_string(),
+            });
+        }
+        (dest, src) => *dest = src,
+    };
+    Ok(())
+}
+
+/// Merge one `Value` node into another if they are both `Value::Object`, otherwise overwrite.
+pub(crate) fn merge_shallow(dest: &mut Value, src: Value) {
+    match (dest, src) {
+        (Value::Object(dest), Value::Object(src)) => {
+            for (key, value) in src {
+                dest.insert(key, value);
+            }
+        }
+        (dest, src) => *dest = src,
+    };
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_merge_shallow() {
+        //given
+        let mut dest = json!({"a": {"b": 1}, "c": 1});
+
+        //when
+        merge_values(&mut dest, json!({"a": {"d": 2}}), MergeStrategy::Shallow).unwrap();
+
+        //then
+        assert_eq!(dest, json!({"a": {"d": 2}, "c": 1}));
+    }
+
+    #[test]
+    fn test_merge_deep() {
+        //given
+        let mut dest = json!({"a": {"b": 1, "arr": [1]}, "c": 1});
+
+        //when
+        merge_values(
+            &mut dest,
+            json!({"a": {"d": 2, "arr": [2]}, "c": "x"}),
+            MergeStrategy::Deep,
+        )
+        .unwrap();
+
+        //then
+        assert_eq!(dest, json!({"a": {"b": 1, "arr": [2], "d": 2}, "c": "x"}));
+    }
+
+    #[test]
+    fn test_merge_array_concat() {
+        //given
+        let mut dest = json!({"a": {"arr": [1]}, "b": [1]});
+
+        //when
+        merge_values(
+            &mut dest,
+            json!({"a": {"arr": [2, 3]}, "b": 2}),
+            MergeStrategy::ArrayConcat,
+        )
+        .unwrap();
+
+        //then
+        assert_eq!(dest, json!({"a": {"arr": [1, 2, 3]}, "b": 2}));
+    }
+
+    #[test]
+    fn test_merge_error_on_conflict() {
+        //given
+        let mut dest = json!({"a": {"b": 1, "c/d": true}});
+
+        //when
+        let same = merge_values(&mut dest, json!({"a": {"b": 1}}), MergeStrategy::Error);
+        let different = merge_values(
+            &mut dest,
+            json!({"a": {"c/d": false}}),
+            MergeStrategy::Error,
+        );
+
+        //then
+        assert_eq!(same, Ok(()));
+        assert_eq!(
+            different,
+            Err(Error::OutputConflict {
+                path: "/a/c~1d".to_string(),
+                first: "true".to_string(),
+                second: "false".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use serde_json::{Map, Value};
+
+/// A rewrite performed by [`TransformSpec::migrate_legacy`](crate::TransformSpec::migrate_legacy).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationNote {
+    /// [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointer to the rewritten key or
+    /// value in the specification.
+    pub pointer: String,
+    /// The expression before the migration.
+    pub original: String,
+    /// The expression after the migration.
+    pub migrated: String,
+    /// Explanation of the rewrite.
+    pub message: String,
+}
+
+pub(crate) fn migrate_legacy(spec: &str) -> serde_json::Result<(String, Vec<MigrationNote>)> {
+    let mut spec: Value = serde_json::from_str(spec)?;
+    let mut notes = Vec::new();
+
+    if let Value::Array(entries) = &mut spec {
+        for (idx, entry) in entries.iter_mut().enumerate() {
+            let is_shift = entry.get("operation").and_then(Value::as_str) == Some("shift");
+            if let (true, Some(shift)) = (is_shift, entry.get_mut("spec")) {
+                let pointer = format!("/{idx}/spec");
+                *shift = migrate_value(std::mem::take(shift), &pointer, &mut notes);
+            }
+        }
+    }
+
+    Ok((serde_json::to_string_pretty(&spec)?, notes))
+}
+
+fn migrate_value(value: Value, pointer: &str, notes: &mut Vec<MigrationNote>) -> Value {
+    match value {
+        Value::Object(obj) => {
+            let mut migrated = Map::new();
+            for (key, value) in obj {
+                let new_key = migrate_expr(&key, pointer, notes);
+                let pointer = format!("{pointer}/{}", escape(&new_key));
+                let value = migrate_value(value, &pointer, notes);
+                migrated.insert(new_key, value);
+            }
+            Value::Object(migrated)
+        }
+        Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .enumerate()
+                .map(|(idx, value)| migrate_value(value, &format!("{pointer}/{idx}"), notes))
+                .collect(),
+        ),
+        Value::String(expr) => Value::String(migrate_expr(&expr, pointer, notes)),
+        other => other,
+    }
+}
+
+// Rewrite the legacy `&N` syntax of an expression to `&(N)`
+fn migrate_expr(expr: &str, pointer: &str, notes: &mut Vec<MigrationNote>) -> String {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut out = String::with_capacity(expr.len());
+    let mut messages = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                out.push('\\');
+                if let Some(c) = chars.get(i + 1) {
+                    out.push(*c);
+                }
+                i += 2;
+            }
+            '&' => {
+                let digits = take_digits(&chars, i + 1);
+                if digits.is_empty() {
+                    out.push('&');
+                    i += 1;
+                } else {
+                    out.push_str(&format!("&({digits})"));
+                    messages.push(format!("`&{digits}` is rewritten to `&({digits})`"));
+                    i += 1 + digits.len();
+                }
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    for message in messages {
+        notes.push(MigrationNote {
+            pointer: pointer.to_string(),
+            original: expr.to_string(),
+            migrated: out.clone(),
+            message,
+        });
+    }
+
+    out
+}
+
+fn take_digits(chars: &[char], start: usize) -> String {
+    chars
+        .iter()
+        .skip(start)
+        .take_while(|c| c.is_ascii_digit())
+        .collect()
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_migrate_amp() {
+        //given
+        let spec = json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "*": {
+                        "&1": "data.&12.&(1,0).\\&3"
+                    }
+                }
+            },
+            {
+                "operation": "default",
+                "spec": {
+                    "a": "&1"
+                }
+            }
+        ])
+        .to_string();
+
+        //when
+        let (migrated, notes) = migrate_legacy(&spec).expect("migrated spec");
+
+        //then
+        let migrated: Value = serde_json::from_str(&migrated).unwrap();
+        assert_eq!(
+            migrated,
+            json!([
+                {
+                    "operation": "shift",
+                    "spec": {
+                        "*": {
+                            "&(1)": "data.&(12).&(1,0).\\&3"
+                        }
+                    }
+                },
+                {
+                    "operation": "default",
+                    "spec": {
+                        "a": "&1"
+                    }
+                }
+            ])
+        );
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].pointer, "/0/spec/*");
+        assert_eq!(notes[0].original, "&1");
+        assert_eq!(notes[1].pointer, "/0/spec/*/&(1)");
+        assert_eq!(notes[1].migrated, "data.&(12).&(1,0).\\&3");
+    }
+
+    #[test]
+    fn test_migrate_keeps_counters() {
+        //given
+        let spec = json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "items": {
+                        "*": {
+                            "id": "ids[#2]"
+                        }
+                    }
+                }
+            }
+        ])
+        .to_string();
+
+        //when
+        let (migrated, notes) = migrate_legacy(&spec).expect("migrated spec");
+
+        //then
+        let migrated: Value = serde_json::from_str(&migrated).unwrap();
+        assert_eq!(migrated, serde_json::from_str::<Value>(&spec).unwrap());
+        assert!(notes.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Error, OperationRef};
+
+/// Hooks called while a chain is applied, e.g. to record the metrics or the traces of the host
+/// application without the crate depending on a metrics library. The observer is set with
+/// [`TransformOptions::observer`](crate::TransformOptions::observer).
+///
+/// All the hooks do nothing by default. They are called on the thread applying the chain, in the
+/// order of the chain, so the hooks of a transformation can be correlated with the operation
+/// started last on the thread.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use serde_json::json;
+/// use fluvio_jolt::{
+///     transform_with_options, OperationRef, TransformObserver, TransformOptions, TransformSpec,
+/// };
+///
+/// #[derive(Default)]
+/// struct Counters {
+///     operations: AtomicUsize,
+///     matches: AtomicUsize,
+/// }
+///
+/// impl TransformObserver for Counters {
+///     fn on_operation_end(&self, _operation: OperationRef<'_>, _index: usize) {
+///         self.operations.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn on_match(&self, _key: &str) {
+///         self.matches.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"*": "data.&"}},
+///     {"operation": "default", "spec": {"data": {"kind": "user"}}}
+/// ]))
+/// .unwrap();
+/// let counters = Arc::new(Counters::default());
+/// let options = TransformOptions::default().observer(counters.clone());
+///
+/// transform_with_options(json!({"id": 1, "name": "John"}), &spec, &options).unwrap();
+///
+/// assert_eq!(counters.operations.load(Ordering::Relaxed), 2);
+/// assert_eq!(counters.matches.load(Ordering::Relaxed), 2);
+/// ```
+pub trait TransformObserver: Send + Sync {
+    /// Called before the operation at the index of the chain is applied. The operations skipped
+    /// by their `enabled` or `when` fields are not observed.
+    fn on_operation_start(&self, _operation: OperationRef<'_>, _index: usize) {}
+
+    /// Called after the operation at the index of the chain is applied, every started operation
+    /// ends. When the operation fails, [`TransformObserver::on_error`] is called after it.
+    fn on_operation_end(&self, _operation: OperationRef<'_>, _index: usize) {}
+
+    /// Called for every input key matched by a key of the spec of a `shift` operation.
+    fn on_match(&self, _key: &str) {}
+
+    /// Called for every input key of an object visited by a `shift` operation that no key of the
+    /// spec matches, so its value is not written to the output.
+    fn on_drop(&self, _key: &str) {}
+
+    /// Called for every array created in the output of a `shift` operation, by a write to an
+    /// array index or by values of the same output path collected into an array.
+    fn on_array_created(&self) {}
+
+    /// Called when the transformation fails, with the error it fails with.
+    fn on_error(&self, _error: &Error) {}
+
+    /// Called after a transformation taking at least the
+    /// [`slow_transform_threshold`](crate::TransformOptions::slow_transform_threshold) of the
+    /// options, whether it succeeds or fails.
+    #[cfg(feature = "slow_transforms")]
+    fn on_slow_transform(&self, _report: &crate::SlowTransform) {}
+}
+
+/// The observer of the options, options are equal when they share the same observer.
+#[derive(Clone)]
+pub(crate) struct Observer(pub(crate) Arc<dyn TransformObserver>);
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer(..)")
+    }
+}
+
+impl PartialEq for Observer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Observer {}
+
+#[cfg(test)]
+mod test {
+
+    use std::sync::Mutex;
+
+    use serde_json::json;
+    use super::*;
+    use crate::{transform_with_options, TransformOptions, TransformSpec};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl TransformObserver for Recorder {
+        fn on_operation_start(&self, operation: OperationRef<'_>, index: usize) {
+            self.record(format!("start {index} {}", operation.kind()));
+        }
+
+        fn on_operation_end(&self, operation: OperationRef<'_>, index: usize) {
+            self.record(format!("end {index} {}", operation.kind()));
+        }
+
+        fn on_match(&self, key: &str) {
+            self.record(format!("match {key}"));
+        }
+
+        fn on_error(&self, error: &Error) {
+            self.record(format!("error {}", error.code()));
+        }
+    }
+
+    impl Recorder {
+        fn record(&self, event: String) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn test_observed_events() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "remove", "enabled": false, "spec": {"id": ""}},
+            {"operation": "shift", "spec": {"id": "data.id", "items": {"*": "data.items[&]"}}}
+        ]))
+        .unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let options = TransformOptions::default().observer(recorder.clone());
+
+        //when
+        let output = transform_with_options(json!({"id": 1}), &spec, &options);
+        let failed = transform_with_options(json!({"items": {"a": 1}}), &spec, &options);
+
+        //then
+        assert!(output.is_ok());
+        assert!(failed.is_err());
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                "start 1 shift",
+                "match id",
+                "end 1 shift",
+                "start 1 shift",
+                "match items",
+                "match a",
+                "end 1 shift",
+                "error invalid_index",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_operation_ends() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "default", "spec": {"kind": "user"}},
+            {"operation": "shift", "name": "index", "spec": {"*": "list[&]"}},
+            {"operation": "remove", "spec": {"kind": ""}}
+        ]))
+        .unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let options = TransformOptions::default().observer(recorder.clone());
+
+        //when
+        let failed = transform_with_options(json!({"id": 1}), &spec, &options);
+
+        //then
+        assert!(matches!(failed, Err(Error::Operation { index: 1, .. })));
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                "start 0 default",
+                "end 0 default",
+                "start 1 shift",
+                "match id",
+                "end 1 shift",
+                "error invalid_index",
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+#[cfg(feature = "slow_transforms")]
+use std::time::Duration;
+
+use crate::observer::{Observer, TransformObserver};
+use crate::sandbox::Sandbox;
+use crate::shift::{Conflicts, MatchOrder, ShiftOptions};
+
+/// Options applied to the whole transformation, on top of the options of the operations.
+///
+/// The default options keep the behavior of [`transform`](crate::transform).
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform_with_options, Conflicts, TransformOptions, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"a": "out", "b": "out"}}
+/// ]))
+/// .unwrap();
+///
+/// let options = TransformOptions::default().conflicts(Conflicts::Overwrite);
+///
+/// let output = transform_with_options(json!({"a": 1, "b": 2}), &spec, &options).unwrap();
+/// assert_eq!(output, json!({"out": 2}));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TransformOptions {
+    strict_lookups: bool,
+    conflicts: Option<Conflicts>,
+    match_order: Option<MatchOrder>,
+    observer: Option<Observer>,
+    sandbox: Option<Sandbox>,
+    fuel: Option<u64>,
+    partial_output: bool,
+    #[cfg(feature = "slow_transforms")]
+    slow_transform_threshold: Option<Duration>,
+    record_writes: bool,
+}
+
+impl TransformOptions {
+    /// `@` lookups of missing keys or array indexes fail in all `shift` operations, like with
+    /// their `strict_lookups` option.
+    pub fn strict_lookups(mut self, strict_lookups: bool) -> Self {
+        self.strict_lookups = strict_lookups;
+        self
+    }
+
+    /// Handling of conflicting writes in all `shift` operations, replacing their `conflicts`
+    /// option.
+    pub fn conflicts(mut self, conflicts: Conflicts) -> Self {
+        self.conflicts = Some(conflicts);
+        self
+    }
+
+    /// Order in which the wildcard and `|` keys are tried in all `shift` operations, replacing
+    /// their `match_order` option. [`MatchOrder::Java`] gives the outputs of Java Jolt when an
+    /// input key is matched by several keys.
+    pub fn match_order(mut self, match_order: MatchOrder) -> Self {
+        self.match_order = Some(match_order);
+        self
+    }
+
+    /// Observer of the operations applied by the transformations, see [`TransformObserver`].
+    pub fn observer(mut self, observer: Arc<dyn TransformObserver>) -> Self {
+        self.observer = Some(Observer(observer));
+        self
+    }
+
+    /// Limits of the transformation of an untrusted specification, see [`Sandbox`].
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Steps a transformation can take before failing with
+    /// [`Error::BudgetExhausted`](crate::Error::BudgetExhausted), so a pathological
+    /// combination of specification and input terminates deterministically instead of stalling
+    /// the stream. Every input key tried against the keys of a `shift` spec takes a step, and
+    /// every value written by a `shift` operation takes a step for each of its nodes.
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// A `shift` operation failing midway fails with
+    /// [`Error::PartialOutput`](crate::Error::PartialOutput), carrying the output the operation
+    /// built so far and the pointer of the output value it was writing, so the consumers can
+    /// decide whether the partial document is salvageable.
+    pub fn partial_output(mut self, partial_output: bool) -> Self {
+        self.partial_output = partial_output;
+        self
+    }
+
+    /// Transformations taking the threshold or longer are reported to the observer with
+    /// [`TransformObserver::on_slow_transform`], along with the durations of their operations.
+    /// Nothing is timed without an observer.
+    #[cfg(feature = "slow_transforms")]
+    pub fn slow_transform_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_transform_threshold = Some(threshold);
+        self
+    }
+
+    #[cfg(feature = "slow_transforms")]
+    pub(crate) fn get_slow_transform_threshold(&self) -> Option<Duration> {
+        self.slow_transform_threshold
+    }
+
+    pub(crate) fn get_sandbox(&self) -> Option<Sandbox> {
+        self.sandbox
+    }
+
+    pub(crate) fn get_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    pub(crate) fn get_partial_output(&self) -> bool {
+        self.partial_output
+    }
+
+    // The writes of the `shift` operations are recorded to the budget of the chain, for the
+    // lineage
+    pub(crate) fn record_writes(mut self) -> Self {
+        self.record_writes = true;
+        self
+    }
+
+    pub(crate) fn get_record_writes(&self) -> bool {
+        self.record_writes
+    }
+
+    pub(crate) fn observer_arc(&self) -> Option<Arc<dyn TransformObserver>> {
+        self.observer
+            .as_ref()
+            .map(|observer| Arc::clone(&observer.0))
+    }
+
+    pub(crate) fn get_observer(&self) -> Option<&dyn TransformObserver> {
+        self.observer.as_ref().map(|observer| observer.0.as_ref())
+    }
+
+    pub(crate) fn shift_options(&self, options: &ShiftOptions) -> ShiftOptions {
+        ShiftOptions {
+            strict_lookups: options.strict_lookups || self.strict_lookups,
+            conflicts: self.conflicts.unwrap_or(options.conflicts),
+            match_order: self.match_order.unwrap_or(options.match_order),
+            ..*options
+        }
+    }
+}
@@ -1,54 +1,117 @@
-#![allow(dead_code)]
+//! Paths into JSON documents with the same semantics the engine uses.
+//!
+//! A [`JsonPointer`] converts between the jolt dot-notation, where the special characters of
+//! the [DSL](https://github.com/infinyon/fluvio-jolt/blob/master/SPEC.md) are escaped with `\`,
+//! and [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointers accepted by
+//! [`Value::pointer`](serde_json::Value::pointer).
+//!
+//! ```
+//! use fluvio_jolt::pointer::JsonPointer;
+//!
+//! let pointer = JsonPointer::from_dot_notation(r"data.a\.b.c/d");
+//!
+//! assert_eq!(pointer.join_rfc6901(), "/data/a.b/c~1d");
+//! assert_eq!(JsonPointer::from_rfc6901("/data/a.b/c~1d"), Some(pointer));
+//! ```
 
 use std::str::FromStr;
 
+use crate::dsl::escape_key;
+
+/// Path of a JSON value. The first element is always the empty string that corresponds to the
+/// root level.
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub(crate) struct JsonPointer {
+pub struct JsonPointer {
     entries: Vec<String>,
 }
 
 impl JsonPointer {
-    pub(crate) fn new(mut entries: Vec<String>) -> Self {
-        if entries.first().filter(|p| (**p).eq("")).is_none() {
+    /// Creates the pointer from the path elements, inserting the root level if it is missing.
+    pub fn new(mut entries: Vec<String>) -> Self {
+        if entries.first().filter(|p| p.is_empty()).is_none() {
             entries.insert(0, String::new());
         }
         Self { entries }
     }
 
-    pub(crate) fn from_dot_notation(path: &str) -> Self {
-        Self::new(path.split('.').map(|s| s.to_string()).collect())
+    /// Parses the jolt dot-notation path like `a.b\.c`, a `\` escapes the next character.
+    pub fn from_dot_notation(path: &str) -> Self {
+        let mut entries = Vec::new();
+        let mut entry = String::new();
+        let mut chars = path.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => entries.push(std::mem::take(&mut entry)),
+                '\\' => entry.extend(chars.next()),
+                c => entry.push(c),
+            }
+        }
+        entries.push(entry);
+        Self::new(entries)
+    }
+
+    /// Parses the [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointer like `/a/b~1c`.
+    /// Returns `None` if the pointer is not empty and doesn't start with `/`.
+    pub fn from_rfc6901(pointer: &str) -> Option<Self> {
+        if pointer.is_empty() {
+            return Some(Self::default());
+        }
+        let entries = pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|entry| entry.replace("~1", "/").replace("~0", "~"))
+            .collect();
+        Some(Self::new(entries))
     }
 
-    pub(crate) fn push<T: ToString>(&mut self, value: T) {
+    /// Appends the path element.
+    pub fn push<T: ToString>(&mut self, value: T) {
         self.entries.push(value.to_string());
     }
 
-    pub(crate) fn parent(&self) -> Self {
+    /// Returns the pointer to the parent value, the parent of the root is the root.
+    pub fn parent(&self) -> Self {
         let mut entries = self.entries.clone();
         entries.pop();
         Self::new(entries)
     }
 
-    pub(crate) fn entries(&self) -> &[String] {
+    /// Returns path elements of the pointer. First element is always empty string that corresponds
+    /// to root level.
+    pub fn entries(&self) -> &[String] {
         &self.entries
     }
 
-    pub(crate) fn leaf_name(&self) -> &str {
+    /// Returns the last path element, empty for the root.
+    pub fn leaf_name(&self) -> &str {
         self.entries.last().map(|s| s.as_str()).unwrap_or("")
     }
+
     /// Returns path elements of the pointer. First element is always empty string that corresponds
     /// to root level.
-    pub(crate) fn iter(&self) -> impl Iterator<Item = &String> {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
         self.entries.iter()
     }
 
-    /// Represents the pointer as [String] with the format [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901).
-    pub(crate) fn join_rfc6901(&self) -> String {
-        self.entries.join("/")
+    /// Represents the pointer as [String] with the format
+    /// [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901), escaping `~` and `/`.
+    pub fn join_rfc6901(&self) -> String {
+        to_rfc6901(&self.entries[1..])
+    }
+
+    /// Represents the pointer as [String] with the jolt dot-notation, escaping the special
+    /// characters with `\`.
+    pub fn to_dot_notation(&self) -> String {
+        self.entries[1..]
+            .iter()
+            .map(|entry| escape_key(entry))
+            .collect::<Vec<_>>()
+            .join(".")
     }
 
     /// Finds all path elements with the format '&N' and replaces them by values from
     /// given bindings where N is the index of bindings slice.
+    #[allow(dead_code)]
     pub(crate) fn substitute_vars<T: ToString>(&mut self, bindings: &[T]) {
         for entry in self.entries.iter_mut() {
             if entry.starts_with('&') {
@@ -62,6 +125,41 @@ impl JsonPointer {
     }
 }
 
+/// Converts the jolt dot-notation path like `a.b\\.c` into the
+/// [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointer like `/a/b.c`.
+///
+/// ```
+/// use fluvio_jolt::pointer::dot_to_rfc6901;
+///
+/// assert_eq!(dot_to_rfc6901(r"data.a\.b.c/d"), "/data/a.b/c~1d");
+/// ```
+pub fn dot_to_rfc6901(path: &str) -> String {
+    JsonPointer::from_dot_notation(path).join_rfc6901()
+}
+
+/// Converts the [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointer like `/a/b.c`,
+/// e.g. the paths reported by [`lineage`](crate::lineage) or [`Error::OutputConflict`](crate::Error::OutputConflict),
+/// into the jolt dot-notation path like `a.b\\.c`. Returns `None` if the pointer is not empty
+/// and doesn't start with `/`.
+///
+/// ```
+/// use fluvio_jolt::pointer::rfc6901_to_dot;
+///
+/// assert_eq!(rfc6901_to_dot("/data/a.b/c~1d").as_deref(), Some(r"data.a\.b.c/d"));
+/// ```
+pub fn rfc6901_to_dot(pointer: &str) -> Option<String> {
+    JsonPointer::from_rfc6901(pointer).map(|pointer| pointer.to_dot_notation())
+}
+
+/// Represents the path elements as [String] with the format
+/// [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901), escaping `~` and `/`.
+pub(crate) fn to_rfc6901(entries: &[String]) -> String {
+    entries
+        .iter()
+        .map(|entry| format!("/{}", entry.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
 impl Default for JsonPointer {
     fn default() -> Self {
         Self::new(Vec::default())
@@ -160,4 +258,41 @@ mod test {
         //then
         assert_eq!(pointer.join_rfc6901(), "/a/b/&11")
     }
+
+    #[test]
+    fn test_rfc6901_escaping() {
+        //given
+        let pointer = JsonPointer::new(vec!["a/b".to_string(), "c~d".to_string()]);
+
+        //when
+        let joined = pointer.join_rfc6901();
+
+        //then
+        assert_eq!(joined, "/a~1b/c~0d");
+        assert_eq!(JsonPointer::from_rfc6901(&joined), Some(pointer));
+    }
+
+    #[test]
+    fn test_from_rfc6901() {
+        assert_eq!(JsonPointer::from_rfc6901(""), Some(JsonPointer::default()));
+        assert_eq!(
+            JsonPointer::from_rfc6901("/a/~01").map(|p| p.entries().to_vec()),
+            Some(vec!["".to_string(), "a".to_string(), "~1".to_string()])
+        );
+        assert_eq!(JsonPointer::from_rfc6901("a/b"), None);
+    }
+
+    #[test]
+    fn test_dot_notation_escaping() {
+        //given
+        let pointer = JsonPointer::from_dot_notation(r"a\.b.c\\d.e&");
+
+        //when
+        let dot_notation = pointer.to_dot_notation();
+
+        //then
+        assert_eq!(pointer.entries()[1..], ["a.b", r"c\d", "e&"]);
+        assert_eq!(dot_notation, r"a\.b.c\\d.e\&");
+        assert_eq!(JsonPointer::from_dot_notation(&dot_notation), pointer);
+    }
 }
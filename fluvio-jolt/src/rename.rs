@@ -0,0 +1,163 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::dsl::KeyCase;
+use crate::ir::OperationIr;
+
+/// Specification of the `rename` operation.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub(crate) struct Rename {
+    /// Naming convention the keys are converted to.
+    convention: Convention,
+    /// Whether the keys of the nested objects, including the objects in arrays, are renamed.
+    #[serde(default = "recursive")]
+    recursive: bool,
+    /// Keys kept as they are, at any level.
+    #[serde(default)]
+    except: Vec<String>,
+}
+
+fn recursive() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+enum Convention {
+    #[serde(rename = "snake_case", alias = "snake")]
+    Snake,
+    #[serde(rename = "kebab-case", alias = "kebab")]
+    Kebab,
+    #[serde(rename = "camelCase", alias = "camel")]
+    Camel,
+    #[serde(rename = "PascalCase", alias = "pascal")]
+    Pascal,
+    #[serde(rename = "lowercase", alias = "lower")]
+    Lower,
+    #[serde(rename = "UPPERCASE", alias = "upper")]
+    Upper,
+}
+
+impl Convention {
+    fn case(&self) -> KeyCase {
+        match self {
+            Convention::Snake => KeyCase::Snake,
+            Convention::Kebab => KeyCase::Kebab,
+            Convention::Camel => KeyCase::Camel,
+            Convention::Pascal => KeyCase::Pascal,
+            Convention::Lower => KeyCase::Lower,
+            Convention::Upper => KeyCase::Upper,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Convention::Snake => "snake_case",
+            Convention::Kebab => "kebab-case",
+            Convention::Camel => "camelCase",
+            Convention::Pascal => "PascalCase",
+            Convention::Lower => "lowercase",
+            Convention::Upper => "UPPERCASE",
+        }
+    }
+}
+
+impl Rename {
+    pub(crate) fn to_ir(&self) -> OperationIr {
+        OperationIr::Rename {
+            convention: self.convention.name().to_string(),
+            recursive: self.recursive,
+            except: self.except.clone(),
+        }
+    }
+}
+
+pub(crate) fn rename(input: Value, spec: &Rename) -> Value {
+    rename_keys(input, spec, spec.convention.case())
+}
+
+fn rename_keys(input: Value, spec: &Rename, case: KeyCase) -> Value {
+    match input {
+        Value::Object(map) => {
+            let mut renamed = Map::with_capacity(map.len());
+            for (key, value) in map {
+                let value = if spec.recursive {
+                    rename_keys(value, spec, case)
+                } else {
+                    value
+                };
+                let key = if spec.except.contains(&key) {
+                    key
+                } else {
+                    case.convert(&key)
+                };
+                // the last of the keys renamed to the same key wins
+                renamed.insert(key, value);
+            }
+            Value::Object(renamed)
+        }
+        Value::Array(arr) if spec.recursive => Value::Array(
+            arr.into_iter()
+                .map(|value| rename_keys(value, spec, case))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_rename() {
+        //given
+        let spec: Rename = serde_json::from_value(json!({
+            "convention": "snake_case",
+            "except": ["HTTPHeaders"]
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "userId": 1,
+            "HTTPHeaders": {"ContentType": "json"},
+            "orderLines": [{"lineNo": 1}, 2]
+        });
+
+        //when
+        let output = rename(input, &spec);
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "user_id": 1,
+                "HTTPHeaders": {"content_type": "json"},
+                "order_lines": [{"line_no": 1}, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn test_rename_top_level() {
+        //given
+        let spec: Rename = serde_json::from_value(json!({
+            "convention": "camel",
+            "recursive": false
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = rename(json!({"first_name": {"last_name": "x"}}), &spec);
+
+        //then
+        assert_eq!(output, json!({"firstName": {"last_name": "x"}}));
+    }
+
+    #[test]
+    fn test_unknown_convention() {
+        let spec = serde_json::from_value::<Rename>(json!({"convention": "title"}));
+
+        assert!(spec.is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::dsl::{Lhs, Stars};
+use crate::ir::{self, KeyMatch, RetainNode};
+use crate::scratch::with_scratch;
+use crate::shift::match_stars;
+
+/// Specification of the `retain` operation. Every entry is a key pattern and, optionally,
+/// the specification for the children of the matched value. Entries without children
+/// retain the whole matched value.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Retain(Vec<(Pattern, Option<Retain>)>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    Literal(String),
+    Pipes(Vec<Stars>),
+}
+
+impl Retain {
+    // literals take precedence over wildcards, wildcards are tried in the spec order
+    fn find(&self, key: &str) -> Option<Option<&Retain>> {
+        self.0
+            .iter()
+            .find(|(pattern, _)| matches!(pattern, Pattern::Literal(lit) if lit == key))
+            .or_else(|| {
+                self.0.iter().find(|(pattern, _)| match pattern {
+                    Pattern::Literal(_) => false,
+                    Pattern::Pipes(pipes) => with_scratch(|scratch| {
+                        pipes
+                            .iter()
+                            .any(|stars| match_stars(scratch, stars, Cow::Borrowed(key)).is_some())
+                    }),
+                })
+            })
+            .map(|(_, children)| children.as_ref())
+    }
+}
+
+impl Retain {
+    pub(crate) fn to_ir(&self) -> Vec<RetainNode> {
+        self.0
+            .iter()
+            .map(|(pattern, children)| RetainNode {
+                key: match pattern {
+                    Pattern::Literal(value) => KeyMatch::Literal {
+                        value: value.clone(),
+                        marker: None,
+                    },
+                    Pattern::Pipes(pipes) => ir::wildcard(pipes),
+                },
+                children: children.as_ref().map(Retain::to_ir),
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn retain(input: Value, spec: &Retain) -> Value {
+    retain_children(input, spec).unwrap_or(Value::Null)
+}
+
+// Returns `None` if the value has no children to retain.
+fn retain_children(input: Value, spec: &Retain) -> Option<Value> {
+    match input {
+        Value::Object(map) => {
+            let retained: Map<String, Value> = map
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let value = retain_value(value, spec.find(&key)?)?;
+                    Some((key, value))
+                })
+                .collect();
+            Some(Value::Object(retained))
+        }
+        Value::Array(arr) => {
+            let retained: Vec<Value> = arr
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, value)| retain_value(value, spec.find(&idx.to_string())?))
+                .collect();
+            Some(Value::Array(retained))
+        }
+        _ => None,
+    }
+}
+
+fn retain_value(value: Value, children: Option<&Retain>) -> Option<Value> {
+    match children {
+        Some(children) => retain_children(value, children),
+        None => Some(value),
+    }
+}
+
+struct RetainVisitor;
+
+impl<'de> Visitor<'de> for RetainVisitor {
+    type Value = Retain;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let pattern = match Lhs::parse(&key) {
+                Ok(Lhs::Literal(lit)) => Pattern::Literal(lit),
+                Ok(Lhs::Pipes(pipes)) => Pattern::Pipes(pipes),
+                Ok(_) => {
+                    return Err(de::Error::custom(format!(
+                        "unsupported key in retain spec: {key}"
+                    )))
+                }
+                Err(e) => {
+                    return Err(de::Error::custom(format!(
+                        "failed to parse: {key}.error={e}"
+                    )))
+                }
+            };
+
+            let children = match map.next_value::<Value>()? {
+                Value::Object(obj) => {
+                    Some(Retain::deserialize(Value::Object(obj)).map_err(de::Error::custom)?)
+                }
+                _ => None,
+            };
+
+            entries.push((pattern, children));
+        }
+
+        Ok(Retain(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for Retain {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(RetainVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_retain_literals() {
+        //given
+        let spec: Retain = serde_json::from_value(json!({
+            "a" : "",
+            "d" : {
+                "e" : ""
+            }
+        }))
+        .expect("parsed spec");
+
+        let input: Value = serde_json::from_value(json!({
+            "a" : {
+                "b" : "b"
+            },
+            "c" : "c",
+            "d" : {
+                "e" : "e",
+                "f" : "f"
+            }
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = retain(input, &spec);
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "a" : {
+                    "b" : "b"
+                },
+                "d" : {
+                    "e" : "e"
+                }
+            })
+        )
+    }
+
+    #[test]
+    fn test_retain_wildcards() {
+        //given
+        let spec: Retain = serde_json::from_value(json!({
+            "items" : {
+                "*" : {
+                    "id|name" : ""
+                }
+            },
+            "meta_*" : ""
+        }))
+        .expect("parsed spec");
+
+        let input: Value = serde_json::from_value(json!({
+            "items" : [
+                { "id" : 1, "name" : "a", "volatile" : true },
+                { "id" : 2, "volatile" : false }
+            ],
+            "meta_source" : "s",
+            "other" : "o"
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = retain(input, &spec);
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "items" : [
+                    { "id" : 1, "name" : "a" },
+                    { "id" : 2 }
+                ],
+                "meta_source" : "s"
+            })
+        )
+    }
+
+    #[test]
+    fn test_retain_drops_scalars_with_children_spec() {
+        //given
+        let spec: Retain = serde_json::from_value(json!({
+            "a" : {
+                "b" : ""
+            }
+        }))
+        .expect("parsed spec");
+
+        let input: Value = serde_json::from_value(json!({
+            "a" : "a"
+        }))
+        .expect("parsed spec");
+
+        //when
+        let output = retain(input, &spec);
+
+        //then
+        assert_eq!(output, json!({}))
+    }
+
+    #[test]
+    fn test_unsupported_key() {
+        let result = serde_json::from_value::<Retain>(json!({
+            "@(1,a)" : ""
+        }));
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Deserialize;
+
+use crate::ir::OperationIr;
+use crate::Context;
+
+/// Specification of the `sample` operation. The documents it is applied to are counted by the
+/// context, under the id of the operation, which is kept by the clones of the specification.
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Sample {
+    #[serde(flatten)]
+    frequency: Frequency,
+    #[serde(skip, default = "next_id")]
+    id: u64,
+}
+
+fn next_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The fraction of documents passed through.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Frequency {
+    /// Fraction between 0 and 1, the passed documents are evenly spaced.
+    Rate(f64),
+    /// Every n-th document, starting with the first one.
+    EveryN(u64),
+    /// Every document with the probability between 0 and 1, drawn from the random source of
+    /// the context.
+    Probability(f64),
+}
+
+impl Sample {
+    /// Whether the next document is passed through.
+    pub(crate) fn keep(&self, context: &Context) -> bool {
+        let n = context.count_sample(self.id);
+        match self.frequency {
+            Frequency::Rate(rate) => {
+                // the n-th document is kept when it completes another whole document
+                let rate = rate.clamp(0.0, 1.0);
+                (n as f64 * rate).floor() > ((n as f64 - 1.0) * rate).floor()
+            }
+            Frequency::EveryN(every_n) => n.is_multiple_of(every_n.max(1)),
+            Frequency::Probability(probability) => context.next_fraction() < probability,
+        }
+    }
+
+    pub(crate) fn to_ir(&self) -> OperationIr {
+        let (rate, every_n, probability) = match self.frequency {
+            Frequency::Rate(rate) => (Some(rate), None, None),
+            Frequency::EveryN(every_n) => (None, Some(every_n), None),
+            Frequency::Probability(probability) => (None, None, Some(probability)),
+        };
+        OperationIr::Sample {
+            rate,
+            every_n,
+            probability,
+        }
+    }
+}
+
+impl PartialEq for Sample {
+    fn eq(&self, other: &Self) -> bool {
+        self.frequency == other.frequency
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use crate::{transform_stepwise, JoltEngine, TransformSpec};
+
+    use super::*;
+
+    #[test]
+    fn test_every_n() {
+        //given
+        let sample: Sample = serde_json::from_value(json!({"every_n": 3})).unwrap();
+        let context = Context::default();
+
+        //when
+        let kept: Vec<usize> = (0..10).filter(|_| sample.keep(&context)).collect();
+
+        //then
+        assert_eq!(kept, vec![0, 3, 6, 9]);
+    }
+
+    #[test]
+    fn test_rate() {
+        //given
+        let sample: Sample = serde_json::from_value(json!({"rate": 0.25})).unwrap();
+        let context = Context::default();
+
+        //when
+        let kept: Vec<usize> = (0..10).filter(|_| sample.keep(&context)).collect();
+
+        //then
+        assert_eq!(kept, vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn test_rate_bounds() {
+        //given
+        let none: Sample = serde_json::from_value(json!({"rate": 0.0})).unwrap();
+        let all: Sample = serde_json::from_value(json!({"rate": 1.0})).unwrap();
+        let context = Context::default();
+
+        //then
+        assert!((0..10).all(|_| !none.keep(&context)));
+        assert!((0..10).all(|_| all.keep(&context)));
+    }
+
+    #[test]
+    fn test_probability_seeded() {
+        //given
+        let sample: Sample = serde_json::from_value(json!({"probability": 0.5})).unwrap();
+        let run = |seed| {
+            let context = Context::default().seed(seed);
+            (0..100)
+                .filter(|_| sample.keep(&context))
+                .collect::<Vec<usize>>()
+        };
+
+        //when
+        let first = run(42);
+        let second = run(42);
+
+        //then
+        assert_eq!(first, second);
+        assert!(first.len() > 30 && first.len() < 70);
+        assert_ne!(first, run(43));
+    }
+
+    #[test]
+    fn test_counts_per_context() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "tests": [{"input": {"id": -1}, "expected": {"id": -1}}],
+            "operations": [{"operation": "sample", "spec": {"every_n": 2}}]
+        }))
+        .expect("parsed spec");
+        let engine = JoltEngine::new(spec.clone());
+
+        //when
+        let kept: Vec<i64> = (0..6)
+            .filter(|id| {
+                // the runs with their own contexts don't count the documents of the engine
+                assert_eq!(spec.run_embedded_tests(), Ok(1));
+                assert_eq!(engine.spec().run_embedded_tests(), Ok(1));
+                assert!(transform_stepwise(json!({"id": -1}), engine.spec()).is_ok());
+                engine
+                    .run_filtered(json!({ "id": id }))
+                    .expect("transformed")
+                    .is_some()
+            })
+            .collect();
+
+        //then
+        assert_eq!(kept, vec![0, 2, 4]);
+    }
+}
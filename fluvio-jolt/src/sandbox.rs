@@ -0,0 +1,291 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+
+use crate::shift::Recorded;
+use crate::stats::count_nodes;
+use crate::{Error, Result, TransformOptions, TransformSpec};
+
+use serde_json::Value;
+
+/// Limits of the specifications supplied by untrusted parties, e.g. the tenants of a service,
+/// so an adversarial specification can't exhaust the memory of the process. A limit that is
+/// exceeded fails with [`Error::SandboxLimitExceeded`]. No limit is set by default, the time
+/// is limited by the [`fuel`](crate::TransformOptions::fuel) of the options.
+///
+/// The limits are checked by [`Sandbox::check_spec`] once the specification is parsed, and
+/// during the transformations applied with the sandbox of the [`TransformOptions`]:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{
+///     transform_with_options, Error, Sandbox, SandboxLimit, TransformOptions, TransformSpec,
+/// };
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"items": {"*": "list[]"}}}
+/// ]))
+/// .unwrap();
+/// let sandbox = Sandbox::default().max_operations(10).max_fan_out(2);
+/// sandbox.check_spec(&spec).unwrap();
+///
+/// let options = TransformOptions::default().sandbox(sandbox);
+/// let error = transform_with_options(json!({"items": [1, 2, 3]}), &spec, &options).unwrap_err();
+///
+/// assert_eq!(
+///     error,
+///     Error::SandboxLimitExceeded {
+///         limit: SandboxLimit::FanOut,
+///         max: 2
+///     }
+/// );
+/// ```
+///
+/// [`TransformOptions`]: crate::TransformOptions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Sandbox {
+    max_operations: Option<usize>,
+    max_fan_out: Option<usize>,
+    max_output_nodes: Option<usize>,
+}
+
+impl Sandbox {
+    /// Operations of the chain, including the disabled ones.
+    pub fn max_operations(mut self, max: usize) -> Self {
+        self.max_operations = Some(max);
+        self
+    }
+
+    /// Input keys matched by a level of a `shift` spec in an input object or array, e.g. the
+    /// elements of an array matched by `*`.
+    pub fn max_fan_out(mut self, max: usize) -> Self {
+        self.max_fan_out = Some(max);
+        self
+    }
+
+    /// Values of the output of every operation, the objects, arrays and scalars at all the
+    /// levels. The values written by a `shift` operation are counted as they are written, so a
+    /// value written several times is counted every time.
+    pub fn max_output_nodes(mut self, max: usize) -> Self {
+        self.max_output_nodes = Some(max);
+        self
+    }
+
+    /// Checks the limits of the parsed specification.
+    pub fn check_spec(&self, spec: &TransformSpec) -> Result<()> {
+        check(SandboxLimit::Operations, spec.len(), self.max_operations)
+    }
+}
+
+/// The limit of the [`Sandbox`] that is exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SandboxLimit {
+    Operations,
+    FanOut,
+    OutputNodes,
+}
+
+impl fmt::Display for SandboxLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxLimit::Operations => write!(f, "operations"),
+            SandboxLimit::FanOut => write!(f, "fan-out"),
+            SandboxLimit::OutputNodes => write!(f, "output nodes"),
+        }
+    }
+}
+
+fn check(limit: SandboxLimit, value: usize, max: Option<usize>) -> Result<()> {
+    match max {
+        Some(max) if value > max => Err(Error::SandboxLimitExceeded {
+            limit,
+            max: max as u64,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The limits of a transformation along with the fuel spent and, if the options record them,
+/// the writes of the `shift` operations.
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    sandbox: Sandbox,
+    fuel: Option<u64>,
+    spent: Cell<u64>,
+    writes: Option<RefCell<Vec<Recorded>>>,
+}
+
+impl Budget {
+    pub(crate) fn new(options: &TransformOptions) -> Self {
+        Self {
+            sandbox: options.get_sandbox().unwrap_or_default(),
+            fuel: options.get_fuel(),
+            spent: Cell::new(0),
+            writes: options.get_record_writes().then(Default::default),
+        }
+    }
+
+    pub(crate) fn is_unlimited(&self) -> bool {
+        self.sandbox == Sandbox::default() && self.fuel.is_none()
+    }
+
+    pub(crate) fn records_writes(&self) -> bool {
+        self.writes.is_some()
+    }
+
+    pub(crate) fn record(&self, writes: impl IntoIterator<Item = Recorded>) {
+        if let Some(recorded) = &self.writes {
+            recorded.borrow_mut().extend(writes);
+        }
+    }
+
+    /// Takes the writes recorded since the last call.
+    pub(crate) fn take_writes(&self) -> Vec<Recorded> {
+        self.writes
+            .as_ref()
+            .map(|recorded| recorded.take())
+            .unwrap_or_default()
+    }
+
+    /// Whether the values written by the `shift` operations are counted.
+    pub(crate) fn counts_writes(&self) -> bool {
+        self.fuel.is_some() || self.sandbox.max_output_nodes.is_some()
+    }
+
+    pub(crate) fn check_spec(&self, spec: &TransformSpec) -> Result<()> {
+        self.sandbox.check_spec(spec)
+    }
+
+    pub(crate) fn spend(&self, steps: u64) -> Result<()> {
+        let Some(fuel) = self.fuel else {
+            return Ok(());
+        };
+        let spent = self.spent.get().saturating_add(steps);
+        self.spent.set(spent);
+        if spent > fuel {
+            return Err(Error::BudgetExhausted { fuel });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_fan_out(&self, matched: usize) -> Result<()> {
+        check(SandboxLimit::FanOut, matched, self.sandbox.max_fan_out)
+    }
+
+    pub(crate) fn check_output_nodes(&self, nodes: usize) -> Result<()> {
+        check(
+            SandboxLimit::OutputNodes,
+            nodes,
+            self.sandbox.max_output_nodes,
+        )
+    }
+
+    /// Checks the output of an operation.
+    pub(crate) fn check_output(&self, output: &Value) -> Result<()> {
+        match self.sandbox.max_output_nodes {
+            Some(_) => self.check_output_nodes(count_nodes(output)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+    use crate::{transform_with_options, TransformOptions};
+
+    fn run(sandbox: Sandbox, input: Value, spec: Value) -> Result<Value> {
+        let spec: TransformSpec = serde_json::from_value(spec).expect("parsed spec");
+        transform_with_options(input, &spec, &TransformOptions::default().sandbox(sandbox))
+    }
+
+    fn exceeded(limit: SandboxLimit, max: u64) -> Result<Value> {
+        Err(Error::SandboxLimitExceeded { limit, max })
+    }
+
+    #[test]
+    fn test_max_operations() {
+        let spec = json!([
+            {"operation": "remove", "spec": {"a": ""}},
+            {"operation": "remove", "spec": {"b": ""}}
+        ]);
+
+        assert_eq!(
+            run(
+                Sandbox::default().max_operations(1),
+                json!({}),
+                spec.clone()
+            ),
+            exceeded(SandboxLimit::Operations, 1)
+        );
+        assert_eq!(
+            run(
+                Sandbox::default().max_operations(2),
+                json!({"a": 1, "c": 2}),
+                spec
+            ),
+            Ok(json!({"c": 2}))
+        );
+    }
+
+    #[test]
+    fn test_max_fan_out() {
+        let spec = json!([{"operation": "shift", "spec": {"*": {"*": "&(1)[]"}}}]);
+        let sandbox = Sandbox::default().max_fan_out(2);
+
+        assert_eq!(
+            run(sandbox, json!({"a": [1, 2], "b": [3, 4]}), spec.clone()),
+            Ok(json!({"a": [1, 2], "b": [3, 4]}))
+        );
+        assert_eq!(
+            run(sandbox, json!({"a": [1, 2, 3]}), spec),
+            exceeded(SandboxLimit::FanOut, 2)
+        );
+    }
+
+    #[test]
+    fn test_max_output_nodes() {
+        // the input is written 3 times
+        let spec = json!([{"operation": "shift", "spec": {"@": ["a", "b", "c"]}}]);
+        let input = json!({"x": [1, 2]});
+
+        assert_eq!(
+            run(
+                Sandbox::default().max_output_nodes(8),
+                input.clone(),
+                spec.clone()
+            ),
+            exceeded(SandboxLimit::OutputNodes, 8)
+        );
+        assert!(run(Sandbox::default().max_output_nodes(13), input, spec).is_ok());
+
+        let default = json!([{"operation": "default", "spec": {"a": {"b": {"c": 1}}}}]);
+        assert_eq!(
+            run(Sandbox::default().max_output_nodes(3), json!({}), default),
+            exceeded(SandboxLimit::OutputNodes, 3)
+        );
+    }
+
+    #[test]
+    fn test_fuel() {
+        // a step for each input key tried and for each written value: 3 + 2 by the first
+        // operation, 2 + 2 by the second one
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a": "x", "b": "y"}},
+            {"operation": "shift", "append_order": "spec", "spec": {"*": "&"}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2, "c": 3});
+        let run = |fuel| {
+            transform_with_options(
+                input.clone(),
+                &spec,
+                &TransformOptions::default().fuel(fuel),
+            )
+        };
+
+        assert_eq!(run(9), Ok(json!({"x": 1, "y": 2})));
+        assert_eq!(run(8), Err(Error::BudgetExhausted { fuel: 8 }));
+        assert_eq!(run(2), Err(Error::BudgetExhausted { fuel: 2 }));
+    }
+}
@@ -0,0 +1,140 @@
+//! Scratch space of the `shift` operation: the stack of the matched keys, the matches of the keys
+//! and the keys computed by the right hand side expressions.
+//!
+//! With the `arena` feature the scratch space is a bump allocator owned by the thread. It is
+//! reset before every `shift` of a record, so the transformations of a batch reuse the memory of
+//! the previous records instead of allocating it again. Without the feature the scratch space is
+//! allocated on the heap like the other values.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+#[cfg(feature = "arena")]
+mod imp {
+    use std::cell::RefCell;
+
+    use bumpalo::Bump;
+
+    pub(crate) type ScratchVec<'s, T> = bumpalo::collections::Vec<'s, T>;
+    pub(crate) type ScratchString<'s> = bumpalo::collections::String<'s>;
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Scratch {
+        bump: Bump,
+    }
+
+    impl Scratch {
+        pub(crate) fn vec<T>(&self) -> ScratchVec<'_, T> {
+            ScratchVec::new_in(&self.bump)
+        }
+
+        pub(crate) fn vec_with_capacity<T>(&self, capacity: usize) -> ScratchVec<'_, T> {
+            ScratchVec::with_capacity_in(capacity, &self.bump)
+        }
+
+        pub(crate) fn string(&self) -> ScratchString<'_> {
+            ScratchString::new_in(&self.bump)
+        }
+
+        pub(crate) fn finish(string: ScratchString<'_>) -> super::Cow<'_, str> {
+            super::Cow::Borrowed(string.into_bump_str())
+        }
+    }
+
+    pub(crate) fn with_scratch<R>(f: impl FnOnce(&Scratch) -> R) -> R {
+        thread_local! {
+            static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::default());
+        }
+
+        SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+            Ok(mut scratch) => {
+                scratch.bump.reset();
+                f(&scratch)
+            }
+            // the scratch space of the thread is in use by an outer transformation
+            Err(_) => f(&Scratch::default()),
+        })
+    }
+}
+
+#[cfg(not(feature = "arena"))]
+mod imp {
+    pub(crate) type ScratchVec<'s, T> = Vec<T>;
+    pub(crate) type ScratchString<'s> = String;
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Scratch;
+
+    impl Scratch {
+        pub(crate) fn vec<T>(&self) -> ScratchVec<'_, T> {
+            Vec::new()
+        }
+
+        pub(crate) fn vec_with_capacity<T>(&self, capacity: usize) -> ScratchVec<'_, T> {
+            Vec::with_capacity(capacity)
+        }
+
+        pub(crate) fn string(&self) -> ScratchString<'_> {
+            String::new()
+        }
+
+        pub(crate) fn finish<'s>(string: ScratchString<'s>) -> super::Cow<'s, str> {
+            super::Cow::Owned(string)
+        }
+    }
+
+    pub(crate) fn with_scratch<R>(f: impl FnOnce(&Scratch) -> R) -> R {
+        f(&Scratch)
+    }
+}
+
+pub(crate) use imp::{with_scratch, Scratch, ScratchVec};
+
+impl Scratch {
+    /// A vector of a single value.
+    pub(crate) fn vec_of<T>(&self, value: T) -> ScratchVec<'_, T> {
+        let mut vec = self.vec_with_capacity(1);
+        vec.push(value);
+        vec
+    }
+
+    /// A copy of the string, borrowed from the scratch space with the `arena` feature.
+    pub(crate) fn str<'s>(&'s self, s: &str) -> Cow<'s, str> {
+        let mut string = self.string();
+        string.push_str(s);
+        Self::finish(string)
+    }
+
+    /// The string representation of the value, borrowed from the scratch space with the `arena`
+    /// feature.
+    pub(crate) fn display<'s>(&'s self, value: impl Display) -> Cow<'s, str> {
+        use std::fmt::Write;
+
+        let mut string = self.string();
+        // writing to a string doesn't fail
+        let _ = write!(string, "{value}");
+        Self::finish(string)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_nested_scratch() {
+        //given
+        let outer = with_scratch(|scratch| {
+            let key = scratch.display(42);
+
+            //when
+            let inner = with_scratch(|scratch| scratch.str("inner").into_owned());
+
+            //then
+            assert_eq!(key, "42");
+            inner
+        });
+        assert_eq!(outer, "inner");
+    }
+}
@@ -1,100 +1,918 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::slice;
+use std::sync::OnceLock;
 
 use serde_json::Value;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::dsl::{Object, REntry, InfallibleLhs, Rhs, RhsEntry, IndexOp, RhsPart};
+use crate::dsl::{Object, REntry, InfallibleLhs, Lhs, Rhs, RhsEntry, IndexOp, RhsPart, Stars, Marker};
+use crate::ir::{self, MatchNode};
 use crate::transform::Transform;
-use crate::{Error, Result};
+use crate::pointer::{to_rfc6901, JsonPointer};
+use crate::scratch::{with_scratch, Scratch, ScratchVec};
+use crate::observer::TransformObserver;
+use crate::sandbox::Budget;
+use crate::stats::count_nodes;
+use crate::spec_path::ROOT_KEY;
+use crate::{Context, Error, Result};
 
-const ROOT_KEY: &str = "root";
+// A level of the input: the matches of the key, the value and the number of keys
+// matched before it at the same level
+type PathEntry<'s> = (ScratchVec<'s, Cow<'s, str>>, &'s Value, usize);
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Shift(Object);
 
+impl Shift {
+    pub(crate) fn to_ir(&self) -> Vec<MatchNode> {
+        ir::match_nodes(&self.0)
+    }
+
+    /// The input and output paths of the values moved from literal keys to paths of plain keys,
+    /// in the dot-notation. Wildcards, captures, lookups, constants and array indexes are skipped.
+    pub(crate) fn rename_map(&self) -> Vec<(String, String)> {
+        fn visit(obj: &Object, input: &mut Vec<String>, out: &mut Vec<(String, String)>) {
+            for (key, entry) in obj.literal.iter() {
+                input.push(key.clone());
+                match entry {
+                    REntry::Obj(obj) => visit(obj, input, out),
+                    REntry::Rhs(rhss) => {
+                        for rhs in rhss {
+                            let output: Option<Vec<String>> = rhs
+                                .0
+                                .iter()
+                                .map(|part| match part {
+                                    RhsPart::Key(RhsEntry::Key(key)) => Some(key.clone()),
+                                    _ => None,
+                                })
+                                .collect();
+                            if let Some(output) = output {
+                                out.push((
+                                    JsonPointer::new(input.clone()).to_dot_notation(),
+                                    JsonPointer::new(output).to_dot_notation(),
+                                ));
+                            }
+                        }
+                    }
+                    REntry::Thrash => {}
+                }
+                input.pop();
+            }
+        }
+
+        let mut out = Vec::new();
+        visit(&self.0, &mut Vec::new(), &mut out);
+        out
+    }
+}
+
+/// Options of the `shift` operation, specified next to the `spec` field of the operation.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShiftOptions {
+    /// `$` emits array indexes as numbers and matched booleans, numbers and nulls with their
+    /// original type instead of strings.
+    #[serde(default)]
+    pub(crate) typed_captures: bool,
+    /// `#` writes literals that are JSON numbers, booleans or `null` with their type instead of
+    /// strings.
+    #[serde(default)]
+    pub(crate) typed_literals: bool,
+    /// Order in which the values matched by sibling keys are written to the output.
+    #[serde(default)]
+    pub(crate) append_order: AppendOrder,
+    /// `[]` skips values that are already present in the output array.
+    #[serde(default)]
+    pub(crate) dedup_appends: bool,
+    /// Handling of the indexes skipped by writes to literal or computed array indexes.
+    #[serde(default)]
+    pub(crate) sparse_arrays: SparseArrays,
+    /// `@` lookups of missing keys or array indexes fail instead of skipping the write.
+    #[serde(default)]
+    pub(crate) strict_lookups: bool,
+    /// Handling of writes to output paths whose values have an incompatible type.
+    #[serde(default)]
+    pub(crate) conflicts: Conflicts,
+    /// Handling of `null` values of the input by the keys and `@` lookups of the spec.
+    #[serde(default)]
+    pub(crate) nulls: Nulls,
+    /// Order in which the wildcard and `|` keys of the spec are tried against an input key.
+    #[serde(default)]
+    pub(crate) match_order: MatchOrder,
+}
+
+/// Handling of `null` values of the input. A missing key is never matched and its `@` lookups
+/// are missing lookups, the variants differ in how a key present with a `null` value is handled.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Nulls {
+    /// The key is matched and the nested keys of the spec match the `null` value as the `"null"`
+    /// key, like the other scalars. `@` lookups of the key return `null`, lookups of keys inside
+    /// the `null` value fail with [`Error::UnexpectedRhsEntry`].
+    #[default]
+    Key,
+    /// The key is matched and the `null` value has no keys: the nested keys of the spec don't
+    /// match it. `@` lookups of the key return `null`, lookups of keys inside the `null` value
+    /// are missing lookups.
+    Value,
+    /// The key is handled as a missing key: it is not matched and its `@` lookups are missing
+    /// lookups.
+    Absent,
+}
+
+/// Handling of writes that conflict with the values written earlier: an output path goes
+/// through a value that is not an object or an array, or an object and another value are
+/// written to the same output path.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Conflicts {
+    /// Values written to the same path are collected into an array, values that are not objects
+    /// are replaced by objects when an output path goes through them.
+    #[default]
+    Merge,
+    /// The later write replaces the earlier value.
+    Overwrite,
+    /// Conflicting writes fail with [`Error::OutputConflict`].
+    Error,
+}
+
+/// The largest number of indexes a write to an array index may skip, writes skipping more
+/// indexes fail with [`Error::SparseArrayWrite`] whatever the `sparse_arrays` option, so an index
+/// taken from the input can't exhaust the memory.
+pub const MAX_ARRAY_PADDING: usize = 65_536;
+
+/// Handling of the array indexes skipped by writes like `foo[5]`.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SparseArrays {
+    /// Skipped indexes are padded with `null`.
+    #[default]
+    Pad,
+    /// Skipped indexes are padded with `null` and the padding left unwritten is removed once the
+    /// shift operation completes. The `null` values written explicitly are kept.
+    Compact,
+    /// The shift fails with [`Error::SparseArrayWrite`] when skipped indexes are left unwritten
+    /// once the operation completes, so indexes can be written in any order.
+    Error,
+}
+
+/// Order in which the values matched by sibling keys are written to the output. It is visible when
+/// several values are appended to the same array with `[]`.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppendOrder {
+    /// Values are written in the order of the keys of the input.
+    #[default]
+    Input,
+    /// Values are written in the order of the spec. Literal keys go first, then `&` keys and
+    /// then wildcards, each group in the order of the spec.
+    Spec,
+}
+
+/// Order in which the wildcard and `|` keys of the spec are tried against an input key, the first
+/// matching key wins. Literal keys and `&` keys are always tried first. It is visible when an input
+/// key is matched by several keys of the spec writing to different outputs.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOrder {
+    /// Keys are tried in the order of the spec, and the alternatives of a `|` key in the order
+    /// they are written.
+    #[default]
+    Spec,
+    /// Keys are tried in the alphabetical order of their text, and the alternatives of a `|` key
+    /// too.
+    Alphabetical,
+    /// Keys are tried in the order of Java Jolt: keys with two `*` first, then keys with one `*`,
+    /// keys with more `*`, `|` keys and the `*` key last. Keys of the same kind are tried from the
+    /// longest to the shortest, then alphabetically. The alternatives of a `|` key are tried in the
+    /// order they are written.
+    Java,
+}
+
+impl MatchOrder {
+    // Compares the wildcard keys of two entries, the entries with equal keys are tried in the
+    // order of the spec
+    fn cmp_keys(self, a: &[Stars], b: &[Stars]) -> Ordering {
+        match self {
+            MatchOrder::Spec => Ordering::Equal,
+            MatchOrder::Alphabetical => key_text(a).cmp(key_text(b)),
+            MatchOrder::Java => java_kind(a)
+                .cmp(&java_kind(b))
+                .then_with(|| key_text(b).count().cmp(&key_text(a).count()))
+                .then_with(|| key_text(a).cmp(key_text(b))),
+        }
+    }
+
+    // Matches the key with the first matching alternative of the entry
+    fn match_alternatives<'s>(
+        self,
+        scratch: &'s Scratch,
+        pipes: &'s [Stars],
+        k: &Cow<'s, str>,
+    ) -> Option<ScratchVec<'s, Cow<'s, str>>> {
+        let mut matches = pipes
+            .iter()
+            .filter_map(|stars| match_stars(scratch, stars, Cow::clone(k)).map(|m| (stars, m)));
+        match self {
+            MatchOrder::Alphabetical => matches
+                .min_by(|(a, _), (b, _)| {
+                    key_text(slice::from_ref(*a)).cmp(key_text(slice::from_ref(*b)))
+                })
+                .map(|(_, m)| m),
+            MatchOrder::Spec | MatchOrder::Java => matches.next().map(|(_, m)| m),
+        }
+    }
+}
+
+// The text of a wildcard key, without the escapes
+fn key_text(pipes: &[Stars]) -> impl Iterator<Item = char> + '_ {
+    pipes.iter().enumerate().flat_map(|(idx, stars)| {
+        let pieces = stars
+            .pieces()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(idx, piece)| (idx > 0).then_some('*').into_iter().chain(piece.chars()));
+        (idx > 0).then_some('|').into_iter().chain(pieces)
+    })
+}
+
+// The kind of a wildcard key, ranked in the order of the computed keys of Java Jolt
+fn java_kind(pipes: &[Stars]) -> u8 {
+    match pipes {
+        [Stars::Prefix(prefix)] if prefix.is_empty() => 4,
+        [stars] => match stars.pieces().len().saturating_sub(1) {
+            2 => 0,
+            0 | 1 => 1,
+            _ => 2,
+        },
+        _ => 3,
+    }
+}
+
 impl Transform for Shift {
-    fn apply(&self, val: &Value) -> Result<Value> {
-        let mut path = vec![(vec![Cow::Borrowed(ROOT_KEY)], val)];
+    type Options = ShiftOptions;
+
+    fn apply(&self, val: &Value, options: &ShiftOptions, _context: &Context) -> Result<Value> {
+        let mut out = Output::default();
+        match self.run(val, options, &mut out) {
+            Ok(()) => Ok(out.value),
+            Err(e) => Err(out.located(e)),
+        }
+    }
+}
+
+impl Shift {
+    /// Applies the shift, reporting the matched keys to the observer, if any, and enforcing
+    /// the limits of the budget. The errors of the spec keys are returned as [`Error::SpecKey`]
+    /// and, with `partial`, as [`Error::PartialOutput`] along with the output written so far.
+    /// The writes are recorded to the budget if it records them.
+    pub(crate) fn apply_limited(
+        &self,
+        val: &Value,
+        options: &ShiftOptions,
+        observer: Option<&dyn TransformObserver>,
+        budget: &Budget,
+        partial: bool,
+    ) -> Result<Value> {
+        let mut out = Output {
+            observer,
+            budget: Some(budget),
+            partial,
+            writes: budget.records_writes().then(Vec::new),
+            ..Default::default()
+        };
+        match self.run(val, options, &mut out) {
+            Ok(()) => {
+                if budget.records_writes() {
+                    budget.record(
+                        out.writes
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(Recorded::from),
+                    );
+                }
+                Ok(out.value)
+            }
+            Err(e) if partial => Err(Error::PartialOutput {
+                pointer: out.failed_write.as_deref().map(to_rfc6901),
+                source: Box::new(out.located(e)),
+                output: Box::new(out.value),
+            }),
+            Err(e) => Err(out.located(e)),
+        }
+    }
+
+    /// Applies the shift to an owned input: the objects and arrays of the input are moved to the
+    /// output instead of being copied, except the values written several times. The errors of
+    /// the spec keys are returned as [`Error::SpecKey`].
+    pub(crate) fn apply_owned(&self, val: Value, options: &ShiftOptions) -> Result<Value> {
+        // the writes checking the written values see the copies
+        let movable = options.conflicts != Conflicts::Error
+            && !options.dedup_appends
+            && options.sparse_arrays != SparseArrays::Compact;
+        if !movable {
+            return self.apply(&val, options, &Context::default());
+        }
+        let mut out = Output {
+            moved: Some(Moved {
+                input: &val,
+                values: HashMap::new(),
+            }),
+            ..Default::default()
+        };
+        if let Err(e) = self.run(&val, options, &mut out) {
+            return Err(out.located(e));
+        }
+        let moved = out.moved.map(|moved| moved.values).unwrap_or_default();
+        let mut value = out.value;
+        move_values(&mut value, val, moved);
+        Ok(value)
+    }
+
+    fn run<'input>(
+        &'input self,
+        val: &'input Value,
+        options: &ShiftOptions,
+        out: &mut Output<'input>,
+    ) -> Result<()> {
+        // conflicts are reported along with the output paths of the earlier writes
+        if options.conflicts == Conflicts::Error && out.writes.is_none() {
+            out.writes = Some(Vec::new());
+        }
+
+        with_scratch(|scratch| {
+            let mut path = scratch.vec();
+            path.push((scratch.vec_of(Cow::Borrowed(ROOT_KEY)), val, 0));
+
+            apply(&self.0, &mut path, out, options, scratch)?;
+
+            path.pop().ok_or(Error::ShiftEmptyPath)?;
+            // path should always be empty at this point
+            // if not, the implementation is broken
+            if !path.is_empty() {
+                return Err(Error::ShiftPathNotEmpty);
+            }
+            Ok(())
+        })?;
+
+        match options.sparse_arrays {
+            SparseArrays::Pad => (),
+            SparseArrays::Compact => out.compact(),
+            SparseArrays::Error => {
+                if let Some(&(idx, len)) = out
+                    .padded
+                    .iter()
+                    .find(|(slot, _)| value_at(&out.value, slot).is_some_and(Value::is_null))
+                    .map(|(_, write)| write)
+                {
+                    return Err(Error::SparseArrayWrite { idx, len });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A value written by the shift: the output pointer it was written to, the input pointer it
+/// was read from and the output path expression. Values not read from the input (`#` literals)
+/// have no input pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Write<'input> {
+    pub(crate) output: Vec<String>,
+    pub(crate) input: Option<Vec<String>>,
+    pub(crate) rhs: &'input Rhs,
+}
+
+/// A write recorded to the budget of the chain, without the output path expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Recorded {
+    pub(crate) output: Vec<String>,
+    pub(crate) input: Option<Vec<String>>,
+}
+
+impl From<Write<'_>> for Recorded {
+    fn from(write: Write<'_>) -> Self {
+        Self {
+            output: write.output,
+            input: write.input,
+        }
+    }
+}
+
+// The output of the shift along with the writes, if they are recorded, the observer of the
+// matches, the budget of the sandbox with the nodes written so far, for a partial output, the
+// output pointer of the write that failed and, for an owned input, the values moved once the
+// shift is done. With the `sparse_arrays` option other than `pad`, the output pointers of the
+// array elements padded with `null` and not written since, with the index and the length of the
+// array of the write that padded them
+#[derive(Default)]
+pub(crate) struct Output<'input> {
+    value: Value,
+    writes: Option<Vec<Write<'input>>>,
+    moved: Option<Moved<'input>>,
+    observer: Option<&'input dyn TransformObserver>,
+    budget: Option<&'input Budget>,
+    written_nodes: usize,
+    partial: bool,
+    failed_write: Option<Vec<String>>,
+    padded: BTreeMap<Vec<String>, (usize, usize)>,
+    failed_keys: Vec<String>,
+}
+
+impl<'input> Output<'input> {
+    fn matched(&self, key: &str) {
+        if let Some(observer) = self.observer {
+            observer.on_match(key);
+        }
+    }
+
+    // An input key is tried against the keys of the spec
+    fn attempted(&self) -> Result<()> {
+        match self.budget {
+            Some(budget) => budget.spend(1),
+            None => Ok(()),
+        }
+    }
+
+    fn check_fan_out(&self, matched: usize) -> Result<()> {
+        match self.budget {
+            Some(budget) => budget.check_fan_out(matched),
+            None => Ok(()),
+        }
+    }
+
+    fn written(&mut self, value: &Value) -> Result<()> {
+        match self.budget {
+            Some(budget) if budget.counts_writes() => {
+                let nodes = count_nodes(value);
+                self.written_nodes += nodes;
+                budget.spend(nodes as u64)?;
+                budget.check_output_nodes(self.written_nodes)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn dropped(&self, key: &str) {
+        if let Some(observer) = self.observer {
+            observer.on_drop(key);
+        }
+    }
+
+    fn is_recorded(&self) -> bool {
+        self.writes.is_some()
+    }
+
+    // The entry of the spec key failed with the error, the keys are recorded from the innermost
+    // one as the error is returned
+    fn failed_key(&mut self, key: Option<String>, error: Error) -> Error {
+        self.failed_keys.extend(key);
+        error
+    }
+
+    // The error along with the pointer of the spec key that failed, if any. The limits of the
+    // sandbox are exceeded by the whole shift rather than by a key.
+    fn located(&mut self, error: Error) -> Error {
+        if self.failed_keys.is_empty()
+            || matches!(
+                error,
+                Error::SandboxLimitExceeded { .. } | Error::BudgetExhausted { .. }
+            )
+        {
+            return error;
+        }
+        self.failed_keys.reverse();
+        Error::SpecKey {
+            path: to_rfc6901(&std::mem::take(&mut self.failed_keys)),
+            source: Box::new(error),
+        }
+    }
+
+    // The write to the output pointer of the steps failed with the error
+    fn failed(&mut self, steps: &[Step<'_>], error: Error) -> Error {
+        if self.partial {
+            self.failed_write = Some(steps_pointer(steps));
+        }
+        error
+    }
+
+    fn record(&mut self, output: Vec<String>, input: Option<Vec<String>>, rhs: &'input Rhs) {
+        if let Some(writes) = self.writes.as_mut() {
+            writes.push(Write { output, input, rhs });
+        }
+    }
+
+    // The value at the pointer is moved into the first element of a new array
+    fn wrap(&mut self, ptr: &[String]) {
+        if let Some(writes) = self.writes.as_mut() {
+            for write in writes.iter_mut() {
+                if write.output.starts_with(ptr) {
+                    write.output.insert(ptr.len(), "0".to_string());
+                }
+            }
+        }
+        if self.padded.keys().any(|slot| slot.starts_with(ptr)) {
+            self.padded = std::mem::take(&mut self.padded)
+                .into_iter()
+                .map(|(mut slot, write)| {
+                    if slot.starts_with(ptr) {
+                        slot.insert(ptr.len(), "0".to_string());
+                    }
+                    (slot, write)
+                })
+                .collect();
+        }
+        if let Some(moved) = self.moved.as_mut() {
+            moved.values = std::mem::take(&mut moved.values)
+                .into_iter()
+                .map(|(mut output, input)| {
+                    if output.starts_with(ptr) {
+                        output.insert(ptr.len(), "0".to_string());
+                    }
+                    (output, input)
+                })
+                .collect();
+        }
+    }
+
+    // Remove the padded elements left unwritten, the ones of the deepest arrays first so the
+    // pointers of the other ones stay valid, and remap the recorded writes
+    fn compact(&mut self) {
+        let mut slots: Vec<_> = std::mem::take(&mut self.padded)
+            .into_keys()
+            .filter_map(|mut slot| {
+                let idx = slot.pop()?.parse::<usize>().ok()?;
+                Some((slot, idx))
+            })
+            .collect();
+        slots.sort_by(|(a, a_idx), (b, b_idx)| {
+            b.len()
+                .cmp(&a.len())
+                .then_with(|| a.cmp(b))
+                .then(b_idx.cmp(a_idx))
+        });
+        let mut removed: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+        for (arr_ptr, idx) in slots {
+            if let Some(Value::Array(arr)) = value_at_mut(&mut self.value, &arr_ptr) {
+                if arr.get(idx).is_some_and(Value::is_null) {
+                    arr.remove(idx);
+                    removed.entry(arr_ptr).or_default().push(idx);
+                }
+            }
+        }
+        if let Some(writes) = self.writes.as_mut() {
+            compact_writes(&removed, writes);
+        }
+    }
+
+    // The value at the pointer is replaced
+    fn replace(&mut self, ptr: &[String]) {
+        if let Some(writes) = self.writes.as_mut() {
+            writes.retain(|write| !write.output.starts_with(ptr));
+        }
+        self.padded.retain(|slot, _| !slot.starts_with(ptr));
+        if let Some(moved) = self.moved.as_mut() {
+            moved.values.retain(|output, _| !output.starts_with(ptr));
+        }
+    }
+
+    // The input pointer of a value matched in the input if it can be moved to the output once
+    // the shift is done. Scalars and empty values are cheap to copy.
+    fn movable(&self, v: &Value, path: &[PathEntry<'_>]) -> Option<Vec<String>> {
+        let moved = self.moved.as_ref()?;
+        let non_empty = match v {
+            Value::Object(obj) => !obj.is_empty(),
+            Value::Array(arr) => !arr.is_empty(),
+            _ => false,
+        };
+        if !non_empty {
+            return None;
+        }
+        let ptr = input_pointer(path);
+        value_at(moved.input, &ptr)
+            .is_some_and(|found| std::ptr::eq(found, v))
+            .then_some(ptr)
+    }
+}
+
+// The values of the input written to the output as `null` placeholders, by output pointer, with
+// their input pointers. A placeholder reached by a later write is replaced by a copy of its
+// value first, so the writes behave like the values were written.
+pub(crate) struct Moved<'input> {
+    input: &'input Value,
+    values: HashMap<Vec<String>, Vec<String>>,
+}
+
+impl Moved<'_> {
+    fn copy(&mut self, ptr: &[String], slot: &mut Value) {
+        if let Some(input) = self.values.remove(ptr) {
+            *slot = value_at(self.input, &input).cloned().unwrap_or_default();
+        }
+    }
 
-        let mut out = Value::Null;
-        apply(&self.0, &mut path, &mut out)?;
+    // Copies the placeholders among the elements of the array at the pointer
+    fn copy_elements(&mut self, ptr: &[String], arr: &mut [Value]) {
+        let elements: Vec<_> = self
+            .values
+            .keys()
+            .filter(|output| output.len() == ptr.len() + 1 && output.starts_with(ptr))
+            .cloned()
+            .collect();
+        for output in elements {
+            if let Some(slot) = output[ptr.len()]
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| arr.get_mut(idx))
+            {
+                self.copy(&output, slot);
+            }
+        }
+    }
+}
 
-        path.pop().ok_or(Error::ShiftEmptyPath)?;
-        // path should always be empty at this point
-        // if not, the implementation is broken
-        if !path.is_empty() {
-            return Err(Error::ShiftPathNotEmpty);
+// Moves the values of the input to their placeholders in the output. A value written several
+// times, or inside another written value, is copied, the values inside are copied before the
+// values they are in are moved.
+fn move_values(output: &mut Value, mut input: Value, moved: HashMap<Vec<String>, Vec<String>>) {
+    let mut counts: HashMap<&[String], usize> = HashMap::new();
+    for ptr in moved.values() {
+        *counts.entry(ptr.as_slice()).or_default() += 1;
+    }
+    let written: HashSet<&[String]> = counts.keys().copied().collect();
+    let mut values: Vec<_> = moved.iter().collect();
+    values.sort_by_key(|(_, input)| std::cmp::Reverse(input.len()));
+
+    let mut copies: Vec<(Vec<String>, Value)> = Vec::with_capacity(values.len());
+    for (output, ptr) in values {
+        let nested = (0..ptr.len()).any(|len| written.contains(&ptr[..len]));
+        let count = counts.entry(ptr.as_slice()).or_default();
+        *count -= 1;
+        let value = if nested || *count > 0 {
+            value_at(&input, ptr).cloned()
+        } else {
+            value_at_mut(&mut input, ptr).map(std::mem::take)
+        };
+        copies.push((output.clone(), value.unwrap_or_default()));
+    }
+    for (ptr, value) in copies {
+        if let Some(slot) = value_at_mut(output, &ptr) {
+            *slot = value;
         }
+    }
+}
+
+fn value_at<'v>(mut value: &'v Value, ptr: &[String]) -> Option<&'v Value> {
+    for segment in ptr {
+        value = match value {
+            Value::Object(obj) => obj.get(segment.as_str())?,
+            Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn value_at_mut<'v>(mut value: &'v mut Value, ptr: &[String]) -> Option<&'v mut Value> {
+    for segment in ptr {
+        value = match value {
+            Value::Object(obj) => obj.get_mut(segment.as_str())?,
+            Value::Array(arr) => arr.get_mut(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
 
-        Ok(out)
+// The error for a write of the rhs that conflicts with the value at the pointer.
+// The earlier write is the latest one that wrote the value, a value inside of it or a value it
+// is inside of.
+fn conflict(writes: Option<&[Write<'_>]>, ptr: &[String], rhs: &Rhs) -> Error {
+    let first = writes
+        .unwrap_or_default()
+        .iter()
+        .rev()
+        .find(|write| write.output.starts_with(ptr) || ptr.starts_with(&write.output))
+        .map(|write| write.rhs.to_string())
+        .unwrap_or_default();
+    Error::OutputConflict {
+        path: to_rfc6901(ptr),
+        first,
+        second: rhs.to_string(),
     }
 }
 
+// Remap the output pointers of the writes to the indexes of the compacted arrays, the removed
+// indexes are by the pointer of their array before the compaction
+fn compact_writes(removed: &HashMap<Vec<String>, Vec<usize>>, writes: &mut Vec<Write<'_>>) {
+    writes.retain_mut(|write| {
+        let original = write.output.clone();
+        for (depth, segment) in write.output.iter_mut().enumerate() {
+            let Some(indexes) = removed.get(&original[..depth]) else {
+                continue;
+            };
+            let Ok(idx) = segment.parse::<usize>() else {
+                continue;
+            };
+            if indexes.contains(&idx) {
+                return false;
+            }
+            let shift = indexes.iter().filter(|removed| **removed < idx).count();
+            *segment = (idx - shift).to_string();
+        }
+        true
+    });
+}
+
+// Pointer of the input value at the tip of the path. Entries that repeat the value of the previous
+// entry are skipped, they are scalars matched by their value or copies of the tip.
+fn input_pointer(path: &[PathEntry<'_>]) -> Vec<String> {
+    path.windows(2)
+        .filter(|w| !std::ptr::eq(w[0].1, w[1].1))
+        .map(|w| w[1].0[0].to_string())
+        .collect()
+}
+
 // Apply an object from spec to the input
 // input is passed using the path and the current input should be
 // at the tip of the path
-fn apply<'ctx, 'input: 'ctx>(
+fn apply<'s, 'input: 's>(
     obj: &'input Object,
-    path: &'ctx mut Vec<(Vec<Cow<'input, str>>, &'input Value)>,
-    out: &'ctx mut Value,
+    path: &mut ScratchVec<'s, PathEntry<'s>>,
+    out: &mut Output<'input>,
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
 ) -> Result<()> {
     let tip = path.last().ok_or(Error::ShiftEmptyPath)?.clone();
 
-    for (lhs, rhs) in obj.infallible.iter() {
-        let v = match lhs {
-            InfallibleLhs::DollarSign(idx0, idx1) => {
-                let s = get_match((*idx0, *idx1), path)?;
-                Value::String(s.into())
-            }
-            InfallibleLhs::At(idx, rhs) => eval_at((*idx, rhs), path)?,
-            InfallibleLhs::Square(lit) => Value::String(lit.clone()),
-        };
-
-        path.push(tip.clone());
-        for rhs in rhs.iter() {
-            insert_val_to_rhs(rhs, v.clone(), path, out)?;
+    for (key, marker) in obj.markers.iter() {
+        if *marker == Marker::Required && !has_key(tip.1, key, options.nulls) {
+            let error = Error::MissingRequiredKey {
+                key: key.clone(),
+                pointer: to_rfc6901(&input_pointer(path)),
+            };
+            return Err(out.failed_key(Some(obj.literal_key(key)), error));
         }
-        path.pop().ok_or(Error::ShiftEmptyPath)?;
+    }
+
+    for (lhs, rhs) in obj.infallible.iter() {
+        apply_infallible(lhs, rhs, &tip, path, out, options, scratch)
+            .map_err(|e| out.failed_key(Some(lhs.key()), e))?;
     }
 
     match tip.1 {
         Value::Object(input) => {
-            for (k, v) in input.iter() {
-                match_obj_and_key(obj, path, Cow::Borrowed(k), v, out)?;
-            }
+            let keys = input
+                .iter()
+                .filter(|(_, v)| options.nulls != Nulls::Absent || !v.is_null())
+                .map(|(k, v)| (Cow::Borrowed(k.as_str()), v));
+            match_obj_and_keys(obj, path, keys, out, options, scratch)?;
         }
         Value::Bool(b) => {
             let k = if *b { "true" } else { "false" };
 
-            match_obj_and_key(obj, path, Cow::Borrowed(k), tip.1, out)?;
+            match_obj_and_key(obj, path, Cow::Borrowed(k), tip.1, 0, out, options, scratch)?;
         }
         Value::Array(arr) => {
-            for (k, v) in arr.iter().enumerate() {
-                let k = k.to_string();
-                match_obj_and_key(
-                    obj,
-                    path,
-                    // this makes the downstream functions to do some extra allocations.
-                    // could avoid some of these allocations by mapping some small indexes to static str's
-                    Cow::Owned(k),
-                    v,
-                    out,
-                )?;
-            }
+            let keys = arr
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| options.nulls != Nulls::Absent || !v.is_null())
+                .map(|(k, v)| (index_key(k), v));
+            match_obj_and_keys(obj, path, keys, out, options, scratch)?;
         }
         Value::Number(n) => {
-            let k = n.to_string();
+            let k = scratch.display(n);
 
-            match_obj_and_key(obj, path, Cow::Owned(k), tip.1, out)?;
+            match_obj_and_key(obj, path, k, tip.1, 0, out, options, scratch)?;
         }
         Value::String(k) => {
-            match_obj_and_key(obj, path, Cow::Borrowed(k), tip.1, out)?;
+            match_obj_and_key(obj, path, Cow::Borrowed(k), tip.1, 0, out, options, scratch)?;
         }
         Value::Null => {
-            let k = "null";
-            match_obj_and_key(obj, path, Cow::Borrowed(k), tip.1, out)?;
+            if options.nulls == Nulls::Key {
+                let k = "null";
+                match_obj_and_key(obj, path, Cow::Borrowed(k), tip.1, 0, out, options, scratch)?;
+            }
+        }
+    };
+
+    Ok(())
+}
+
+// Apply an entry of the spec with an infallible lhs, the entries written to the output don't
+// depend on the keys of the input
+#[allow(clippy::too_many_arguments)]
+fn apply_infallible<'s, 'input: 's>(
+    lhs: &'input InfallibleLhs,
+    rhs: &'input [Rhs],
+    tip: &PathEntry<'s>,
+    path: &mut ScratchVec<'s, PathEntry<'s>>,
+    out: &mut Output<'input>,
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
+) -> Result<()> {
+    let v = match lhs {
+        InfallibleLhs::DollarSign(idx0, idx1) => {
+            if options.typed_captures {
+                get_typed_match((*idx0, *idx1), path)?
+            } else {
+                let s = get_match((*idx0, *idx1), path)?;
+                Value::String(s.into())
+            }
         }
+        InfallibleLhs::At(idx, rhs, marker) => {
+            let strict = strict_lookup(*marker, options);
+            match lookup(eval_at((*idx, rhs), path, options.nulls, scratch), strict)? {
+                Some(v) => v,
+                None => return Ok(()),
+            }
+        }
+        InfallibleLhs::Square(lit) => literal_value(lit, options.typed_literals),
     };
+    let input = if out.is_recorded() {
+        infallible_input_pointer(lhs, path, options, scratch)?
+    } else {
+        None
+    };
+
+    path.push(tip.clone());
+    for rhs in rhs.iter() {
+        insert_val_to_rhs(
+            rhs,
+            v.clone(),
+            input.clone(),
+            None,
+            path,
+            out,
+            options,
+            scratch,
+        )?;
+    }
+    path.pop().ok_or(Error::ShiftEmptyPath)?;
+    Ok(())
+}
+
+// Number of array indexes whose keys are shared by all the transformations
+const SHARED_INDEX_KEYS: usize = 1024;
+
+// The key of an array index. Keys of small indexes are borrowed from a table shared by all the
+// transformations, so matching arrays doesn't allocate a string for every element.
+fn index_key(idx: usize) -> Cow<'static, str> {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+
+    match KEYS
+        .get_or_init(|| (0..SHARED_INDEX_KEYS).map(|idx| idx.to_string()).collect())
+        .get(idx)
+    {
+        Some(key) => Cow::Borrowed(key.as_str()),
+        None => Cow::Owned(idx.to_string()),
+    }
+}
+
+// Match an object in the spec with the key/value pairs from the input
+// in the order given by the options
+fn match_obj_and_keys<'s, 'input: 's>(
+    obj: &'input Object,
+    path: &mut ScratchVec<'s, PathEntry<'s>>,
+    keys: impl Iterator<Item = (Cow<'s, str>, &'s Value)>,
+    out: &mut Output<'input>,
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
+) -> Result<()> {
+    match options.append_order {
+        AppendOrder::Input => {
+            let mut count = 0;
+            for (k, v) in keys {
+                if match_obj_and_key(obj, path, k, v, count, out, options, scratch)? {
+                    count += 1;
+                    out.check_fan_out(count)?;
+                }
+            }
+        }
+        AppendOrder::Spec => {
+            let mut matches = scratch.vec();
+            for (k, v) in keys {
+                out.attempted()?;
+                match find_match(obj, path, &k, options.match_order, scratch)? {
+                    Some((rank, m, rhs)) => matches.push((rank, m, v, rhs)),
+                    None => out.dropped(&k),
+                }
+            }
+            out.check_fan_out(matches.len())?;
+            // stable sort keeps the input order of keys matched by the same entry
+            matches.sort_by_key(|(rank, ..)| *rank);
+
+            for (count, (_, m, v, rhs)) in matches.into_iter().enumerate() {
+                out.matched(&m[0]);
+                path.push((m, v, count));
+                apply_match(v, rhs, path, out, options, scratch)
+                    .map_err(|e| out.failed_key(obj.key_of(rhs), e))?;
+                path.pop().ok_or(Error::ShiftEmptyPath)?;
+            }
+        }
+    }
 
     Ok(())
 }
@@ -102,58 +920,126 @@ fn apply<'ctx, 'input: 'ctx>(
 // Match and object in the spec with a key/value pair from the input
 // This function only runs the k/v pairs that have a fallible lhs in the spec
 // The infallible ones should have ran beforehand
-fn match_obj_and_key<'ctx, 'input: 'ctx>(
+// Returns whether the key is matched
+#[allow(clippy::too_many_arguments)]
+fn match_obj_and_key<'s, 'input: 's>(
     obj: &'input Object,
-    path: &'ctx mut Vec<(Vec<Cow<'input, str>>, &'input Value)>,
-    k: Cow<'input, str>,
-    v: &'input Value,
-    out: &'ctx mut Value,
-) -> Result<()> {
-    for (lit, rhs) in obj.literal.iter() {
-        let lit = Cow::Borrowed(lit.as_ref());
-        if lit == k {
-            path.push((vec![lit], v));
-            apply_match(v, rhs, path, out)?;
+    path: &mut ScratchVec<'s, PathEntry<'s>>,
+    k: Cow<'s, str>,
+    v: &'s Value,
+    count: usize,
+    out: &mut Output<'input>,
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
+) -> Result<bool> {
+    out.attempted()?;
+    match find_match(obj, path, &k, options.match_order, scratch)? {
+        Some((_, m, rhs)) => {
+            out.matched(&m[0]);
+            path.push((m, v, count));
+            apply_match(v, rhs, path, out, options, scratch)
+                .map_err(|e| out.failed_key(obj.key_of(rhs), e))?;
             path.pop().ok_or(Error::ShiftEmptyPath)?;
-            return Ok(());
+            Ok(true)
+        }
+        None => {
+            out.dropped(&k);
+            Ok(false)
+        }
+    }
+}
+
+// Rank of the matched entry, the matches and the rhs of the entry
+type Match<'s, 'input> = (usize, ScratchVec<'s, Cow<'s, str>>, &'input REntry);
+
+// Find the entry of an object in the spec that matches the key
+// Literals are ranked first, then amps and then pipes in the match order
+fn find_match<'s, 'input: 's>(
+    obj: &'input Object,
+    path: &[PathEntry<'s>],
+    k: &Cow<'s, str>,
+    order: MatchOrder,
+    scratch: &'s Scratch,
+) -> Result<Option<Match<'s, 'input>>> {
+    for (rank, (lit, rhs)) in obj.literal.iter().enumerate() {
+        let lit = Cow::Borrowed(lit.as_ref());
+        if lit == *k {
+            return Ok(Some((rank, scratch.vec_of(lit), rhs)));
         }
     }
 
-    for (amp, rhs) in obj.amp.iter() {
+    let offset = obj.literal.len();
+    for (rank, (amp, rhs)) in obj.amp.iter().enumerate() {
         let m = get_match(*amp, path)?;
-        if m == k {
-            path.push((vec![m], v));
-            apply_match(v, rhs, path, out)?;
-            path.pop().ok_or(Error::ShiftEmptyPath)?;
-            return Ok(());
+        if m == *k {
+            return Ok(Some((offset + rank, scratch.vec_of(m), rhs)));
         }
     }
 
-    for (pipes, rhs) in obj.pipes.iter() {
-        for stars in pipes.iter() {
-            if let Some(m) = match_stars(&stars.0, Cow::clone(&k)) {
-                path.push((m, v));
-                apply_match(v, rhs, path, out)?;
-                path.pop().ok_or(Error::ShiftEmptyPath)?;
-                return Ok(());
+    let offset = offset + obj.amp.len();
+    if order == MatchOrder::Spec {
+        for (rank, (pipes, rhs)) in obj.pipes.iter().enumerate() {
+            for stars in pipes.iter() {
+                if let Some(m) = match_stars(scratch, stars, Cow::clone(k)) {
+                    return Ok(Some((offset + rank, m, rhs)));
+                }
             }
         }
+        return Ok(None);
     }
 
-    Ok(())
+    // only the entries tried before the best match so far are tried
+    let mut best: Option<(usize, ScratchVec<'s, Cow<'s, str>>, &REntry)> = None;
+    for (idx, (pipes, rhs)) in obj.pipes.iter().enumerate() {
+        if let Some((best_idx, ..)) = &best {
+            if order.cmp_keys(pipes, &obj.pipes[*best_idx].0) != Ordering::Less {
+                continue;
+            }
+        }
+        if let Some(m) = order.match_alternatives(scratch, pipes, k) {
+            best = Some((idx, m, rhs));
+        }
+    }
+
+    Ok(best.map(|(idx, m, rhs)| {
+        let key = &obj.pipes[idx].0;
+        let rank = obj
+            .pipes
+            .iter()
+            .enumerate()
+            .filter(|(other, (pipes, _))| match order.cmp_keys(pipes, key) {
+                Ordering::Less => true,
+                Ordering::Equal => *other < idx,
+                Ordering::Greater => false,
+            })
+            .count();
+        (offset + rank, m, rhs)
+    }))
 }
 
-fn apply_match<'ctx, 'input: 'ctx>(
-    v: &'input Value,
+fn apply_match<'s, 'input: 's>(
+    v: &'s Value,
     rhs: &'input REntry,
-    path: &'ctx mut Vec<(Vec<Cow<'input, str>>, &'input Value)>,
-    out: &'ctx mut Value,
+    path: &mut ScratchVec<'s, PathEntry<'s>>,
+    out: &mut Output<'input>,
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
 ) -> Result<()> {
     match rhs {
-        REntry::Obj(object) => apply(object, path, out),
+        REntry::Obj(object) => apply(object, path, out, options, scratch),
         REntry::Rhs(rhs) => {
+            let input = out.is_recorded().then(|| input_pointer(path));
+            let movable = out.movable(v, path);
             for rhs in rhs.iter() {
-                insert_val_to_rhs(rhs, v.clone(), path, out)?;
+                // the unique appends compare the value with the elements
+                let moved = movable
+                    .clone()
+                    .filter(|_| !matches!(rhs.0.last(), Some(RhsPart::Index(IndexOp::Unique))));
+                let v = match moved {
+                    Some(_) => Value::Null,
+                    None => v.clone(),
+                };
+                insert_val_to_rhs(rhs, v, input.clone(), moved, path, out, options, scratch)?;
             }
             Ok(())
         }
@@ -161,8 +1047,95 @@ fn apply_match<'ctx, 'input: 'ctx>(
     }
 }
 
+// Pointer of the input value an infallible lhs reads:
+// The value written by `#`, a literal number, boolean or `null` keeps its type if typed
+fn literal_value(lit: &str, typed: bool) -> Value {
+    if typed {
+        if let Ok(v @ (Value::Number(_) | Value::Bool(_) | Value::Null)) = serde_json::from_str(lit)
+        {
+            return v;
+        }
+    }
+    Value::String(lit.to_owned())
+}
+
+// the value whose key is captured by `$`, the value found by `@` and none for `#`
+fn infallible_input_pointer<'s>(
+    lhs: &'s InfallibleLhs,
+    path: &[PathEntry<'s>],
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
+) -> Result<Option<Vec<String>>> {
+    let ptr = match lhs {
+        InfallibleLhs::DollarSign(idx0, _) => {
+            let len = path.len().saturating_sub(*idx0);
+            Some(input_pointer(&path[..len]))
+        }
+        // the gathered values don't have a single pointer
+        InfallibleLhs::At(_, rhs, _) if gathers(&rhs.0) => None,
+        InfallibleLhs::At(idx, rhs, marker) => {
+            let len = path.len().saturating_sub(*idx);
+            let mut ptr = input_pointer(&path[..len]);
+            let mut steps = scratch.vec_with_capacity(rhs.0.len());
+            let strict = strict_lookup(*marker, options);
+            match lookup(resolve_rhs(rhs, path, scratch, &mut steps), strict)? {
+                Some(()) => {
+                    for step in steps {
+                        match step {
+                            Step::Key(key) => ptr.push(key.into_owned()),
+                            Step::Index(idx) => ptr.push(idx.to_string()),
+                            Step::Append | Step::AppendUnique => return Ok(None),
+                        }
+                    }
+                    Some(ptr)
+                }
+                None => None,
+            }
+        }
+        InfallibleLhs::Square(_) => None,
+    };
+    Ok(ptr)
+}
+
+static NULL: Value = Value::Null;
+
+// Evaluate the `&` or `@` expression of a `default` value written to the given pointer.
+// The levels of the pointer absent from the input are `null`, failed lookups evaluate to none.
+pub(crate) fn eval_default(lhs: &Lhs, input: &Value, pointer: &JsonPointer) -> Option<Value> {
+    with_scratch(|scratch| {
+        let mut path = scratch.vec();
+        path.push((scratch.vec_of(Cow::Borrowed(ROOT_KEY)), input, 0));
+        let mut current = Some(input);
+        for key in pointer.entries().iter().skip(1) {
+            current = current.and_then(|v| match v {
+                Value::Object(obj) => obj.get(key),
+                Value::Array(arr) => key.parse::<usize>().ok().and_then(|idx| arr.get(idx)),
+                _ => None,
+            });
+            path.push((
+                scratch.vec_of(Cow::Borrowed(key.as_str())),
+                current.unwrap_or(&NULL),
+                0,
+            ));
+        }
+
+        match lhs {
+            Lhs::At(idx, rhs) => eval_at((*idx, rhs), &path, Nulls::Key, scratch).ok(),
+            Lhs::Amp(idx0, idx1) => get_match((*idx0, *idx1), &path)
+                .ok()
+                .map(|m| Value::String(m.into())),
+            _ => None,
+        }
+    })
+}
+
 // Evaluate an @ expression into a json value using the given path
-fn eval_at(at: (usize, &Rhs), path: &[(Vec<Cow<'_, str>>, &Value)]) -> Result<Value> {
+fn eval_at<'s>(
+    at: (usize, &'s Rhs),
+    path: &[PathEntry<'s>],
+    nulls: Nulls,
+    scratch: &'s Scratch,
+) -> Result<Value> {
     if at.0 >= path.len() {
         return Err(Error::PathIndexOutOfRange {
             idx: at.0,
@@ -172,38 +1145,57 @@ fn eval_at(at: (usize, &Rhs), path: &[(Vec<Cow<'_, str>>, &Value)]) -> Result<Va
 
     let v = &path[path.len() - at.0 - 1];
 
-    eval_rhs(at.1, v.1, path)
+    eval_rhs(at.1, v.1, path, nulls, scratch)
 }
 
 // Evaluate a rhs expression into a json value using the given path
-fn eval_rhs(rhs: &Rhs, v: &Value, path: &[(Vec<Cow<'_, str>>, &Value)]) -> Result<Value> {
+fn eval_rhs<'s>(
+    rhs: &'s Rhs,
+    v: &Value,
+    path: &[PathEntry<'s>],
+    nulls: Nulls,
+    scratch: &'s Scratch,
+) -> Result<Value> {
+    eval_parts(rhs, &rhs.0, v, path, nulls, scratch)
+}
+
+// Evaluate the parts of a rhs expression into a json value
+// `[*]` gathers the values of the rest of the parts in all the elements into an array, skipping
+// the elements where they are missing
+fn eval_parts<'s>(
+    rhs: &'s Rhs,
+    parts: &'s [RhsPart],
+    v: &Value,
+    path: &[PathEntry<'s>],
+    nulls: Nulls,
+    scratch: &'s Scratch,
+) -> Result<Value> {
     let mut v = v;
 
-    for part in rhs.0.iter() {
+    for (part_idx, part) in parts.iter().enumerate() {
+        if v.is_null() && nulls != Nulls::Key {
+            return Err(Error::KeyNotFound(rhs.to_string()));
+        }
         match part {
+            RhsPart::Index(IndexOp::Star) => {
+                let arr = v.as_array().ok_or(Error::UnexpectedRhsEntry)?;
+                let rest = &parts[part_idx + 1..];
+                // the values gathered by a nested `[*]` are flattened
+                let nested = gathers(rest);
+                let mut values = Vec::new();
+                for elem in arr {
+                    match eval_parts(rhs, rest, elem, path, nulls, scratch) {
+                        Ok(Value::Array(gathered)) if nested => values.extend(gathered),
+                        Ok(value) => values.push(value),
+                        Err(Error::KeyNotFound(_) | Error::ArrIndexOutOfRange { .. }) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                return Ok(Value::Array(values));
+            }
             RhsPart::Index(idx_op) => match v {
                 Value::Array(a) => {
-                    let idx = match idx_op {
-                        IndexOp::Amp(idx0, idx1) => {
-                            let m = get_match((*idx0, *idx1), path)?;
-                            m.parse().map_err(Error::InvalidIndex)?
-                        }
-                        IndexOp::Literal(idx) => *idx,
-                        IndexOp::At(idx, rhs) => match eval_at((*idx, rhs), path)? {
-                            Value::Number(n) => n
-                                .clone()
-                                .as_u64()
-                                .ok_or(Error::InvalidIndexVal(Value::Number(n.clone())))?
-                                .try_into()
-                                .map_err(|_| Error::InvalidIndexVal(Value::Number(n)))?,
-                            Value::String(s) => s.parse().map_err(Error::InvalidIndex)?,
-                            v => return Err(Error::InvalidIndexVal(v)),
-                        },
-                        IndexOp::Empty => {
-                            return Err(Error::UnexpectedRhsEntry);
-                        }
-                    };
-
+                    let idx = eval_index_op(idx_op, path, scratch)?;
                     v = a
                         .get(idx)
                         .ok_or(Error::ArrIndexOutOfRange { idx, len: a.len() })?;
@@ -213,34 +1205,105 @@ fn eval_rhs(rhs: &Rhs, v: &Value, path: &[(Vec<Cow<'_, str>>, &Value)]) -> Resul
                 }
             },
             RhsPart::CompositeKey(entries) => {
-                let mut key = String::new();
+                let mut key = scratch.string();
 
                 for entry in entries {
-                    let cow = rhs_entry_to_cow(entry, path)?;
-                    key += cow.as_ref();
+                    let cow = rhs_entry_to_cow(entry, path, scratch)?;
+                    key.push_str(cow.as_ref());
                 }
 
                 v = key_into_object(v, &key)?;
             }
             RhsPart::Key(entry) => {
-                let cow = rhs_entry_to_cow(entry, path)?;
+                let cow = rhs_entry_to_cow(entry, path, scratch)?;
                 v = key_into_object(v, cow.as_ref())?;
             }
         }
-    }
+    }
+
+    if v.is_null() && nulls == Nulls::Absent {
+        return Err(Error::KeyNotFound(rhs.to_string()));
+    }
+
+    Ok(Value::clone(v))
+}
+
+// Whether the parts of a rhs expression gather the values of array elements with `[*]`
+fn gathers(parts: &[RhsPart]) -> bool {
+    parts
+        .iter()
+        .any(|part| matches!(part, RhsPart::Index(IndexOp::Star)))
+}
+
+// Evaluate an index operation into an array index
+// `[]`, `[?]` and `[*]` don't evaluate to an index and are handled by the callers
+fn eval_index_op<'s>(
+    idx_op: &'s IndexOp,
+    path: &[PathEntry<'s>],
+    scratch: &'s Scratch,
+) -> Result<usize> {
+    let idx = match idx_op {
+        IndexOp::Amp(idx0, idx1) => {
+            let m = get_match((*idx0, *idx1), path)?;
+            m.parse().map_err(|_| Error::InvalidIndex {
+                reference: idx_op.to_string(),
+                value: Value::String(m.into()),
+            })?
+        }
+        IndexOp::DollarSign(idx0, idx1) => {
+            let value = get_typed_match((*idx0, *idx1), path)?;
+            index_value(&value).ok_or_else(|| Error::InvalidIndex {
+                reference: idx_op.to_string(),
+                value,
+            })?
+        }
+        IndexOp::Literal(idx) => *idx,
+        IndexOp::Square(level) => {
+            let pos = path
+                .len()
+                .checked_sub(*level)
+                .filter(|_| *level > 0)
+                .ok_or(Error::PathIndexOutOfRange {
+                    idx: *level,
+                    len: path.len(),
+                })?;
+            path[pos].2
+        }
+        IndexOp::At(idx, rhs) => {
+            let value = eval_at((*idx, rhs), path, Nulls::Key, scratch)?;
+            index_value(&value).ok_or_else(|| Error::InvalidIndexVal {
+                lookup: idx_op.to_string(),
+                value,
+            })?
+        }
+        IndexOp::Empty | IndexOp::Unique | IndexOp::Star => return Err(Error::UnexpectedRhsEntry),
+    };
 
-    Ok(Value::clone(v))
+    Ok(idx)
+}
+
+// A non-negative integer or a string of digits
+fn index_value(value: &Value) -> Option<usize> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| usize::try_from(n).ok()),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
 }
 
 // Evaluate a rhs expression into a string
-fn rhs_entry_to_cow<'ctx, 'input: 'ctx>(
-    entry: &'input RhsEntry,
-    path: &'ctx [(Vec<Cow<'input, str>>, &'input Value)],
-) -> Result<Cow<'input, str>> {
+fn rhs_entry_to_cow<'s>(
+    entry: &'s RhsEntry,
+    path: &[PathEntry<'s>],
+    scratch: &'s Scratch,
+) -> Result<Cow<'s, str>> {
     let cow = match entry {
         RhsEntry::Amp(idx0, idx1) => get_match((*idx0, *idx1), path)?,
+        RhsEntry::AmpFormat(idx0, idx1, format) => {
+            Cow::Owned(format.apply(&get_match((*idx0, *idx1), path)?))
+        }
         RhsEntry::At(idx, rhs) => {
-            let key = eval_at((*idx, rhs), path)?;
+            let key = eval_at((*idx, rhs), path, Nulls::Key, scratch)?;
             match key {
                 Value::String(s) => Cow::Owned(s),
                 Value::Number(n) => Cow::Owned(n.to_string()),
@@ -271,83 +1334,219 @@ fn key_into_object<'input>(v: &'input Value, key: &str) -> Result<&'input Value>
     }
 }
 
-fn insert_val_to_rhs<'ctx, 'input: 'ctx>(
-    rhs: &Rhs,
+// A resolved part of a rhs expression
+enum Step<'s> {
+    Key(Cow<'s, str>),
+    Index(usize),
+    Append,
+    AppendUnique,
+}
+
+// The output pointer of the steps, appends are pointed at with `-` like in RFC 6902
+fn steps_pointer(steps: &[Step<'_>]) -> Vec<String> {
+    steps
+        .iter()
+        .map(|step| match step {
+            Step::Key(key) => key.to_string(),
+            Step::Index(idx) => idx.to_string(),
+            Step::Append | Step::AppendUnique => "-".to_string(),
+        })
+        .collect()
+}
+
+// Resolve the parts of a rhs expression into the keys and indexes to write to, the steps are
+// resolved up to the failed part on errors
+fn resolve_rhs<'s>(
+    rhs: &'s Rhs,
+    path: &[PathEntry<'s>],
+    scratch: &'s Scratch,
+    steps: &mut ScratchVec<'s, Step<'s>>,
+) -> Result<()> {
+    for part in rhs.0.iter() {
+        let step = match part {
+            RhsPart::Index(IndexOp::Empty) => Step::Append,
+            RhsPart::Index(IndexOp::Unique) => Step::AppendUnique,
+            RhsPart::Index(idx_op) => Step::Index(eval_index_op(idx_op, path, scratch)?),
+            RhsPart::CompositeKey(entries) => {
+                let mut key = scratch.string();
+
+                for entry in entries {
+                    let cow = rhs_entry_to_cow(entry, path, scratch)?;
+                    key.push_str(cow.as_ref());
+                }
+
+                Step::Key(Scratch::finish(key))
+            }
+            RhsPart::Key(entry) => Step::Key(rhs_entry_to_cow(entry, path, scratch)?),
+        };
+        steps.push(step);
+    }
+
+    Ok(())
+}
+
+// The value is a placeholder if it's moved from the input pointer once the shift is done
+#[allow(clippy::too_many_arguments)]
+fn insert_val_to_rhs<'s, 'input: 's>(
+    rhs: &'input Rhs,
     v: Value,
-    path: &'ctx [(Vec<Cow<'input, str>>, &'input Value)],
-    out: &mut Value,
+    input: Option<Vec<String>>,
+    moved: Option<Vec<String>>,
+    path: &[PathEntry<'s>],
+    output: &mut Output<'input>,
+    options: &ShiftOptions,
+    scratch: &'s Scratch,
 ) -> Result<()> {
-    let mut out = out;
+    // resolve the whole rhs before writing so a failed lookup doesn't leave a partial output
+    let mut steps = scratch.vec_with_capacity(rhs.0.len());
+    match lookup(
+        resolve_rhs(rhs, path, scratch, &mut steps),
+        options.strict_lookups,
+    ) {
+        Ok(Some(())) => (),
+        Ok(None) => return Ok(()),
+        Err(e) => return Err(output.failed(&steps, e)),
+    }
+    let failed = output.partial.then(|| steps_pointer(&steps));
+    let written = write_steps(rhs, v, input, moved, steps, output, options);
+    if written.is_err() {
+        output.failed_write = failed;
+    }
+    written
+}
 
-    for part in rhs.0.iter() {
-        match part {
-            RhsPart::Index(idx_op) => {
-                let arr = if out.is_array() {
-                    out.as_array_mut().unwrap()
-                } else if out.is_null() {
-                    *out = Value::Array(Vec::new());
-                    out.as_array_mut().unwrap()
-                } else {
-                    *out = Value::Array(vec![std::mem::take(out)]);
-                    out.as_array_mut().unwrap()
-                };
+// Write the value at the resolved steps of the rhs
+fn write_steps<'s, 'input: 's>(
+    rhs: &'input Rhs,
+    v: Value,
+    input: Option<Vec<String>>,
+    moved: Option<Vec<String>>,
+    steps: ScratchVec<'s, Step<'s>>,
+    output: &mut Output<'input>,
+    options: &ShiftOptions,
+) -> Result<()> {
+    let steps_len = steps.len();
+    output.written(&v)?;
+
+    // the output pointer is tracked for the recorded writes, the moved values and the padding
+    let recorded = output.is_recorded()
+        || output.moved.is_some()
+        || options.sparse_arrays != SparseArrays::Pad;
+    let observer = output.observer;
+    let array_created = || {
+        if let Some(observer) = observer {
+            observer.on_array_created();
+        }
+    };
+    // output pointer of the write and the wrapped or replaced values on the way
+    let mut ptr = Vec::new();
+    let mut wrapped = Vec::new();
+    let mut replaced = Vec::new();
+
+    let mut out = &mut output.value;
+    let mut moved_values = output.moved.as_mut();
+    if let Some(moved) = moved_values.as_deref_mut() {
+        moved.copy(&ptr, out);
+    }
 
-                let idx = match idx_op {
-                    IndexOp::Amp(idx0, idx1) => {
-                        let m = get_match((*idx0, *idx1), path)?;
-                        m.parse().map_err(Error::InvalidIndex)?
+    for (step_idx, step) in steps.into_iter().enumerate() {
+        match step {
+            Step::Index(_) | Step::Append | Step::AppendUnique => {
+                if !out.is_array() {
+                    if out.is_null() {
+                        array_created();
+                        *out = Value::Array(Vec::new());
+                    } else {
+                        match options.conflicts {
+                            Conflicts::Merge => {
+                                if recorded {
+                                    wrapped.push(ptr.clone());
+                                }
+                                array_created();
+                                *out = Value::Array(vec![std::mem::take(out)]);
+                            }
+                            Conflicts::Overwrite => {
+                                if recorded {
+                                    replaced.push(ptr.clone());
+                                }
+                                array_created();
+                                *out = Value::Array(Vec::new());
+                            }
+                            Conflicts::Error => {
+                                return Err(conflict(output.writes.as_deref(), &ptr, rhs));
+                            }
+                        }
                     }
-                    IndexOp::Literal(idx) => *idx,
-                    IndexOp::At(idx, rhs) => match eval_at((*idx, rhs), path)? {
-                        Value::Number(n) => n
-                            .clone()
-                            .as_u64()
-                            .ok_or(Error::InvalidIndexVal(Value::Number(n.clone())))?
-                            .try_into()
-                            .map_err(|_| Error::InvalidIndexVal(Value::Number(n)))?,
-                        Value::String(s) => s.parse().map_err(Error::InvalidIndex)?,
-                        v => return Err(Error::InvalidIndexVal(v)),
-                    },
-                    IndexOp::Empty => {
+                }
+                let Value::Array(arr) = out else {
+                    return Err(Error::ShiftUnexpectedOutput);
+                };
+
+                let idx = match step {
+                    Step::Index(idx) => idx,
+                    _ => {
+                        let unique = options.dedup_appends || matches!(step, Step::AppendUnique);
+                        if let Some(moved) = moved_values.as_deref_mut().filter(|_| unique) {
+                            moved.copy_elements(&ptr, arr);
+                        }
+                        if unique && step_idx + 1 == steps_len && arr.contains(&v) {
+                            return Ok(());
+                        }
+                        if recorded {
+                            ptr.push(arr.len().to_string());
+                        }
                         arr.push(Value::Null);
-                        out = arr.last_mut().unwrap();
+                        out = arr.last_mut().ok_or(Error::ShiftUnexpectedOutput)?;
                         continue;
                     }
                 };
 
+                let len = arr.len();
+                if idx.saturating_sub(len) > MAX_ARRAY_PADDING {
+                    return Err(Error::SparseArrayWrite { idx, len });
+                }
+
                 while arr.len() <= idx {
+                    if arr.len() < idx && options.sparse_arrays != SparseArrays::Pad {
+                        let mut slot = ptr.clone();
+                        slot.push(arr.len().to_string());
+                        output.padded.insert(slot, (idx, len));
+                    }
                     arr.push(Value::Null);
                 }
 
-                out = arr.get_mut(idx).unwrap();
-            }
-            RhsPart::CompositeKey(entries) => {
-                let mut key = String::new();
-
-                for entry in entries {
-                    let cow = rhs_entry_to_cow(entry, path)?;
-                    key += cow.as_ref();
+                if recorded {
+                    ptr.push(idx.to_string());
+                    output.padded.remove(&ptr);
+                }
+                out = arr.get_mut(idx).ok_or(Error::ShiftUnexpectedOutput)?;
+                if let Some(moved) = moved_values.as_deref_mut() {
+                    moved.copy(&ptr, out);
                 }
-
-                let obj = if out.is_object() {
-                    out.as_object_mut().unwrap()
-                } else {
-                    *out = Value::Object(Default::default());
-                    out.as_object_mut().unwrap()
-                };
-
-                out = obj.entry(&key).or_insert(Value::Null);
             }
-            RhsPart::Key(entry) => {
-                let cow = rhs_entry_to_cow(entry, path)?;
-                let obj = if out.is_object() {
-                    out.as_object_mut().unwrap()
-                } else {
+            Step::Key(key) => {
+                if !out.is_object() {
+                    if !out.is_null() {
+                        if options.conflicts == Conflicts::Error {
+                            return Err(conflict(output.writes.as_deref(), &ptr, rhs));
+                        }
+                        if recorded {
+                            replaced.push(ptr.clone());
+                        }
+                    }
                     *out = Value::Object(Default::default());
-                    out.as_object_mut().unwrap()
+                }
+                let Value::Object(obj) = out else {
+                    return Err(Error::ShiftUnexpectedOutput);
                 };
 
-                out = obj.entry(cow.as_ref()).or_insert(Value::Null);
+                if recorded {
+                    ptr.push(key.to_string());
+                }
+                out = obj.entry(key).or_insert(Value::Null);
+                if let Some(moved) = moved_values.as_deref_mut() {
+                    moved.copy(&ptr, out);
+                }
             }
         }
     }
@@ -356,33 +1555,125 @@ fn insert_val_to_rhs<'ctx, 'input: 'ctx>(
         Value::Null => {
             *out = v;
         }
+        val if options.conflicts == Conflicts::Overwrite => {
+            if recorded {
+                replaced.push(ptr.clone());
+            }
+            *val = v;
+        }
+        val if options.conflicts == Conflicts::Error && val.is_object() != v.is_object() => {
+            return Err(conflict(output.writes.as_deref(), &ptr, rhs));
+        }
         Value::Array(arr) => {
+            if recorded {
+                ptr.push(arr.len().to_string());
+            }
             arr.push(v);
         }
         val => {
+            array_created();
             let v = Value::Array(vec![std::mem::take(val), v]);
             *val = v;
+            if recorded {
+                wrapped.push(ptr.clone());
+                ptr.push("1".to_string());
+            }
         }
     }
 
+    for ptr in wrapped {
+        output.wrap(&ptr);
+    }
+    for ptr in replaced {
+        output.replace(&ptr);
+    }
+    if let (Some(moved), Some(from)) = (output.moved.as_mut(), moved) {
+        moved.values.insert(ptr.clone(), from);
+    }
+    output.record(ptr, input, rhs);
+
     Ok(())
 }
 
-fn match_stars<'ctx, 'input: 'ctx>(
-    stars: &'input [String],
-    k: Cow<'input, str>,
-) -> Option<Vec<Cow<'input, str>>> {
+// Map lookups of missing keys and indexes to `None` unless the lookup is strict
+fn lookup<T>(res: Result<T>, strict: bool) -> Result<Option<T>> {
+    match res {
+        Ok(v) => Ok(Some(v)),
+        Err(Error::KeyNotFound(_) | Error::ArrIndexOutOfRange { .. }) if !strict => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// The marker of a `@` lookup on the left hand side overrides the strict lookups of the shift
+fn strict_lookup(marker: Option<Marker>, options: &ShiftOptions) -> bool {
+    match marker {
+        Some(Marker::Optional) => false,
+        Some(Marker::Required) => true,
+        None => options.strict_lookups,
+    }
+}
+
+// Whether the value has the key matched by a literal key of the spec, scalars are matched by their
+// value like in `apply`
+fn has_key(value: &Value, key: &str, nulls: Nulls) -> bool {
+    let present = |v: &Value| nulls != Nulls::Absent || !v.is_null();
+    match value {
+        Value::Object(map) => map.get(key).is_some_and(present),
+        Value::Array(arr) => key
+            .parse::<usize>()
+            .ok()
+            .and_then(|idx| arr.get(idx))
+            .is_some_and(present),
+        Value::Bool(b) => key == if *b { "true" } else { "false" },
+        Value::Number(n) => key == n.to_string(),
+        Value::String(s) => key == s,
+        Value::Null => nulls == Nulls::Key && key == "null",
+    }
+}
+
+pub(crate) fn match_stars<'s>(
+    scratch: &'s Scratch,
+    stars: &'s Stars,
+    k: Cow<'s, str>,
+) -> Option<ScratchVec<'s, Cow<'s, str>>> {
+    let stars = match stars {
+        Stars::Prefix(prefix) => {
+            let rest = match &k {
+                Cow::Borrowed(s) => Cow::Borrowed(s.strip_prefix(prefix.as_str())?),
+                Cow::Owned(s) => scratch.str(s.strip_prefix(prefix.as_str())?),
+            };
+            let mut m = scratch.vec_with_capacity(2);
+            m.push(k);
+            m.push(rest);
+            return Some(m);
+        }
+        Stars::Suffix(suffix) => {
+            // like the other patterns, the key matches if it contains the literal, the star
+            // capturing the text before its first occurrence
+            let idx = k.find(suffix.as_str())?;
+            let rest = match &k {
+                Cow::Borrowed(s) => Cow::Borrowed(&s[..idx]),
+                Cow::Owned(s) => scratch.str(&s[..idx]),
+            };
+            let mut m = scratch.vec_with_capacity(2);
+            m.push(k);
+            m.push(rest);
+            return Some(m);
+        }
+        Stars::Pieces(pieces) => pieces,
+    };
+
     match stars.len() {
         0 => {
             return if k.is_empty() {
-                Some(vec!["".into()])
+                Some(scratch.vec_of("".into()))
             } else {
                 None
             };
         }
         1 => {
             return if k == stars[0].as_str() {
-                Some(vec![k])
+                Some(scratch.vec_of(k))
             } else {
                 None
             };
@@ -390,7 +1681,8 @@ fn match_stars<'ctx, 'input: 'ctx>(
         _ => (),
     }
 
-    let mut m = vec![k.clone()];
+    let mut m = scratch.vec_with_capacity(stars.len());
+    m.push(k.clone());
 
     let prefix = stars[0].as_str();
 
@@ -404,7 +1696,7 @@ fn match_stars<'ctx, 'input: 'ctx>(
             }
             Cow::Owned(s) => {
                 let s = s.strip_prefix(prefix)?;
-                Cow::Owned(s.to_owned())
+                scratch.str(s)
             }
         }
     };
@@ -419,8 +1711,8 @@ fn match_stars<'ctx, 'input: 'ctx>(
                         k = Cow::Borrowed(&s[idx..]);
                     }
                     Cow::Owned(s) => {
-                        m.push(Cow::Owned(s[..idx].to_owned()));
-                        k = Cow::Owned(s[idx..].to_owned());
+                        m.push(scratch.str(&s[..idx]));
+                        k = scratch.str(&s[idx..]);
                     }
                 },
             }
@@ -432,10 +1724,7 @@ fn match_stars<'ctx, 'input: 'ctx>(
     Some(m)
 }
 
-fn get_match<'ctx, 'input: 'ctx>(
-    idx: (usize, usize),
-    path: &'ctx [(Vec<Cow<'input, str>>, &'input Value)],
-) -> Result<Cow<'input, str>> {
+fn get_match<'s>(idx: (usize, usize), path: &[PathEntry<'s>]) -> Result<Cow<'s, str>> {
     if idx.0 >= path.len() {
         return Err(Error::PathIndexOutOfRange {
             idx: idx.0,
@@ -443,7 +1732,7 @@ fn get_match<'ctx, 'input: 'ctx>(
         });
     }
 
-    let (matches, _) = &path[path.len() - idx.0 - 1];
+    let (matches, ..) = &path[path.len() - idx.0 - 1];
 
     let m = matches.get(idx.1).ok_or(Error::MatchIndexOutOfRange {
         idx: idx.1,
@@ -452,3 +1741,816 @@ fn get_match<'ctx, 'input: 'ctx>(
 
     Ok(m.clone())
 }
+
+// Get a `$` match keeping the type of the matched key:
+// array indexes are numbers and matched booleans, numbers and nulls keep their type.
+// Only whole key matches are typed, partial matches of wildcards are always strings.
+fn get_typed_match(idx: (usize, usize), path: &[PathEntry<'_>]) -> Result<Value> {
+    let m = get_match(idx, path)?;
+
+    let parent = match path.len().checked_sub(idx.0 + 2) {
+        Some(parent) if idx.1 == 0 => path[parent].1,
+        _ => return Ok(Value::String(m.into())),
+    };
+
+    let v = match parent {
+        Value::Array(_) => match m.parse::<u64>() {
+            Ok(idx) => Value::from(idx),
+            Err(_) => Value::String(m.into()),
+        },
+        Value::Bool(b) => Value::Bool(*b),
+        Value::Number(n) => Value::Number(n.clone()),
+        Value::Null => Value::Null,
+        Value::Object(_) | Value::String(_) => Value::String(m.into()),
+    };
+
+    Ok(v)
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_rename_map() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "id": ["data.id", "ids[]"],
+            "a\\.b": {"c": "out.x\\.y", "*": "rest.&"},
+            "&(0)": "captured",
+            "removed": null,
+            "name": "data.&(0)|data.name"
+        }))
+        .expect("parsed spec");
+
+        //when
+        let map = shift.rename_map();
+
+        //then
+        assert_eq!(
+            map,
+            vec![
+                ("id".to_string(), "data.id".to_string()),
+                ("a\\.b.c".to_string(), "out.x\\.y".to_string()),
+                ("name".to_string(), "data.name".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sparse_arrays_error() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "a": "list[2]"
+        }))
+        .expect("parsed spec");
+        let options = ShiftOptions {
+            sparse_arrays: SparseArrays::Error,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&json!({"a": "a"}), &options, &Context::default());
+
+        //then
+        assert!(matches!(
+            result,
+            Err(Error::SparseArrayWrite { idx: 2, len: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_sparse_arrays_error_out_of_order() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": "out[&]",
+            "gap": "gaps[1]"
+        }))
+        .expect("parsed spec");
+        let options = ShiftOptions {
+            sparse_arrays: SparseArrays::Error,
+            ..Default::default()
+        };
+        let mut input = serde_json::Map::new();
+        input.insert("1".to_string(), json!("b"));
+        input.insert("0".to_string(), json!("a"));
+
+        //when
+        let result = shift.apply(&Value::Object(input), &options, &Context::default());
+        let gap = shift.apply(&json!({"gap": "a"}), &options, &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"out": ["a", "b"]}));
+        assert!(matches!(
+            gap,
+            Err(Error::SparseArrayWrite { idx: 1, len: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_sparse_arrays_compact_keeps_nulls() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "list": "list",
+            "first": "out[0]",
+            "third": "out[2]",
+            "nested": {"*": "nested[1].items[&]"}
+        }))
+        .expect("parsed spec");
+        let options = ShiftOptions {
+            sparse_arrays: SparseArrays::Compact,
+            ..Default::default()
+        };
+        let input = json!({
+            "list": [1, null, 3],
+            "first": null,
+            "third": "c",
+            "nested": {"2": "x"}
+        });
+
+        let budget = Budget::new(&crate::TransformOptions::default().record_writes());
+
+        //when
+        let output = shift
+            .apply_limited(&input, &options, None, &budget, false)
+            .expect("applied shift");
+        let writes = budget.take_writes();
+
+        //then
+        assert_eq!(
+            output,
+            json!({
+                "list": [1, null, 3],
+                "out": [null, "c"],
+                "nested": [{"items": ["x"]}]
+            })
+        );
+        let mut pointers: Vec<_> = writes.into_iter().map(|write| write.output).collect();
+        pointers.sort();
+        assert_eq!(
+            pointers,
+            vec![
+                vec!["list".to_string()],
+                vec![
+                    "nested".to_string(),
+                    "0".to_string(),
+                    "items".to_string(),
+                    "0".to_string()
+                ],
+                vec!["out".to_string(), "0".to_string()],
+                vec!["out".to_string(), "1".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sparse_arrays_padding_limit() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": {"$": "list[&(1)]"}
+        }))
+        .expect("parsed spec");
+        let input = json!({"1": "a", "18446744073709551615": "b"});
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert!(matches!(
+            result,
+            Err(Error::SpecKey { path, source }) if path == "/*/$(0,0)" && matches!(
+                *source,
+                Error::SparseArrayWrite {
+                    idx: usize::MAX,
+                    len: 2
+                }
+            )
+        ));
+    }
+
+    #[test]
+    fn test_sparse_arrays_error_allows_dense_writes() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": "list[&]"
+        }))
+        .expect("parsed spec");
+        let options = ShiftOptions {
+            sparse_arrays: SparseArrays::Error,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&json!({"0": "a", "1": "b"}), &options, &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"list": ["a", "b"]}));
+    }
+
+    #[test]
+    fn test_missing_lookup_skips_write() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": {
+                "name": "names.@(1,id)",
+                "@(0,missing)": "missing"
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "a": {"id": "1", "name": "a"},
+            "b": {"name": "b"}
+        });
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"names": {"1": "a"}}));
+    }
+
+    #[test]
+    fn test_missing_lookup_strict() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": {
+                "name": "names.@(1,id)"
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "b": {"name": "b"}
+        });
+        let options = ShiftOptions {
+            strict_lookups: true,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+
+        //then
+        assert!(matches!(
+            result,
+            Err(Error::SpecKey { path, source })
+                if path == "/*/name" && matches!(&*source, Error::KeyNotFound(key) if key == "id")
+        ));
+    }
+
+    #[test]
+    fn test_apply_owned() {
+        //given
+        let input = json!({
+            "id": 1,
+            "account": {"id": 1000, "type": "Checking"},
+            "items": [{"sku": "a"}, {"sku": "b"}],
+            "extra": {"sku": "a"},
+            "tags": ["x", "y"]
+        });
+        let specs = [
+            json!({"account": ["data.account", "copy.account"], "id": "data.id"}),
+            json!({"account": "data", "id": "data.id"}),
+            json!({"account": "data", "tags": "data"}),
+            json!({"items": {"*": "list[]"}, "extra": "list[?]"}),
+            json!({"items": "list", "tags": "list[1]"}),
+            json!({"*": "&", "tags": {"1": "tags"}}),
+            json!({"items": {"*": "&(1).&"}, "account": "items"}),
+        ];
+        let overwrite = ShiftOptions {
+            conflicts: Conflicts::Overwrite,
+            ..Default::default()
+        };
+
+        for spec in specs {
+            for options in [ShiftOptions::default(), overwrite] {
+                let shift: Shift = serde_json::from_value(spec.clone()).expect("parsed spec");
+
+                //when
+                let copied = shift.apply(&input, &options, &Context::default());
+                let moved = shift.apply_owned(input.clone(), &options);
+
+                //then
+                assert_eq!(moved, copied, "spec: {spec}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_marked_keys() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "orders": {
+                "*": {
+                    "id!": "ids[]",
+                    "@(0,note)?": "notes[]",
+                    "done\\?": "done[]"
+                }
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({"orders": [{"id": 1, "note": "a", "done?": true}, {"id": 2}]});
+        let missing = json!({"orders": [{"id": 1}, {"note": "b"}]});
+        let options = ShiftOptions {
+            strict_lookups: true,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+        let missing_result = shift.apply(&missing, &options, &Context::default());
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({"ids": [1, 2], "notes": ["a"], "done": [true]})
+        );
+        assert_eq!(
+            missing_result,
+            Err(Error::SpecKey {
+                path: "/orders/*/id!".to_string(),
+                source: Box::new(Error::MissingRequiredKey {
+                    key: "id".to_string(),
+                    pointer: "/orders/1".to_string()
+                })
+            })
+        );
+        let serialized = serde_json::to_value(&shift).expect("serialized spec");
+        assert_eq!(
+            serialized,
+            json!({"orders": {"*": {"@(0,note)?": "notes[]", "id!": "ids[]", "done\\?": "done[]"}}})
+        );
+    }
+
+    #[test]
+    fn test_required_lookup() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "@(0,a.b)!": "out"
+        }))
+        .expect("parsed spec");
+
+        //when
+        let result = shift.apply(
+            &json!({"a": {}}),
+            &ShiftOptions::default(),
+            &Context::default(),
+        );
+
+        //then
+        assert!(matches!(
+            result,
+            Err(Error::SpecKey { path, source })
+                if path == "/@(0,a.b)!" && matches!(&*source, Error::KeyNotFound(key) if key == "b")
+        ));
+    }
+
+    #[test]
+    fn test_null_lookup_strict() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "@(0,a.b)": "out"
+        }))
+        .expect("parsed spec");
+        let input = json!({"a": null});
+        let value = ShiftOptions {
+            strict_lookups: true,
+            nulls: Nulls::Value,
+            ..Default::default()
+        };
+        let key = ShiftOptions {
+            strict_lookups: true,
+            ..Default::default()
+        };
+
+        //when
+        let value_result = shift.apply(&input, &value, &Context::default());
+        let key_result = shift.apply(&input, &key, &Context::default());
+
+        //then
+        assert!(matches!(
+            value_result,
+            Err(Error::SpecKey { source, .. })
+                if matches!(&*source, Error::KeyNotFound(key) if key == "a.b")
+        ));
+        assert!(matches!(
+            key_result,
+            Err(Error::SpecKey { source, .. }) if *source == Error::UnexpectedRhsEntry
+        ));
+    }
+
+    #[test]
+    fn test_match_indexes() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": {
+                "*": {
+                    "name": "&(2)[$(1)].name",
+                    "flag": {
+                        "true": "flags[$(0)]"
+                    }
+                }
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({"list": [{"name": "a"}, {"name": "b"}], "map": {"1": {"name": "c"}}});
+        let invalid = json!({"map": {"x": {"name": "a"}}});
+        let flag = json!({"list": [{"flag": true}]});
+        let options = ShiftOptions::default();
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+        let invalid_result = shift.apply(&invalid, &options, &Context::default());
+        let flag_result = shift.apply(&flag, &options, &Context::default());
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({"list": [{"name": "a"}, {"name": "b"}], "map": [null, {"name": "c"}]})
+        );
+        assert_eq!(
+            invalid_result,
+            Err(Error::SpecKey {
+                path: "/*/*/name".to_string(),
+                source: Box::new(Error::InvalidIndex {
+                    reference: "$(1,0)".to_string(),
+                    value: json!("x")
+                })
+            })
+        );
+        assert_eq!(
+            flag_result,
+            Err(Error::SpecKey {
+                path: "/*/*/flag/true".to_string(),
+                source: Box::new(Error::InvalidIndex {
+                    reference: "$(0,0)".to_string(),
+                    value: json!(true)
+                })
+            })
+        );
+    }
+
+    #[test]
+    fn test_capture_case() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": {
+                "*": "&(1):snake.&:camel",
+                "@": "&:kebab-copy"
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({"UserInfo": {"first_name": "John"}});
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({
+                "user_info": {"firstName": "John"},
+                "user-info-copy": {"first_name": "John"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_capture_zero_pad() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "items": {
+                "*": {
+                    "id": "items.&(1):%03d"
+                }
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({"items": (0..11).map(|id| json!({"id": id})).collect::<Vec<_>>()});
+
+        //when
+        let result = shift
+            .apply(&input, &ShiftOptions::default(), &Context::default())
+            .unwrap();
+
+        //then
+        let keys: Vec<&String> = result["items"].as_object().unwrap().keys().collect();
+        assert_eq!(keys.first().map(|key| key.as_str()), Some("000"));
+        assert_eq!(keys.last().map(|key| key.as_str()), Some("010"));
+        assert!(keys.is_sorted());
+    }
+
+    #[test]
+    fn test_lookup_indexes() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "items": {
+                "*": {
+                    "sku": "orders[@(1,idx)].lines[@(1,line)].sku"
+                }
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({"items": [{"idx": 1, "line": "1", "sku": "a"}]});
+        let invalid = |idx, line| json!({"items": [{"idx": idx, "line": line, "sku": "a"}]});
+        let options = ShiftOptions::default();
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+        let errors: Vec<Error> = [
+            invalid(json!(0), json!("x")),
+            invalid(json!(-1), json!(0)),
+            invalid(json!(1.5), json!(0)),
+        ]
+        .iter()
+        .filter_map(
+            |input| match shift.apply(input, &options, &Context::default()) {
+                Err(Error::SpecKey { path, source }) if path == "/items/*/sku" => Some(*source),
+                _ => None,
+            },
+        )
+        .collect();
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({"orders": [null, {"lines": [null, {"sku": "a"}]}]})
+        );
+        assert_eq!(
+            errors,
+            vec![
+                Error::InvalidIndexVal {
+                    lookup: "@(1,line)".to_string(),
+                    value: json!("x")
+                },
+                Error::InvalidIndexVal {
+                    lookup: "@(1,idx)".to_string(),
+                    value: json!(-1)
+                },
+                Error::InvalidIndexVal {
+                    lookup: "@(1,idx)".to_string(),
+                    value: json!(1.5)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counter_index_skips_unmatched_keys() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "items": {
+                "a*": {
+                    "id": "ids[#2]"
+                }
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "items": {
+                "a1": {"id": 1},
+                "b": {"id": 2},
+                "a2": {"id": 3}
+            }
+        });
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"ids": [1, 3]}));
+    }
+
+    #[test]
+    fn test_conflicts_merge() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "a": "out",
+            "b": "out.x"
+        }))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2});
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"out": {"x": 2}}));
+    }
+
+    #[test]
+    fn test_conflicts_overwrite() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "a": "out",
+            "b": "out",
+            "c": "list[0]",
+            "d": "list"
+        }))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2, "c": 3, "d": 4});
+        let options = ShiftOptions {
+            conflicts: Conflicts::Overwrite,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"out": 2, "list": 4}));
+    }
+
+    #[test]
+    fn test_conflicts_error() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "a": "out.&(0,0)",
+            "b": "out"
+        }))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2});
+        let options = ShiftOptions {
+            conflicts: Conflicts::Error,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+
+        //then
+        assert!(matches!(
+            result,
+            Err(Error::SpecKey { path: key, source }) if key == "/b" && matches!(
+                &*source,
+                Error::OutputConflict { path, first, second }
+                    if path == "/out" && first == "out.&(0,0)" && second == "out"
+            )
+        ));
+    }
+
+    #[test]
+    fn test_conflicts_error_allows_collections() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "a": "out",
+            "b": "out",
+            "c": "list[]",
+            "d": "list[]"
+        }))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2, "c": {"x": 3}, "d": {"x": 4}});
+        let options = ShiftOptions {
+            conflicts: Conflicts::Error,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({"out": [1, 2], "list": [{"x": 3}, {"x": 4}]})
+        );
+    }
+
+    #[test]
+    fn test_match_order() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*": "all.&",
+            "b|a": "or.&",
+            "a*": "short.&",
+            "ab*": "single.&",
+            "a*c*": "double.&"
+        }))
+        .expect("parsed spec");
+        let input = json!({"abc": 1, "abd": 2, "b": 3, "xyz": 4});
+        let java = ShiftOptions {
+            match_order: MatchOrder::Java,
+            ..Default::default()
+        };
+
+        //when
+        let spec_order = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+        let java_order = shift.apply(&input, &java, &Context::default());
+
+        //then
+        assert_eq!(spec_order.unwrap(), json!({"all": input}));
+        assert_eq!(
+            java_order.unwrap(),
+            json!({
+                "double": {"abc": 1},
+                "single": {"abd": 2},
+                "or": {"b": 3},
+                "all": {"xyz": 4}
+            })
+        );
+    }
+
+    #[test]
+    fn test_match_order_alphabetical() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "b|a": "or.&",
+            "a*": "prefix.&"
+        }))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2});
+        let options = ShiftOptions {
+            match_order: MatchOrder::Alphabetical,
+            append_order: AppendOrder::Spec,
+            ..Default::default()
+        };
+
+        //when
+        let result = shift.apply(&input, &options, &Context::default());
+
+        //then
+        assert_eq!(result.unwrap(), json!({"prefix": {"a": 1}, "or": {"b": 2}}));
+    }
+
+    #[test]
+    fn test_lookup_gathers_array_values() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "items": {
+                "0": {
+                    "@(2,items[*].id)": "ids",
+                    "@(2,items[*].tags[*])": "tags",
+                    "@(2,items[*].missing)": "missing"
+                }
+            }
+        }))
+        .expect("parsed spec");
+        let input = json!({
+            "items": [
+                {"id": 1, "tags": ["a"]},
+                {"id": null, "tags": ["b", "c"]},
+                {"name": "no id"}
+            ]
+        });
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({"ids": [1, null], "tags": ["a", "b", "c"], "missing": []})
+        );
+    }
+
+    #[test]
+    fn test_gather_in_output_path() {
+        let shift = serde_json::from_value::<Shift>(json!({"a": "out[*].a"}));
+
+        assert!(shift.is_err());
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_match_prefix_suffix() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "user_*": "users.&(0,1)",
+            "*_count": "counts.&(0,1)"
+        }))
+        .expect("parsed spec");
+        let input = json!({"user_name": "John", "page_count": 3, "user_count": 1, "count_x": 0});
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        assert_eq!(
+            result.unwrap(),
+            json!({"users": {"name": "John", "count": 1}, "counts": {"page": 3}})
+        );
+    }
+
+    #[test]
+    fn test_match_suffix_not_anchored() {
+        //given
+        let shift: Shift = serde_json::from_value(json!({
+            "*_id": "ids.&(0,1)",
+            "*_a*": "pieces.&(0,1)"
+        }))
+        .expect("parsed spec");
+        let input = json!({"order_id": 1, "user_id_old": 2, "x_id_y_id": 3, "b_a_c": 4});
+
+        //when
+        let result = shift.apply(&input, &ShiftOptions::default(), &Context::default());
+
+        //then
+        // the literal after a leading star isn't anchored at the end of the key, the star
+        // captures the text before its first occurrence, like with more stars
+        assert_eq!(
+            result.unwrap(),
+            json!({"ids": {"order": 1, "user": 2, "x": 3}, "pieces": {"b": 4}})
+        );
+    }
+}
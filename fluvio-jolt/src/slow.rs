@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+use std::io;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::{apply_chain, Context, Result, TransformOptions, TransformSpec};
+
+/// Report of a transformation taking at least the
+/// [`slow_transform_threshold`](crate::TransformOptions::slow_transform_threshold) of the
+/// options, passed to [`TransformObserver::on_slow_transform`](crate::TransformObserver::on_slow_transform)
+/// to find the pathological records of a topic without tracing every transformation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowTransform {
+    /// Fingerprint of the specification, see [`TransformSpec::fingerprint`].
+    pub spec_fingerprint: u64,
+    /// Length of the input serialized as compact JSON, in bytes.
+    pub record_size: usize,
+    /// Duration of the whole transformation.
+    pub elapsed: Duration,
+    /// Durations of the operations applied, in the order of the chain. The operations skipped by
+    /// their `enabled` or `when` fields are not listed.
+    pub operations: Vec<OperationTiming>,
+}
+
+/// Duration of an operation of a [`SlowTransform`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationTiming {
+    /// Index of the operation in the chain.
+    pub index: usize,
+    /// Kind of the operation, e.g. `shift`.
+    pub kind: &'static str,
+    /// The `name` field of the operation, if any.
+    pub name: Option<String>,
+    /// Duration of the operation.
+    pub elapsed: Duration,
+}
+
+/// Applies the chain like [`apply_chain`], reporting the transformation to the observer of the
+/// options if it takes longer than the threshold.
+pub(crate) fn apply_timed(
+    input: Value,
+    spec: &TransformSpec,
+    options: &TransformOptions,
+    context: &Context,
+    threshold: Duration,
+) -> Result<Option<Value>> {
+    let Some(observer) = options.get_observer() else {
+        return apply_chain(Cow::Owned(input), spec, options, context, |_| {});
+    };
+    let record_size = serialized_len(&input);
+    let mut operations = Vec::new();
+    let start = Instant::now();
+    let mut last = start;
+    let result = apply_chain(Cow::Owned(input), spec, options, context, |applied| {
+        let now = Instant::now();
+        operations.push(OperationTiming {
+            index: applied.index,
+            kind: applied.operation.kind(),
+            name: applied.operation.as_ref().name().map(str::to_owned),
+            elapsed: now - last,
+        });
+        last = now;
+    });
+    let elapsed = start.elapsed();
+    if elapsed >= threshold {
+        observer.on_slow_transform(&SlowTransform {
+            spec_fingerprint: spec.fingerprint(),
+            record_size,
+            elapsed,
+            operations,
+        });
+    }
+    result
+}
+
+// Length of the value serialized as compact JSON, without allocating the serialized value
+fn serialized_len(value: &Value) -> usize {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    match serde_json::to_writer(&mut counter, value) {
+        Ok(()) => counter.0,
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+    use super::*;
+    use crate::{transform_with_options, TransformObserver};
+
+    #[derive(Default)]
+    struct Reports(Mutex<Vec<SlowTransform>>);
+
+    impl TransformObserver for Reports {
+        fn on_slow_transform(&self, report: &SlowTransform) {
+            self.0.lock().unwrap().push(report.clone());
+        }
+    }
+
+    #[test]
+    fn test_slow_transform() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "name": "move", "spec": {"id": "data.id"}},
+            {"operation": "remove", "enabled": false, "spec": {"data": ""}},
+            {"operation": "default", "spec": {"data": {"kind": "user"}}}
+        ]))
+        .unwrap();
+        let reports = Arc::new(Reports::default());
+        let slow = TransformOptions::default()
+            .observer(reports.clone())
+            .slow_transform_threshold(Duration::ZERO);
+        let fast = TransformOptions::default()
+            .observer(reports.clone())
+            .slow_transform_threshold(Duration::from_secs(60));
+
+        //when
+        let output = transform_with_options(json!({"id": 1}), &spec, &slow);
+        transform_with_options(json!({"id": 2}), &spec, &fast).unwrap();
+
+        //then
+        assert_eq!(output.unwrap(), json!({"data": {"id": 1, "kind": "user"}}));
+        let reports = reports.0.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.spec_fingerprint, spec.fingerprint());
+        assert_eq!(report.record_size, r#"{"id":1}"#.len());
+        let operations: Vec<_> = report
+            .operations
+            .iter()
+            .map(|op| (op.index, op.kind, op.name.as_deref()))
+            .collect();
+        assert_eq!(
+            operations,
+            vec![(0, "shift", Some("move")), (2, "default", None)]
+        );
+        assert!(
+            report
+                .operations
+                .iter()
+                .map(|op| op.elapsed)
+                .sum::<Duration>()
+                <= report.elapsed
+        );
+    }
+}
@@ -1,6 +1,30 @@
-use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
-use crate::{JsonPointer, shift::Shift};
+use crate::{
+    Context, Error, JsonPointer, migrate, MigrationNote, SpecStats, TransformOptions,
+    diff::{self, SpecChange},
+    compat::{self, CompatibilityReport},
+    constants::{self, Constants},
+    strict,
+    sandbox::Budget,
+    expects::Expects,
+    envelope::Envelope,
+    embedded_tests::{EmbeddedTest, EmbeddedTestFailure},
+    shift::{Shift, ShiftOptions},
+    default::DefaultOptions,
+    retain::Retain,
+    identity::Identity,
+    halt::{HaltIf, HaltOptions, When},
+    sample::Sample,
+    rename::Rename,
+    try_catch::{self, TryOptions},
+    ir::{DefaultValue, OperationIr, SpecIr, IR_VERSION},
+};
 
 /// The JSON transformation specification.
 ///
@@ -22,6 +46,243 @@ use crate::{JsonPointer, shift::Shift};
 ///   ]"#).unwrap();
 /// ```
 ///
+/// ### Toggling operations
+/// Every operation accepts the following optional fields next to its `spec`, so a step can be
+/// turned on and off per environment with the same specification:
+///  1. `enabled` - the operation is skipped if `false`, `true` by default
+///  2. `when` - the operation is applied only if the condition holds for the current document.
+///     The condition is written like the spec of the [`halt-if`](#halt-if-operation) operation, or
+///     as `{"exists": "/pointer"}` with a RFC 6901 pointer of a value that must be present
+///
+/// For example, to add the debug fields only to the records having headers:
+/// <pre>
+/// [
+///     {
+///         "operation": "default",
+///         "when": {"exists": "/headers"},
+///         "spec": {
+///             "debug": true
+///         }
+///     },
+///     {
+///         "operation": "remove",
+///         "enabled": false,
+///         "spec": {
+///             "headers": ""
+///         }
+///     }
+/// ]
+/// </pre>
+///
+/// ### Scoping operations
+/// The optional `root` field of an operation is a RFC 6901 pointer of the part of the document
+/// the operation applies to. The operation transforms the value at the pointer as if it were the
+/// whole document, the rest of the document passes through untouched. The operation is skipped
+/// if the document has no value at the pointer. For example, to rename the fields of a payload
+/// while keeping the metadata of the record, without copying every sibling with `"*": "payload.&"`:
+/// <pre>
+/// [
+///     {
+///         "operation": "shift",
+///         "root": "/payload",
+///         "spec": {
+///             "id": "user_id",
+///             "*": "&"
+///         }
+///     }
+/// ]
+/// </pre>
+///
+/// ### Input expectations
+/// The specification can also be written as an object with the `operations` of the chain and an
+/// `expects` block, mapping the RFC 6901 pointers of the values the input requires to their
+/// types: `null`, `boolean`, `number`, `string`, `array`, `object`, or `any` type. A list of types
+/// allows any of them. The input is checked before any operation is applied, and the
+/// transformation fails with [`Error::UnmetExpectations`](crate::Error::UnmetExpectations)
+/// listing every missing or mistyped value, instead of producing an empty output from a malformed
+/// input:
+/// <pre>
+/// {
+///     "expects": {
+///         "/id": "number",
+///         "/account/type": ["string", "null"]
+///     },
+///     "operations": [
+///         {
+///             "operation": "shift",
+///             "spec": {
+///                 "id": "data.id",
+///                 "account": "data.account"
+///             }
+///         }
+///     ]
+/// }
+/// </pre>
+///
+/// ### Embedded tests
+/// A `tests` block, next to the `operations` of the chain, lists test cases with an `input`, the
+/// `expected` output, `null` for the documents dropped by the chain, and an optional `name`. The
+/// cases are run by [`TransformSpec::run_embedded_tests`], or by the `fluvio-jolt test` command,
+/// so the specification files validate themselves:
+/// <pre>
+/// {
+///     "tests": [
+///         {
+///             "name": "moves the id",
+///             "input": {"id": 1},
+///             "expected": {"data": {"id": 1}}
+///         }
+///     ],
+///     "operations": [
+///         {
+///             "operation": "shift",
+///             "spec": {
+///                 "id": "data.id"
+///             }
+///         }
+///     ]
+/// }
+/// </pre>
+///
+/// ### Output envelope
+/// An `envelope` block, next to the `operations` of the chain, wraps the output of the chain in a
+/// standard envelope, instead of every specification mapping its output to the envelope. The
+/// envelope has the `schema_version` of the block, the `produced_at` time in milliseconds since
+/// the Unix epoch, read from the clock of the [`Context`], unless `produced_at` is `false`, and
+/// the output under the `data` key, or the key named by `data`. The dropped documents aren't
+/// wrapped. [`transform_stepwise`](crate::transform_stepwise) wraps only its last document, the
+/// output of the chain. The embedded tests are run with the clock at the epoch, so their `produced_at` is `0`:
+/// <pre>
+/// {
+///     "envelope": {
+///         "schema_version": "2.1"
+///     },
+///     "tests": [
+///         {
+///             "input": {"id": 1},
+///             "expected": {"schema_version": "2.1", "produced_at": 0, "data": {"id": 1}}
+///         }
+///     ],
+///     "operations": [
+///         {
+///             "operation": "shift",
+///             "spec": {
+///                 "id": "id"
+///             }
+///         }
+///     ]
+/// }
+/// </pre>
+///
+/// ### Constants
+/// A `constants` object, next to the `operations` of the chain or next to the `spec` of a `shift`
+/// operation, defines values referenced as `${name}` from the output paths and the `#` literals of
+/// the `shift` operations, so the values repeated by a spec are written once. The constants of an
+/// operation replace the constants of the chain with the same name. A constant is a string, a
+/// number or a boolean, and it is inserted as a single key, so the dots of its value don't split
+/// the output path:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!({
+///     "constants": {"version": "v2", "source": "crm"},
+///     "operations": [
+///         {
+///             "operation": "shift",
+///             "constants": {"source": "erp"},
+///             "spec": {
+///                 "id": "${version}.id",
+///                 "#${source}": "${version}.source"
+///             }
+///         }
+///     ]
+/// }))
+/// .unwrap();
+///
+/// assert_eq!(
+///     transform(json!({"id": 1}), &spec).unwrap(),
+///     json!({"v2": {"id": 1, "source": "erp"}})
+/// );
+/// ```
+/// An escaped `\${name}` is the literal `${name}`, like the other escaped characters, and the
+/// specs of a chain without constants are parsed as they are.
+///
+/// ### Naming operations
+/// Every operation accepts an optional `name` field. The errors of a named operation are
+/// reported as [`Error::Operation`](crate::Error::Operation) with the name and the index of the
+/// operation in the chain, wrapping the error of the operation and keeping its
+/// [`code`](crate::Error::code), e.g. `Operation 'normalize-address' (index 3) failed: ...`. The
+/// parsing errors of a named operation are prefixed by its name as well.
+/// <pre>
+/// [
+///     {
+///         "operation": "shift",
+///         "name": "normalize-address",
+///         "spec": {
+///             "street|city": "address.&"
+///         }
+///     }
+/// ]
+/// </pre>
+///
+/// ### Documenting operations
+/// The optional `description` field of an operation, or its `docs` alias, and the `x-docs`
+/// object of notes on the keys of its `spec` are ignored by the transformation. They are kept
+/// along with the operation, see [`OperationRef::description`] and [`OperationRef::docs`], e.g.
+/// to generate data dictionaries. The notes are keyed by the dot-notation path of the keys, the
+/// dots of a key being escaped with `\`:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::TransformSpec;
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "description": "Moves the user fields under `data`",
+///         "x-docs": {
+///             "user.name": "Full name of the user",
+///             "user.id": "Identifier of the account"
+///         },
+///         "spec": {
+///             "user": {
+///                 "name": "data.name",
+///                 "id": "data.id"
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// let operation = spec.get(0).unwrap();
+/// assert_eq!(operation.description(), Some("Moves the user fields under `data`"));
+/// assert_eq!(operation.doc("user.name"), Some("Full name of the user"));
+/// assert_eq!(operation.docs().count(), 2);
+/// ```
+///
+/// ### Strict parsing
+/// The fields of the operations unknown to their kind are ignored, so a typo like `"specs"` or
+/// `"sparse_array"` silently falls back to the defaults. With `"strict": true` next to the
+/// `operations` of the chain, the unknown fields and operations fail the parsing, naming the
+/// closest valid field or operation:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::TransformSpec;
+///
+/// let error = serde_json::from_value::<TransformSpec>(json!({
+///     "strict": true,
+///     "operations": [
+///         {"operation": "shift", "specs": {"id": "data.id"}}
+///     ]
+/// }))
+/// .unwrap_err();
+///
+/// assert_eq!(
+///     error.to_string(),
+///     "unknown field `specs` of operation `shift`, did you mean `spec`?"
+/// );
+/// ```
+///
 /// ### `Shift` operation
 /// Specifies where the data from the input JSON should be placed in the output JSON, or in other
 /// words, how the input JSON/data should be shifted around to make the output JSON/data.
@@ -143,6 +404,402 @@ use crate::{JsonPointer, shift::Shift};
 /// }
 /// </pre>
 ///
+/// #### Multiple destinations
+/// The value can be written to several destinations with an array of output paths or with the
+/// output paths separated by `|`. These specs are equivalent:
+/// <pre>
+/// {
+///     "id": ["data.id", "meta.key"]
+/// }
+/// </pre>
+/// <pre>
+/// {
+///     "id": "data.id|meta.key"
+/// }
+/// </pre>
+///
+/// #### Missing lookups
+/// Like in Java Jolt, when an `@` lookup references a missing key or array index, the value is not
+/// written. With the `strict_lookups` option, the transformation fails with
+/// [`Error::KeyNotFound`](crate::Error::KeyNotFound) or
+/// [`Error::ArrIndexOutOfRange`](crate::Error::ArrIndexOutOfRange) instead.
+///
+/// #### Required and optional keys
+/// A literal key or an `@` lookup ending with `!` is required, one ending with `?` is optional.
+/// The transformation fails with [`Error::MissingRequiredKey`](crate::Error::MissingRequiredKey)
+/// when the input doesn't have a required literal key. A required lookup is strict and an optional
+/// one isn't, whatever the `strict_lookups` option. Here the `id` must be present and the missing
+/// nickname doesn't fail the strict lookups:
+/// <pre>
+/// {
+///     "operation": "shift",
+///     "strict_lookups": true,
+///     "spec": {
+///         "id!": "data.id",
+///         "@(0,profile.name)": "data.name",
+///         "@(0,profile.nickname)?": "data.nickname"
+///     }
+/// }
+/// </pre>
+/// Other keys ending with `?` or `!` are literals, e.g. `#yes!`, and a literal key ending with
+/// `?` or `!` is written with `\?` or `\!`.
+///
+/// #### Gathering array values
+/// `[*]` in the path of an `@` lookup gathers the values of the rest of the path in all the
+/// elements of the array into an array, skipping the elements where they are missing. The values
+/// gathered by several `[*]` are flattened into a single array:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "order": {
+///                 "@(0,items[*].id)": "item_ids",
+///                 "@(0,items[*].tags[*])": "tags"
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// let input = json!({
+///     "order": {
+///         "items": [
+///             {"id": 1, "tags": ["a", "b"]},
+///             {"id": 2, "tags": ["c"]},
+///             {"tags": []}
+///         ]
+///     }
+/// });
+///
+/// assert_eq!(
+///     transform(input, &spec).unwrap(),
+///     json!({"item_ids": [1, 2], "tags": ["a", "b", "c"]})
+/// );
+/// ```
+///
+/// #### Typed captures
+/// `$` emits the matched keys as strings. With the `typed_captures` option set next to the `spec`
+/// field of the operation, array indexes are emitted as numbers and matched booleans, numbers and
+/// `null` keep their original type:
+/// <pre>
+/// {
+///     "operation": "shift",
+///     "typed_captures": true,
+///     "spec": {
+///         "items": {
+///             "*": {
+///                 "$": "indexes[]"
+///             }
+///         }
+///     }
+/// }
+/// </pre>
+///
+/// #### Formatting captured keys
+/// `:snake`, `:kebab`, `:camel` or `:pascal` right after a `&` capture converts the naming
+/// convention of the matched key, splitting its words at the `_`, `-` and spaces and at the case
+/// changes. `:lower` and `:upper` only change the case of the letters. A single wildcard rule
+/// renames all the fields, a key following the case name is written after the converted key:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "*": "&:snake",
+///             "Links": {
+///                 "*": "links.&:lower_url"
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// assert_eq!(
+///     transform(
+///         json!({"UserId": 1, "HTTPStatus": 200, "Links": {"Home": "/"}}),
+///         &spec
+///     )
+///     .unwrap(),
+///     json!({"user_id": 1, "http_status": 200, "links": {"home_url": "/"}})
+/// );
+/// ```
+///
+/// `:%0Nd` pads a numeric capture with zeros to N digits, e.g. when the elements of an array are
+/// keyed by their index, so the keys sort in the order of the elements. Captures that are not
+/// non-negative integers are written as they are:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "lines": {
+///                 "*": "lines.line-&(0):%03d"
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// assert_eq!(
+///     transform(json!({"lines": ["a", "b"]}), &spec).unwrap(),
+///     json!({"lines": {"line-000": "a", "line-001": "b"}})
+/// );
+/// ```
+///
+/// #### Literal values
+/// `#` followed by a value writes the value instead of an input value, e.g. `"#user": "kind"`
+/// writes `"user"` to `kind`, the dots of the value don't need to be escaped. The `&` captures and the `[#N]` counters of its output path are
+/// those of the key it belongs to, so a literal is written to the same array element as the
+/// values matched next to it. The literals are strings, with the `typed_literals` option
+/// literals that are JSON numbers, booleans or `null` are written with their type:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "typed_literals": true,
+///         "spec": {
+///             "items": {
+///                 "*": {
+///                     "id": "out[&(1)].id",
+///                     "#1": "out[&(1)].version",
+///                     "#true": "out[#2].imported"
+///                 }
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// assert_eq!(
+///     transform(json!({"items": [{"id": "a"}, {"id": "b"}]}), &spec).unwrap(),
+///     json!({
+///         "out": [
+///             {"id": "a", "version": 1, "imported": true},
+///             {"id": "b", "version": 1, "imported": true}
+///         ]
+///     })
+/// );
+/// ```
+///
+/// #### Grouping values
+/// An `@` lookup in the output path writes under a key read from the input, and `[]` after it
+/// appends to the array of that key, so the elements of an array are grouped by one of their
+/// fields in a single `shift`. On the left hand side, `@key.path` is the shorthand of
+/// `@(0,key.path)`:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "events": {
+///                 "*": {
+///                     "@value": "totals.@(1,type)[]"
+///                 }
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// let input = json!({
+///     "events": [
+///         {"type": "click", "value": 1},
+///         {"type": "view", "value": 2},
+///         {"type": "click", "value": 3}
+///     ]
+/// });
+///
+/// assert_eq!(
+///     transform(input, &spec).unwrap(),
+///     json!({"totals": {"click": [1, 3], "view": [2]}})
+/// );
+/// ```
+///
+/// #### Appending to arrays
+/// `[]` at the end of the output path appends the value to the output array. When sibling keys
+/// append to the same array, the values are appended in the order of the input keys. The
+/// `append_order` option set to `spec` appends them in the order of the spec instead (literal keys
+/// first, then `&` keys and then wildcards, each group in the order of the spec).
+/// The `dedup_appends` option skips values that are already present in the array:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "append_order": "spec",
+///         "dedup_appends": true,
+///         "spec": {
+///             "primary": "tags[]",
+///             "secondary": "tags[]",
+///             "*": "tags[]"
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// let input = json!({
+///     "other": "c",
+///     "primary": "a",
+///     "secondary": "b",
+///     "duplicate": "b"
+/// });
+///
+/// assert_eq!(
+///     transform(input, &spec).unwrap(),
+///     json!({ "tags": ["a", "b", "c"] })
+/// );
+/// ```
+///
+/// `[?]` at the end of the output path skips duplicates of a single destination, e.g.
+/// `"*": "tags[?]"` collects the distinct values, while the other `[]` of the spec still append
+/// every value.
+///
+/// #### Match counters
+/// `[#N]` in the output path writes to the array index equal to the number of keys matched before
+/// the key `N` levels up, `#1` being the level of the key the output path belongs to. Unlike
+/// `[&(N-1)]`, the index doesn't depend on the input keys, so maps can be turned into arrays and
+/// values matched by the same key are grouped into the same element:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "users": {
+///                 "*": {
+///                     "$": "users[#2].id",
+///                     "name": "users[#2].name"
+///                 }
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// let input = json!({
+///     "users": {
+///         "u1": { "name": "John" },
+///         "u2": { "name": "Jane" }
+///     }
+/// });
+///
+/// assert_eq!(
+///     transform(input, &spec).unwrap(),
+///     json!({
+///         "users": [
+///             { "id": "u1", "name": "John" },
+///             { "id": "u2", "name": "Jane" }
+///         ]
+///     })
+/// );
+/// ```
+///
+/// #### Lookup indexes
+/// `[@(N,path)]` in the output path writes to the array index looked up in the input, a number or
+/// a string of digits. The output path can contain several lookup indexes, e.g. to place the
+/// values of a flat list into nested arrays in one pass. A lookup that doesn't evaluate to a
+/// non-negative integer fails with [`Error::InvalidIndexVal`](crate::Error::InvalidIndexVal):
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "items": {
+///                 "*": {
+///                     "sku": "orders[@(1,order)].lines[@(1,line)].sku"
+///                 }
+///             }
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// let input = json!({
+///     "items": [
+///         { "order": 0, "line": 1, "sku": "b" },
+///         { "order": "0", "line": 0, "sku": "a" }
+///     ]
+/// });
+///
+/// assert_eq!(
+///     transform(input, &spec).unwrap(),
+///     json!({ "orders": [{ "lines": [{ "sku": "a" }, { "sku": "b" }] }] })
+/// );
+/// ```
+///
+/// Likewise, `[&(N,M)]` and `[$(N,M)]` write to the array index matched by a key of the input, the
+/// index of an array element or a key of digits. `$` keeps the type of the matched key like with
+/// `typed_captures`, so a match that isn't a non-negative integer fails with
+/// [`Error::InvalidIndex`](crate::Error::InvalidIndex) showing the matched value, e.g. `true` for
+/// a boolean value matched by the `true` key.
+///
+/// #### Sparse arrays
+/// Writing to an array index like `foo[5]` pads the skipped indexes with `null`. The
+/// `sparse_arrays` option controls this behavior:
+///  1. `pad` (default) - skipped indexes are padded with `null`
+///  2. `compact` - the padding left unwritten is removed once the shift operation completes, the
+///     `null` values written explicitly or copied from the input are kept. Each shift of a chain
+///     compacts its own output, the arrays written by the other operations are left as they are
+///  3. `error` - the shift fails when skipped indexes are left unwritten once it completes, so
+///     `out[1]` may be written before `out[0]`
+///
+/// Writes skipping more than [`MAX_ARRAY_PADDING`](crate::MAX_ARRAY_PADDING) indexes fail in
+/// every mode.
+///
+/// #### Conflicting writes
+/// Like in Java Jolt, values written to the same output path are collected into an array, and a
+/// value that is not an object is replaced when an output path goes through it. The `conflicts`
+/// option controls this behavior:
+///  1. `merge` (default) - the values are collected into an array or replaced
+///  2. `overwrite` - the later write replaces the earlier value
+///  3. `error` - writes through values that are not objects or arrays, and writes of an object
+///     and another value to the same output path fail with
+///     [`Error::OutputConflict`](crate::Error::OutputConflict) naming both output paths
+///
+/// #### Null values
+/// A missing key is never matched and its `@` lookups are missing lookups. The `nulls` option
+/// controls how a key present with a `null` value is handled:
+///  1. `key` (default) - the key is matched, `@` lookups of it return `null` and the nested keys
+///     of the spec match the `null` value as the `"null"` key, e.g. `*` captures `"null"` for `$`
+///  2. `value` - the key is matched, `@` lookups of it return `null`, the nested keys of the spec
+///     don't match the `null` value and `@` lookups of keys inside it are missing lookups
+///  3. `absent` - the key is handled as a missing key
+///
+/// #### Match order
+/// An input key is matched by the first matching key of the spec: literal keys first, then `&`
+/// keys and then the wildcard and `|` keys. The `match_order` option controls the order in which
+/// the wildcard and `|` keys are tried, visible when an input key is matched by several of them:
+///  1. `spec` (default) - in the order of the spec
+///  2. `alphabetical` - in the alphabetical order of the keys
+///  3. `java` - in the order of Java Jolt: keys with two `*`, keys with one `*`, keys with more
+///     `*`, `|` keys and the `*` key last, keys of the same kind from the longest to the shortest
+///
+/// The order can be set for all `shift` operations with
+/// [`TransformOptions::match_order`](crate::TransformOptions::match_order).
+///
 /// ### `Default` operation
 /// Applies default values if the value is not present in the input JSON.
 ///
@@ -176,6 +833,27 @@ use crate::{JsonPointer, shift::Shift};
 /// </pre>
 /// As you can see, the field `mobile` remains not affected while the `code` has a default '+1' value.
 ///
+/// By default, only absent fields are considered missing. The operation accepts the following
+/// options next to the `spec` field:
+///  1. `replace_null` - also apply default values to fields that are `null`
+///  2. `replace_empty_string` - also apply default values to fields that are empty strings
+///  3. `expressions` - evaluate string values starting with `&` or `@` against the input, like
+///     the left hand side of `shift`, as if the missing field was matched. For example,
+///     `"display_name": "@(1,first_name)"` defaults to the `first_name` sibling, and
+///     `"tenant": "@context(tenant)"` to the `tenant` variable of the [`Context`]. The field is
+///     left missing if the lookup fails or the variable isn't set
+/// <pre>
+/// {
+///     "operation": "default",
+///     "replace_null": true,
+///     "spec": {
+///         "phones": {
+///             "code": "+1"
+///         }
+///     }
+/// }
+/// </pre>
+///
 /// ### `Remove` operation
 /// Removes content from the input JSON.
 /// The spec structure matches the input JSON structure. The value of fields is ignored.
@@ -205,34 +883,1172 @@ use crate::{JsonPointer, shift::Shift};
 ///     }
 /// }
 /// </pre>
-#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
-pub struct TransformSpec(Vec<SpecEntry>);
+///
+/// ### `Retain` operation
+/// The inverse of `remove`: keeps only the content listed in the spec and drops everything else.
+/// The operation is also available under the `whitelist` name.
+/// The spec structure matches the input JSON structure. Keys support the `*` and `|` wildcards
+/// of the `shift` operation, array elements are matched by their index. If the value of a field is
+/// an object, only the listed children of the matched value are kept, otherwise the matched value
+/// is kept as a whole.
+///
+///  For example, given this simple input JSON:
+///  <pre>
+/// {
+///     "id": 1,
+///     "phones": {
+///         "mobile": 01234567,
+///         "country": "US"
+///     },
+///     "tracking": {
+///         "session": "a1b2"
+///     }
+/// }
+/// </pre>
+/// with the following specification for `retain` operation:
+/// <pre>
+/// {
+///     "id": "",
+///     "phones": {
+///         "mob*": ""
+///     }
+/// }
+/// </pre>
+/// the output JSON will be:
+/// <pre>
+/// {
+///     "id": 1,
+///     "phones": {
+///         "mobile": 01234567
+///     }
+/// }
+/// </pre>
+///
+/// ### `Identity` operation
+/// Copies the whole input to the output. The spec is optional, it is an object with the following
+/// optional fields:
+///  1. `prefix` - dot notation path the input is placed under
+///  2. `depth` - maximum number of nested objects and arrays copied, deeper values are dropped
+///  3. `breadth` - maximum number of entries copied from every object and array
+///
+/// For example, to keep the original payload under `raw` before extracting a few fields from it:
+/// <pre>
+/// [
+///     {
+///         "operation": "identity",
+///         "spec": {
+///             "prefix": "raw"
+///         }
+///     },
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "raw": {
+///                 "@": "raw",
+///                 "id": "id"
+///             }
+///         }
+///     }
+/// ]
+/// </pre>
+///
+/// Without a spec, the operation passes the input through unchanged. It is also available under
+/// the `noop` name, e.g. to scaffold a chain whose steps are filled in later, or as the explicit
+/// "do nothing" step of a chain:
+/// <pre>
+/// [
+///     {
+///         "operation": "noop",
+///         "name": "enrich-later"
+///     }
+/// ]
+/// </pre>
+///
+/// ### `Rename` operation
+/// Renames the keys of the objects to a naming convention, e.g. the `camelCase` fields of an
+/// upstream service to `snake_case`. The spec is an object with the following fields:
+///  1. `convention` - `snake_case`, `kebab-case`, `camelCase`, `PascalCase`, `lowercase` or
+///     `UPPERCASE`, the same conversions as the `&:case` captures of the `shift` operation
+///  2. `recursive` - whether the keys of the nested objects, also in arrays, are renamed, `true`
+///     by default
+///  3. `except` - optional list of the keys kept as they are, at any level
+///
+/// When several keys of an object are renamed to the same key, the last one wins.
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "rename",
+///         "spec": {
+///             "convention": "snake_case",
+///             "except": ["eTag"]
+///         }
+///     }
+/// ]))
+/// .unwrap();
+///
+/// assert_eq!(
+///     transform(
+///         json!({"orderId": 1, "eTag": "x", "lineItems": [{"unitPrice": 2}]}),
+///         &spec
+///     )
+///     .unwrap(),
+///     json!({"order_id": 1, "eTag": "x", "line_items": [{"unit_price": 2}]})
+/// );
+/// ```
+///
+/// ### `Try` operation
+/// Applies the chain of its `spec` to the document, or the fallback chain of its `on_error` field
+/// to the original document when the chain fails, e.g. when a `shift` with `strict_lookups` looks
+/// into an optional structure. Without `on_error`, the document passes through unchanged when
+/// the chain fails. The nested chains are lists of operations, parsed with the constants of the
+/// enclosing chain. The errors of the [`Sandbox`](crate::Sandbox) limits and of the `fuel` are
+/// not caught. A `halt-if` operation halts the nested chain only, a dropped document is `null`.
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {
+///         "operation": "try",
+///         "spec": [
+///             {
+///                 "operation": "shift",
+///                 "strict_lookups": true,
+///                 "spec": {"@(0,address.city)": "city"}
+///             }
+///         ],
+///         "on_error": [
+///             {"operation": "default", "spec": {"city": "unknown"}}
+///         ]
+///     }
+/// ]))
+/// .unwrap();
+///
+/// assert_eq!(
+///     transform(json!({"address": {"city": "Paris"}}), &spec).unwrap(),
+///     json!({"city": "Paris"})
+/// );
+/// assert_eq!(
+///     transform(json!({"name": "John"}), &spec).unwrap(),
+///     json!({"name": "John", "city": "unknown"})
+/// );
+/// ```
+///
+/// ### `Halt-if` operation
+/// Stops the chain when the condition holds, the following operations are not applied. The spec
+/// is an object with the dot notation `path` of the tested value and one of the conditions:
+///  1. `equals` - the value is present and equal to the given one
+///  2. `not_equals` - the value is absent or not equal to the given one
+///  3. `exists` - the value is present if `true`, absent if `false`
+///
+/// The current document is returned, or dropped with the `drop` option: [`transform`](crate::transform)
+/// returns `null` and [`transform_filtered`](crate::transform_filtered) returns `None`.
+///
+/// For example, to skip the expensive operations for records that are not orders and drop them:
+/// <pre>
+/// [
+///     {
+///         "operation": "halt-if",
+///         "drop": true,
+///         "spec": {
+///             "path": "type",
+///             "not_equals": "order"
+///         }
+///     },
+///     {
+///         "operation": "shift",
+///         "spec": {
+///             "items": "order.items"
+///         }
+///     }
+/// ]
+/// </pre>
+///
+/// ### `Sample` operation
+/// Passes only a fraction of the documents through the rest of the chain, the other documents
+/// are dropped like with the `drop` option of `halt-if`. The spec is an object with one of the
+/// fields:
+///  1. `rate` - fraction of the documents between 0 and 1, the passed documents are evenly spaced
+///  2. `every_n` - every n-th document, starting with the first one
+///  3. `probability` - every document with the probability between 0 and 1, drawn from the
+///     [`RandomSource`](crate::RandomSource) of the [`Context`], so the sampled documents are
+///     reproducible with a seeded context
+///
+/// The documents are counted by the [`Context`] they are transformed with, so the count is shared
+/// by all the threads applying the specification with the same context, e.g. a
+/// [`JoltEngine`](crate::JoltEngine). The functions creating their own context, like
+/// [`transform`](crate::transform), the embedded tests or
+/// [`transform_stepwise`](crate::transform_stepwise), count every document from zero. For
+/// example, to pass one record out of a hundred to a debug topic:
+/// <pre>
+/// [
+///     {
+///         "operation": "sample",
+///         "spec": {
+///             "every_n": 100
+///         }
+///     }
+/// ]
+/// </pre>
+///
+/// ### Serialization
+/// A specification serializes to the JSON it is parsed from, e.g. after it is built from
+/// operations. The `spec` of a `shift` operation is written from its parsed expressions with the
+/// constants substituted, the keys with special characters are escaped again, so the serialized
+/// specification parses into the same specification. [`escape_key`](crate::escape_key) escapes
+/// the keys the same way for the tools generating specifications:
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{escape_key, TransformSpec};
+///
+/// let key = escape_key("user.name");
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {key: "name", "\\@id": "id"}}
+/// ]))
+/// .unwrap();
+///
+/// let serialized = serde_json::to_value(&spec).unwrap();
+/// assert_eq!(
+///     serialized,
+///     json!([{"operation": "shift", "spec": {"user\\.name": "name", "\\@id": "id"}}])
+/// );
+/// assert_eq!(serde_json::from_value::<TransformSpec>(serialized).unwrap(), spec);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransformSpec {
+    operations: Vec<Operation>,
+    expects: Expects,
+    tests: Vec<EmbeddedTest>,
+    envelope: Option<Envelope>,
+}
+
+/// An operation of the chain along with the JSON it is parsed from.
+///
+/// A single operation can be parsed and applied without the chain, e.g. by embedders with their
+/// own orchestration of the operations. It is parsed from its kind and its `spec` field, or from
+/// the whole operation object, including the options:
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{Context, Operation, TransformOptions, TransformSpec};
+///
+/// let shift = Operation::parse("shift", json!({"id": "data.id"})).unwrap();
+/// let default: Operation =
+///     serde_json::from_value(json!({"operation": "default", "spec": {"data": {"kind": "user"}}}))
+///         .unwrap();
+///
+/// let options = TransformOptions::default();
+/// let context = Context::default();
+/// let shifted = shift.apply(json!({"id": 1}), &options, &context).unwrap().unwrap();
+/// let output = default.apply(shifted, &options, &context).unwrap();
+///
+/// assert_eq!(output, Some(json!({"data": {"id": 1, "kind": "user"}})));
+///
+/// // the operations can also be chained
+/// let spec: TransformSpec = [shift, default].into_iter().collect();
+/// assert_eq!(spec.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Operation {
+    entry: SpecEntry,
+    raw: Value,
+    fields: OperationFields,
+}
 
+/// The fields common to all the operations, next to the `spec` field.
 #[derive(Debug, Deserialize, Clone, PartialEq)]
-#[serde(tag = "operation", content = "spec")]
+pub(crate) struct OperationFields {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default = "enabled")]
+    enabled: bool,
+    #[serde(default)]
+    when: Option<When>,
+    #[serde(default, deserialize_with = "root_pointer")]
+    root: Option<String>,
+    #[serde(default)]
+    constants: Constants,
+    #[serde(default, alias = "docs")]
+    description: Option<String>,
+    #[serde(default, rename = "x-docs")]
+    docs: BTreeMap<String, String>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn root_pointer<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let pointer = String::deserialize(deserializer)?;
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Err(D::Error::custom(format!(
+            "expected a RFC 6901 pointer as the root, found `{pointer}`"
+        )));
+    }
+    Ok(Some(pointer))
+}
+
+/// Read-only access to an operation of the [`TransformSpec`] chain.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::TransformSpec;
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"a": "b"}},
+///     {"operation": "default", "spec": {"c": "d"}},
+///     {"operation": "remove", "spec": {"a": ""}}
+/// ]))
+/// .unwrap();
+///
+/// assert_eq!(spec.len(), 3);
+/// assert_eq!(spec.get(1).unwrap().kind(), "default");
+/// assert_eq!(spec.get(1).unwrap().spec(), &json!({"c": "d"}));
+///
+/// // the chain without the `remove` operation
+/// let disabled: TransformSpec = spec.iter().filter(|op| op.kind() != "remove").collect();
+/// assert_eq!(disabled.len(), 2);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct OperationRef<'a>(&'a Operation);
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(tag = "operation")]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum SpecEntry {
-    Shift(Shift),
-    Default(Spec),
-    Remove(Spec),
+    Shift {
+        spec: Shift,
+        #[serde(flatten)]
+        options: ShiftOptions,
+    },
+    Default {
+        spec: Spec,
+        #[serde(flatten)]
+        options: DefaultOptions,
+    },
+    Remove {
+        spec: Spec,
+    },
+    #[serde(alias = "whitelist")]
+    Retain {
+        spec: Retain,
+    },
+    #[serde(alias = "noop")]
+    Identity {
+        #[serde(default)]
+        spec: Identity,
+    },
+    #[serde(rename = "halt-if")]
+    HaltIf {
+        spec: HaltIf,
+        #[serde(flatten)]
+        options: HaltOptions,
+    },
+    Sample {
+        spec: Sample,
+    },
+    Rename {
+        spec: Rename,
+    },
+    Try {
+        spec: TransformSpec,
+        #[serde(flatten)]
+        options: TryOptions,
+    },
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq)]
 pub(crate) struct Spec(Value);
 
+// The chain-level fields of the specification written as an object
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Chain {
+    #[serde(default)]
+    expects: Expects,
+    #[serde(default)]
+    constants: Constants,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    tests: Vec<EmbeddedTest>,
+    #[serde(default)]
+    envelope: Option<Envelope>,
+    operations: Vec<Value>,
+}
+
+impl<'de> Deserialize<'de> for TransformSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // a list of operations or an object with the operations and the chain-level fields
+        let raw = Value::deserialize(deserializer)?;
+        let spec = match raw {
+            Value::Object(_) => {
+                let Chain {
+                    expects,
+                    constants,
+                    strict,
+                    tests,
+                    envelope,
+                    operations,
+                } = Chain::deserialize(raw).map_err(D::Error::custom)?;
+                let operations = operations
+                    .into_iter()
+                    .map(|raw| Operation::from_raw(raw, &constants, strict))
+                    .collect::<serde_json::Result<_>>()
+                    .map_err(D::Error::custom)?;
+                Self {
+                    operations,
+                    expects,
+                    tests,
+                    envelope,
+                }
+            }
+            raw => Vec::<Operation>::deserialize(raw)
+                .map_err(D::Error::custom)?
+                .into_iter()
+                .collect(),
+        };
+        Ok(spec)
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = Value::deserialize(deserializer)?;
+        Operation::from_raw(raw, &Constants::new(), false).map_err(D::Error::custom)
+    }
+}
+
+// A list of the operations, or an object with the operations when the chain has expectations,
+// tests or an envelope
+impl Serialize for TransformSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.expects.is_empty() && self.tests.is_empty() && self.envelope.is_none() {
+            return serializer.collect_seq(&self.operations);
+        }
+        let mut map = serializer.serialize_map(None)?;
+        if !self.expects.is_empty() {
+            map.serialize_entry("expects", &self.expects)?;
+        }
+        if !self.tests.is_empty() {
+            map.serialize_entry("tests", &self.tests)?;
+        }
+        if let Some(envelope) = &self.envelope {
+            map.serialize_entry("envelope", envelope)?;
+        }
+        map.serialize_entry("operations", &self.operations)?;
+        map.end()
+    }
+}
+
+// The JSON the operation is parsed from, except the `spec` of a `shift` operation which is written
+// from the parsed expressions, with the constants substituted and the special characters of the
+// keys escaped, so the chain-level constants aren't needed to parse it again. The chains of a
+// `try` operation are written from their parsed operations for the same reason.
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (SpecEntry::Shift { .. } | SpecEntry::Try { .. }, Value::Object(raw)) =
+            (&self.entry, &self.raw)
+        else {
+            return self.raw.serialize(serializer);
+        };
+        let mut map = serializer.serialize_map(Some(raw.len()))?;
+        for (key, value) in raw {
+            match (&self.entry, key.as_str()) {
+                (SpecEntry::Shift { spec, .. }, "spec") => map.serialize_entry(key, spec)?,
+                (SpecEntry::Try { spec, .. }, "spec") => map.serialize_entry(key, spec)?,
+                (SpecEntry::Try { options, .. }, "on_error") => {
+                    map.serialize_entry(key, &options.on_error)?
+                }
+                _ => map.serialize_entry(key, value)?,
+            }
+        }
+        map.end()
+    }
+}
+
+// Operations are equal when they are parsed into the same operation, whatever the formatting
+impl PartialEq for Operation {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry == other.entry && self.fields == other.fields
+    }
+}
+
+impl Operation {
+    /// Parses the operation of the given kind, e.g. `shift`, from its `spec` field, with the
+    /// default options.
+    pub fn parse(kind: &str, spec: Value) -> serde_json::Result<Self> {
+        serde_json::from_value(serde_json::json!({"operation": kind, "spec": spec}))
+    }
+
+    /// Name of the operation, e.g. `shift`.
+    pub fn kind(&self) -> &'static str {
+        self.as_ref().kind()
+    }
+
+    /// Read-only access to the operation, like the operations of a chain.
+    pub fn as_ref(&self) -> OperationRef<'_> {
+        OperationRef(self)
+    }
+
+    /// Applies the operation to the input. Returns `None` if the document is dropped by a
+    /// `halt-if` operation with `drop` or by a `sample` operation. A `halt-if` operation whose
+    /// condition holds without `drop` returns the input unchanged, as does an operation that is
+    /// disabled or whose `when` condition doesn't hold.
+    pub fn apply(
+        &self,
+        input: Value,
+        options: &TransformOptions,
+        context: &Context,
+    ) -> crate::Result<Option<Value>> {
+        if !self.applies(&input) {
+            return Ok(Some(input));
+        }
+        match self.halts(&input, context) {
+            Some(true) => Ok(None),
+            Some(false) => Ok(Some(input)),
+            None => {
+                let budget = Budget::new(options);
+                self.apply_scoped(Cow::Owned(input), |input| {
+                    crate::apply_entry(input, &self.entry, options, context, &budget)
+                })
+                .map(Some)
+            }
+        }
+    }
+}
+
+impl Operation {
+    // Parses the operation, the constants referenced by a `shift` spec are the constants of the
+    // operation and of the chain. The unknown fields of the operation are rejected if strict.
+    pub(crate) fn from_raw(
+        raw: Value,
+        chain_constants: &Constants,
+        strict: bool,
+    ) -> serde_json::Result<Self> {
+        let fields = OperationFields::deserialize(&raw)?;
+        let entry = if strict {
+            strict::check_operation(&raw).map_err(serde_json::Error::custom)
+        } else {
+            Ok(())
+        }
+        .and_then(|_| Self::parse_entry(&raw, &fields, chain_constants, strict))
+        .map_err(|e| match &fields.name {
+            Some(name) => serde_json::Error::custom(format!("operation '{name}': {e}")),
+            None => e,
+        })?;
+        Ok(Self { entry, raw, fields })
+    }
+
+    fn parse_entry(
+        raw: &Value,
+        fields: &OperationFields,
+        chain_constants: &Constants,
+        strict: bool,
+    ) -> serde_json::Result<SpecEntry> {
+        let constants = || {
+            let mut constants = chain_constants.clone();
+            constants.extend(fields.constants.clone());
+            constants
+        };
+        if raw["operation"] == "try" {
+            // the nested chains are parsed like the operations of the chain, with its constants
+            let constants = constants();
+            let chain = |key: &str| match raw.get(key) {
+                Some(Value::Array(operations)) => operations
+                    .iter()
+                    .map(|raw| Operation::from_raw(raw.clone(), &constants, strict))
+                    .collect::<serde_json::Result<TransformSpec>>(),
+                Some(_) => Err(serde_json::Error::custom(format!(
+                    "expected a list of operations as `{key}`"
+                ))),
+                None if key == "spec" => Err(serde_json::Error::custom("missing field `spec`")),
+                None => Ok(TransformSpec::default()),
+            };
+            Ok(SpecEntry::Try {
+                spec: chain("spec")?,
+                options: TryOptions {
+                    on_error: chain("on_error")?,
+                },
+            })
+        } else if raw["operation"] == "shift" {
+            let constants = constants();
+            // the specs of the chains without constants are parsed as they are
+            if constants.is_empty() {
+                return SpecEntry::deserialize(raw);
+            }
+            let mut resolved = raw.clone();
+            match resolved.get_mut("spec") {
+                Some(spec) => constants::substitute(spec, &constants),
+                None => Ok(()),
+            }
+            .map_err(serde_json::Error::custom)
+            .and_then(|_| SpecEntry::deserialize(&resolved))
+        } else {
+            SpecEntry::deserialize(raw)
+        }
+    }
+
+    /// Whether the operation is enabled, its `when` condition holds for the document and the
+    /// document has its `root`.
+    pub(crate) fn applies(&self, input: &Value) -> bool {
+        self.fields.enabled
+            && self
+                .fields
+                .when
+                .as_ref()
+                .is_none_or(|when| when.holds(input))
+            && self.scope(input).is_some()
+    }
+
+    /// The path elements of the `root` of the operation, empty if the operation applies to the
+    /// whole document.
+    pub(crate) fn root(&self) -> Vec<String> {
+        self.fields
+            .root
+            .as_deref()
+            .and_then(JsonPointer::from_rfc6901)
+            .map(|root| root.entries()[1..].to_vec())
+            .unwrap_or_default()
+    }
+
+    /// The part of the document the operation applies to, the value at its `root`.
+    pub(crate) fn scope<'v>(&self, document: &'v Value) -> Option<&'v Value> {
+        match &self.fields.root {
+            Some(root) => document.pointer(root),
+            None => Some(document),
+        }
+    }
+
+    /// Whether the chain halts before the operation, and whether the document is then dropped.
+    /// The condition of a `halt-if` operation is checked against the value at its `root`.
+    pub(crate) fn halts(&self, document: &Value, context: &Context) -> Option<bool> {
+        crate::halt(&self.entry, self.scope(document)?, context)
+    }
+
+    /// Replaces the value at the `root` of the operation by the result of `apply`, the rest of
+    /// the document is kept as it is. A borrowed document is copied if the operation is scoped.
+    pub(crate) fn apply_scoped(
+        &self,
+        document: Cow<'_, Value>,
+        apply: impl FnOnce(Cow<'_, Value>) -> crate::Result<Value>,
+    ) -> crate::Result<Value> {
+        let Some(root) = self.fields.root.as_deref().filter(|root| !root.is_empty()) else {
+            return apply(document);
+        };
+        let mut document = document.into_owned();
+        if let Some(scoped) = document.pointer_mut(root) {
+            *scoped = apply(Cow::Owned(std::mem::take(scoped)))?;
+        }
+        Ok(document)
+    }
+
+    pub(crate) fn entry(&self) -> &SpecEntry {
+        &self.entry
+    }
+
+    // Attributes the error to the operation at the index of the chain if the operation is named
+    pub(crate) fn attribute(&self, index: usize, error: Error) -> Error {
+        match &self.fields.name {
+            Some(name) => Error::Operation {
+                name: name.clone(),
+                index,
+                source: Box::new(error),
+            },
+            None => error,
+        }
+    }
+}
+
+impl From<Operation> for TransformSpec {
+    fn from(operation: Operation) -> Self {
+        Self::from_iter([operation])
+    }
+}
+
+impl FromIterator<Operation> for TransformSpec {
+    fn from_iter<T: IntoIterator<Item = Operation>>(iter: T) -> Self {
+        Self {
+            operations: iter.into_iter().collect(),
+            expects: Expects::default(),
+            tests: Vec::new(),
+            envelope: None,
+        }
+    }
+}
+
+impl<'a> OperationRef<'a> {
+    /// Name of the operation, e.g. `shift`.
+    pub fn kind(&self) -> &'static str {
+        match self.0.entry {
+            SpecEntry::Shift { .. } => "shift",
+            SpecEntry::Default { .. } => "default",
+            SpecEntry::Remove { .. } => "remove",
+            SpecEntry::Retain { .. } => "retain",
+            SpecEntry::Identity { .. } => "identity",
+            SpecEntry::HaltIf { .. } => "halt-if",
+            SpecEntry::Sample { .. } => "sample",
+            SpecEntry::Rename { .. } => "rename",
+            SpecEntry::Try { .. } => "try",
+        }
+    }
+
+    /// The `name` field of the operation, if any.
+    pub fn name(&self) -> Option<&'a str> {
+        self.0.fields.name.as_deref()
+    }
+
+    /// The `description` field of the operation, or its `docs` alias, if any.
+    pub fn description(&self) -> Option<&'a str> {
+        self.0.fields.description.as_deref()
+    }
+
+    /// The notes of the `x-docs` field of the operation, keyed by the dot-notation paths of the
+    /// keys of the `spec` field they document, in the order of the paths.
+    pub fn docs(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.0
+            .fields
+            .docs
+            .iter()
+            .map(|(path, doc)| (path.as_str(), doc.as_str()))
+    }
+
+    /// The note of the `x-docs` field of the operation for the dot-notation path of a key of the
+    /// `spec` field, e.g. `user.name` for the `name` key of `{"user": {"name": "..."}}`.
+    pub fn doc(&self, path: &str) -> Option<&'a str> {
+        self.0.fields.docs.get(path).map(String::as_str)
+    }
+
+    /// Whether the operation is enabled, see the `enabled` field of the operations.
+    pub fn enabled(&self) -> bool {
+        self.0.fields.enabled
+    }
+
+    /// The `spec` field of the operation as it was parsed.
+    pub fn spec(&self) -> &'a Value {
+        &self.0.raw["spec"]
+    }
+
+    /// The whole operation as it was parsed, including the `operation` field and the options.
+    pub fn raw(&self) -> &'a Value {
+        &self.0.raw
+    }
+
+    /// Returns the machine-readable intermediate representation of the operation.
+    pub fn to_ir(&self) -> OperationIr {
+        self.0.entry.to_ir()
+    }
+}
+
+impl<'a> FromIterator<OperationRef<'a>> for TransformSpec {
+    fn from_iter<T: IntoIterator<Item = OperationRef<'a>>>(iter: T) -> Self {
+        iter.into_iter().map(|op| op.0.clone()).collect()
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct SpecIter<'a> {
     path: Vec<(&'a Value, usize, String)>,
 }
 
 impl TransformSpec {
+    /// Composes several specifications into a single chain. The operations of each
+    /// specification are applied in the given order, the input is checked against the `expects`
+    /// block of the first specification and the output is wrapped in the `envelope` of the last
+    /// one. The `tests` blocks are not kept, they test the chains they are written for.
+    ///
+    /// ```
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let first: TransformSpec =
+    ///     serde_json::from_str(r#"[{"operation": "shift", "spec": {"a": "b"}}]"#).unwrap();
+    /// let second: TransformSpec =
+    ///     serde_json::from_str(r#"[{"operation": "default", "spec": {"c": "d"}}]"#).unwrap();
+    ///
+    /// let spec = TransformSpec::compose([first, second]);
+    /// ```
+    pub fn compose(specs: impl IntoIterator<Item = TransformSpec>) -> Self {
+        let mut specs = specs.into_iter();
+        let Some(mut composed) = specs.next() else {
+            return Self::default();
+        };
+        for spec in specs {
+            composed.operations.extend(spec.operations);
+            composed.envelope = spec.envelope;
+        }
+        composed.tests.clear();
+        composed
+    }
+
+    /// Rewrites a specification written for older releases of the library to the current syntax.
+    ///
+    /// The `&N` expressions of `shift` operations are rewritten to `&(N)`.
+    ///
+    /// Returns the migrated specification along with a note for every rewrite.
+    ///
+    /// ```
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let (spec, notes) = TransformSpec::migrate_legacy(
+    ///     r#"[{"operation": "shift", "spec": {"*": "data.&0"}}]"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// assert!(spec.contains("data.&(0)"));
+    /// assert_eq!(notes.len(), 1);
+    /// ```
+    pub fn migrate_legacy(spec: &str) -> serde_json::Result<(String, Vec<MigrationNote>)> {
+        migrate::migrate_legacy(spec)
+    }
+
+    /// Returns the machine-readable intermediate representation of the specification.
+    /// The schema is documented in the [`ir`](crate::ir) module.
+    ///
+    /// ```
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let spec: TransformSpec =
+    ///     serde_json::from_str(r#"[{"operation": "shift", "spec": {"a": "b"}}]"#).unwrap();
+    ///
+    /// let ir = serde_json::to_value(spec.to_ir()).unwrap();
+    /// assert_eq!(ir["operations"][0]["matches"][0]["key"]["value"], "a");
+    /// ```
+    pub fn to_ir(&self) -> SpecIr {
+        SpecIr {
+            version: IR_VERSION,
+            operations: self.entries().map(SpecEntry::to_ir).collect(),
+        }
+    }
+
+    /// Complexity metrics of the chain, see [`SpecStats`].
+    ///
+    /// ```
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let spec: TransformSpec = serde_json::from_str(
+    ///     r#"[{"operation": "shift", "spec": {"items": {"*": "list[]"}}}]"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let stats = spec.stats();
+    /// assert_eq!(stats.wildcards, 1);
+    /// assert_eq!(stats.max_depth, 2);
+    /// ```
+    pub fn stats(&self) -> SpecStats {
+        SpecStats::new(&self.to_ir())
+    }
+
+    /// Fingerprint of the chain, the 64-bit FNV-1a hash of the compact JSON of its operations as
+    /// they are written, see [`OperationRef::raw`]. It identifies the version of a specification
+    /// in logs and metrics, the same operations giving the same fingerprint on every platform.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for operation in &self.operations {
+            for byte in operation.raw.to_string().bytes() {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+
+    /// Number of operations of the chain.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Whether the chain has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Returns the operation at the given index of the chain.
+    pub fn get(&self, idx: usize) -> Option<OperationRef<'_>> {
+        self.operations.get(idx).map(OperationRef)
+    }
+
+    /// Reports the mappings and operations added, removed or modified by the `other` version of
+    /// the specification. The operations of both versions are paired by their kinds, in order.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let old: TransformSpec = serde_json::from_value(json!([
+    ///     {"operation": "shift", "spec": {"id": "data.id", "name": "data.name"}}
+    /// ]))
+    /// .unwrap();
+    /// let new: TransformSpec = serde_json::from_value(json!([
+    ///     {"operation": "shift", "spec": {"id": "data.id", "email": "data.email"}}
+    /// ]))
+    /// .unwrap();
+    ///
+    /// let summary: Vec<String> = old.diff(&new).iter().map(ToString::to_string).collect();
+    ///
+    /// assert_eq!(
+    ///     summary,
+    ///     vec![
+    ///         r#"operation 0 (shift): - name -> "data.name""#,
+    ///         r#"operation 0 (shift): + email -> "data.email""#,
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff(&self, other: &TransformSpec) -> Vec<SpecChange> {
+        diff::diff(self, other)
+    }
+
+    /// Classifies the upgrade to the `other` version of the specification as neutral, additive
+    /// or breaking.
+    ///
+    /// The changes reported by [`diff`](Self::diff) are classified by the operation they belong
+    /// to, e.g. a mapping added to a `shift` is additive while a mapping removed from it is
+    /// breaking. Both versions are also applied to the sample inputs and the output fields are
+    /// compared, so removed fields and type changes are caught.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::{Compatibility, TransformSpec};
+    ///
+    /// let old: TransformSpec = serde_json::from_value(json!([
+    ///     {"operation": "shift", "spec": {"id": "data.id"}}
+    /// ]))
+    /// .unwrap();
+    /// let new: TransformSpec = serde_json::from_value(json!([
+    ///     {"operation": "shift", "spec": {"id": "data.id"}},
+    ///     {"operation": "default", "spec": {"data": {"source": "crm"}}}
+    /// ]))
+    /// .unwrap();
+    ///
+    /// let report = old.compatibility(&new, &[json!({"id": 1})]).unwrap();
+    ///
+    /// assert_eq!(report.compatibility, Compatibility::Additive);
+    /// ```
+    pub fn compatibility(
+        &self,
+        other: &TransformSpec,
+        samples: &[Value],
+    ) -> crate::Result<CompatibilityReport> {
+        compat::compatibility(self, other, samples)
+    }
+
+    /// Returns the input and output paths, in the dot-notation, of the fields renamed by the
+    /// `shift` operations of the chain, in the order of the operations.
+    ///
+    /// Only the simple mappings are reported: the keys leading to the value are literals and the
+    /// output path is made of plain keys. Mappings with wildcards, captures, lookups, constants or
+    /// array indexes are skipped, as are the other and the disabled operations. The mappings of a
+    /// `shift` read the output of the operations before it.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let spec: TransformSpec = serde_json::from_value(json!([
+    ///     {
+    ///         "operation": "shift",
+    ///         "spec": {
+    ///             "account": {"name": "data.account_name"},
+    ///             "id": "data.id",
+    ///             "items": {"*": "data.items[]"}
+    ///         }
+    ///     }
+    /// ]))
+    /// .unwrap();
+    ///
+    /// assert_eq!(
+    ///     spec.rename_map(),
+    ///     vec![
+    ///         ("account.name".to_string(), "data.account_name".to_string()),
+    ///         ("id".to_string(), "data.id".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn rename_map(&self) -> Vec<(String, String)> {
+        self.operations()
+            .filter(|op| op.fields.enabled)
+            .flat_map(|op| match op.entry() {
+                SpecEntry::Shift { spec, .. } => spec.rename_map(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Returns the chain of the operations in the given range of indexes. The `expects` block is
+    /// kept when the range starts with the first operation, the `envelope` when it ends with the
+    /// last one, the `tests` block is not kept.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let spec: TransformSpec = serde_json::from_value(json!([
+    ///     {"operation": "shift", "spec": {"a": "b"}},
+    ///     {"operation": "default", "spec": {"c": "d"}},
+    ///     {"operation": "remove", "spec": {"b": ""}}
+    /// ]))
+    /// .unwrap();
+    ///
+    /// assert_eq!(spec.slice(1..).len(), 2);
+    /// assert_eq!(spec.slice(..1).get(0).unwrap().kind(), "shift");
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the range is out of the bounds of the chain, like slice indexing.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let from_input = matches!(range.0, Bound::Unbounded | Bound::Included(0));
+        let to_output = match range.1 {
+            Bound::Unbounded => true,
+            Bound::Included(end) => end + 1 == self.len(),
+            Bound::Excluded(end) => end == self.len(),
+        };
+        Self {
+            operations: self.operations[range].to_vec(),
+            expects: if from_input {
+                self.expects.clone()
+            } else {
+                Expects::default()
+            },
+            tests: Vec::new(),
+            envelope: self.envelope.clone().filter(|_| to_output),
+        }
+    }
+
+    /// Iterates over the operations of the chain, in order.
+    pub fn iter(&self) -> impl Iterator<Item = OperationRef<'_>> {
+        self.operations.iter().map(OperationRef)
+    }
+
+    /// Whether the specification has an `expects` block, see [`TransformSpec::check_input`].
+    pub fn has_expectations(&self) -> bool {
+        !self.expects.is_empty()
+    }
+
+    /// Checks the input against the `expects` block of the specification, failing with
+    /// [`Error::UnmetExpectations`] listing every missing or mistyped value. The transformations
+    /// of the chain check the input before applying the operations.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let spec: TransformSpec = serde_json::from_value(json!({
+    ///     "expects": {"/headers": ["object", "null"], "/id": "number"},
+    ///     "operations": [{"operation": "shift", "spec": {"id": "data.id"}}]
+    /// }))
+    /// .unwrap();
+    ///
+    /// assert!(spec.check_input(&json!({"id": 1, "headers": null})).is_ok());
+    /// assert_eq!(
+    ///     spec.check_input(&json!({"id": "1"})).unwrap_err().to_string(),
+    ///     "Input doesn't meet the expectations of the specification: \
+    ///      /headers: missing, expected object or null; /id: expected number, found string"
+    /// );
+    /// ```
+    pub fn check_input(&self, input: &Value) -> crate::Result<()> {
+        if !self.has_expectations() {
+            return Ok(());
+        }
+        self.expects.check(input)
+    }
+
+    /// Whether the specification has an `envelope` block the output of the chain is wrapped in.
+    pub fn has_envelope(&self) -> bool {
+        self.envelope.is_some()
+    }
+
+    // The key of the output in the envelope of the specification, if any
+    pub(crate) fn envelope_data(&self) -> Option<&str> {
+        self.envelope.as_ref().map(Envelope::data)
+    }
+
+    // Wraps the output of the chain in the envelope of the specification, if any
+    pub(crate) fn wrap_output(&self, output: Value, context: &Context) -> Value {
+        match &self.envelope {
+            Some(envelope) => envelope.wrap(output, context),
+            None => output,
+        }
+    }
+
+    /// Runs the test cases of the `tests` block of the specification, returning the number of the
+    /// test cases that passed or all the failed ones.
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use fluvio_jolt::TransformSpec;
+    ///
+    /// let spec: TransformSpec = serde_json::from_value(json!({
+    ///     "tests": [
+    ///         {"name": "renames the id", "input": {"id": 1}, "expected": {"data": {"id": 1}}},
+    ///         {"input": {"id": 2, "name": "John"}, "expected": {"data": {"id": 2}}}
+    ///     ],
+    ///     "operations": [{"operation": "shift", "spec": {"id": "data.id"}}]
+    /// }))
+    /// .unwrap();
+    ///
+    /// assert_eq!(spec.run_embedded_tests(), Ok(2));
+    /// ```
+    pub fn run_embedded_tests(&self) -> Result<usize, Vec<EmbeddedTestFailure>> {
+        let failures: Vec<EmbeddedTestFailure> = self
+            .tests
+            .iter()
+            .enumerate()
+            .filter_map(|(index, test)| test.run(index, self))
+            .collect();
+        if failures.is_empty() {
+            Ok(self.tests.len())
+        } else {
+            Err(failures)
+        }
+    }
+
     pub(crate) fn entries(&self) -> impl Iterator<Item = &SpecEntry> {
-        self.0.iter()
+        self.operations.iter().map(|op| &op.entry)
+    }
+
+    pub(crate) fn operations(&self) -> impl Iterator<Item = &Operation> {
+        self.operations.iter()
+    }
+}
+
+impl SpecEntry {
+    fn to_ir(&self) -> OperationIr {
+        match self {
+            SpecEntry::Shift { spec, .. } => OperationIr::Shift {
+                matches: spec.to_ir(),
+            },
+            SpecEntry::Default { spec, .. } => OperationIr::Default {
+                values: spec
+                    .iter()
+                    .map(|(path, value)| DefaultValue {
+                        path: path.entries().iter().skip(1).cloned().collect(),
+                        value: value.clone(),
+                    })
+                    .collect(),
+            },
+            SpecEntry::Remove { spec } => OperationIr::Remove {
+                paths: spec
+                    .iter()
+                    .map(|(path, _)| path.entries().iter().skip(1).cloned().collect())
+                    .collect(),
+            },
+            SpecEntry::Retain { spec } => OperationIr::Retain {
+                matches: spec.to_ir(),
+            },
+            SpecEntry::Identity { spec } => spec.to_ir(),
+            SpecEntry::HaltIf { spec, options } => spec.to_ir(options),
+            SpecEntry::Sample { spec } => spec.to_ir(),
+            SpecEntry::Rename { spec } => spec.to_ir(),
+            SpecEntry::Try { spec, options } => try_catch::to_ir(spec, options),
+        }
     }
 }
 
 impl Spec {
-    pub(crate) fn iter(&self) -> SpecIter {
+    pub(crate) fn iter(&self) -> SpecIter<'_> {
         SpecIter::new(self)
     }
 }
@@ -297,15 +2113,212 @@ mod test {
         let result: TransformSpec = serde_json::from_str(spec).expect("parsed transform spec");
 
         assert_eq!(
-            result,
-            TransformSpec(vec![SpecEntry::Shift(
-                serde_json::from_value(json!({
+            result.entries().collect::<Vec<_>>(),
+            vec![&SpecEntry::Shift {
+                spec: serde_json::from_value(json!({
                     "id": "__data.id",
                     "name": "__data.name",
                     "account": "__data.account"
                 }))
-                .unwrap()
-            )])
+                .unwrap(),
+                options: ShiftOptions::default(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_de_default_options() {
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "default",
+                "replace_null": true,
+                "spec": {
+                    "a": "b"
+                }
+            }
+        ]))
+        .expect("parsed transform spec");
+
+        assert_eq!(
+            spec.entries().collect::<Vec<_>>(),
+            vec![&SpecEntry::Default {
+                spec: Spec(json!({"a": "b"})),
+                options: DefaultOptions {
+                    replace_null: true,
+                    replace_empty_string: false,
+                    expressions: false,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let first: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "a": "b"
+                }
+            }
+        ]))
+        .expect("parsed transform spec");
+        let second: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "default",
+                "spec": {
+                    "c": "d"
+                }
+            },
+            {
+                "operation": "remove",
+                "spec": {
+                    "b": ""
+                }
+            }
+        ]))
+        .expect("parsed transform spec");
+
+        let result = TransformSpec::compose([first.clone(), second.clone()]);
+
+        let mut expected = first.operations;
+        expected.extend(second.operations);
+        assert_eq!(result, expected.into_iter().collect());
+    }
+
+    #[test]
+    fn test_parse_operation() {
+        //given
+        let halt = Operation::parse("halt-if", json!({"path": "type", "equals": "ping"}))
+            .expect("parsed operation");
+        let ping = json!({"type": "ping"});
+
+        //when
+        let unknown = Operation::parse("rename", json!({"a": "b"}));
+        let halted = halt.apply(
+            ping.clone(),
+            &TransformOptions::default(),
+            &Context::default(),
+        );
+
+        //then
+        assert!(unknown.is_err());
+        assert_eq!(halt.kind(), "halt-if");
+        assert_eq!(
+            halt.as_ref().spec(),
+            &json!({"path": "type", "equals": "ping"})
+        );
+        assert_eq!(halted.unwrap(), Some(ping));
+        assert_eq!(TransformSpec::from(halt).len(), 1);
+    }
+    #[test]
+    fn test_de_strict() {
+        //given
+        let operations = json!([
+            {"operation": "shift", "spec": {"a": "b"}},
+            {"operation": "default", "name": "defaults", "replace_nul": true, "spec": {"c": "d"}}
+        ]);
+
+        //when
+        let lenient: serde_json::Result<TransformSpec> = serde_json::from_value(json!({
+            "operations": operations
+        }));
+        let strict: serde_json::Result<TransformSpec> = serde_json::from_value(json!({
+            "strict": true,
+            "operations": operations
+        }));
+
+        //then
+        assert_eq!(lenient.map(|spec| spec.len()).ok(), Some(2));
+        assert_eq!(
+            strict.unwrap_err().to_string(),
+            "operation 'defaults': unknown field `replace_nul` of operation `default`, did you \
+             mean `replace_null`?"
+        );
+    }
+
+    #[test]
+    fn test_escaped_constant_reference() {
+        //given
+        let with_constants: TransformSpec = serde_json::from_value(json!({
+            "constants": {"x": "b"},
+            "operations": [{"operation": "shift", "spec": {"a": "\\${x}", "b": "${x}"}}]
+        }))
+        .expect("parsed spec");
+        let without_constants: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a": "\\${x}"}}
+        ]))
+        .expect("parsed spec");
+        let input = json!({"a": 1, "b": 2});
+
+        //when
+        let serialized = serde_json::to_value(&with_constants).expect("serialized spec");
+        let reparsed: TransformSpec = serde_json::from_value(serialized).expect("parsed spec");
+
+        //then
+        let expected = json!({"${x}": 1, "b": 2});
+        assert_eq!(
+            crate::transform(input.clone(), &with_constants).unwrap(),
+            expected
+        );
+        assert_eq!(
+            crate::transform(input.clone(), &reparsed).unwrap(),
+            expected
+        );
+        assert_eq!(
+            crate::transform(input, &without_constants).unwrap(),
+            json!({"${x}": 1})
+        );
+    }
+
+    #[test]
+    fn test_ser_round_trip() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "expects": {"/id": "number", "/tags": ["array", "null"]},
+            "constants": {"prefix": "a.b"},
+            "operations": [
+                {
+                    "operation": "shift",
+                    "name": "move",
+                    "spec": {
+                        "a*": "${prefix}.&(0,1)|copy.&",
+                        "\\$id": ["ids[]", "first"],
+                        "@(0,tags)": "tags",
+                        "dropped": null
+                    }
+                },
+                {"operation": "default", "spec": {"x\\.y": 1}}
+            ]
+        }))
+        .expect("parsed spec");
+
+        //when
+        let serialized = serde_json::to_value(&spec).expect("serialized spec");
+
+        //then
+        assert_eq!(
+            serialized,
+            json!({
+                "expects": {"/id": "number", "/tags": ["array", "null"]},
+                "operations": [
+                    {
+                        "operation": "shift",
+                        "name": "move",
+                        "spec": {
+                            "@(0,tags)": "tags",
+                            "\\$id": ["ids[]", "first"],
+                            "dropped": null,
+                            "a*": ["a\\.b.&(0,1)", "copy.&(0,0)"]
+                        }
+                    },
+                    {"operation": "default", "spec": {"x\\.y": 1}}
+                ]
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<TransformSpec>(serialized).expect("parsed spec"),
+            spec
         );
     }
 }
@@ -0,0 +1,215 @@
+//! Depth semantics of the references of the `shift` specification.
+//!
+//! `&(N,M)`, `$(N,M)` and `@(N,path)` are resolved against the path of the input keys matched
+//! by the spec. The path starts with a synthetic level whose key is [`ROOT_KEY`], followed by a
+//! level for every key of the spec leading to the reference. `N` counts the levels up from the
+//! last one and `M` selects a match of the key at that level: `0` is the whole key and `1..` are
+//! the parts captured by the `*` of a wildcard.
+//!
+//! The levels seen by a reference depend on where it is written:
+//!  - the right hand side of a key sees the level of the key itself, `&(0)` being the key
+//!  - the `$`, `@` and `#` keys match no input key, their right hand side sees the level of the
+//!    enclosing key twice, so `&(0)` and `&(1)` are both the enclosing key
+//!  - references written in a key, like `$(1)` or `&(1)` keys, see the levels of the keys before
+//!    it
+//!
+//! [`SpecPath`] computes the valid references of a position of the spec, reporting the errors
+//! the engine fails with for invalid ones:
+//! ```
+//! use fluvio_jolt::spec_path::SpecPath;
+//!
+//! let path = SpecPath::parse(["items", "*-*", "name"]).unwrap();
+//!
+//! assert_eq!(path.rhs_depth().valid_levels(), 0..=3);
+//! assert_eq!(path.rhs_match_count(1), Some(3));
+//! assert!(path.check_rhs(1, 2).is_ok());
+//! assert!(path.check_rhs(4, 0).is_err());
+//! ```
+
+use std::ops::RangeInclusive;
+
+use serde::de::Error as _;
+
+use crate::dsl::Lhs;
+use crate::{Error, Result};
+
+/// The key of the synthetic level at the root of the input path, e.g. `&(2)` of the right hand
+/// side of the `"b"` key of `{"a": {"b": "&(2)"}}` evaluates to it.
+pub const ROOT_KEY: &str = "root";
+
+/// The number of levels of the input path a reference is resolved against, including the root
+/// level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathDepth(usize);
+
+impl PathDepth {
+    /// The number of levels, at least 1 for the root level.
+    pub fn levels(&self) -> usize {
+        self.0
+    }
+
+    /// The range of the valid `N` of `&(N,M)`, `$(N,M)` and `@(N,path)`, `N` being the last
+    /// value referring to the root level.
+    pub fn valid_levels(&self) -> RangeInclusive<usize> {
+        0..=self.0 - 1
+    }
+}
+
+/// The keys of a `shift` spec from its root to a position of the spec, e.g. `["items", "*",
+/// "name"]` for the right hand side of `{"items": {"*": {"name": "names[]"}}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecPath {
+    // The number of matches of every level, including the root
+    matches: Vec<usize>,
+}
+
+impl SpecPath {
+    /// Parses the keys with the syntax of the left hand side of the spec, failing with the error
+    /// of the spec deserialization.
+    pub fn parse<I, S>(keys: I) -> serde_json::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut matches = vec![1];
+        for key in keys {
+            let key = key.as_ref();
+            let (lhs, _) = Lhs::parse_marked(key).map_err(|e| {
+                serde_json::Error::custom(format!("failed to parse: {key}.error={e}"))
+            })?;
+            let count = match lhs {
+                // the level of the enclosing key is repeated
+                Lhs::DollarSign(..) | Lhs::At(..) | Lhs::Square(_) => *matches.last().unwrap_or(&1),
+                Lhs::Pipes(pipes) => pipes
+                    .iter()
+                    .map(|stars| stars.pieces().len().max(1))
+                    .min()
+                    .unwrap_or(1),
+                Lhs::Amp(..) | Lhs::Literal(_) => 1,
+            };
+            matches.push(count);
+        }
+        Ok(Self { matches })
+    }
+
+    /// The depth of the references of the right hand side of the last key.
+    pub fn rhs_depth(&self) -> PathDepth {
+        PathDepth(self.matches.len())
+    }
+
+    /// The depth of the references written in the last key, the root if the path is empty.
+    pub fn lhs_depth(&self) -> PathDepth {
+        PathDepth(self.matches.len().saturating_sub(1).max(1))
+    }
+
+    /// The number of matches of the level `level` levels up from the right hand side of the last
+    /// key, `None` if the level is out of the path. For a key with alternatives separated by `|`
+    /// it is the number of matches of every alternative.
+    pub fn rhs_match_count(&self, level: usize) -> Option<usize> {
+        Self::match_count(&self.matches, level)
+    }
+
+    /// Checks `&(level,index)` of the right hand side of the last key, failing with the error
+    /// of the engine.
+    pub fn check_rhs(&self, level: usize, index: usize) -> Result<()> {
+        Self::check(&self.matches, level, index)
+    }
+
+    /// Checks `&(level,index)` written in the last key, failing with the error of the engine.
+    pub fn check_lhs(&self, level: usize, index: usize) -> Result<()> {
+        let len = self.lhs_depth().levels();
+        Self::check(&self.matches[..len], level, index)
+    }
+
+    fn match_count(matches: &[usize], level: usize) -> Option<usize> {
+        matches.len().checked_sub(level + 1).map(|idx| matches[idx])
+    }
+
+    fn check(matches: &[usize], level: usize, index: usize) -> Result<()> {
+        let count = Self::match_count(matches, level).ok_or(Error::PathIndexOutOfRange {
+            idx: level,
+            len: matches.len(),
+        })?;
+        if index >= count {
+            return Err(Error::MatchIndexOutOfRange {
+                idx: index,
+                len: count,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+    use crate::{transform, TransformSpec};
+
+    #[test]
+    fn test_depths() {
+        //given
+        let path = SpecPath::parse(["a", "b*c*", "$"]).expect("parsed path");
+
+        //then
+        assert_eq!(path.rhs_depth().levels(), 4);
+        assert_eq!(path.lhs_depth().levels(), 3);
+        assert_eq!(path.rhs_match_count(0), Some(3));
+        assert_eq!(path.rhs_match_count(1), Some(3));
+        assert_eq!(path.rhs_match_count(3), Some(1));
+        assert_eq!(path.rhs_match_count(4), None);
+        assert_eq!(
+            SpecPath::parse(Vec::<&str>::new())
+                .expect("parsed path")
+                .lhs_depth()
+                .valid_levels(),
+            0..=0
+        );
+    }
+
+    #[test]
+    fn test_matches_engine() {
+        //given
+        let input = json!({"a": {"bxcy": "v"}});
+        let keys = ["a", "b*c*"];
+        let path = SpecPath::parse(keys).expect("parsed path");
+
+        for level in 0..4 {
+            for index in 0..4 {
+                let spec: TransformSpec = serde_json::from_value(json!([{
+                    "operation": "shift",
+                    "spec": {"a": {"b*c*": format!("out.&({level},{index})")}}
+                }]))
+                .expect("parsed spec");
+
+                //when
+                let engine = transform(input.clone(), &spec).map(|_| ());
+                let checked = path.check_rhs(level, index);
+
+                //then
+                assert_eq!(
+                    engine.err().map(|e| e.code()),
+                    checked.err().map(|e| e.code()),
+                    "&({level},{index})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_root_key() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([{
+            "operation": "shift",
+            "spec": {"a": {"b": "&(2)"}}
+        }]))
+        .expect("parsed spec");
+
+        //when
+        let output = transform(json!({"a": {"b": 1}}), &spec).unwrap();
+
+        //then
+        assert_eq!(output, json!({ ROOT_KEY: 1 }));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::TransformSpec;
+
+/// Named transformation chains defined by a single document, an object mapping the names of the
+/// chains to their specifications, written as lists of operations or as objects with the chain
+/// fields. The chains keep the order of the document.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{transform, TransformSpecSet};
+///
+/// let specs: TransformSpecSet = r#"{
+///     "normalize": [
+///         {"operation": "shift", "spec": {"ID": "id"}}
+///     ],
+///     "enrich": {
+///         "operations": [
+///             {"operation": "default", "spec": {"source": "crm"}}
+///         ]
+///     }
+/// }"#
+/// .parse()
+/// .unwrap();
+///
+/// assert_eq!(specs.names().collect::<Vec<_>>(), vec!["normalize", "enrich"]);
+///
+/// let normalized = transform(json!({"ID": 1}), specs.get("normalize").unwrap()).unwrap();
+/// let enriched = transform(normalized, specs.get("enrich").unwrap()).unwrap();
+/// assert_eq!(enriched, json!({"id": 1, "source": "crm"}));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransformSpecSet {
+    specs: Vec<(String, TransformSpec)>,
+}
+
+impl TransformSpecSet {
+    /// Returns the chain with the name.
+    pub fn get(&self, name: &str) -> Option<&TransformSpec> {
+        self.specs
+            .iter()
+            .find(|(spec_name, _)| spec_name == name)
+            .map(|(_, spec)| spec)
+    }
+
+    /// Adds the chain with the name, returning the chain it replaces.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        spec: TransformSpec,
+    ) -> Option<TransformSpec> {
+        let name = name.into();
+        match self
+            .specs
+            .iter_mut()
+            .find(|(spec_name, _)| *spec_name == name)
+        {
+            Some((_, existing)) => Some(std::mem::replace(existing, spec)),
+            None => {
+                self.specs.push((name, spec));
+                None
+            }
+        }
+    }
+
+    /// The names of the chains.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.specs.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The names of the chains along with the chains.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TransformSpec)> {
+        self.specs.iter().map(|(name, spec)| (name.as_str(), spec))
+    }
+
+    pub fn len(&self) -> usize {
+        self.specs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+}
+
+impl FromStr for TransformSpecSet {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for TransformSpecSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(SpecSetVisitor)
+    }
+}
+
+// Visits the chains in the order of the document, whatever the order of the JSON objects
+struct SpecSetVisitor;
+
+impl<'de> Visitor<'de> for SpecSetVisitor {
+    type Value = TransformSpecSet;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object mapping the names of the chains to their specifications")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut specs = TransformSpecSet::default();
+        while let Some((name, spec)) = map.next_entry::<String, Value>()? {
+            let spec = TransformSpec::deserialize(spec)
+                .map_err(|e| de::Error::custom(format!("chain '{name}': {e}")))?;
+            if specs.insert(name.clone(), spec).is_some() {
+                return Err(de::Error::custom(format!("duplicate chain '{name}'")));
+            }
+        }
+        Ok(specs)
+    }
+}
+
+impl TransformSpec {
+    /// Parses the named chains of a document, see [`TransformSpecSet`].
+    pub fn many_from_str(s: &str) -> serde_json::Result<TransformSpecSet> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_many_from_str() {
+        //given
+        let specs = r#"{
+            "normalize": [{"operation": "shift", "spec": {"a": "b"}}],
+            "noop": []
+        }"#;
+
+        //when
+        let mut specs = TransformSpec::many_from_str(specs).expect("parsed specs");
+        let replaced = specs.insert("noop", TransformSpec::default());
+
+        //then
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs.get("normalize").map(TransformSpec::len), Some(1));
+        assert_eq!(replaced, Some(TransformSpec::default()));
+        assert!(specs.get("enrich").is_none());
+    }
+
+    #[test]
+    fn test_invalid_chain() {
+        //given
+        let specs = r#"{
+            "normalize": [{"operation": "shift", "spec": {"a": "b"}}],
+            "enrich": [{"operation": "unknown", "spec": {}}]
+        }"#;
+
+        //when
+        let result = TransformSpec::many_from_str(specs);
+
+        //then
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("chain 'enrich': "), "{error}");
+    }
+}
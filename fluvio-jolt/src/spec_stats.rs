@@ -0,0 +1,192 @@
+use serde::Serialize;
+
+use crate::ir::{KeyMatch, MatchNode, OperationIr, PathSegment, RetainNode, SpecIr};
+
+/// The keys nested under this many wildcards weigh as much as under one less, so the score of
+/// deep specifications doesn't overflow.
+const MAX_WILDCARD_NESTING: u32 = 32;
+
+/// Complexity metrics of a specification returned by
+/// [`TransformSpec::stats`](crate::TransformSpec::stats), e.g. to enforce budget limits on the
+/// specifications supplied by the tenants of a service.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SpecStats {
+    /// Operations of the chain, including the disabled ones and the operations of the chains
+    /// nested in `try` operations.
+    pub operations: usize,
+    /// Keys of the `shift` and `retain` specs matching the input keys with `*` patterns.
+    pub wildcards: usize,
+    /// Deepest nesting of the keys of the specs, `1` for a key at the root level.
+    pub max_depth: usize,
+    /// `@` lookups of the keys and the output paths of the `shift` specs, including the lookups
+    /// nested in the paths of other lookups.
+    pub lookups: usize,
+    /// Estimated cost of a transformation relative to the other specifications. Every
+    /// operation, key and output path adds 1, doubled for every wildcard key it is nested in,
+    /// and every lookup adds 4.
+    pub complexity: u64,
+}
+
+impl SpecStats {
+    pub(crate) fn new(ir: &SpecIr) -> Self {
+        let mut stats = Self::default();
+        ir.operations
+            .iter()
+            .for_each(|operation| stats.add_operation(operation));
+        stats
+    }
+
+    // The operations nested in a `try` operation are counted like the operations of the chain
+    fn add_operation(&mut self, operation: &OperationIr) {
+        self.operations += 1;
+        self.add(1, 0);
+        match operation {
+            OperationIr::Shift { matches } => {
+                matches.iter().for_each(|node| self.add_match(node, 1, 0))
+            }
+            OperationIr::Retain { matches } => {
+                matches.iter().for_each(|node| self.add_retain(node, 1, 0))
+            }
+            OperationIr::Default { values } => {
+                for value in values {
+                    self.depth(value.path.len());
+                    self.add(1, 0);
+                }
+            }
+            OperationIr::Remove { paths } => {
+                for path in paths {
+                    self.depth(path.len());
+                    self.add(1, 0);
+                }
+            }
+            OperationIr::HaltIf { path, .. } => self.depth(path.len()),
+            OperationIr::Try {
+                operations,
+                on_error,
+            } => operations
+                .iter()
+                .chain(on_error)
+                .for_each(|operation| self.add_operation(operation)),
+            OperationIr::Identity { .. }
+            | OperationIr::Sample { .. }
+            | OperationIr::Rename { .. } => (),
+        }
+    }
+
+    fn add_match(&mut self, node: &MatchNode, depth: usize, wildcards: u32) {
+        let wildcards = wildcards + self.add_key(&node.key, depth, wildcards);
+        for destination in &node.destinations {
+            self.add(1, wildcards);
+            self.add_path(destination, wildcards);
+        }
+        for child in &node.children {
+            self.add_match(child, depth + 1, wildcards);
+        }
+    }
+
+    fn add_retain(&mut self, node: &RetainNode, depth: usize, wildcards: u32) {
+        let wildcards = wildcards + self.add_key(&node.key, depth, wildcards);
+        for child in node.children.iter().flatten() {
+            self.add_retain(child, depth + 1, wildcards);
+        }
+    }
+
+    // Counts the key, returning 1 if it is a wildcard
+    fn add_key(&mut self, key: &KeyMatch, depth: usize, wildcards: u32) -> u32 {
+        self.depth(depth);
+        let wildcard = u32::from(matches!(key, KeyMatch::Wildcard { .. }));
+        self.wildcards += wildcard as usize;
+        self.add(1, wildcards + wildcard);
+        if let KeyMatch::Lookup { path, .. } = key {
+            self.add_lookup(path, wildcards);
+        }
+        wildcard
+    }
+
+    fn add_path(&mut self, path: &[PathSegment], wildcards: u32) {
+        for segment in path {
+            match segment {
+                PathSegment::Lookup { path, .. } | PathSegment::LookupIndex { path, .. } => {
+                    self.add_lookup(path, wildcards)
+                }
+                PathSegment::Composite { parts } => self.add_path(parts, wildcards),
+                _ => (),
+            }
+        }
+    }
+
+    fn add_lookup(&mut self, path: &[PathSegment], wildcards: u32) {
+        self.lookups += 1;
+        self.complexity = self.complexity.saturating_add(4);
+        self.add_path(path, wildcards);
+    }
+
+    fn add(&mut self, cost: u64, wildcards: u32) {
+        let factor = 1u64 << wildcards.min(MAX_WILDCARD_NESTING);
+        self.complexity = self.complexity.saturating_add(cost.saturating_mul(factor));
+    }
+
+    fn depth(&mut self, depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+    use crate::TransformSpec;
+
+    #[test]
+    fn test_spec_stats() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "shift",
+                "spec": {
+                    "id": "data.id",
+                    "items": {
+                        "*": {
+                            "@(0,name)": "names.@(1,type)[]"
+                        }
+                    }
+                }
+            },
+            {"operation": "default", "spec": {"a": {"b": {"c": {"d": 1}}}}},
+            {"operation": "remove", "spec": {"tmp": ""}},
+        ]))
+        .expect("parsed spec");
+
+        //when
+        let stats = spec.stats();
+
+        //then
+        assert_eq!(
+            stats,
+            SpecStats {
+                operations: 3,
+                wildcards: 1,
+                max_depth: 4,
+                lookups: 2,
+                // 3 operations, `id` with its path, `items`, `*`, the lookup key with its path
+                // under the wildcard, the 2 lookups, the default value and the removed path
+                complexity: 3 + 2 + 1 + 2 + 4 + 8 + 1 + 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_spec_stats_nested_wildcards() {
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"*": {"*": {"*": "&(2).&(1).&(0)"}}}}
+        ]))
+        .expect("parsed spec");
+
+        let stats = spec.stats();
+
+        assert_eq!(stats.wildcards, 3);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.complexity, 1 + 2 + 4 + 8 + 8);
+    }
+}
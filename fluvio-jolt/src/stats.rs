@@ -0,0 +1,157 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Error, OperationRef, TransformObserver};
+
+/// Statistics of a transformation returned by
+/// [`transform_with_stats`](crate::transform_with_stats), e.g. to chart the dropped keys of a
+/// topic and notice the schema of the inputs drifting before the consumers of the outputs break.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TransformStats {
+    /// Values of the input document, the objects, arrays and scalars at all the levels.
+    pub input_nodes: usize,
+    /// Values of the output document, counted like the values of the input.
+    pub output_nodes: usize,
+    /// Input keys matched by the keys of the `shift` specs.
+    pub matched_keys: usize,
+    /// Input keys of the objects visited by the `shift` operations that no key of the specs
+    /// matched, their values are not written to the output.
+    pub dropped_keys: usize,
+    /// Arrays created in the outputs of the `shift` operations.
+    pub arrays_created: usize,
+}
+
+/// Counts the events of the transformation, forwarding them to the observer of the options.
+#[derive(Default)]
+pub(crate) struct StatsObserver {
+    inner: Option<Arc<dyn TransformObserver>>,
+    matched_keys: AtomicUsize,
+    dropped_keys: AtomicUsize,
+    arrays_created: AtomicUsize,
+}
+
+impl StatsObserver {
+    pub(crate) fn new(inner: Option<Arc<dyn TransformObserver>>) -> Self {
+        Self {
+            inner,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn stats(&self, input_nodes: usize, output: &Value) -> TransformStats {
+        TransformStats {
+            input_nodes,
+            output_nodes: count_nodes(output),
+            matched_keys: self.matched_keys.load(Ordering::Relaxed),
+            dropped_keys: self.dropped_keys.load(Ordering::Relaxed),
+            arrays_created: self.arrays_created.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl TransformObserver for StatsObserver {
+    fn on_operation_start(&self, operation: OperationRef<'_>, index: usize) {
+        if let Some(inner) = &self.inner {
+            inner.on_operation_start(operation, index);
+        }
+    }
+
+    fn on_operation_end(&self, operation: OperationRef<'_>, index: usize) {
+        if let Some(inner) = &self.inner {
+            inner.on_operation_end(operation, index);
+        }
+    }
+
+    fn on_match(&self, key: &str) {
+        self.matched_keys.fetch_add(1, Ordering::Relaxed);
+        if let Some(inner) = &self.inner {
+            inner.on_match(key);
+        }
+    }
+
+    fn on_drop(&self, key: &str) {
+        self.dropped_keys.fetch_add(1, Ordering::Relaxed);
+        if let Some(inner) = &self.inner {
+            inner.on_drop(key);
+        }
+    }
+
+    fn on_array_created(&self) {
+        self.arrays_created.fetch_add(1, Ordering::Relaxed);
+        if let Some(inner) = &self.inner {
+            inner.on_array_created();
+        }
+    }
+
+    fn on_error(&self, error: &Error) {
+        if let Some(inner) = &self.inner {
+            inner.on_error(error);
+        }
+    }
+
+    #[cfg(feature = "slow_transforms")]
+    fn on_slow_transform(&self, report: &crate::SlowTransform) {
+        if let Some(inner) = &self.inner {
+            inner.on_slow_transform(report);
+        }
+    }
+}
+
+/// The number of values of the document.
+pub(crate) fn count_nodes(value: &Value) -> usize {
+    1 + match value {
+        Value::Array(arr) => arr.iter().map(count_nodes).sum(),
+        Value::Object(obj) => obj.values().map(count_nodes).sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+    use crate::{transform_with_stats, TransformOptions, TransformSpec};
+
+    #[derive(Default)]
+    struct Drops(AtomicUsize);
+
+    impl TransformObserver for Drops {
+        fn on_drop(&self, _key: &str) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"a": "out", "b": "out", "nested": {"x": "x"}}},
+            {"operation": "shift", "spec": {"out": "out", "x": "list[1]"}}
+        ]))
+        .unwrap();
+        let input = json!({"a": 1, "b": 2, "c": 3, "nested": {"x": 4, "y": 5}});
+        let drops = Arc::new(Drops::default());
+        let options = TransformOptions::default().observer(drops.clone());
+
+        //when
+        let (output, stats) = transform_with_stats(input, &spec, &options).unwrap();
+
+        //then
+        assert_eq!(output, json!({"out": [1, 2], "list": [null, 4]}));
+        assert_eq!(
+            stats,
+            TransformStats {
+                input_nodes: 7,
+                output_nodes: 7,
+                matched_keys: 6,
+                dropped_keys: 2,
+                arrays_created: 2,
+            }
+        );
+        assert_eq!(drops.0.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,199 @@
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{Map, Value};
+
+use crate::default::DefaultOptions;
+use crate::halt::HaltOptions;
+use crate::shift::ShiftOptions;
+use crate::spec::OperationFields;
+use crate::try_catch::TryOptions;
+
+/// The kinds of the operations, as written in the `operation` field.
+const OPERATIONS: [&str; 10] = [
+    "shift", "default", "remove", "retain", "identity", "noop", "halt-if", "sample", "rename",
+    "try",
+];
+
+/// Checks the fields of an operation of a strict chain, the unknown fields and operations are
+/// rejected with the closest valid name, e.g. ``unknown field `specs`, did you mean `spec`?``.
+pub(crate) fn check_operation(raw: &Value) -> Result<(), String> {
+    let Value::Object(obj) = raw else {
+        return Ok(());
+    };
+    if let Some((_, message)) = unknown_fields(obj).into_iter().next() {
+        return Err(message);
+    }
+    match obj.get("operation").and_then(Value::as_str) {
+        Some(kind) => check_kind(kind),
+        None => Ok(()),
+    }
+}
+
+/// The unknown fields of the operation along with their error message.
+pub(crate) fn unknown_fields(obj: &Map<String, Value>) -> Vec<(&str, String)> {
+    let kind = obj.get("operation").and_then(Value::as_str);
+    let options = match kind {
+        Some("shift") => struct_fields::<ShiftOptions>(),
+        Some("default") => struct_fields::<DefaultOptions>(),
+        Some("halt-if") => struct_fields::<HaltOptions>(),
+        Some("try") => struct_fields::<TryOptions>(),
+        _ => &[],
+    };
+    let fields: Vec<&str> = ["operation", "spec"]
+        .into_iter()
+        .chain(struct_fields::<OperationFields>().iter().copied())
+        .chain(options.iter().copied())
+        .collect();
+
+    obj.keys()
+        .filter(|key| !fields.contains(&key.as_str()))
+        .map(|key| {
+            let mut message = match kind {
+                Some(kind) => format!("unknown field `{key}` of operation `{kind}`"),
+                None => format!("unknown field `{key}`"),
+            };
+            if let Some(closest) = closest(key, &fields) {
+                message.push_str(&format!(", did you mean `{closest}`?"));
+            }
+            (key.as_str(), message)
+        })
+        .collect()
+}
+
+/// Checks the kind of the operation, naming the closest operation if it is unknown.
+pub(crate) fn check_kind(kind: &str) -> Result<(), String> {
+    // `whitelist` is the legacy name of `retain`
+    if OPERATIONS.contains(&kind) || kind == "whitelist" {
+        return Ok(());
+    }
+    let mut message = format!("unknown operation `{kind}`");
+    if let Some(closest) = closest(kind, &OPERATIONS) {
+        message.push_str(&format!(", did you mean `{closest}`?"));
+    }
+    Err(message)
+}
+
+// The valid name closest to the given one, if it is close enough to be a typo of it
+fn closest<'a>(name: &str, valid: &[&'a str]) -> Option<&'a str> {
+    let max_distance = name.chars().count().max(3) / 3;
+    valid
+        .iter()
+        .map(|candidate| (distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// Edit distance between the two names, counting the insertions, deletions, substitutions and
+// transpositions of adjacent characters
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// The names of the fields of a struct deriving `Deserialize`, which the derived implementation
+// passes to `Deserializer::deserialize_struct`
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldsDeserializer(&mut fields));
+    fields
+}
+
+struct FieldsDeserializer<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldsDeserializer<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields recorded"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+    use crate::Operation;
+
+    #[test]
+    fn test_check_operation() {
+        assert!(check_operation(&json!({
+            "operation": "shift",
+            "name": "move",
+            "sparse_arrays": "compact",
+            "spec": {}
+        }))
+        .is_ok());
+        assert!(check_operation(&json!({"operation": "whitelist", "spec": {}})).is_ok());
+        assert!(check_operation(&json!({"operation": "noop"})).is_ok());
+        assert!(check_operation(&json!({
+            "operation": "remove",
+            "docs": "drops the internal fields",
+            "x-docs": {"internal": "flags of the backend"},
+            "spec": {"internal": ""}
+        }))
+        .is_ok());
+
+        assert_eq!(
+            check_operation(&json!({"operaton": "shift", "spec": {}})),
+            Err("unknown field `operaton`, did you mean `operation`?".to_string())
+        );
+        assert_eq!(
+            check_operation(&json!({"operation": "shift", "specs": {}})),
+            Err("unknown field `specs` of operation `shift`, did you mean `spec`?".to_string())
+        );
+        assert_eq!(
+            check_operation(&json!({"operation": "remove", "replace_null": true, "spec": {}})),
+            Err("unknown field `replace_null` of operation `remove`".to_string())
+        );
+        assert_eq!(
+            check_operation(&json!({"operation": "shfit", "spec": {}})),
+            Err("unknown operation `shfit`, did you mean `shift`?".to_string())
+        );
+    }
+
+    #[test]
+    fn test_operations_are_known() {
+        for kind in OPERATIONS {
+            if let Err(error) = Operation::parse(kind, json!(null)) {
+                assert!(!error.to_string().contains("unknown variant"), "{error}");
+            }
+        }
+    }
+}
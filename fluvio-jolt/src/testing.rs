@@ -0,0 +1,173 @@
+//! Helpers for testing specifications, enabled by the `testing` feature.
+//!
+//! [`snapshot`] compares the output of a transformation with an
+//! [insta](https://insta.rs) snapshot stored in the `tests/snapshots` directory of the crate
+//! running the tests. New and changed snapshots are reviewed with `cargo insta review`.
+//!
+//! [`import_demo`] converts a spec/input/output triple saved from the
+//! [jolt-demo](https://jolt-demo.appspot.com) site into a test case of the `tests/data` corpus,
+//! so parity bugs can be reported with a ready-to-run reproducer.
+//!
+//! ```toml
+//! [dev-dependencies]
+//! fluvio-jolt = { version = "0.3", features = ["testing"] }
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{transform, TransformSpec};
+
+/// Transforms the input with the specification and asserts that the output matches the
+/// snapshot with the given name.
+///
+/// The output of a failed transformation is snapshotted as the error, so specifications that
+/// are expected to fail can be snapshotted too.
+///
+/// ```no_run
+/// use serde_json::json;
+/// use fluvio_jolt::{testing, TransformSpec};
+///
+/// let spec: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id"}}
+/// ]))
+/// .unwrap();
+///
+/// testing::snapshot("repack_id", json!({"id": 1}), &spec);
+/// ```
+#[track_caller]
+pub fn snapshot(name: &str, input: Value, spec: &TransformSpec) {
+    let output = match transform(input.clone(), spec) {
+        Ok(output) => output,
+        Err(err) => err.to_json(&input),
+    };
+
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_path(snapshot_dir());
+    settings.set_prepend_module_to_snapshot(false);
+    settings.set_omit_expression(true);
+    settings.set_description(format!("input: {input}"));
+    settings.bind(|| insta::assert_json_snapshot!(name, output));
+}
+
+/// A spec/input/output triple saved from the jolt-demo site. The output produced by Java Jolt
+/// is read from the `output` field, or from the `expected` field.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DemoExport {
+    /// The input of the transformation.
+    pub input: Value,
+    /// The chain of operations, as accepted by [`TransformSpec`].
+    pub spec: Value,
+    /// The output of Java Jolt.
+    #[serde(alias = "expected")]
+    pub output: Value,
+}
+
+/// Converts the jolt-demo export into the `<name>.json` test case of the corpus directory and
+/// returns the path of the written file.
+///
+/// The specification must be supported by this crate, the output is not checked so the test
+/// case can reproduce a parity bug. Add the name to the list of `test_all` to run it.
+///
+/// ```no_run
+/// use fluvio_jolt::testing;
+///
+/// let export = std::fs::read_to_string("export.json").unwrap();
+/// testing::import_demo("wildcard_precedence", &export, "tests/data").unwrap();
+/// ```
+pub fn import_demo(name: &str, export: &str, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let DemoExport {
+        input,
+        spec,
+        output,
+    } = serde_json::from_str(export)?;
+    serde_json::from_value::<TransformSpec>(spec.clone())?;
+
+    let test_case = json!({
+        "input": input,
+        "spec": spec,
+        "expected": output,
+    });
+    let path = dir.as_ref().join(format!("{name}.json"));
+    fs::write(&path, serde_json::to_string_pretty(&test_case)? + "\n")?;
+    Ok(path)
+}
+
+/// The snapshots are stored in the crate running the tests, not in this crate.
+fn snapshot_dir() -> PathBuf {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .expect("CARGO_MANIFEST_DIR is set when running tests with cargo");
+    PathBuf::from(manifest_dir).join("tests").join("snapshots")
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "shift", "spec": {"id": "data.id", "name": "data.name"}},
+            {"operation": "default", "spec": {"data": {"active": true}}}
+        ]))
+        .unwrap();
+
+        snapshot(
+            "testing_snapshot",
+            json!({"id": 1, "name": "John Smith"}),
+            &spec,
+        );
+    }
+
+    #[test]
+    fn test_import_demo() {
+        //given
+        let export = r#"{
+            "input": {"id": 1},
+            "spec": [{"operation": "shift", "spec": {"id": "data.id"}}],
+            "output": {"data": {"id": 1}}
+        }"#;
+        let dir = std::env::temp_dir();
+
+        //when
+        let path = import_demo("fluvio_jolt_import_demo", export, &dir).unwrap();
+
+        //then
+        let test_case: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            test_case,
+            json!({
+                "input": {"id": 1},
+                "spec": [{"operation": "shift", "spec": {"id": "data.id"}}],
+                "expected": {"data": {"id": 1}}
+            })
+        );
+    }
+
+    #[test]
+    fn test_import_demo_unsupported_spec() {
+        //given
+        let export = r#"{
+            "input": {"id": 1},
+            "spec": [{"operation": "modify-overwrite-beta", "spec": {"id": "=toString"}}],
+            "output": {"id": "1"}
+        }"#;
+
+        //when
+        let result = import_demo(
+            "fluvio_jolt_import_demo_unsupported",
+            export,
+            std::env::temp_dir(),
+        );
+
+        //then
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,105 @@
+//! Tokens of the jolt expressions with their byte spans, for the syntax highlighting and the
+//! hovers of editor tooling.
+//!
+//! The keys are unescaped, while their span covers the escaped text of the expression:
+//! ```
+//! use fluvio_jolt::tokens::{tokenize, TokenKind};
+//!
+//! let tokens = tokenize(r"a\.b[&(1)]").collect::<Result<Vec<_>, _>>().unwrap();
+//!
+//! assert_eq!(tokens[0].kind, TokenKind::Key("a.b".into()));
+//! assert_eq!(tokens[0].span, 0..4);
+//! assert_eq!(tokens[1].kind, TokenKind::OpenBrkt);
+//! assert_eq!(tokens[1].span, 4..5);
+//! assert_eq!(tokens.len(), 7);
+//! ```
+
+use std::ops::Range;
+
+pub use crate::dsl::{ParseError, TokenKind};
+use crate::dsl::Tokenizer;
+
+/// Token of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub kind: TokenKind,
+    /// Byte range of the token in the expression.
+    pub span: Range<usize>,
+}
+
+/// Splits the left or right hand side expression into tokens, see [`Tokens`].
+pub fn tokenize(expr: &str) -> Tokens<'_> {
+    Tokens {
+        inner: Tokenizer::new(expr),
+        failed: false,
+    }
+}
+
+/// Iterator over the tokens of an expression. It ends after the first error, e.g. the escape of
+/// a character that is not special.
+pub struct Tokens<'input> {
+    inner: Tokenizer<'input>,
+    failed: bool,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<SpannedToken, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.inner.next() {
+            Ok(Some(token)) => Some(Ok(SpannedToken {
+                kind: token.kind,
+                span: token.pos..self.inner.pos(),
+            })),
+            Ok(None) => None,
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spans(expr: &str) -> Vec<(TokenKind, Range<usize>)> {
+        tokenize(expr)
+            .map(|token| token.map(|t| (t.kind, t.span)))
+            .collect::<Result<_, _>>()
+            .expect("tokenized")
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        assert_eq!(
+            spans("@(1,ключ)|*"),
+            vec![
+                (TokenKind::At, 0..1),
+                (TokenKind::OpenPrnth, 1..2),
+                (TokenKind::Key("1".into()), 2..3),
+                (TokenKind::Comma, 3..4),
+                (TokenKind::Key("ключ".into()), 4..12),
+                (TokenKind::ClosePrnth, 12..13),
+                (TokenKind::Pipe, 13..14),
+                (TokenKind::Star, 14..15),
+            ]
+        );
+        assert!(spans("").is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let mut tokens = tokenize(r"a.\b.c");
+
+        assert_eq!(tokens.next().unwrap().unwrap().span, 0..1);
+        assert_eq!(tokens.next().unwrap().unwrap().span, 1..2);
+        assert_eq!(tokens.next().unwrap().unwrap_err().pos(), 4);
+        assert!(tokens.next().is_none());
+    }
+}
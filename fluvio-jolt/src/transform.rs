@@ -1,8 +1,16 @@
 use serde_json::Value as JsonValue;
-use crate::Result;
+use crate::{Context, Result};
 
 /// Transform interface for individual jolt operations
 pub trait Transform {
+    /// Options of the operation
+    type Options;
+
     /// Apply a transform to an input and get an output value
-    fn apply(&self, val: &JsonValue) -> Result<JsonValue>;
+    fn apply(
+        &self,
+        val: &JsonValue,
+        options: &Self::Options,
+        context: &Context,
+    ) -> Result<JsonValue>;
 }
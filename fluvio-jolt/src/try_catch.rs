@@ -0,0 +1,144 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ir::OperationIr;
+use crate::sandbox::Budget;
+use crate::{apply_nested, Context, Error, Result, TransformOptions, TransformSpec};
+
+/// Options of the `try` operation.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub(crate) struct TryOptions {
+    /// The chain applied to the input of the operation when its chain fails.
+    #[serde(default)]
+    pub(crate) on_error: TransformSpec,
+}
+
+pub(crate) fn to_ir(spec: &TransformSpec, options: &TryOptions) -> OperationIr {
+    OperationIr::Try {
+        operations: spec.to_ir().operations,
+        on_error: options.on_error.to_ir().operations,
+    }
+}
+
+/// Applies the chain to the input, or the `on_error` chain if the chain fails. The limits of the
+/// sandbox and the budget are not caught, they fail the operation. The chain borrows the input,
+/// so it's only copied if the chain modifies it.
+pub(crate) fn try_catch(
+    input: Cow<'_, Value>,
+    spec: &TransformSpec,
+    options: &TryOptions,
+    transform_options: &TransformOptions,
+    context: &Context,
+    budget: &Budget,
+) -> Result<Value> {
+    match apply_nested(
+        Cow::Borrowed(&input),
+        spec,
+        transform_options,
+        context,
+        budget,
+    ) {
+        Err(e) if !is_limit(&e) => {
+            apply_nested(input, &options.on_error, transform_options, context, budget)
+        }
+        result => result,
+    }
+}
+
+fn is_limit(error: &Error) -> bool {
+    match error {
+        Error::SandboxLimitExceeded { .. } | Error::BudgetExhausted { .. } => true,
+        Error::Operation { source, .. }
+        | Error::PartialOutput { source, .. }
+        | Error::SpecKey { source, .. } => is_limit(source),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use crate::{transform, transform_with_options};
+
+    use super::*;
+
+    #[test]
+    fn test_try_catch() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {
+                "operation": "try",
+                "spec": [
+                    {"operation": "shift", "strict_lookups": true, "spec": {
+                        "id": "id",
+                        "@(0,profile.name)": "name"
+                    }}
+                ],
+                "on_error": [
+                    {"operation": "shift", "spec": {"id": "id"}},
+                    {"operation": "default", "spec": {"name": "unknown"}}
+                ]
+            }
+        ]))
+        .expect("parsed spec");
+
+        //when
+        let found = transform(json!({"id": 1, "profile": {"name": "John"}}), &spec);
+        let missing = transform(json!({"id": 2}), &spec);
+
+        //then
+        assert_eq!(found.unwrap(), json!({"id": 1, "name": "John"}));
+        assert_eq!(missing.unwrap(), json!({"id": 2, "name": "unknown"}));
+    }
+
+    #[test]
+    fn test_try_without_fallback() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "constants": {"OUT": "data"},
+            "operations": [
+                {
+                    "operation": "try",
+                    "spec": [
+                        {"operation": "shift", "spec": {"id": "${OUT}.id", "*": "${OUT}.list[&(0)]"}}
+                    ]
+                }
+            ]
+        }))
+        .expect("parsed spec");
+
+        //when
+        let applied = transform(json!({"id": 1}), &spec);
+        let failed = transform(json!({"id": 1, "a": 2}), &spec);
+
+        //then
+        assert_eq!(applied.unwrap(), json!({"data": {"id": 1}}));
+        assert_eq!(failed.unwrap(), json!({"id": 1, "a": 2}));
+        let serialized = serde_json::to_value(&spec).expect("serialized spec");
+        assert_eq!(serialized[0]["spec"][0]["spec"]["id"], "data.id");
+        assert_eq!(
+            serde_json::from_value::<TransformSpec>(serialized).expect("parsed spec"),
+            spec
+        );
+        assert_eq!(spec.stats().operations, 2);
+    }
+
+    #[test]
+    fn test_try_budget_not_caught() {
+        //given
+        let spec: TransformSpec = serde_json::from_value(json!([
+            {"operation": "try", "spec": [{"operation": "shift", "spec": {"*": "&"}}]}
+        ]))
+        .expect("parsed spec");
+        let options = TransformOptions::default().fuel(1);
+
+        //when
+        let result = transform_with_options(json!({"a": 1, "b": 2}), &spec, &options);
+
+        //then
+        assert!(matches!(result, Err(Error::BudgetExhausted { fuel: 1 })));
+    }
+}
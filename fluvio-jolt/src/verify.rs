@@ -0,0 +1,237 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::pointer::JsonPointer;
+use crate::{transform_filtered, Context, Result, TransformOptions, TransformSpec};
+
+/// The result of [`verify`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpecEquivalenceReport {
+    /// Whether both chains give the same output, drop the input or fail with the same error
+    /// code.
+    pub equivalent: bool,
+    /// The differences of the output of the new chain from the output of the old one.
+    pub differences: Vec<OutputDifference>,
+}
+
+/// A difference between the outputs of two chains, located by the
+/// [RFC6901](https://datatracker.ietf.org/doc/html/rfc6901) pointer of the value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "difference", rename_all = "snake_case")]
+pub enum OutputDifference {
+    /// The value is only in the new output.
+    Added { pointer: String, value: Value },
+    /// The value is only in the old output.
+    Removed { pointer: String, value: Value },
+    /// The value is in both outputs, with a different scalar value or a different type.
+    Changed {
+        pointer: String,
+        from: Value,
+        to: Value,
+    },
+    /// One of the chains fails or drops the input while the other doesn't, or the chains fail
+    /// with different errors. The outcomes are `output`, `dropped` or the error code.
+    Outcome { from: String, to: String },
+}
+
+/// Applies both chains to the input and compares their outputs, e.g. over a sample of a stream
+/// while the consumers are migrated from the old specification to the new one.
+///
+/// ```
+/// use serde_json::json;
+/// use fluvio_jolt::{verify, OutputDifference, TransformSpec};
+///
+/// let old: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id", "name": "data.name"}}
+/// ]))
+/// .unwrap();
+/// let new: TransformSpec = serde_json::from_value(json!([
+///     {"operation": "shift", "spec": {"id": "data.id", "name": "data.full_name"}}
+/// ]))
+/// .unwrap();
+///
+/// let report = verify(&json!({"id": 1, "name": "John"}), &old, &new);
+///
+/// assert!(!report.equivalent);
+/// assert_eq!(
+///     report.differences,
+///     vec![
+///         OutputDifference::Removed {
+///             pointer: "/data/name".to_string(),
+///             value: json!("John")
+///         },
+///         OutputDifference::Added {
+///             pointer: "/data/full_name".to_string(),
+///             value: json!("John")
+///         },
+///     ]
+/// );
+/// ```
+pub fn verify(input: &Value, old: &TransformSpec, new: &TransformSpec) -> SpecEquivalenceReport {
+    let run = |spec| {
+        transform_filtered(
+            input.clone(),
+            spec,
+            &TransformOptions::default(),
+            &Context::default(),
+        )
+    };
+    let mut differences = Vec::new();
+    match (run(old), run(new)) {
+        (Ok(Some(from)), Ok(Some(to))) => {
+            diff(&from, &to, &JsonPointer::default(), &mut differences)
+        }
+        (from, to) => {
+            let (from, to) = (outcome(&from), outcome(&to));
+            if from != to {
+                differences.push(OutputDifference::Outcome { from, to });
+            }
+        }
+    }
+    SpecEquivalenceReport {
+        equivalent: differences.is_empty(),
+        differences,
+    }
+}
+
+fn outcome(result: &Result<Option<Value>>) -> String {
+    match result {
+        Ok(Some(_)) => "output".to_string(),
+        Ok(None) => "dropped".to_string(),
+        Err(e) => e.code().to_string(),
+    }
+}
+
+// The differences of the values, the keys of the objects in the order of the old value followed
+// by the keys only in the new value
+fn diff(from: &Value, to: &Value, pointer: &JsonPointer, out: &mut Vec<OutputDifference>) {
+    let mut child = |key: String, from: Option<&Value>, to: Option<&Value>| {
+        let mut pointer = pointer.clone();
+        pointer.push(key);
+        match (from, to) {
+            (Some(from), Some(to)) => diff(from, to, &pointer, out),
+            (Some(value), None) => out.push(OutputDifference::Removed {
+                pointer: pointer.join_rfc6901(),
+                value: value.clone(),
+            }),
+            (None, Some(value)) => out.push(OutputDifference::Added {
+                pointer: pointer.join_rfc6901(),
+                value: value.clone(),
+            }),
+            (None, None) => (),
+        }
+    };
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            for (key, value) in from {
+                child(key.clone(), Some(value), to.get(key));
+            }
+            for (key, value) in to.iter().filter(|(key, _)| !from.contains_key(*key)) {
+                child(key.clone(), None, Some(value));
+            }
+        }
+        (Value::Array(from), Value::Array(to)) => {
+            for idx in 0..from.len().max(to.len()) {
+                child(idx.to_string(), from.get(idx), to.get(idx));
+            }
+        }
+        (from, to) if from != to => out.push(OutputDifference::Changed {
+            pointer: pointer.join_rfc6901(),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+    use super::*;
+
+    fn spec(value: Value) -> TransformSpec {
+        serde_json::from_value(value).expect("parsed spec")
+    }
+
+    #[test]
+    fn test_verify_equivalent() {
+        //given
+        let old = spec(json!([{"operation": "shift", "spec": {"a": "x", "b": "y"}}]));
+        let new = spec(json!([
+            {"operation": "shift", "spec": {"a": "x"}},
+            {"operation": "default", "spec": {}}
+        ]));
+
+        //when
+        let report = verify(&json!({"a": 1}), &old, &new);
+
+        //then
+        assert!(report.equivalent);
+        assert!(report.differences.is_empty());
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_verify_nested_differences() {
+        //given
+        let old = spec(json!([{"operation": "shift", "spec": {"*": "&"}}]));
+        let new = spec(json!([
+            {"operation": "shift", "spec": {"list": {"0": "list[]"}, "tags": "tags", "id": "id.value"}},
+            {"operation": "default", "spec": {"tags": {"extra": true}}}
+        ]));
+
+        //when
+        let report = verify(
+            &json!({"list": [1, 2], "tags": {"a/b": 1}, "id": 1}),
+            &old,
+            &new,
+        );
+
+        //then
+        assert_eq!(
+            report.differences,
+            vec![
+                OutputDifference::Removed {
+                    pointer: "/list/1".to_string(),
+                    value: json!(2),
+                },
+                OutputDifference::Added {
+                    pointer: "/tags/extra".to_string(),
+                    value: json!(true),
+                },
+                OutputDifference::Changed {
+                    pointer: "/id".to_string(),
+                    from: json!(1),
+                    to: json!({"value": 1}),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_outcomes() {
+        //given
+        let old = spec(json!([{"operation": "shift", "spec": {"a": "x"}}]));
+        let new = spec(json!([
+            {"operation": "halt-if", "drop": true, "spec": {"path": "a", "exists": true}}
+        ]));
+        let failing = spec(json!([
+            {"operation": "shift", "strict_lookups": true, "spec": {"@(0,missing)": "x"}}
+        ]));
+
+        //when
+        let dropped = verify(&json!({"a": 1}), &old, &new);
+        let failed = verify(&json!({"a": 1}), &failing, &failing);
+
+        //then
+        assert_eq!(
+            dropped.differences,
+            vec![OutputDifference::Outcome {
+                from: "output".to_string(),
+                to: "dropped".to_string(),
+            }]
+        );
+        assert!(failed.equivalent);
+    }
+}
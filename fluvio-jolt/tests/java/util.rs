@@ -69,6 +69,13 @@ pub fn test_dir(dir_path: &str, operation: &str, skiplist: &[&str]) {
             }
         };
 
+        // the serialized spec parses into the same spec
+        let serialized = serde_json::to_value(&spec).unwrap();
+        match serde_json::from_value::<TransformSpec>(serialized.clone()) {
+            Ok(reparsed) if reparsed == spec => (),
+            _ => panic!("failed round trip of test case at {path}.\nserialized={serialized}"),
+        }
+
         let output = match transform(case.input, &spec) {
             Ok(output) => output,
             Err(e) => {
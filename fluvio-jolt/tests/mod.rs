@@ -4,6 +4,7 @@ use serde::Deserialize;
 use fluvio_jolt::TransformSpec;
 
 mod java;
+mod properties;
 
 #[derive(Debug, Deserialize)]
 struct TestData {
@@ -18,12 +19,25 @@ fn test_all() {
         "simple",
         "shift_and_default",
         "remove",
+        "retain",
         "shift_wildcards",
         "simple_wildcards",
         "shift_with_or_condition",
         "variables",
         "from_benchmark",
         "nested_variables",
+        "typed_captures",
+        "sparse_arrays",
+        "halt_if",
+        #[cfg(feature = "preserve_order")]
+        "key_collection",
+        "null_values",
+        "unique_appends",
+        "toggled_operations",
+        "expects",
+        "typed_literals",
+        "constants",
+        "grouping",
     ];
     for name in tests {
         do_test(name);
@@ -47,6 +61,14 @@ fn do_test(name: &str) {
 
     //when
     let result = fluvio_jolt::transform(input, &spec).unwrap();
+    let serialized = serde_json::to_value(&spec).unwrap();
+    let reparsed: TransformSpec = serde_json::from_value(serialized).unwrap_or_else(|err| {
+        panic!(
+            "unable to parse serialized spec for test `{}`: {:?}",
+            name, err
+        )
+    });
+    assert_eq!(reparsed, spec, "failed round trip for test `{}`", name);
 
     if result != expected {
         panic!(
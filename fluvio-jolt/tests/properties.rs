@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use proptest::prelude::*;
+use serde_json::{json, Map, Value};
+use fluvio_jolt::{transform, TransformSpec};
+
+fn key() -> impl Strategy<Value = String> {
+    "[a-z]{1,3}"
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        "[a-z]{0,5}".prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::btree_map(key(), inner, 0..4)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn object() -> impl Strategy<Value = Value> {
+    prop::collection::btree_map(key(), value(), 0..6)
+        .prop_map(|map| Value::Object(map.into_iter().collect()))
+}
+
+fn spec(spec: Value) -> TransformSpec {
+    serde_json::from_value(spec).expect("parsed spec")
+}
+
+// The specs of the corpus of `tests/data` along with their inputs
+fn corpus() -> &'static [(String, Value, TransformSpec)] {
+    static CORPUS: OnceLock<Vec<(String, Value, TransformSpec)>> = OnceLock::new();
+    CORPUS.get_or_init(|| {
+        let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        fs::read_dir(data)
+            .expect("corpus directory")
+            .map(|entry| entry.expect("corpus entry").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| {
+                let file = fs::read_to_string(&path).expect("corpus file");
+                let mut test: Value = serde_json::from_str(&file).expect("corpus test");
+                let input = test["input"].take();
+                (path.display().to_string(), input, spec(test["spec"].take()))
+            })
+            .collect()
+    })
+}
+
+proptest! {
+    #[test]
+    fn shift_wildcard_is_identity(input in object()) {
+        let spec = spec(json!([{"operation": "shift", "spec": {"*": "&(0)"}}]));
+
+        let output = transform(input.clone(), &spec).unwrap();
+
+        // the shift of an empty object writes nothing
+        let expected = match &input {
+            Value::Object(obj) if obj.is_empty() => Value::Null,
+            _ => input,
+        };
+        prop_assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn remove_undoes_disjoint_default(input in object(), defaults in object()) {
+        // the keys of the input are lowercase, the defaults are written under uppercase keys
+        let defaults: Map<String, Value> = defaults
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.to_uppercase(), v.clone()))
+            .collect();
+        let removed: Map<String, Value> = defaults
+            .keys()
+            .map(|k| (k.clone(), Value::String(String::new())))
+            .collect();
+        let spec = spec(json!([
+            {"operation": "default", "spec": defaults},
+            {"operation": "remove", "spec": removed}
+        ]));
+
+        let output = transform(input.clone(), &spec).unwrap();
+
+        prop_assert_eq!(output, input);
+    }
+
+    #[test]
+    fn retain_is_idempotent(input in object(), retained in prop::collection::vec(key(), 0..4)) {
+        let retained: Map<String, Value> = retained.into_iter().map(|k| (k, Value::Null)).collect();
+        let once = spec(json!([{"operation": "retain", "spec": retained}]));
+        let twice = spec(json!([
+            {"operation": "retain", "spec": retained},
+            {"operation": "retain", "spec": retained}
+        ]));
+
+        prop_assert_eq!(
+            transform(input.clone(), &once).unwrap(),
+            transform(input, &twice).unwrap()
+        );
+    }
+
+    #[test]
+    fn corpus_never_panics(
+        input in value(),
+        mutations in prop::collection::vec(prop::option::of(value()), 0..8),
+    ) {
+        for (name, corpus_input, spec) in corpus() {
+            // the values of the corpus input replaced by arbitrary ones, to reach the matching
+            // of the spec with unexpected types
+            let mut mutated = corpus_input.clone();
+            if let Value::Object(obj) = &mut mutated {
+                for (value, mutation) in obj.values_mut().zip(&mutations) {
+                    if let Some(mutation) = mutation {
+                        *value = mutation.clone();
+                    }
+                }
+            }
+            for input in [input.clone(), mutated] {
+                // errors are expected, a panic fails the test
+                let result = std::panic::catch_unwind(|| transform(input, spec));
+                prop_assert!(result.is_ok(), "transformation with spec {} panicked", name);
+            }
+        }
+    }
+
+    #[test]
+    fn identity_without_limits_is_identity(input in value()) {
+        let spec = spec(json!([{"operation": "identity", "spec": {}}]));
+
+        prop_assert_eq!(transform(input.clone(), &spec).unwrap(), input);
+    }
+}
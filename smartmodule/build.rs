@@ -0,0 +1,41 @@
+#[cfg(feature = "baked-spec")]
+use std::env;
+#[cfg(feature = "baked-spec")]
+use std::fs;
+#[cfg(feature = "baked-spec")]
+use std::path::PathBuf;
+
+#[cfg(feature = "baked-spec")]
+use fluvio_jolt::TransformSpec;
+#[cfg(feature = "baked-spec")]
+use serde_json::Value;
+
+#[cfg(feature = "baked-spec")]
+const SPEC_ENV: &str = "JOLT_SPEC";
+
+fn main() {
+    #[cfg(feature = "baked-spec")]
+    bake_spec();
+}
+
+// With the `baked-spec` feature, the specification of the file at the `JOLT_SPEC` path is
+// validated and written compactly to `OUT_DIR/baked_spec.json`, which the SmartModule includes
+#[cfg(feature = "baked-spec")]
+fn bake_spec() {
+    println!("cargo:rerun-if-env-changed={SPEC_ENV}");
+
+    let path = env::var(SPEC_ENV)
+        .unwrap_or_else(|_| panic!("the `baked-spec` feature requires the `{SPEC_ENV}` variable"));
+    println!("cargo:rerun-if-changed={path}");
+    let raw = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("could not read the specification `{path}`: {err}"));
+    let spec: Value = serde_json::from_str(&raw)
+        .unwrap_or_else(|err| panic!("could not parse the specification `{path}`: {err}"));
+    if let Err(err) = serde_json::from_value::<TransformSpec>(spec.clone()) {
+        panic!("invalid specification `{path}`: {err}");
+    }
+
+    let out = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    fs::write(out.join("baked_spec.json"), spec.to_string())
+        .expect("baked specification is written");
+}
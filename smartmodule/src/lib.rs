@@ -1,44 +1,695 @@
+use std::io::Read;
+#[cfg(feature = "join")]
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "join")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
 use once_cell::sync::OnceCell;
 
+use base64::Engine;
 use eyre::ContextCompat;
-use fluvio_jolt::TransformSpec;
+use flate2::read::GzDecoder;
+use fluvio_jolt::{transform_with_context, Conflicts, JoltEngine, TransformOptions, TransformSpec};
+use serde_json::Value;
 use fluvio_smartmodule::dataplane::smartmodule::SmartModuleInitError;
 use fluvio_smartmodule::{
     dataplane::smartmodule::SmartModuleExtraParams, smartmodule, SmartModuleRecord, RecordData,
     Result,
 };
 
-static SPEC: OnceCell<TransformSpec> = OnceCell::new();
+// every variant feature exports its own SmartModule entry point
+#[cfg(any(
+    all(
+        feature = "aggregate",
+        any(feature = "filter-map", feature = "array-map", feature = "join")
+    ),
+    all(feature = "filter-map", any(feature = "array-map", feature = "join")),
+    all(feature = "array-map", feature = "join"),
+))]
+compile_error!(
+    "the `aggregate`, `filter-map`, `array-map` and `join` features select the type of the SmartModule, enable at most one of them"
+);
+
+static ENGINE: OnceCell<JoltEngine> = OnceCell::new();
+static PAYLOAD: OnceCell<Payload> = OnceCell::new();
+static FORMATS: OnceCell<Formats> = OnceCell::new();
+#[cfg(feature = "join")]
+static JOIN: OnceCell<Join> = OnceCell::new();
+#[cfg(feature = "array-map")]
+static EXPLODE: OnceCell<Option<Explode>> = OnceCell::new();
 
 const PARAM_NAME: &str = "spec";
+/// The largest number `N` of the `spec.N` params. The params can't be listed, so the `spec.N`
+/// params are looked up by number.
+const MAX_SPEC_PARAMS: usize = 100;
+const ENCODING_PARAM_NAME: &str = "spec-encoding";
+const SAMPLE_PARAM_NAME: &str = "sample";
+const PAYLOAD_PARAM_NAME: &str = "payload";
+const INPUT_FORMAT_PARAM_NAME: &str = "input-format";
+const OUTPUT_FORMAT_PARAM_NAME: &str = "output-format";
+const STRICT_LOOKUPS_PARAM_NAME: &str = "strict-lookups";
+const CONFLICTS_PARAM_NAME: &str = "conflicts";
+#[cfg(feature = "array-map")]
+const EXPLODE_PARAM_NAME: &str = "explode";
+#[cfg(feature = "join")]
+const STATE_MATCH_PARAM_NAME: &str = "state-match";
+#[cfg(feature = "join")]
+const STATE_KEY_PARAM_NAME: &str = "state-key";
+#[cfg(feature = "join")]
+const STATE_WINDOW_PARAM_NAME: &str = "state-window";
+#[cfg(feature = "join")]
+const STATE_SIZE_PARAM_NAME: &str = "state-size";
+/// The specification baked into the SmartModule at build time, see `build.rs`.
+#[cfg(feature = "baked-spec")]
+const BAKED_SPEC: Option<&str> = Some(include_str!(concat!(env!("OUT_DIR"), "/baked_spec.json")));
+#[cfg(not(feature = "baked-spec"))]
+const BAKED_SPEC: Option<&str> = None;
+#[cfg(feature = "aggregate")]
+const ACC_KEY: &str = "$acc";
+#[cfg(any(feature = "aggregate", feature = "join"))]
+const CUR_KEY: &str = "$cur";
+#[cfg(feature = "join")]
+const STATE_KEY: &str = "$state";
+
+/// Format of the record values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Payload {
+    /// A single JSON document per record.
+    Json,
+    /// Newline-delimited JSON documents, each one is transformed separately.
+    Ndjson,
+}
+
+impl Payload {
+    fn from_param(param: Option<&str>) -> Result<Self> {
+        match param.unwrap_or("json") {
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            other => Err(eyre::eyre!(
+                "unsupported `{PAYLOAD_PARAM_NAME}` param: {other}. Expected one of: json, ndjson"
+            )),
+        }
+    }
+}
+
+/// Encoding of the record values, the documents are decoded to JSON values before the
+/// transformation and the outputs are encoded back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Msgpack,
+    Cbor,
+}
+
+impl Format {
+    fn from_param(name: &str, param: Option<&str>) -> Result<Self> {
+        match param.unwrap_or("json") {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::Msgpack),
+            "cbor" => Ok(Self::Cbor),
+            other => Err(eyre::eyre!(
+                "unsupported `{name}` param: {other}. Expected one of: json, msgpack, cbor"
+            )),
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<Value> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::Msgpack => Ok(rmp_serde::from_slice(bytes)?),
+            Self::Cbor => Ok(ciborium::de::from_reader(bytes)?),
+        }
+    }
+
+    fn encode(self, value: &Value, out: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Self::Json => serde_json::to_writer(out, value)?,
+            Self::Msgpack => rmp_serde::encode::write(out, value)?,
+            Self::Cbor => ciborium::ser::into_writer(value, out)?,
+        }
+        Ok(())
+    }
+}
+
+/// Formats of the input and output record values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Formats {
+    input: Format,
+    output: Format,
+}
+
+impl Default for Formats {
+    fn default() -> Self {
+        Self {
+            input: Format::Json,
+            output: Format::Json,
+        }
+    }
+}
+
+impl Formats {
+    fn from_params(params: &SmartModuleExtraParams, payload: Payload) -> Result<Self> {
+        let format = |name: &str| Format::from_param(name, params.get(name).map(String::as_str));
+        let formats = Self {
+            input: format(INPUT_FORMAT_PARAM_NAME)?,
+            output: format(OUTPUT_FORMAT_PARAM_NAME)?,
+        };
+        // the documents of a batch are delimited by newlines, which binary formats may contain
+        if payload == Payload::Ndjson && formats != Self::default() {
+            return Err(eyre::eyre!(
+                "`{PAYLOAD_PARAM_NAME}: ndjson` requires the json `{INPUT_FORMAT_PARAM_NAME}` and `{OUTPUT_FORMAT_PARAM_NAME}` params"
+            ));
+        }
+        Ok(formats)
+    }
+}
+
+/// Records emitted by the `array-map` variant for the documents of a record. Without it, the
+/// outputs of the documents are joined into a single record like with the `map` variant.
+#[cfg(feature = "array-map")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Explode {
+    /// Every document is emitted as a record, an array output included.
+    Documents,
+    /// Every element of the array at the pointer of the output of a document is emitted as a
+    /// record, the outputs without an array at the pointer are emitted as they are.
+    Pointer(String),
+}
+
+#[cfg(feature = "array-map")]
+impl Explode {
+    fn from_param(param: Option<&str>) -> Result<Option<Self>> {
+        match param {
+            None | Some("false") => Ok(None),
+            Some("true") => Ok(Some(Self::Documents)),
+            Some(pointer) if is_pointer(pointer) => Ok(Some(Self::Pointer(pointer.to_string()))),
+            Some(other) => Err(eyre::eyre!(
+                "unsupported `{EXPLODE_PARAM_NAME}` param: {other}. Expected `true`, `false` or a JSON pointer, e.g. `/items`"
+            )),
+        }
+    }
+
+    fn outputs(&self, mut output: Value) -> Vec<Value> {
+        if let Self::Pointer(pointer) = self {
+            if let Some(Value::Array(elements)) = output.pointer_mut(pointer) {
+                return std::mem::take(elements);
+            }
+        }
+        vec![output]
+    }
+}
+
+/// Lookup records kept by the `join` variant and exposed to the spec.
+#[cfg(feature = "join")]
+#[derive(Debug)]
+struct Join {
+    /// Pointer and value of the field identifying the lookup records.
+    match_pointer: String,
+    match_value: Value,
+    /// Pointer of the key of the lookup records.
+    key_pointer: String,
+    /// The lookup records older than the window, in milliseconds, are evicted.
+    window: i64,
+    /// Maximum number of keys, the lookup records stored first are evicted first.
+    size: usize,
+    state: Mutex<JoinState>,
+}
+
+/// The input of the spec holding the lookup records by key, along with the order they were
+/// stored in, so the oldest records are evicted without scanning the state.
+#[cfg(feature = "join")]
+#[derive(Debug)]
+struct JoinState {
+    /// `{"$cur": <document>, "$state": {<key>: <lookup record>}}`, the document is `null`
+    /// between the transformations.
+    input: Value,
+    /// The stored keys, from the oldest, with the timestamps and the sequence numbers of the
+    /// storings. The entries of the keys stored again since, or evicted, are stale.
+    order: VecDeque<(String, i64, u64)>,
+    /// The sequence number of the latest storing of every key of the state.
+    stored: HashMap<String, u64>,
+    next: u64,
+}
+
+#[cfg(feature = "join")]
+impl Join {
+    fn from_params(params: &SmartModuleExtraParams) -> Result<Self> {
+        let (match_pointer, match_value) = params
+            .get(STATE_MATCH_PARAM_NAME)
+            .and_then(|param| param.split_once('='))
+            .filter(|(pointer, _)| is_pointer(pointer))
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "missing or invalid `{STATE_MATCH_PARAM_NAME}` param. Expected `<pointer>=<value>`, e.g. `/type=customer`"
+                )
+            })?;
+        let key_pointer = params
+            .get(STATE_KEY_PARAM_NAME)
+            .ok_or_else(|| SmartModuleInitError::MissingParam(STATE_KEY_PARAM_NAME.to_string()))?;
+        if !is_pointer(key_pointer) {
+            return Err(eyre::eyre!(
+                "unsupported `{STATE_KEY_PARAM_NAME}` param: {key_pointer}. Expected a JSON pointer, e.g. `/id`"
+            ));
+        }
+        let number = |name: &str, default: u64| match params.get(name) {
+            Some(param) => param.parse::<u64>().map_err(|_| {
+                eyre::eyre!("unsupported `{name}` param: {param}. Expected a positive number")
+            }),
+            None => Ok(default),
+        };
+        let window = number(STATE_WINDOW_PARAM_NAME, 60)?;
+        let size = number(STATE_SIZE_PARAM_NAME, 1000)?;
+
+        Ok(Self {
+            match_pointer: match_pointer.to_string(),
+            // a value that isn't valid JSON is a string, e.g. `customer`
+            match_value: serde_json::from_str(match_value)
+                .unwrap_or_else(|_| Value::String(match_value.to_string())),
+            key_pointer: key_pointer.clone(),
+            window: i64::try_from(window.saturating_mul(1000)).unwrap_or(i64::MAX),
+            size: usize::try_from(size).unwrap_or(usize::MAX),
+            state: Mutex::new(JoinState::new()),
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, JoinState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Keeps the document in the state if it is a lookup record, otherwise returns it.
+    /// Lookup records without a string or number key are dropped.
+    fn store(&self, document: Value, timestamp: i64) -> Option<Value> {
+        if document.pointer(&self.match_pointer) != Some(&self.match_value) {
+            return Some(document);
+        }
+        let key = match document.pointer(&self.key_pointer) {
+            Some(Value::String(key)) => key.clone(),
+            Some(Value::Number(key)) => key.to_string(),
+            _ => return None,
+        };
+        let mut state = self.state();
+        state.store(key, timestamp, document);
+        while state.stored.len() > self.size && state.evict_oldest(|_| true) {}
+        None
+    }
+
+    /// Applies the spec to the document along with the lookup records of the window ending at
+    /// the timestamp. The records are evicted from the oldest stored, those without a timestamp
+    /// aren't evicted by the window, nor are the records stored after them. The spec reads the
+    /// records in place, they are only copied if an operation modifies its input.
+    fn apply(&self, engine: &JoltEngine, document: Value, timestamp: i64) -> Result<Option<Value>> {
+        let mut state = self.state();
+        if timestamp >= 0 {
+            let start = timestamp.saturating_sub(self.window);
+            while state.evict_oldest(|time| time >= 0 && time < start) {}
+        }
+        state.input[CUR_KEY] = document;
+        let output = engine.run_filtered_ref(&state.input);
+        state.input[CUR_KEY] = Value::Null;
+        Ok(output?)
+    }
+}
+
+#[cfg(feature = "join")]
+impl JoinState {
+    fn new() -> Self {
+        let mut input = serde_json::Map::new();
+        input.insert(CUR_KEY.to_string(), Value::Null);
+        input.insert(STATE_KEY.to_string(), Value::Object(serde_json::Map::new()));
+        Self {
+            input: Value::Object(input),
+            order: VecDeque::new(),
+            stored: HashMap::new(),
+            next: 0,
+        }
+    }
+
+    fn records(&mut self) -> &mut serde_json::Map<String, Value> {
+        self.input[STATE_KEY]
+            .as_object_mut()
+            .expect("the lookup records are an object")
+    }
+
+    fn store(&mut self, key: String, timestamp: i64, document: Value) {
+        let seq = self.next;
+        self.next += 1;
+        self.stored.insert(key.clone(), seq);
+        self.order.push_back((key.clone(), timestamp, seq));
+        self.records().insert(key, document);
+        // drops the stale entries once they outnumber the stored keys, so the order doesn't grow
+        // with the keys stored again
+        if self.order.len() > 2 * self.stored.len() + 16 {
+            let stored = &self.stored;
+            self.order
+                .retain(|(key, _, seq)| stored.get(key) == Some(seq));
+        }
+    }
+
+    /// Evicts the oldest lookup record if `evict` holds for its timestamp, returns whether a
+    /// record is evicted.
+    fn evict_oldest(&mut self, evict: impl Fn(i64) -> bool) -> bool {
+        while let Some((key, time, seq)) = self.order.front() {
+            if self.stored.get(key) != Some(seq) {
+                self.order.pop_front();
+                continue;
+            }
+            if !evict(*time) {
+                return false;
+            }
+            let (key, ..) = self.order.pop_front().expect("the oldest entry");
+            self.stored.remove(&key);
+            self.records().remove(&key);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(any(feature = "join", feature = "array-map"))]
+fn is_pointer(pointer: &str) -> bool {
+    pointer.is_empty() || pointer.starts_with('/')
+}
 
 #[smartmodule(init)]
 fn init(params: SmartModuleExtraParams) -> Result<()> {
-    if let Some(raw_spec) = params.get(PARAM_NAME) {
-        match serde_json::from_str(raw_spec) {
-            Ok(spec) => {
-                SPEC.set(spec).expect("spec is already initialized");
-                Ok(())
-            }
-            Err(err) => {
-                eprintln!("unable to parse spec from params: {err:?}");
-                Err(eyre::Report::msg(
-                    "could not parse the specification from `spec` param",
+    let payload = Payload::from_param(params.get(PAYLOAD_PARAM_NAME).map(String::as_str))?;
+    let formats = Formats::from_params(&params, payload)?;
+    let encoding = params.get(ENCODING_PARAM_NAME).map(String::as_str);
+    let options = parse_options(&params)?;
+    #[cfg(feature = "join")]
+    let join = Join::from_params(&params)?;
+    #[cfg(feature = "array-map")]
+    let explode = Explode::from_param(params.get(EXPLODE_PARAM_NAME).map(String::as_str))?;
+
+    let mut specs = Vec::new();
+    if let Some(baked_spec) = BAKED_SPEC {
+        let spec = serde_json::from_str(baked_spec)
+            .map_err(|err| eyre::eyre!("could not parse the baked specification: {err}"))?;
+        specs.push(spec);
+    }
+    if params.get(&format!("{PARAM_NAME}.0")).is_some() {
+        return Err(eyre::eyre!(
+            "unsupported `{PARAM_NAME}.0` param, the first specification is passed in `{PARAM_NAME}`"
+        ));
+    }
+    let names = std::iter::once(PARAM_NAME.to_string())
+        .chain((1..=MAX_SPEC_PARAMS).map(|idx| format!("{PARAM_NAME}.{idx}")));
+    let mut missing = None;
+    for name in names {
+        match (params.get(&name), &missing) {
+            (Some(raw_spec), None) => specs.push(parse_spec(&name, raw_spec, encoding)?),
+            (Some(_), Some(missing)) => {
+                return Err(eyre::eyre!(
+                    "`{name}` param is passed without `{missing}` param, the specifications must be numbered contiguously"
                 ))
             }
+            (None, None) => missing = Some(name),
+            (None, Some(_)) => (),
         }
-    } else {
-        Err(SmartModuleInitError::MissingParam(PARAM_NAME.to_string()).into())
+    }
+
+    if specs.is_empty() {
+        return Err(SmartModuleInitError::MissingParam(PARAM_NAME.to_string()).into());
+    }
+
+    let engine = JoltEngine::new(TransformSpec::compose(specs)).with_options(options);
+
+    if let Some(sample) = params.get(SAMPLE_PARAM_NAME) {
+        verify_sample(&engine, sample)?;
+    }
+
+    ENGINE.set(engine).expect("engine is already initialized");
+    PAYLOAD
+        .set(payload)
+        .expect("payload is already initialized");
+    FORMATS
+        .set(formats)
+        .expect("formats are already initialized");
+    #[cfg(feature = "join")]
+    JOIN.set(join).expect("join state is already initialized");
+    #[cfg(feature = "array-map")]
+    EXPLODE
+        .set(explode)
+        .expect("explode is already initialized");
+    Ok(())
+}
+
+/// Reads the transformation options from the params. Absent params keep the defaults.
+fn parse_options(params: &SmartModuleExtraParams) -> Result<TransformOptions> {
+    let mut options = TransformOptions::default();
+
+    if let Some(strict_lookups) = params.get(STRICT_LOOKUPS_PARAM_NAME) {
+        let strict_lookups = strict_lookups.parse().map_err(|_| {
+            eyre::eyre!(
+                "unsupported `{STRICT_LOOKUPS_PARAM_NAME}` param: {strict_lookups}. Expected one of: true, false"
+            )
+        })?;
+        options = options.strict_lookups(strict_lookups);
+    }
+
+    if let Some(conflicts) = params.get(CONFLICTS_PARAM_NAME) {
+        let conflicts: Conflicts = serde_json::from_value(Value::String(conflicts.clone()))
+            .map_err(|_| {
+                eyre::eyre!(
+                    "unsupported `{CONFLICTS_PARAM_NAME}` param: {conflicts}. Expected one of: merge, overwrite, error"
+                )
+            })?;
+        options = options.conflicts(conflicts);
+    }
+
+    Ok(options)
+}
+
+/// Runs the transformation once on the `sample` param so that broken specifications
+/// fail at deploy time instead of producing empty records. The run has its own stream, so it
+/// isn't counted by the `sample` operations applied to the records.
+fn verify_sample(engine: &JoltEngine, sample: &str) -> Result<()> {
+    let sample: Value = serde_json::from_str(sample).map_err(|err| {
+        eyre::eyre!("could not parse the sample record from `{SAMPLE_PARAM_NAME}` param: {err}")
+    })?;
+
+    let context = engine.context().new_stream();
+    let output = transform_with_context(sample, engine.spec(), engine.options(), &context)
+        .map_err(|err| {
+            eyre::eyre!("transformation of the `{SAMPLE_PARAM_NAME}` param failed: {err}")
+        })?;
+
+    let is_empty = match &output {
+        Value::Null => true,
+        Value::Object(obj) => obj.is_empty(),
+        Value::Array(arr) => arr.is_empty(),
+        _ => false,
+    };
+    if is_empty {
+        return Err(eyre::eyre!(
+            "transformation of the `{SAMPLE_PARAM_NAME}` param produced an empty document: {output}"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses the specification passed in the `name` param.
+fn parse_spec(name: &str, raw_spec: &str, encoding: Option<&str>) -> Result<TransformSpec> {
+    let raw_spec = decode_spec(raw_spec, encoding)?;
+    match serde_json::from_str(&raw_spec) {
+        Ok(spec) => Ok(spec),
+        Err(err) => {
+            eprintln!("unable to parse spec from params: {err:?}");
+            Err(eyre::Report::msg(format!(
+                "could not parse the specification from `{name}` param"
+            )))
+        }
+    }
+}
+
+/// Decodes the raw `spec` param according to the `spec-encoding` param.
+///
+/// Supported encodings are `json` (default), `base64` and `gzip+base64`.
+fn decode_spec(raw_spec: &str, encoding: Option<&str>) -> Result<String> {
+    let encoding = encoding.unwrap_or("json");
+    match encoding {
+        "json" => Ok(raw_spec.to_string()),
+        "base64" => {
+            let bytes = decode_base64(raw_spec)?;
+            Ok(String::from_utf8(bytes)?)
+        }
+        "gzip+base64" => {
+            let bytes = decode_base64(raw_spec)?;
+            let mut decoded = String::new();
+            GzDecoder::new(bytes.as_slice()).read_to_string(&mut decoded)?;
+            Ok(decoded)
+        }
+        other => Err(eyre::eyre!(
+            "unsupported `{ENCODING_PARAM_NAME}` param: {other}. Expected one of: json, base64, gzip+base64"
+        )),
     }
 }
 
+fn decode_base64(input: &str) -> Result<Vec<u8>> {
+    // connector configs may wrap long values, so whitespace is ignored
+    let input: String = input.split_whitespace().collect();
+    Ok(base64::engine::general_purpose::STANDARD.decode(input)?)
+}
+
+#[cfg(not(any(
+    feature = "aggregate",
+    feature = "filter-map",
+    feature = "join",
+    feature = "array-map"
+)))]
 #[smartmodule(map)]
 pub fn map(record: &SmartModuleRecord) -> Result<(Option<RecordData>, RecordData)> {
-    let spec = SPEC.get().wrap_err("jolt spec is not initialized")?;
+    let engine = ENGINE.get().wrap_err("jolt spec is not initialized")?;
+
+    let payload = PAYLOAD.get().copied().unwrap_or(Payload::Json);
+    let formats = FORMATS.get().copied().unwrap_or_default();
 
     let key = record.key.clone();
-    let record = serde_json::from_slice(record.value.as_ref())?;
-    let transformed = fluvio_jolt::transform(record, spec)?;
+    let mut value = Vec::with_capacity(record.value.len());
+    for document in documents(record.value.as_ref(), payload, formats.input)? {
+        if !value.is_empty() {
+            value.push(b'\n');
+        }
+        let transformed = engine.run(document)?;
+        formats.output.encode(&transformed, &mut value)?;
+    }
+
+    Ok((key, value.into()))
+}
+
+/// Transforms records like `map`, dropping the documents dropped by `halt-if` and `sample`
+/// operations. Records whose documents are all dropped are filtered out.
+///
+/// With the `join` feature, the lookup records are kept in the state instead of being
+/// transformed, see [`join`].
+#[cfg(any(feature = "filter-map", feature = "join"))]
+#[smartmodule(filter_map)]
+pub fn filter_map(record: &SmartModuleRecord) -> Result<Option<(Option<RecordData>, RecordData)>> {
+    let engine = ENGINE.get().wrap_err("jolt spec is not initialized")?;
+
+    let payload = PAYLOAD.get().copied().unwrap_or(Payload::Json);
+    let formats = FORMATS.get().copied().unwrap_or_default();
 
-    Ok((key, serde_json::to_vec(&transformed)?.into()))
+    let mut value = Vec::with_capacity(record.value.len());
+    let mut kept = false;
+    for document in documents(record.value.as_ref(), payload, formats.input)? {
+        #[cfg(feature = "join")]
+        let transformed = join(engine, document, record.timestamp())?;
+        #[cfg(not(feature = "join"))]
+        let transformed = engine.run_filtered(document)?;
+        let Some(transformed) = transformed else {
+            continue;
+        };
+        if kept {
+            value.push(b'\n');
+        }
+        formats.output.encode(&transformed, &mut value)?;
+        kept = true;
+    }
+
+    Ok(kept.then(|| (record.key.clone(), value.into())))
+}
+
+/// Transforms records like `filter-map`, exploding them as selected by the `explode` param
+/// instead of joining the outputs, see [`Explode`]. Documents dropped by `halt-if` and `sample`
+/// operations are removed.
+#[cfg(feature = "array-map")]
+#[smartmodule(array_map)]
+pub fn array_map(record: &SmartModuleRecord) -> Result<Vec<(Option<RecordData>, RecordData)>> {
+    let engine = ENGINE.get().wrap_err("jolt spec is not initialized")?;
+
+    let payload = PAYLOAD.get().copied().unwrap_or(Payload::Json);
+    let formats = FORMATS.get().copied().unwrap_or_default();
+    let explode = EXPLODE.get().and_then(Option::as_ref);
+
+    let mut records = Vec::new();
+    let mut joined = Vec::new();
+    let mut kept = false;
+    for document in documents(record.value.as_ref(), payload, formats.input)? {
+        let Some(transformed) = engine.run_filtered(document)? else {
+            continue;
+        };
+        match explode {
+            Some(explode) => {
+                for output in explode.outputs(transformed) {
+                    let mut value = Vec::new();
+                    formats.output.encode(&output, &mut value)?;
+                    records.push((record.key.clone(), value.into()));
+                }
+            }
+            // the outputs are joined like with the `map` variant
+            None => {
+                if kept {
+                    joined.push(b'\n');
+                }
+                formats.output.encode(&transformed, &mut joined)?;
+                kept = true;
+            }
+        }
+    }
+
+    if kept {
+        records.push((record.key.clone(), joined.into()));
+    }
+    Ok(records)
+}
+
+/// Keeps the lookup records in the state, returns `None` for them. The spec is applied to an
+/// object holding the other documents under the `$cur` key and the lookup records of the window
+/// by key under the `$state` key, e.g. `"@(0,\\$state.@(0,\\$cur.customer_id))"` looks up the
+/// record of the customer of the current document.
+#[cfg(feature = "join")]
+fn join(engine: &JoltEngine, document: Value, timestamp: i64) -> Result<Option<Value>> {
+    let join = JOIN.get().wrap_err("join state is not initialized")?;
+    let Some(document) = join.store(document, timestamp) else {
+        return Ok(None);
+    };
+    join.apply(engine, document, timestamp)
+}
+
+/// Merges records into the accumulator. The spec is applied to an object where the
+/// accumulator is available under the `$acc` key (`null` for the first record) and the
+/// current record under the `$cur` key. Since `$` is a special character in the spec DSL,
+/// the keys are escaped in the spec, e.g. `"\\$acc"`. Records dropped by `halt-if` and `sample`
+/// operations leave the accumulator unchanged.
+#[cfg(feature = "aggregate")]
+#[smartmodule(aggregate)]
+pub fn aggregate(accumulator: RecordData, current: &SmartModuleRecord) -> Result<RecordData> {
+    let engine = ENGINE.get().wrap_err("jolt spec is not initialized")?;
+
+    let payload = PAYLOAD.get().copied().unwrap_or(Payload::Json);
+    let formats = FORMATS.get().copied().unwrap_or_default();
+
+    // the accumulator is encoded in the output format
+    let mut acc = if accumulator.as_ref().is_empty() {
+        Value::Null
+    } else {
+        formats.output.decode(accumulator.as_ref())?
+    };
+    for document in documents(current.value.as_ref(), payload, formats.input)? {
+        let mut input = serde_json::Map::new();
+        input.insert(ACC_KEY.to_string(), acc.clone());
+        input.insert(CUR_KEY.to_string(), document);
+        if let Some(output) = engine.run_filtered(Value::Object(input))? {
+            acc = output;
+        }
+    }
+
+    let mut value = Vec::new();
+    formats.output.encode(&acc, &mut value)?;
+    Ok(value.into())
+}
+
+/// Decodes the documents of the record value according to the payload and input formats.
+/// Blank lines of newline-delimited payloads are skipped.
+fn documents(value: &[u8], payload: Payload, format: Format) -> Result<Vec<Value>> {
+    match payload {
+        Payload::Json => Ok(vec![format.decode(value)?]),
+        Payload::Ndjson => value
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .map(|line| Ok(serde_json::from_slice(line)?))
+            .collect(),
+    }
 }